@@ -16,6 +16,7 @@ from debot.agent.tools import (
     ReadFileTool,
     SpawnTool,
     ToolRegistry,
+    WebFetchManyTool,
     WebFetchTool,
     WebSearchTool,
     WriteFileTool,
@@ -80,7 +81,9 @@ class AgentLoop:
 
         # Web tools
         self.tools.register(WebSearchTool(api_key=self.brave_api_key))
-        self.tools.register(WebFetchTool())
+        web_fetch = WebFetchTool(workspace=str(self.workspace))
+        self.tools.register(web_fetch)
+        self.tools.register(WebFetchManyTool(fetch_tool=web_fetch))
 
         # Message tool
         message_tool = MessageTool(send_callback=self.bus.publish_outbound)
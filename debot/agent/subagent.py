@@ -13,6 +13,7 @@ from debot.agent.tools import (
     ListDirTool,
     ReadFileTool,
     ToolRegistry,
+    WebFetchManyTool,
     WebFetchTool,
     WebSearchTool,
     WriteFileTool,
@@ -100,7 +101,9 @@ class SubagentManager:
             tools.register(ListDirTool())
             tools.register(ExecTool(working_dir=str(self.workspace)))
             tools.register(WebSearchTool(api_key=self.brave_api_key))
-            tools.register(WebFetchTool())
+            web_fetch = WebFetchTool(workspace=str(self.workspace))
+            tools.register(web_fetch)
+            tools.register(WebFetchManyTool(fetch_tool=web_fetch))
 
             # Build messages with subagent-specific prompt
             system_prompt = self._build_subagent_prompt(task)
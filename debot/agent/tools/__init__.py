@@ -11,6 +11,7 @@ try:
         ExecTool,
         ListDirTool,
         ReadFileTool,
+        WebFetchManyTool,
         WebFetchTool,
         WebSearchTool,
         WriteFileTool,
@@ -24,7 +25,7 @@ except ImportError:
         WriteFileTool,
     )
     from debot.agent.tools._shell_py import ExecTool
-    from debot.agent.tools._web_py import WebFetchTool, WebSearchTool
+    from debot.agent.tools._web_py import WebFetchManyTool, WebFetchTool, WebSearchTool
 
 # These stay in Python (depend on Python callbacks/state)
 from debot.agent.tools.message import MessageTool
@@ -40,6 +41,7 @@ __all__ = [
     "ExecTool",
     "WebSearchTool",
     "WebFetchTool",
+    "WebFetchManyTool",
     "MessageTool",
     "SpawnTool",
 ]
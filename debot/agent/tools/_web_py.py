@@ -1,11 +1,27 @@
 """Web tools: web_search and web_fetch."""
 
+import asyncio
+import base64
+import codecs
+import email.utils
+import functools
+import gzip
+import hashlib
 import html
+import ipaddress
 import json
+import math
 import os
 import re
-from typing import Any
-from urllib.parse import urlparse
+import time
+import zlib
+from collections import OrderedDict
+from datetime import date, datetime, timezone
+from http.cookiejar import CookieJar
+from pathlib import Path
+from typing import Any, Callable
+from urllib.parse import parse_qsl, unquote, urldefrag, urlencode, urljoin, urlparse
+from urllib.robotparser import RobotFileParser
 
 import httpx
 
@@ -14,6 +30,232 @@ from debot.agent.tools._base_py import Tool
 # Shared constants
 USER_AGENT = "Mozilla/5.0 (Macintosh; Intel Mac OS X 14_7_2) AppleWebKit/537.36"
 MAX_REDIRECTS = 5  # Limit redirects to prevent DoS attacks
+MAX_BLOCK_DEPTH = 16  # Lists and blockquotes nested deeper are flattened to text
+TRUNCATION_MARKER = "…"  # Ends text cut short at maxChars or maxTokens
+TOKENIZER = "bytes/4"  # How maxTokens counts are estimated
+DEFAULT_MAX_DOWNLOAD_BYTES = 5 * 1024 * 1024  # Bytes of a response body read at most
+ROBOTS_TTL_S = 60 * 60  # Seconds an origin's robots.txt is cached
+ROBOTS_MAX_BYTES = 500 * 1024  # Bytes of a robots.txt read at most
+DEFAULT_CACHE_MAX_ENTRIES = 128  # Pages cached before the least recently used is evicted
+DEFAULT_VALIDATOR_MAX_ENTRIES = 64  # Pages kept with their ETag and Last-Modified
+DEFAULT_REQUESTS_PER_MINUTE = 60.0  # Requests to one registered domain a minute
+DEFAULT_MAX_WAIT_S = 30.0  # Longest a request waits for its domain's bucket to refill
+SECOND_LEVEL_LABELS = {"ac", "co", "com", "edu", "gov", "net", "org"}  # As in example.co.uk
+DEFAULT_MAX_RETRIES = 2  # Retries after the first attempt
+RETRY_BASE_DELAY_S = 0.5  # Delay before the first retry, doubled for each one after
+RETRY_MAX_DELAY_S = 30.0  # A longer Retry-After ends the retries instead
+DENIED_HEADERS = {"host", "content-length", "transfer-encoding", "connection"}  # Set by the client itself
+SENSITIVE_HEADERS = {"authorization", "proxy-authorization", "cookie", "x-api-key", "x-subscription-token"}
+REDACTED = "[REDACTED]"
+METHODS = ("GET", "POST", "PUT", "DELETE", "HEAD")  # Methods web_fetch can send
+WRITE_METHODS = ("POST", "PUT", "DELETE")  # Sent only with allow_write_methods
+PROXY_SCHEMES = ("http", "https", "socks5", "socks5h")  # Accepted for an explicit proxy
+PROXY_AUTH_ERROR = "Proxy authentication failed"  # Kept apart from errors from the site
+META_SCAN_BYTES = 4096  # Bytes at the start of a page searched for a <meta> charset
+DEFAULT_MAX_BASE64_BYTES = 64 * 1024  # Bytes of a binary body returned as base64 at most
+DEFAULT_MAX_LINKS = 200  # Links returned for a page in links mode
+DEFAULT_MAX_URLS = 5  # URLs web_fetch_many takes in a call
+DEFAULT_FETCH_CONCURRENCY = 3  # URLs web_fetch_many fetches at a time
+DEFAULT_MAX_CHARS_TOTAL = 60000  # Characters of text shared by the pages of a web_fetch_many call
+DEFAULT_SEARCH_TIMEOUT_S = 10.0  # Seconds a web_search request may take
+DEFAULT_FETCH_TIMEOUT_S = 30.0  # Seconds a web_fetch request may take
+MAX_REFRESH_HOPS = 3  # Most meta refreshes followed from the page asked for
+NEAR_EMPTY_CHARS = 32  # Visible characters below which a page canonical on another host is an interstitial
+HEAD_TIMEOUT_S = 10.0  # Longest a head fetch waits by default, if the tool's limit is longer
+MAX_SITEMAPS = 10  # Most sitemaps of a sitemap index read, the latest first
+DEFAULT_MEDIAWIKI_HOSTS = ("wikipedia.org",)  # Domains of sites whose articles come from the MediaWiki API
+MAX_TIMEOUT_S = 120.0  # Longest a call's timeoutS may be
+BRAVE_URL = "https://api.search.brave.com/res/v1/web/search"
+BRAVE_NEWS_URL = "https://api.search.brave.com/res/v1/news/search"
+BRAVE_IMAGES_URL = "https://api.search.brave.com/res/v1/images/search"
+GOOGLE_CSE_URL = "https://www.googleapis.com/customsearch/v1"
+GOOGLE_CSE_MAX_NUM = 10  # Results Google Custom Search returns for a request at most
+GOOGLE_QUOTA_REASONS = ("dailyLimitExceeded", "quotaExceeded", "rateLimitExceeded", "userRateLimitExceeded")
+DUCKDUCKGO_LITE_URL = "https://lite.duckduckgo.com/lite/"  # Needs no API key
+SEARCH_PROVIDERS = ("brave", "google_cse", "searxng", "duckduckgo")  # In the order fallbacks are tried
+SEARCH_MODES = ("web", "news", "images")  # Values of web_search's mode
+SEARCH_FORMATS = ("text", "json")  # Values of web_search's format
+MAX_SEARCH_OFFSET = 90  # Keeps Google's start and num within the 100 results it serves
+BRAVE_MAX_PAGE = 9  # The last page of results Brave serves, counting from 0
+SEARXNG_PAGE_SIZE = 10  # The instance decides; taken as 10 to turn an offset into a page number
+FRESHNESS = ("day", "week", "month", "year")  # Values of web_search's freshness
+SAFESEARCH = ("off", "moderate", "strict")  # Values of web_search's safesearch
+LANG_PATTERN = r"^[A-Za-z]{2,3}(-[A-Za-z0-9]{2,4})?$"  # Language codes web_search accepts
+SEARXNG_JSON_DISABLED = "SearXNG instance does not serve JSON; add json to search.formats in its settings.yml"
+BLOCK_MARKER = "\ue000"  # Private use, so not in the page; marks where a converted block goes back
+SNIFF_BYTES = 8000  # Bytes at the start of a body searched for a NUL, which text never has
+TEXTUAL_TYPES = {
+    "application/json",
+    "application/xml",
+    "application/javascript",
+    "application/ecmascript",
+    "application/x-www-form-urlencoded",
+}
+_META_CHARSET = re.compile(rb"""<meta[^>]*?charset\s*=\s*["']?\s*([a-z0-9_.:-]+)""", re.I)
+
+
+def _check_proxy(proxy: str | None) -> str | None:
+    scheme = urlparse(proxy).scheme if proxy is not None else None
+    if scheme is not None and scheme not in PROXY_SCHEMES:
+        raise ValueError(f"Unsupported proxy scheme '{scheme}', expected one of {', '.join(PROXY_SCHEMES)}")
+    return proxy
+
+
+def _proxy_for(proxy: str | None, url: str) -> str | None:
+    """The explicit proxy for url, unless NO_PROXY lists its host; without one httpx uses *_PROXY itself."""
+    host = (urlparse(url).hostname or "").lower()
+    for entry in os.environ.get("NO_PROXY", os.environ.get("no_proxy", "")).split(","):
+        entry = entry.strip().lower().lstrip(".")
+        if entry == "*" or (entry and (host == entry or host.endswith("." + entry))):
+            return None
+    return proxy
+
+
+def _is_proxy_auth_error(e: Exception) -> bool:
+    """Whether a request failed because the proxy refused our credentials."""
+    message = str(e).lower()
+    return isinstance(e, httpx.ProxyError) and ("407" in message or "auth" in message)
+
+
+def _redact_proxy(text: str, proxy: str | None) -> str:
+    password = urlparse(proxy).password if proxy else None
+    return text.replace(password, REDACTED) if password else text
+
+
+def _check_timeout(timeout_s: float) -> float:
+    if not 0 < timeout_s < math.inf:
+        raise ValueError("timeout_s must be a positive number of seconds")
+    return float(timeout_s)
+
+
+def _call_timeout(default: float, timeout_s: float | None) -> float:
+    """The time limit on a call asking for timeout_s, at most MAX_TIMEOUT_S, or default if it asks for none."""
+    return min(timeout_s, MAX_TIMEOUT_S) if timeout_s is not None and 0 < timeout_s < math.inf else default
+
+
+def _timeout_prop(default: float) -> dict[str, Any]:
+    return {
+        "type": "number",
+        "description": "Seconds each request may take",
+        "exclusiveMinimum": 0,
+        "maximum": MAX_TIMEOUT_S,
+        "default": default,
+    }
+
+
+def _timeout_error(timeout: float) -> str:
+    return f"Timed out after {timeout:g}s"
+
+
+def _is_sensitive(name: str) -> bool:
+    """Whether header name holds a credential: a known one, or one named like a token, secret, password or key."""
+    return name in SENSITIVE_HEADERS or any(w in name for w in ("token", "secret", "password", "api-key", "apikey"))
+
+
+def _parse_headers(headers: dict[str, str]) -> dict[str, str]:
+    """Lowercase header names, refusing invalid ones and those in DENIED_HEADERS."""
+    parsed = {}
+    for name, value in headers.items():
+        key = str(name).strip().lower()
+        if not re.fullmatch(r"[!#$%&'*+.^_`|~0-9a-z-]+", key):
+            raise ValueError(f"Invalid header name '{name}'")
+        if key in DENIED_HEADERS:
+            raise ValueError(f"Header '{key}' cannot be set")
+        if not isinstance(value, str) or "\r" in value or "\n" in value:
+            raise ValueError(f"Invalid value for header '{key}'")
+        parsed[key] = value.strip()
+    return parsed
+
+
+def _redact_secrets(text: str, headers: dict[str, str]) -> str:
+    """text with the values of sensitive headers redacted, for error messages that may quote them."""
+    for name, value in headers.items():
+        if _is_sensitive(name):
+            # A bearer token may be quoted without its scheme
+            for secret in filter(None, (value, value.partition(" ")[2])):
+                text = text.replace(secret, REDACTED)
+    return text
+
+
+def _retry_delay(r: httpx.Response | None, attempt: int) -> float | None:
+    """Seconds to wait before retrying, given the response if any; None if not worth retrying."""
+    if r is not None and r.status_code != 429 and r.status_code < 500:
+        return None
+    delay = RETRY_BASE_DELAY_S * 2 ** (attempt - 1)
+    after = r.headers.get("retry-after", "").strip() if r is not None else ""
+    if after.isdigit():
+        delay = float(after)
+    elif after:
+        try:
+            delay = max(email.utils.parsedate_to_datetime(after).timestamp() - time.time(), 0.0)
+        except (TypeError, ValueError):
+            pass
+    return delay if delay <= RETRY_MAX_DELAY_S else None
+
+
+async def _send_with_retry(
+    client: httpx.AsyncClient, request: httpx.Request, max_retries: int, stream: bool = False
+) -> tuple[httpx.Response | httpx.TransportError, int]:
+    """Send request, retrying timeouts, connection errors, 429s and 5xx; returns the last outcome and attempts."""
+    attempt = 0
+    while True:
+        attempt += 1
+        try:
+            r = await client.send(request, stream=stream)
+        except httpx.TransportError as e:
+            if attempt > max_retries or _is_proxy_auth_error(e):
+                return e, attempt
+            delay = _retry_delay(None, attempt)
+        else:
+            delay = _retry_delay(r, attempt)
+            if attempt > max_retries or delay is None:
+                return r, attempt
+            await r.aclose()
+        await asyncio.sleep(delay)
+
+
+def _registered_domain(host: str) -> str:
+    """The domain host is registered under: the last two labels, or three under suffixes like co.uk."""
+    host = host.rstrip(".").lower()
+    try:
+        ipaddress.ip_address(host.strip("[]"))
+        return host
+    except ValueError:
+        pass
+    labels = host.split(".")
+    keep = 3 if len(labels) >= 2 and len(labels[-1]) == 2 and labels[-2] in SECOND_LEVEL_LABELS else 2
+    return ".".join(labels[-keep:])
+
+
+class _RateLimiter:
+    """A token bucket per registered domain holding a tenth of a minute's requests."""
+
+    def __init__(self, per_minute: float):
+        self.per_minute = per_minute
+        self._buckets: dict[str, tuple[float, float]] = {}  # domain -> (tokens, updated at)
+
+    def _try_acquire(self, domain: str) -> float:
+        """Take a token for domain; returns 0, or the seconds until one is free."""
+        capacity, per_second, now = max(self.per_minute / 10, 1.0), self.per_minute / 60, time.monotonic()
+        tokens, updated = self._buckets.get(domain, (capacity, now))
+        tokens = min(tokens + (now - updated) * per_second, capacity)
+        if tokens >= 1:
+            self._buckets[domain] = (tokens - 1, now)
+            return 0.0
+        self._buckets[domain] = (tokens, now)
+        return (1 - tokens) / per_second
+
+    async def acquire(self, domain: str, wait: bool, max_wait_s: float) -> float:
+        """Take a token, waiting up to max_wait_s if wait; returns 0, or the seconds until one is free."""
+        deadline = time.monotonic() + max_wait_s
+        while retry_after := self._try_acquire(domain):
+            if not wait or time.monotonic() + retry_after > deadline:
+                return retry_after
+            await asyncio.sleep(retry_after)
+        return 0.0
+
+
+_SHARED_LIMITER = _RateLimiter(DEFAULT_REQUESTS_PER_MINUTE)  # Used by every tool without its own rate
 
 
 def _strip_tags(text: str) -> str:
@@ -24,12 +266,667 @@ def _strip_tags(text: str) -> str:
     return html.unescape(text).strip()
 
 
+def _html_to_text(html_text: str) -> str:
+    """Plain text, with a space after table cells and a newline after rows, list items, paragraphs, divs, headings."""
+    text = re.sub(r"</t[dh]\s*>", " ", html_text, flags=re.I)
+    text = re.sub(r"</(?:li|tr|p|div|h[1-6])\s*>|<br\s*/?>", "\n", text, flags=re.I)
+    return _strip_tags(text)
+
+
+def _cell_text(html_text: str) -> str:
+    """A table cell's text: tags removed, whitespace collapsed, pipes escaped; entities are decoded later."""
+    return re.sub(r"\s+", " ", re.sub(r"<[^>]+>", " ", html_text)).strip().replace("|", "\\|")
+
+
+def _span(attrs: str, name: str) -> int:
+    """A colspan or rowspan attribute, 1 if missing or invalid."""
+    m = re.search(rf"""\b{name}\s*=\s*["']?(\d+)""", attrs, flags=re.I)
+    return min(max(int(m[1]), 1), 100) if m else 1
+
+
+def _table_rows(html_text: str) -> list[list[str]]:
+    """Each row's cell texts, with cells a colspan or rowspan covers left blank."""
+    rows: list[list[str]] = []
+    pending: list[int] = []  # Rows still covered by a rowspan from above, by column
+    row_starts = list(re.finditer(r"<tr\b[^>]*>", html_text, flags=re.I))
+    for i, start in enumerate(row_starts):
+        end = row_starts[i + 1].start() if i + 1 < len(row_starts) else len(html_text)
+        row_html = html_text[start.end() : end]
+        cells = list(re.finditer(r"<t[dh]\b([^>]*)>", row_html, flags=re.I))
+        row: list[str] = []
+        for j, cell in enumerate(cells):
+            cell_end = cells[j + 1].start() if j + 1 < len(cells) else len(row_html)
+            text = _cell_text(row_html[cell.end() : cell_end])
+            while len(row) < len(pending) and pending[len(row)] > 0:
+                pending[len(row)] -= 1
+                row.append("")
+            colspan, rowspan = _span(cell[1], "colspan"), _span(cell[1], "rowspan")
+            for k in range(colspan):
+                if len(pending) <= len(row):
+                    pending.extend([0] * (len(row) + 1 - len(pending)))
+                pending[len(row)] = rowspan - 1
+                row.append(text if k == 0 else "")
+        # Columns at the end still covered from above
+        while len(row) < len(pending):
+            pending[len(row)] = max(pending[len(row)] - 1, 0)
+            row.append("")
+        if cells:
+            rows.append(row)
+    return rows
+
+
+def _flatten_table(html_text: str) -> str:
+    """A table nested in another's cell, as its cell texts separated by spaces."""
+    return " " + " ".join(cell for row in _table_rows(html_text) for cell in row if cell) + " "
+
+
+def _table_to_markdown(html_text: str) -> str:
+    """A table as markdown, with its first row, usually of <th> cells, as the header."""
+    rows = _table_rows(html_text)
+    if not rows:
+        return _flatten_table(html_text)
+    width = max(len(row) for row in rows)
+    rows = [row + [""] * (width - len(row)) for row in rows]
+    lines = []
+    caption = re.search(r"<caption[^>]*>([\s\S]*?)</caption>", html_text, flags=re.I)
+    if caption and _cell_text(caption[1]):
+        lines += [_cell_text(caption[1]), ""]
+    lines += [f"| {' | '.join(cells)} |" for cells in (rows[0], ["---"] * width, *rows[1:])]
+    return "\n\n" + "\n".join(lines) + "\n\n"
+
+
+def _convert_tables(html_text: str) -> str:
+    """Convert each <table> to a markdown table, flattening tables nested in another."""
+    while True:
+        lower = html_text.lower()
+        close = lower.find("</table")
+        if close < 0:
+            return html_text
+        close_end = lower.find(">", close) + 1 or len(lower)
+        # The innermost table is the last one opened before the first close
+        open_at = lower.rfind("<table", 0, close)
+        if open_at < 0:
+            html_text = html_text[:close] + html_text[close_end:]  # A stray end tag
+            continue
+        content_start = lower.find(">", open_at, close) + 1 or close
+        nested = lower.count("<table", 0, open_at) > lower.count("</table", 0, open_at)
+        content = html_text[content_start:close]
+        replacement = _flatten_table(content) if nested else _table_to_markdown(content)
+        html_text = html_text[:open_at] + replacement + html_text[close_end:]
+
+
+def _mark_block(blocks: list[str], block: str) -> str:
+    """Store a converted block, returning the marker to put in its place."""
+    blocks.append(block)
+    return f"\n\n{BLOCK_MARKER}{len(blocks) - 1}{BLOCK_MARKER}\n\n"
+
+
+def _restore_blocks(text: str, blocks: list[str]) -> str:
+    """text with its markers replaced by the blocks they stand for."""
+    return re.sub(rf"{BLOCK_MARKER}(\d+){BLOCK_MARKER}", lambda m: blocks[int(m[1])], text)
+
+
+def _extract_code_blocks(html_text: str, blocks: list[str]) -> str:
+    """Replace each <pre> section with a marker for its fenced code block, stored in blocks."""
+
+    def fence(m: re.Match) -> str:
+        code_tag = re.match(r"\s*<code\b([^>]*)>", m[2], flags=re.I)
+        lang = re.search(r"\b(?:language|lang)-([\w+#.-]+)", m[1]) or (
+            code_tag and re.search(r"\b(?:language|lang)-([\w+#.-]+)", code_tag[1])
+        )
+        # Highlighting leaves the code in spans; only entities remain
+        code = html.unescape(re.sub(r"<[^>]+>", "", m[2]))
+        # A newline straight after <pre> is not part of the content
+        code = code.removeprefix("\n").rstrip()
+        # A fence longer than any run of backticks in the code
+        ticks = "`" * (max([2, *(len(run) for run in re.findall(r"`+", code))]) + 1)
+        return _mark_block(blocks, f"{ticks}{lang[1] if lang else ''}\n{code}\n{ticks}")
+
+    return re.sub(r"<pre\b([^>]*)>([\s\S]*?)</pre>", fence, html_text, flags=re.I)
+
+
+def _replace_blocks(html_text: str, replace: Callable[[str, str, str], str]) -> str:
+    """html_text with each outermost list and blockquote replaced by replace(tag, attrs, content)."""
+    out: list[str] = []
+    open_tags: list[str] = []  # Open within the current outermost element
+    element_start = content_start = last = 0
+    attrs = ""
+    for m in re.finditer(r"<(/?)(ul|ol|blockquote)\b([^>]*)>", html_text, flags=re.I):
+        name = m[2].lower()
+        if not m[1]:
+            if not open_tags:
+                element_start, content_start, attrs = m.start(), m.end(), m[3]
+            open_tags.append(name)
+            continue
+        # A close without its open is ignored; one closing an outer tag closes those left open inside it
+        if name not in open_tags:
+            continue
+        del open_tags[len(open_tags) - 1 - open_tags[::-1].index(name) :]
+        if not open_tags:
+            out += [html_text[last:element_start], replace(name, attrs, html_text[content_start : m.start()])]
+            last = m.end()
+    if open_tags:
+        out += [html_text[last:element_start], replace(open_tags[0], attrs, html_text[content_start:])]
+    else:
+        out.append(html_text[last:])
+    return "".join(out)
+
+
+def _list_items(content: str) -> list[str]:
+    """The HTML of each item of a list, with the lists nested in it."""
+    items: list[str] = []
+    depth = 0
+    item_start: int | None = None
+    for m in re.finditer(r"<(/?)(ul|ol|li)\b[^>]*>", content, flags=re.I):
+        closing, name = bool(m[1]), m[2].lower()
+        if name == "li" and depth == 0:
+            # An item whose end tag was left out ends at the next one
+            if item_start is not None:
+                items.append(content[item_start : m.start()])
+            item_start = None if closing else m.end()
+        elif name != "li":
+            depth = max(depth - 1, 0) if closing else depth + 1
+    if item_start is not None:
+        items.append(content[item_start:])
+    return items
+
+
+def _indent_item(text: str, width: int) -> str:
+    """Indent lines after the first by width, dropping blank lines outside code fences so the list stays tight."""
+    lines: list[str] = []
+    in_fence = False
+    for i, line in enumerate(text.splitlines()):
+        if line.lstrip().startswith("```"):
+            in_fence = not in_fence
+        elif not line.strip() and not in_fence:
+            continue
+        lines.append(line if i == 0 or not line else " " * width + line)
+    return "\n".join(lines)
+
+
+def _block_to_markdown(tag: str, attrs: str, content: str, convert: Callable[[str], str]) -> str:
+    """A list or blockquote as markdown; <ol> items are numbered from its start, nested lists indented."""
+    if tag == "blockquote":
+        return "\n".join(f"> {line}" if line else ">" for line in convert(content).splitlines())
+    start = re.search(r"""\bstart\s*=\s*["']?(-?\d+)""", attrs, flags=re.I)
+    number = int(start[1]) if start else 1
+    lines = []
+    for item in _list_items(content):
+        text = convert(item)
+        if not text:
+            continue
+        if tag == "ol":
+            marker = f"{number}. "
+            number += 1
+        else:
+            marker = "- "
+        lines.append(marker + _indent_item(text, len(marker)))
+    return "\n".join(lines)
+
+
+def _inline_code(m: re.Match) -> str:
+    code = re.sub(r"<[^>]+>", "", m[1])
+    return f"`` {code} ``" if "`" in code else f"`{code}`"
+
+
 def _normalize(text: str) -> str:
     """Normalize whitespace."""
     text = re.sub(r"[ \t]+", " ", text)
     return re.sub(r"\n{3,}", "\n\n", text).strip()
 
 
+async def _read_body(r: httpx.Response, max_bytes: int) -> tuple[bytes, bool]:
+    """Read at most max_bytes of a streamed body; returns its bytes and whether the rest was left unread."""
+    declared = r.headers.get("content-length")
+    truncated = declared is not None and declared.isdigit() and int(declared) > max_bytes
+    body = bytearray()
+    async for chunk in r.aiter_bytes():
+        room = max_bytes - len(body)
+        if len(chunk) > room:
+            body += chunk[:room]
+            truncated = True
+            break
+        body += chunk
+    return bytes(body), truncated
+
+
+def _charset_name(label: str | bytes | None) -> str | None:
+    """The codec for a charset label, or None if Python has no such codec."""
+    if not label:
+        return None
+    try:
+        return codecs.lookup(label.decode("ascii") if isinstance(label, bytes) else label.strip("\"' ")).name
+    except (LookupError, UnicodeDecodeError):
+        return None
+
+
+def _decode_body(body: bytes, content_type: str, partial: bool) -> tuple[str, str]:
+    """Decode a body by its BOM, Content-Type charset, <meta> tag, as UTF-8 or sniffed; returns text and codec."""
+    charset = None
+    for bom, name in ((codecs.BOM_UTF8, "utf-8-sig"), (codecs.BOM_UTF16_LE, "utf-16"), (codecs.BOM_UTF16_BE, "utf-16")):
+        if body.startswith(bom):
+            charset = name
+            break
+    if charset is None:
+        params = [p.split("=", 1) for p in content_type.split(";")[1:] if "=" in p]
+        charset = next((_charset_name(v) for k, v in params if k.strip().lower() == "charset"), None)
+    if charset is None and (match := _META_CHARSET.search(body[:META_SCAN_BYTES])):
+        charset = _charset_name(match.group(1))
+        # A page read as ASCII cannot be UTF-16, whatever it says
+        if charset and charset.startswith("utf-16"):
+            charset = "utf-8"
+    if charset is None:
+        try:
+            body.decode("utf-8")
+            charset = "utf-8"
+        except UnicodeDecodeError as e:
+            # Only a character split at the end of a partial body
+            if partial and e.end == len(body) and len(body) - e.start < 4:
+                charset = "utf-8"
+    if charset is None:
+        try:
+            from charset_normalizer import from_bytes
+
+            best = from_bytes(body).best()
+            charset = best.encoding if best else "utf-8"
+        except ImportError:
+            charset = "utf-8"
+    # Drop a character cut off at the end
+    return body.decode(charset, errors="ignore" if partial else "replace"), charset
+
+
+def _is_binary(content_type: str, body: bytes) -> bool:
+    """Whether a body is binary: its type is not textual, or it has a NUL near its start."""
+    essence = content_type.split(";")[0].strip().lower()
+    textual = essence.startswith("text/") or essence.endswith(("+json", "+xml")) or essence in TEXTUAL_TYPES
+    if essence and not textual:
+        return True
+    # UTF-16 text is full of NULs, but starts with a byte order mark
+    if body.startswith((codecs.BOM_UTF8, codecs.BOM_UTF16_LE, codecs.BOM_UTF16_BE)):
+        return False
+    return b"\0" in body[:SNIFF_BYTES]
+
+
+def _resolve_save_path(workspace: Path | None, save_to: str) -> Path:
+    """Where to save a body: relative paths are taken from workspace, and may not lead outside it."""
+    path = Path(save_to.strip())
+    if not save_to.strip():
+        raise ValueError("save_to is empty")
+    if ".." in path.parts:
+        raise ValueError(f"save_to '{save_to}' may not contain '..'")
+    if workspace is None:
+        return path
+    if path.is_absolute() and not path.is_relative_to(workspace):
+        raise ValueError(f"save_to '{save_to}' is outside the workspace {workspace}")
+    return workspace / path
+
+
+def _trim_partial_html(body: str) -> str:
+    """Cut a partial HTML body before a tag, script or style element left open at its end."""
+    end = len(body)
+    open_at = body.rfind("<")
+    if open_at >= 0 and ">" not in body[open_at:]:
+        end = open_at
+    lower = body[:end].lower()
+    for tag in ("script", "style"):
+        open_at = lower.rfind(f"<{tag}")
+        if open_at >= 0 and f"</{tag}" not in lower[open_at:]:
+            end = min(end, open_at)
+    return body[:end]
+
+
+def _estimate_tokens(text: str) -> int:
+    """The number of tokens text is estimated to take, at four bytes of UTF-8 a token."""
+    return -(-len(text.encode()) // 4)
+
+
+def _truncate_tokens(text: str, max_tokens: int) -> str | None:
+    """Cut text to at most max_tokens estimated tokens, ending in the truncation marker, or None if it fits.
+
+    The cut is at the last paragraph break if that keeps at least half of what fits.
+    """
+    if _estimate_tokens(text) <= max_tokens:
+        return None
+    # The longest prefix that fits with the marker
+    low, high = 0, len(text)
+    while low < high:
+        mid = (low + high + 1) // 2
+        if _estimate_tokens(text[:mid] + TRUNCATION_MARKER) <= max_tokens:
+            low = mid
+        else:
+            high = mid - 1
+    paragraph = text.rfind("\n\n", 0, low)
+    # Half measured in bytes, as tokens are estimated from them
+    if paragraph >= 0 and len(text[:paragraph].encode()) >= len(text[:low].encode()) // 2:
+        low = paragraph
+    return text[:low].rstrip() + TRUNCATION_MARKER
+
+
+def _page_metadata(html_text: str, base: str) -> dict[str, str]:
+    """The page's title, description, siteName, author, publishedTime, canonicalUrl, lang and favicon, if present.
+
+    Open Graph tags are preferred to their plain equivalents, and URLs are resolved against base.
+    """
+    import lxml.html
+    from lxml.etree import ParserError
+
+    try:
+        doc = lxml.html.document_fromstring(html_text)
+    except (ValueError, ParserError):
+        return {}
+
+    def meta(*attrs: tuple[str, str]) -> str | None:
+        for attr, value in attrs:
+            for el in doc.iter("meta"):
+                if el.get(attr) == value and (el.get("content") or "").strip():
+                    return el.get("content").strip()
+        return None
+
+    def link(rel: str) -> str | None:
+        for el in doc.iter("link"):
+            if rel in (el.get("rel") or "").lower().split() and el.get("href"):
+                return urljoin(base, el.get("href").strip())
+        return None
+
+    title_el = doc.find(".//title")
+    og_url = meta(("property", "og:url"))
+    fields = {
+        "title": meta(("property", "og:title")) or (title_el is not None and " ".join(title_el.text_content().split())),
+        "description": meta(("property", "og:description"), ("name", "description"), ("name", "twitter:description")),
+        "siteName": meta(("property", "og:site_name"), ("name", "application-name")),
+        "author": meta(("name", "author"), ("property", "article:author")),
+        "publishedTime": meta(("property", "article:published_time"), ("itemprop", "datePublished"), ("name", "date")),
+        "canonicalUrl": link("canonical") or (og_url and urljoin(base, og_url)),
+        "lang": (doc.get("lang") or "").strip(),
+        "favicon": link("icon"),
+    }
+    return {name: value for name, value in fields.items() if value}
+
+
+def _refresh_url(content: str) -> str | None:
+    """The URL of a meta refresh's content, as in 0; url='/next'."""
+    match = re.match(r"[^;,]*[;,]\s*(.*)", content, re.S)
+    if match is None:
+        return None
+    rest = match.group(1)
+    if rest[:3].lower() == "url":
+        rest = rest[3:].lstrip()
+        if not rest.startswith("="):
+            return None
+        rest = rest[1:]
+    url = rest.strip().strip("\"'").strip()
+    return url or None
+
+
+def _refresh_target(html_text: str, base: str) -> str | None:
+    """Where a page sends readers on to without a redirect, resolved against base.
+
+    That is the URL of a <meta http-equiv="refresh">, or else the page's canonical link if that is on another host
+    and the page has next to no text of its own. A URL back to the page itself is ignored.
+    """
+    import lxml.html
+    from lxml.etree import ParserError
+
+    try:
+        doc = lxml.html.document_fromstring(html_text)
+    except (ValueError, ParserError):
+        return None
+    target = None
+    for el in doc.iter("meta"):
+        if (el.get("http-equiv") or "").strip().lower() == "refresh":
+            url = _refresh_url(el.get("content") or "")
+            if url is not None:
+                target = urljoin(base, url)
+                break
+    if target is None:
+        text = doc.xpath("//text()[not(ancestor::head or ancestor::script or ancestor::style or ancestor::noscript)]")
+        if sum(len("".join(t.split())) for t in text) >= NEAR_EMPTY_CHARS:
+            return None
+        for el in doc.iter("link"):
+            if "canonical" in (el.get("rel") or "").lower().split() and el.get("href"):
+                target = urljoin(base, el.get("href").strip())
+                break
+        if target is None or urlparse(target).hostname == urlparse(base).hostname:
+            return None
+    return target if urldefrag(target)[0] != urldefrag(base)[0] else None
+
+
+def _page_links(html_text: str, base: str) -> list[dict[str, Any]]:
+    """The HTTP(S) links of a page as {url, text, rel}, resolved against its <base> or else base.
+
+    Fragments are dropped, and a URL linked more than once is listed once, with the first text found for it.
+    """
+    import lxml.html
+    from lxml.etree import ParserError
+
+    try:
+        doc = lxml.html.document_fromstring(html_text)
+    except (ValueError, ParserError):
+        return []
+    for el in doc.iter("base"):
+        if el.get("href"):
+            base = urljoin(base, el.get("href").strip())
+            break
+
+    links: dict[str, dict[str, Any]] = {}
+    for a in doc.iter("a"):
+        href = (a.get("href") or "").strip()
+        if not href or href.startswith("#"):
+            continue
+        url = urljoin(base, href).split("#", 1)[0]
+        if urlparse(url).scheme not in ("http", "https"):
+            continue
+        text = " ".join(a.text_content().split())
+        # An image link is known by the image's alt text
+        if not text:
+            alts = (img.get("alt", "").strip() for img in a.iter("img"))
+            text = next((alt for alt in alts if alt), None) or (a.get("title") or "").strip()
+        if url in links:
+            links[url]["text"] = links[url]["text"] or text
+            continue
+        links[url] = {"url": url, "text": text, "rel": " ".join((a.get("rel") or "").split()) or None}
+    return list(links.values())
+
+
+def _duckduckgo_url(href: str) -> str | None:
+    """The target of a DuckDuckGo result link, unwrapped from its /l/?uddg= redirect; None for ads and non-HTTP(S)."""
+    url = urljoin(DUCKDUCKGO_LITE_URL, href.strip())
+    parsed = urlparse(url)
+    if (parsed.hostname or "").endswith("duckduckgo.com"):
+        if parsed.path.startswith("/y.js"):
+            return None
+        url = next((v for k, v in parse_qsl(parsed.query) if k == "uddg"), "")
+    return url if urlparse(url).scheme in ("http", "https") else None
+
+
+def _parse_duckduckgo(html_text: str) -> list[dict[str, str | None]]:
+    """The results on a DuckDuckGo lite or HTML results page as {title, url, snippet}, each snippet after its link."""
+    import lxml.html
+    from lxml.etree import ParserError
+
+    try:
+        doc = lxml.html.document_fromstring(html_text)
+    except (ValueError, ParserError):
+        return []
+    hits: list[dict[str, str | None]] = []
+    # Whether the last link was kept, so its snippet is too
+    kept = False
+    for el in doc.iter():
+        if not isinstance(el.tag, str):
+            continue
+        classes = (el.get("class") or "").split()
+        if el.tag == "a" and ("result-link" in classes or "result__a" in classes):
+            sponsored = any("result-sponsored" in (row.get("class") or "").split() for row in el.iterancestors("tr"))
+            url = None if sponsored else _duckduckgo_url(el.get("href") or "")
+            kept = url is not None and all(hit["url"] != url for hit in hits)
+            if kept:
+                hits.append({"title": " ".join(el.text_content().split()), "url": url, "snippet": None})
+        elif kept and ("result-snippet" in classes or "result__snippet" in classes) and hits[-1]["snippet"] is None:
+            hits[-1]["snippet"] = " ".join(el.text_content().split()) or None
+    return hits
+
+
+def _google_cse_error(r: httpx.Response) -> str:
+    """The error for a 403 or 429 from Google Custom Search: running out of quota, told apart, or the API's message."""
+    try:
+        error = r.json().get("error", {})
+    except ValueError:
+        error = {}
+    message = (error.get("message") or "").strip()
+    over_quota = r.status_code == 429 or any(e.get("reason") in GOOGLE_QUOTA_REASONS for e in error.get("errors", []))
+    if over_quota:
+        return f"Google CSE quota exceeded: {message}" if message else "Google CSE quota exceeded"
+    return f"HTTP {r.status_code}: {message}" if message else f"HTTP {r.status_code}"
+
+
+def _search_filters(
+    freshness: str | None, country: str | None, lang: str | None, safesearch: str | None
+) -> dict[str, str]:
+    """The freshness, country, lang and safesearch given, normalized and checked as the schema does."""
+    filters = {}
+    for name, value, values in (("freshness", freshness, FRESHNESS), ("safesearch", safesearch, SAFESEARCH)):
+        if value is not None:
+            value = value.strip().lower()
+            if value not in values:
+                raise ValueError(f"Invalid {name} '{value}', expected one of {', '.join(values)}")
+            filters[name] = value
+    if country is not None:
+        country = country.strip().upper()
+        if not re.fullmatch(r"[A-Z]{2}", country):
+            raise ValueError(f"Invalid country '{country}', expected a two-letter code")
+        filters["country"] = country
+    if lang is not None:
+        lang = lang.strip().lower()
+        if not re.match(LANG_PATTERN, lang):
+            raise ValueError(f"Invalid lang '{lang}', expected a code such as en or pt-br")
+        filters["lang"] = lang
+    return filters
+
+
+def _describe_filters(filters: dict[str, str]) -> str:
+    """The values given besides the query, as " (freshness: week, ...)"."""
+    values = [f"{name}: {filters[name]}" for name in ("freshness", "country", "lang", "safesearch") if name in filters]
+    return f" ({', '.join(values)})" if values else ""
+
+
+def _filter_params(provider: str, filters: dict[str, str], mode: str = "web") -> dict[str, str]:
+    """The query parameters for filters in provider's terms, leaving out those it has no term for."""
+    freshness, country, lang, safesearch = (filters.get(k) for k in ("freshness", "country", "lang", "safesearch"))
+    params = {}
+    if provider == "brave":
+        params = {"freshness": freshness and f"p{freshness[0]}", "country": country, "search_lang": lang}
+        # Image search is either off or strict
+        params["safesearch"] = "strict" if mode == "images" and safesearch == "moderate" else safesearch
+    elif provider == "google_cse":
+        params = {"dateRestrict": freshness and f"{freshness[0]}1", "gl": country and country.lower()}
+        params["lr"] = lang and f"lang_{lang}"
+        params["safe"] = safesearch and ("off" if safesearch == "off" else "active")
+    elif provider == "searxng":
+        params = {"time_range": freshness, "safesearch": safesearch and str(SAFESEARCH.index(safesearch))}
+        params["language"] = lang and (f"{lang}-{country}" if country and "-" not in lang else lang)
+    elif provider == "duckduckgo":
+        # Regions are a country and language, such as de-de
+        region_lang = (lang or country or "").split("-")[0]
+        params = {"df": freshness and freshness[0], "kl": country and f"{country}-{region_lang}".lower()}
+        params["kp"] = safesearch and {"off": "-2", "moderate": "-1"}.get(safesearch, "1")
+    return {k: v for k, v in params.items() if v}
+
+
+def _page_params(provider: str, n: int, offset: int, mode: str) -> tuple[dict[str, Any], int]:
+    """The query parameters that page to offset, and how many results on that page to skip to reach it.
+
+    Brave counts pages of n results, SearXNG pages of its own size, and Google and DuckDuckGo results;
+    Brave's image search has no pages, so is asked for more results instead. Raises ValueError past Brave's last page.
+    """
+    if not offset:
+        return {}, 0
+    if provider == "brave" and mode == "images":
+        return {}, offset
+    if provider == "brave":
+        if offset // n > BRAVE_MAX_PAGE:
+            raise ValueError(f"brave serves no results past {(BRAVE_MAX_PAGE + 1) * n} with count {n}")
+        return {"offset": offset // n}, offset % n
+    if provider == "google_cse":
+        return {"start": offset + 1}, 0
+    if provider == "searxng":
+        return {"pageno": offset // SEARXNG_PAGE_SIZE + 1}, offset % SEARXNG_PAGE_SIZE
+    return {"s": offset, "dc": offset + 1}, 0
+
+
+def _format_hits(
+    query: str, hits: list[dict[str, str | None]], n: int, filters: dict[str, str], mode: str = "web", offset: int = 0
+) -> str:
+    """The first n hits as web_search returns them, headed by the query and the values given with it.
+
+    News comes newest first, each article's age ahead of its title.
+    """
+    if not hits:
+        return f"No results for: {query}{_describe_filters(filters)}"
+    news = mode == "news"
+    lines = [f"{'News for' if news else 'Results for'}: {query}{_describe_filters(filters)}\n"]
+    for i, hit in enumerate(_ranked(hits, n, mode), offset + 1):
+        if news:
+            age = hit.get("age") or hit.get("published") or "age unknown"
+            lines.append(f"{i}. [{age}] {hit['title']}\n   {hit['url']}")
+            if hit.get("source"):
+                lines.append(f"   Source: {hit['source']}")
+        else:
+            lines.append(f"{i}. {hit['title']}\n   {hit['url']}")
+        if hit["snippet"]:
+            lines.append(f"   {hit['snippet']}")
+    return "\n".join(lines)
+
+
+def _brave_image_hits(data: dict[str, Any]) -> list[dict[str, Any]]:
+    """The image results in a Brave image search response.
+
+    Those whose image is not at an HTTP(S) URL, such as inline data: images, are left out.
+    """
+    hits = []
+    for item in data.get("results", []):
+        properties = item.get("properties") or {}
+        image_url, url = (properties.get("url") or "").strip(), (item.get("url") or "").strip()
+        if urlparse(image_url).scheme not in ("http", "https") or not url:
+            continue
+        source = (item.get("source") or "").strip() or (item.get("meta_url") or {}).get("hostname")
+        hits.append(
+            {
+                "title": (item.get("title") or "").strip(),
+                "url": url,
+                "image_url": image_url,
+                "width": properties.get("width"),
+                "height": properties.get("height"),
+                "source": source or urlparse(url).hostname,
+            }
+        )
+    return hits
+
+
+def _ranked(hits: list[dict[str, Any]], n: int, mode: str) -> list[dict[str, Any]]:
+    """The first n hits in the order web_search lists them, to be ranked from the offset. News comes newest first."""
+    if mode == "news":
+        # ISO 8601 timestamps sort as text; undated articles go last
+        hits = sorted(hits, key=lambda hit: hit.get("published") or "", reverse=True)
+    return hits[:n]
+
+
+def _json_results(query: str, provider: str, hits: list[dict[str, Any]], n: int, mode: str, offset: int = 0) -> str:
+    """The first n hits as the JSON web_search returns, every field present and null if unknown.
+
+    Results are {rank, title, url, snippet}, news with its age, published and source too,
+    and images {rank, title, url, imageUrl, width, height, source}.
+    """
+    if mode == "images":
+        fields = ["title", "url", "imageUrl", "width", "height", "source"]
+    else:
+        fields = ["title", "url", "snippet"] + (["age", "published", "source"] if mode == "news" else [])
+    results = [
+        {"rank": i, **{field: hit.get("image_url" if field == "imageUrl" else field) for field in fields}}
+        for i, hit in enumerate(_ranked(hits, n, mode), offset + 1)
+    ]
+    return json.dumps({"query": query, "provider": provider, "results": results})
+
+
 def _validate_url(url: str) -> tuple[bool, str]:
     """Validate URL: must be http(s) with valid domain."""
     try:
@@ -43,8 +940,138 @@ def _validate_url(url: str) -> tuple[bool, str]:
         return False, str(e)
 
 
+class _DomainNotAllowed(Exception):
+    """A URL, or a redirect, to a host the tool may not fetch from."""
+
+    def __init__(self, host: str):
+        super().__init__(f"domain_not_allowed: {host}")
+        self.host = host
+
+    def result(self, url: str) -> dict[str, Any]:
+        message = f"Fetching from {self.host} is not allowed"
+        return {"error": "domain_not_allowed", "message": message, "url": url, "host": self.host}
+
+
+def _normalize_domains(domains: list[str] | None, setting: str) -> list[str]:
+    """Domain names as matched against hosts: lowercase, without dots or a *. at either end."""
+    normalized = []
+    for domain in domains or []:
+        domain = domain.strip().removeprefix("*.").strip(".").lower()
+        if not domain:
+            raise ValueError(f"{setting} entries must be domain names")
+        normalized.append(domain)
+    return normalized
+
+
+def _is_on_domain(host: str, domain: str) -> bool:
+    """Whether host is domain or one of its subdomains."""
+    return host == domain or host.endswith("." + domain)
+
+
+_WIKI_HEADING = re.compile(r"^(={2,6}) *(.+?) *={2,6} *$", re.M)  # A section heading of a plain text extract
+
+
+def _extracts_url(url: str, hosts: list[str]) -> str | None:
+    """The API URL for the plain text of the article at url, a /wiki/ or index.php page of its current revision."""
+    p = urlparse(url)
+    if not any(_is_on_domain((p.hostname or "").rstrip("."), domain) for domain in hosts):
+        return None
+    if p.path.startswith("/wiki/") and not p.query:
+        title = unquote(p.path.removeprefix("/wiki/"))
+    elif p.path == "/w/index.php":
+        query = parse_qsl(p.query)
+        if any(k != "title" and (k, v) != ("action", "view") for k, v in query):
+            return None
+        title = next((v for k, v in query if k == "title"), "")
+    else:
+        return None
+    if not title.strip():
+        return None
+    params = {"action": "query", "prop": "extracts", "explaintext": "1", "exsectionformat": "wiki"}
+    params.update({"redirects": "1", "format": "json", "formatversion": "2", "titles": title})
+    return f"{urljoin(url, '/w/api.php')}?{urlencode(params)}"
+
+
+def _article_text(data: Any, markdown: bool) -> str | None:
+    """The article in an extracts response under its title, sections as markdown headings or bare lines."""
+    try:
+        page = data["query"]["pages"][0]
+        title, extract = page["title"], page["extract"].strip()
+    except (KeyError, IndexError, TypeError, AttributeError):
+        return None
+    if "missing" in page or "invalid" in page or not extract:
+        return None
+    heading = (lambda m: f"{'#' * len(m[1])} {m[2]}") if markdown else (lambda m: m[2])
+    return f"# {title}\n\n{_WIKI_HEADING.sub(heading, extract)}"
+
+
+_SITEMAP_ENTRY = re.compile(r"<(?:[\w.-]+:)?(url|sitemap)\b[^>]*>(.*?)</(?:[\w.-]+:)?(?:url|sitemap)\s*>", re.I | re.S)
+_SITEMAP_LOC = re.compile(r"<(?:[\w.-]+:)?loc\b[^>]*>(.*?)</", re.I | re.S)
+_SITEMAP_LASTMOD = re.compile(r"<(?:[\w.-]+:)?lastmod\b[^>]*>(.*?)</", re.I | re.S)
+
+
+def _sitemap_url(url: str) -> str:
+    """The sitemap to read for url: /sitemap.xml for a site's front page, or else url itself."""
+    p = urlparse(url)
+    return urljoin(url, "/sitemap.xml") if p.path in ("", "/") and not p.query else url
+
+
+def _xml_text(text: str) -> str:
+    """The text of an element, without CDATA markers or entities."""
+    text = text.strip()
+    if text.startswith("<![CDATA[") and text.endswith("]]>"):
+        text = text[9:-3]
+    return html.unescape(text.strip())
+
+
+def _parse_sitemap(xml: str) -> tuple[list[dict], list[dict]]:
+    """The {url, lastmod} pages and sitemaps a sitemap or sitemap index lists, up to any cut in it."""
+    urls: list[dict] = []
+    sitemaps: list[dict] = []
+    for m in _SITEMAP_ENTRY.finditer(xml):
+        loc, lastmod = _SITEMAP_LOC.search(m[2]), _SITEMAP_LASTMOD.search(m[2])
+        if loc is None or not (url := _xml_text(loc[1])):
+            continue
+        entry = {"url": url, "lastmod": (_xml_text(lastmod[1]) or None) if lastmod else None}
+        (sitemaps if m[1].lower() == "sitemap" else urls).append(entry)
+    return urls, sitemaps
+
+
+def _gunzip(body: bytes, max_bytes: int) -> tuple[bytes, bool]:
+    """body unzipped if gzipped, as .xml.gz sitemaps are, up to max_bytes; and whether it was cut short."""
+    if not body.startswith(b"\x1f\x8b"):
+        return body, False
+    decoder = zlib.decompressobj(16 + zlib.MAX_WBITS)
+    unzipped = decoder.decompress(body, max_bytes + 1)
+    # Unfinished if cut short, and what could be read is kept
+    truncated = len(unzipped) > max_bytes or not decoder.eof
+    if not unzipped and truncated:
+        raise gzip.BadGzipFile("gzip body ends early")
+    return unzipped[:max_bytes], truncated
+
+
+def _modified(entry: dict) -> datetime | None:
+    """When a sitemap entry last changed, if it says in a form we can read."""
+    lastmod = entry["lastmod"] or ""
+    try:
+        t = datetime.fromisoformat(lastmod.replace("Z", "+00:00"))
+    except ValueError:
+        try:
+            t = datetime.combine(date.fromisoformat(lastmod[:10]), datetime.min.time())
+        except ValueError:
+            return None
+    return t.astimezone(timezone.utc) if t.tzinfo else t.replace(tzinfo=timezone.utc)
+
+
+def _newest_first(entries: list[dict]) -> list[dict]:
+    """entries by lastmod, the newest first, those without one last."""
+    keyed = [(_modified(e), e) for e in entries]
+    dated = sorted(((m, e) for m, e in keyed if m is not None), key=lambda d: d[0], reverse=True)
+    return [e for _, e in dated] + [e for m, e in keyed if m is None]
+
+
 class WebSearchTool(Tool):
-    """Search the web using Brave Search API."""
+    """Search the web using Brave Search API or DuckDuckGo."""
 
     name = "web_search"
     description = "Search the web. Returns titles, URLs, and snippets."
@@ -58,41 +1085,279 @@ class WebSearchTool(Tool):
                 "minimum": 1,
                 "maximum": 10,
             },
+            "offset": {
+                "type": "integer",
+                "description": "Results to skip, e.g. 10 for results 11-20",
+                "minimum": 0,
+                "maximum": MAX_SEARCH_OFFSET,
+                "default": 0,
+            },
+            "mode": {
+                "type": "string",
+                "description": (
+                    "web; news for recent articles with their age, newest first; "
+                    "or images for a JSON list of image URLs with their sizes"
+                ),
+                "enum": list(SEARCH_MODES),
+                "default": "web",
+            },
+            "format": {
+                "type": "string",
+                "description": "text, or json for {query, provider, results: [{rank, title, url, snippet}]}",
+                "enum": list(SEARCH_FORMATS),
+                "default": "text",
+            },
+            "freshness": {
+                "type": "string",
+                "description": "Only results from the last day, week, month or year",
+                "enum": list(FRESHNESS),
+            },
+            "country": {
+                "type": "string",
+                "description": "Two-letter country code to search from, e.g. DE",
+                "pattern": "^[A-Za-z]{2}$",
+            },
+            "lang": {
+                "type": "string",
+                "description": "Language of the results, e.g. de or pt-br",
+                "pattern": LANG_PATTERN,
+            },
+            "safesearch": {"type": "string", "enum": list(SAFESEARCH)},
+            "timeoutS": _timeout_prop(DEFAULT_SEARCH_TIMEOUT_S),
         },
         "required": ["query"],
     }
 
-    def __init__(self, api_key: str | None = None, max_results: int = 5):
+    def __init__(
+        self,
+        api_key: str | None = None,
+        max_results: int = 5,
+        max_retries: int = DEFAULT_MAX_RETRIES,
+        proxy: str | None = None,
+        provider: str | None = None,
+        fallback: bool = False,
+        searxng_url: str | None = None,
+        google_cse_key: str | None = None,
+        google_cse_cx: str | None = None,
+        timeout_s: float = DEFAULT_SEARCH_TIMEOUT_S,
+    ):
+        self.timeout_s = _check_timeout(timeout_s)
         self.api_key = api_key or os.environ.get("BRAVE_API_KEY", "")
         self.max_results = max_results
+        self.max_retries = max_retries
+        self.proxy = _check_proxy(proxy)
+        self.google_cse_key = (google_cse_key or os.environ.get("GOOGLE_CSE_KEY", "")).strip()
+        self.google_cse_cx = (google_cse_cx or os.environ.get("GOOGLE_CSE_CX", "")).strip()
+        self.searxng_url = (searxng_url or os.environ.get("SEARXNG_URL", "")).strip() or None
+        if self.searxng_url is not None:
+            is_valid, error = _validate_url(self.searxng_url)
+            if not is_valid:
+                raise ValueError(f"Invalid searxng_url: {error}")
+        if provider is None:
+            provider = next(p for p in SEARCH_PROVIDERS if self._is_configured(p))
+        provider = provider.strip().lower()
+        if provider not in SEARCH_PROVIDERS:
+            raise ValueError(f"Unknown search provider '{provider}', expected one of {', '.join(SEARCH_PROVIDERS)}")
+        self.provider = provider
+        self.fallback = fallback
+
+    def _is_configured(self, provider: str) -> bool:
+        """Whether the tool has what provider needs."""
+        return {
+            "brave": bool(self.api_key),
+            "google_cse": bool(self.google_cse_key and self.google_cse_cx),
+            "searxng": self.searxng_url is not None,
+        }.get(provider, True)
 
-    async def execute(self, query: str, count: int | None = None, **kwargs: Any) -> str:
-        if not self.api_key:
-            return "Error: BRAVE_API_KEY not configured"
+    def _providers(self) -> list[str]:
+        """The providers to try, in order: the chosen one and, with fallback, the others that are configured."""
+        providers = [self.provider]
+        if self.fallback:
+            providers += [p for p in SEARCH_PROVIDERS if p != self.provider and self._is_configured(p)]
+        return providers
 
+    async def _search(
+        self,
+        provider: str,
+        query: str,
+        n: int,
+        filters: dict[str, str],
+        mode: str = "web",
+        offset: int = 0,
+        timeout: float = DEFAULT_SEARCH_TIMEOUT_S,
+    ) -> list[dict[str, str | None]] | str:
+        """The results for query from provider, or the error to report.
+
+        News comes from Brave and SearXNG only, images from Brave only.
+        """
+        news, images = mode == "news", mode == "images"
+        if news and provider in ("google_cse", "duckduckgo"):
+            return f"{provider} has no news search"
+        if images and provider != "brave":
+            return f"{provider} has no image search"
+        if provider == "brave" and not self.api_key:
+            return "BRAVE_API_KEY not configured"
+        if provider == "google_cse" and not self._is_configured(provider):
+            return "GOOGLE_CSE_KEY and GOOGLE_CSE_CX not configured"
+        if provider == "searxng" and self.searxng_url is None:
+            return "SEARXNG_URL not configured"
+        try:
+            page, skip = _page_params(provider, n, offset, mode)
+        except ValueError as e:
+            return str(e)
+        attempts = 0
         try:
-            n = min(max(count or self.max_results, 1), 10)
-            async with httpx.AsyncClient() as client:
-                r = await client.get(
-                    "https://api.search.brave.com/res/v1/web/search",
-                    params={"q": query, "count": n},
-                    headers={"Accept": "application/json", "X-Subscription-Token": self.api_key},
-                    timeout=10.0,
-                )
+            if provider == "brave":
+                search_url = {"news": BRAVE_NEWS_URL, "images": BRAVE_IMAGES_URL}.get(mode, BRAVE_URL)
+                # Image search has no offset, so gets the results skipped too
+                params = {"q": query, "count": offset + n if images else n}
+                headers = {"Accept": "application/json", "X-Subscription-Token": self.api_key}
+            elif provider == "google_cse":
+                search_url, headers = GOOGLE_CSE_URL, {}
+                params = {"key": self.google_cse_key, "cx": self.google_cse_cx, "q": query}
+                params["num"] = min(n, GOOGLE_CSE_MAX_NUM)
+            elif provider == "searxng":
+                search_url = self.searxng_url.rstrip("/") + "/search"
+                params, headers = {"q": query, "format": "json"}, {"Accept": "application/json"}
+                if news:
+                    params["categories"] = "news"
+            else:
+                search_url, params, headers = DUCKDUCKGO_LITE_URL, {"q": query}, {"User-Agent": USER_AGENT}
+            async with httpx.AsyncClient(proxy=_proxy_for(self.proxy, search_url)) as client:
+                params.update(_filter_params(provider, filters, mode))
+                params.update(page)
+                request = client.build_request("GET", search_url, params=params, headers=headers, timeout=timeout)
+                r, attempts = await _send_with_retry(client, request, self.max_retries)
+                if isinstance(r, Exception):
+                    raise r
+                if r.status_code == 407:
+                    return f"{PROXY_AUTH_ERROR} (HTTP 407)"
+                # SearXNG refuses formats its settings do not enable
+                if provider == "searxng" and r.status_code == 403:
+                    return SEARXNG_JSON_DISABLED
+                if provider == "google_cse" and r.status_code in (403, 429):
+                    error = _google_cse_error(r)
+                    return f"{error} (after {attempts} attempts)" if attempts > 1 else error
                 r.raise_for_status()
+                # DuckDuckGo answers a 202 with a challenge when it rate limits
+                if provider == "duckduckgo" and r.status_code != 200:
+                    raise httpx.HTTPStatusError(f"HTTP {r.status_code}", request=request, response=r)
 
-            results = r.json().get("web", {}).get("results", [])
-            if not results:
-                return f"No results for: {query}"
-
-            lines = [f"Results for: {query}\n"]
-            for i, item in enumerate(results[:n], 1):
-                lines.append(f"{i}. {item.get('title', '')}\n   {item.get('url', '')}")
-                if desc := item.get("description"):
-                    lines.append(f"   {desc}")
-            return "\n".join(lines)
+            if provider == "duckduckgo":
+                hits = _parse_duckduckgo(r.text)
+            elif provider == "google_cse":
+                hits = [
+                    {
+                        "title": (item.get("title") or "").strip(),
+                        "url": item["link"].strip(),
+                        # Snippets are wrapped at a fixed width
+                        "snippet": " ".join((item.get("snippet") or "").split()) or None,
+                    }
+                    for item in r.json().get("items", [])
+                    if (item.get("link") or "").strip()
+                ]
+            elif provider == "searxng":
+                try:
+                    results = r.json().get("results", [])
+                except ValueError:
+                    return SEARXNG_JSON_DISABLED
+                hits = [
+                    {
+                        "title": (item.get("title") or "").strip(),
+                        "url": item["url"].strip(),
+                        "snippet": (item.get("content") or "").strip() or None,
+                        "published": item.get("publishedDate"),
+                        "source": urlparse(item["url"].strip()).hostname,
+                    }
+                    for item in results
+                    if (item.get("url") or "").strip()
+                ]
+            elif images:
+                hits = _brave_image_hits(r.json())
+            else:
+                # News results are at the top level
+                data = r.json()
+                results = data.get("results", []) if news else data.get("web", {}).get("results", [])
+                hits = [
+                    {
+                        "title": item.get("title", ""),
+                        "url": item.get("url", ""),
+                        "snippet": item.get("description"),
+                        "age": item.get("age"),
+                        "published": item.get("page_age"),
+                        "source": (item.get("meta_url") or {}).get("hostname"),
+                    }
+                    for item in results
+                ]
+            return hits[skip:]
         except Exception as e:
+            error = _timeout_error(timeout) if isinstance(e, httpx.TimeoutException) else str(e)
+            error = _redact_proxy(error, self.proxy)
+            # The Google key is sent in the URL, which errors quote
+            if self.google_cse_key:
+                error = error.replace(self.google_cse_key, REDACTED)
+            if _is_proxy_auth_error(e):
+                return f"{PROXY_AUTH_ERROR}: {error}"
+            return f"{error} (after {attempts} attempts)" if attempts > 1 else error
+
+    async def execute(
+        self,
+        query: str,
+        count: int | None = None,
+        freshness: str | None = None,
+        country: str | None = None,
+        lang: str | None = None,
+        safesearch: str | None = None,
+        mode: str | None = None,
+        format: str | None = None,
+        offset: int | None = None,
+        timeoutS: float | None = None,
+        **kwargs: Any,
+    ) -> str:
+        timeout = _call_timeout(self.timeout_s, timeoutS)
+        n = min(max(count or self.max_results, 1), 10)
+        offset = min(max(offset or 0, 0), MAX_SEARCH_OFFSET)
+        mode, format = (mode or "web").strip().lower(), (format or "text").strip().lower()
+        if mode not in SEARCH_MODES:
+            return f"Error: Invalid mode '{mode}', expected one of {', '.join(SEARCH_MODES)}"
+        if format not in SEARCH_FORMATS:
+            return f"Error: Invalid format '{format}', expected one of {', '.join(SEARCH_FORMATS)}"
+        # Image results are always JSON
+        as_json = format == "json" or mode == "images"
+        if mode == "images" and freshness is not None:
+            return "Error: freshness does not apply to image search"
+        try:
+            filters = _search_filters(freshness, country, lang, safesearch)
+        except ValueError as e:
             return f"Error: {e}"
+        errors = []
+        for provider in self._providers():
+            hits = await self._search(provider, query, n, filters, mode, offset, timeout)
+            if isinstance(hits, str):
+                errors.append((provider, hits))
+            elif as_json:
+                return _json_results(query, provider, hits, n, mode, offset)
+            else:
+                return _format_hits(query, hits, n, filters, mode, offset)
+        # A single provider's error as it is; several, each by name
+        if len(errors) == 1:
+            message = errors[0][1]
+        else:
+            message = "; ".join(f"{provider}: {error}" for provider, error in errors)
+        if as_json:
+            return json.dumps({"query": query, "error": message})
+        return f"Error: {message}"
+
+
+def _structured(execute: Callable[..., Any]) -> Callable[..., Any]:
+    """Have a tool's execute return its JSON parsed, as a dict or list, if the tool has structured_output."""
+
+    @functools.wraps(execute)
+    async def wrapper(self: Any, *args: Any, **kwargs: Any) -> Any:
+        result = await execute(self, *args, **kwargs)
+        return json.loads(result) if self.structured_output else result
+
+    return wrapper
 
 
 class WebFetchTool(Tool):
@@ -104,71 +1369,711 @@ class WebFetchTool(Tool):
         "type": "object",
         "properties": {
             "url": {"type": "string", "description": "URL to fetch"},
-            "extractMode": {"type": "string", "enum": ["markdown", "text"], "default": "markdown"},
+            "extractMode": {
+                "type": "string",
+                "enum": ["markdown", "text", "full", "metadata", "links", "head", "sitemap"],
+                "default": "markdown",
+            },
             "maxChars": {"type": "integer", "minimum": 100},
+            "maxTokens": {
+                "type": "integer",
+                "minimum": 25,
+                "description": "Cut the text to about this many tokens instead of maxChars",
+            },
+            "method": {"type": "string", "enum": list(METHODS), "default": "GET"},
+            "body": {"type": "string", "description": "Request body, for POST and PUT"},
+            "contentType": {
+                "type": "string",
+                "description": "Content-Type of the body; defaults to JSON if it parses as JSON",
+            },
+            "headers": {
+                "type": "object",
+                "description": "Extra request headers, e.g. Accept",
+                "additionalProperties": {"type": "string"},
+            },
+            "save_to": {
+                "type": "string",
+                "description": "Workspace path to save binary content such as images or archives to",
+            },
+            "sameDomainOnly": {
+                "type": "boolean",
+                "description": "With extractMode links, only links on the page's own domain",
+                "default": False,
+            },
+            "filter": {"type": "string", "description": "With extractMode sitemap, only URLs containing this text"},
+            "timeoutS": _timeout_prop(DEFAULT_FETCH_TIMEOUT_S),
         },
         "required": ["url"],
     }
 
-    def __init__(self, max_chars: int = 50000):
+    def __init__(
+        self,
+        max_chars: int = 50000,
+        max_download_bytes: int = DEFAULT_MAX_DOWNLOAD_BYTES,
+        respect_robots: bool = False,
+        cache_ttl_s: float | None = None,
+        cache_max_entries: int = DEFAULT_CACHE_MAX_ENTRIES,
+        rate_limit_per_minute: float | None = None,
+        rate_limit_wait: bool = True,
+        rate_limit_max_wait_s: float = DEFAULT_MAX_WAIT_S,
+        max_retries: int = DEFAULT_MAX_RETRIES,
+        headers: dict[str, str] | None = None,
+        bearer_token: str | None = None,
+        proxy: str | None = None,
+        allow_write_methods: bool = False,
+        workspace: str | None = None,
+        max_base64_bytes: int = DEFAULT_MAX_BASE64_BYTES,
+        max_links: int = DEFAULT_MAX_LINKS,
+        timeout_s: float = DEFAULT_FETCH_TIMEOUT_S,
+        cookie_store: bool = False,
+        conditional_requests: bool = False,
+        allowed_domains: list[str] | None = None,
+        blocked_domains: list[str] | None = None,
+        mediawiki_hosts: list[str] | None = None,
+        structured_output: bool = False,
+    ):
+        self.timeout_s = _check_timeout(timeout_s)
+        # Cookies sites set, sent back on later calls; kept in memory only
+        self._cookies = CookieJar() if cookie_store else None
+        self.conditional_requests = conditional_requests
+        self._allowed_domains = _normalize_domains(allowed_domains, "allowed_domains")
+        self._blocked_domains = _normalize_domains(blocked_domains, "blocked_domains")
+        # Domains of MediaWiki sites, whose articles are fetched from their API; none turns this off
+        hosts = list(DEFAULT_MEDIAWIKI_HOSTS) if mediawiki_hosts is None else mediawiki_hosts
+        self.mediawiki_hosts = _normalize_domains(hosts, "mediawiki_hosts")
+        self.structured_output = structured_output  # Whether execute returns a dict rather than JSON
+        self.proxy = _check_proxy(proxy)
+        self.allow_write_methods = allow_write_methods
+        self.workspace = Path(workspace) if workspace is not None else None
+        self.max_base64_bytes = max_base64_bytes
+        self.max_links = max_links
+        self.headers = _parse_headers(headers or {})
+        if bearer_token is not None:
+            self.headers["authorization"] = f"Bearer {bearer_token.strip()}"
+        if cache_ttl_s is not None and not cache_ttl_s > 0:
+            raise ValueError("cache_ttl_s must be a positive number of seconds")
+        if cache_ttl_s is not None and cache_max_entries <= 0:
+            raise ValueError("cache_max_entries must be positive")
+        if rate_limit_per_minute is not None and not rate_limit_per_minute > 0:
+            raise ValueError("rate_limit_per_minute must be positive")
+        if not rate_limit_max_wait_s >= 0:
+            raise ValueError("rate_limit_max_wait_s must be a non-negative number of seconds")
         self.max_chars = max_chars
+        self.max_download_bytes = max_download_bytes
+        self.respect_robots = respect_robots
+        self.cache_ttl_s = cache_ttl_s
+        self.cache_max_entries = cache_max_entries
+        self._limiter = _SHARED_LIMITER if rate_limit_per_minute is None else _RateLimiter(rate_limit_per_minute)
+        self.rate_limit_wait = rate_limit_wait
+        self.rate_limit_max_wait_s = rate_limit_max_wait_s
+        self.max_retries = max_retries
+        self._robots: dict[str, tuple[float, RobotFileParser]] = {}  # origin -> (fetched at, rules)
+        self._cache: OrderedDict[tuple[str, str], tuple[float, dict]] = OrderedDict()  # Least recently used first
+        # (url, mode) -> (ETag, Last-Modified, page), least recently used first
+        self._validators: OrderedDict[tuple[str, str], tuple[str | None, str | None, dict]] = OrderedDict()
+
+    @property
+    def allowed_domains(self) -> list[str]:
+        """The domains fetching is limited to, if any."""
+        return list(self._allowed_domains)
+
+    @property
+    def blocked_domains(self) -> list[str]:
+        """The domains never fetched from."""
+        return list(self._blocked_domains)
+
+    def _check_domain(self, url: str) -> None:
+        """Raise _DomainNotAllowed unless url's host is on an allowed domain and none blocked."""
+        host = (urlparse(url).hostname or "").rstrip(".").lower()
+        blocked = any(_is_on_domain(host, d) for d in self._blocked_domains)
+        allowed = not self._allowed_domains or any(_is_on_domain(host, d) for d in self._allowed_domains)
+        if blocked or not allowed:
+            raise _DomainNotAllowed(host)
+
+    async def _check_request(self, request: httpx.Request) -> None:
+        """Check the host of each request, redirects included, before it is sent."""
+        self._check_domain(str(request.url))
+
+    def clear_cookies(self) -> None:
+        """Forget the cookies kept with cookie_store."""
+        if self._cookies is not None:
+            self._cookies.clear()
+
+    def _cached(self, url: str, mode: str) -> dict | None:
+        entry = self._cache.get((url, mode))
+        if entry is None or time.monotonic() - entry[0] >= self.cache_ttl_s:
+            self._cache.pop((url, mode), None)
+            return None
+        self._cache.move_to_end((url, mode))
+        return entry[1]
+
+    def _store(self, url: str, mode: str, page: dict) -> None:
+        """Cache page under the requested and the final URL, evicting the least recently used."""
+        for key in ((url, mode), (page["finalUrl"], mode)):
+            self._cache[key] = (time.monotonic(), page)
+            self._cache.move_to_end(key)
+        while len(self._cache) > self.cache_max_entries:
+            self._cache.popitem(last=False)
+
+    def _validated(self, url: str, mode: str) -> tuple[str | None, str | None, dict] | None:
+        entry = self._validators.get((url, mode))
+        if entry is not None:
+            self._validators.move_to_end((url, mode))
+        return entry
+
+    def _store_validators(self, url: str, mode: str, etag: str | None, last_modified: str | None, page: dict) -> None:
+        """Keep the validators page was served with, evicting the least recently used."""
+        self._validators[(url, mode)] = (etag, last_modified, page)
+        self._validators.move_to_end((url, mode))
+        while len(self._validators) > DEFAULT_VALIDATOR_MAX_ENTRIES:
+            self._validators.popitem(last=False)
+
+    @staticmethod
+    def _result(
+        url: str,
+        page: dict,
+        max_chars: int,
+        links: tuple[int, bool],
+        attempts: int,
+        headers: dict[str, str],
+        max_tokens: int | None = None,
+    ) -> str:
+        """The JSON result for page; attempts is the number of requests made for it, none if cached.
+
+        links is the most links to return in links mode, and whether to keep only those on the page's domain.
+        With max_tokens, the text is cut to that many estimated tokens rather than max_chars characters.
+        """
+        if page["extractor"] in ("metadata", "links"):
+            result = {"url": url, "finalUrl": page["finalUrl"], "status": page["status"]}
+            result.update({"extractor": page["extractor"], "cached": attempts == 0, "attempts": attempts})
+            if page["extractor"] == "metadata":
+                result["meta"] = page["meta"]
+            else:
+                max_links, same_domain_only = links
+                found = page["links"]
+                if same_domain_only:
+                    domain = _registered_domain(urlparse(page["finalUrl"]).hostname or "")
+                    found = [
+                        link for link in found if _registered_domain(urlparse(link["url"]).hostname or "") == domain
+                    ]
+                result.update({"totalLinks": len(found), "truncated": len(found) > max_links})
+                result["links"] = found[:max_links]
+            if headers:
+                result["requestHeaders"] = {k: REDACTED if _is_sensitive(k) else v for k, v in headers.items()}
+            return json.dumps(result)
+        text = page["text"]
+        if max_tokens is not None:
+            cut = _truncate_tokens(text, max_tokens)
+            truncated = cut is not None
+            text = cut if cut is not None else text
+        else:
+            truncated = len(text) > max_chars
+            if truncated:
+                text = text[: max_chars - 1] + TRUNCATION_MARKER
+        result = {
+            "url": url,
+            "finalUrl": page["finalUrl"],
+            "status": page["status"],
+            "extractor": page["extractor"],
+            "truncated": truncated or page["downloadTruncated"],
+            "downloadTruncated": page["downloadTruncated"],
+            "charset": page["charset"],
+            "cached": attempts == 0,
+            "attempts": attempts,
+            "length": len(text),
+            "text": text,
+        }
+        if max_tokens is not None:
+            result.update({"estimatedTokens": _estimate_tokens(text), "tokenizer": TOKENIZER})
+        if page.get("meta") is not None:
+            result["meta"] = page["meta"]
+        if headers:
+            result["requestHeaders"] = {k: REDACTED if _is_sensitive(k) else v for k, v in headers.items()}
+        return json.dumps(result)
+
+    def _binary_result(self, url: str, r: httpx.Response, raw: bytes, truncated: bool, save_path: Path | None) -> dict:
+        """The result for a binary body, saved to save_path if given and otherwise returned as base64."""
+        result = {
+            "url": url,
+            "finalUrl": str(r.url),
+            "status": r.status_code,
+            "extractor": "binary",
+            "contentType": r.headers.get("content-type", ""),
+            "bytes": len(raw),
+            "sha256": hashlib.sha256(raw).hexdigest(),
+            "downloadTruncated": truncated,
+            "cached": False,
+        }
+        if save_path is not None:
+            try:
+                save_path.parent.mkdir(parents=True, exist_ok=True)
+                save_path.write_bytes(raw)
+                result["savedTo"] = str(save_path)
+            except OSError as e:
+                result["error"] = f"Error writing file: {e}"
+        else:
+            shown = raw[: self.max_base64_bytes]
+            result["base64"] = base64.b64encode(shown).decode("ascii")
+            result["base64Truncated"] = len(shown) < len(raw)
+        return result
+
+    @staticmethod
+    def _refresh_of(r: httpx.Response, raw: bytes, truncated: bool) -> str | None:
+        """Where the page in r sends readers on to, if it is HTML that does."""
+        ctype = r.headers.get("content-type", "")
+        if _is_binary(ctype, raw) or "application/json" in ctype:
+            return None
+        body, _ = _decode_body(raw, ctype, truncated)
+        if "text/html" not in ctype and not body.lstrip()[:256].lower().startswith(("<!doctype", "<html")):
+            return None
+        return _refresh_target(body, str(r.url))
+
+    def _refresh_error(self, url: str, target: str, hops: list[str]) -> dict[str, Any] | None:
+        """Why the refresh from url through hops to target is not followed, if it is not."""
+        if len(hops) >= MAX_REFRESH_HOPS:
+            error = {"error": "too_many_refreshes", "message": f"Stopped after {len(hops)} refreshes"}
+        elif target == url or target in hops:
+            error = {"error": "refresh_loop", "message": f"Refreshes lead back to {target}"}
+        else:
+            is_valid, error_msg = _validate_url(target)
+            if not is_valid:
+                error = {"error": f"URL validation failed for refresh to {target}: {error_msg}"}
+            else:
+                try:
+                    self._check_domain(target)
+                    return None
+                except _DomainNotAllowed as e:
+                    error = e.result(url)
+        return {**error, "url": url, "refreshTo": target}
+
+    @staticmethod
+    def _head_result(url: str, r: httpx.Response, method: str) -> dict:
+        """The result of a head fetch, without the body; method is GET if a ranged request was sent instead of HEAD."""
+        size = r.headers.get("content-length")
+        if r.status_code == 206:
+            # The whole body's size follows the range
+            size = r.headers.get("content-range", "").rpartition("/")[2]
+        return {
+            "url": url,
+            "finalUrl": str(r.url),
+            "status": r.status_code,
+            "extractor": "head",
+            "method": method,
+            "contentType": r.headers.get("content-type"),
+            "contentLength": int(size) if size and size.isdigit() else None,
+            "lastModified": r.headers.get("last-modified"),
+            "etag": r.headers.get("etag"),
+        }
+
+    async def _mediawiki_page(
+        self, client: httpx.AsyncClient, api_url: str, url: str, mode: str, headers: dict[str, str], max_retries: int
+    ) -> tuple[dict | None, int]:
+        """The page of the article at url from its site's API, None if that fails; and the requests made."""
+        request = client.build_request("GET", api_url, headers=headers)
+        r, attempts = await _send_with_retry(client, request, max_retries, stream=True)
+        if isinstance(r, Exception):
+            return None, attempts
+        try:
+            if not r.is_success:
+                return None, attempts
+            raw, _ = await _read_body(r, self.max_download_bytes)
+        finally:
+            await r.aclose()
+        try:
+            text = _article_text(json.loads(raw), mode == "markdown")
+        except ValueError:
+            text = None
+        if text is None:
+            return None, attempts
+        page = {"finalUrl": url, "status": r.status_code, "extractor": "mediawiki", "downloadTruncated": False}
+        page.update({"charset": "utf-8", "meta": None, "links": [], "text": _normalize(text)})
+        return page, attempts
+
+    async def _read_sitemap(
+        self, client: httpx.AsyncClient, url: str, headers: dict[str, str], timeout: float
+    ) -> tuple[tuple[list[dict], list[dict], bool] | str, int]:
+        """The pages and sitemaps listed at url and whether it was cut short, or why it was not read; and attempts."""
+        request = client.build_request("GET", url, headers=headers)
+        try:
+            r, attempts = await _send_with_retry(client, request, self.max_retries, stream=True)
+        except _DomainNotAllowed as e:
+            return str(e), 1
+        if isinstance(r, httpx.TimeoutException):
+            return _timeout_error(timeout), attempts
+        if isinstance(r, Exception):
+            return _redact_secrets(_redact_proxy(str(r), self.proxy), headers), attempts
+        try:
+            if not r.is_success:
+                return f"HTTP {r.status_code} {r.reason_phrase}", attempts
+            raw, truncated = await _read_body(r, self.max_download_bytes)
+        except httpx.HTTPError as e:
+            return _redact_secrets(_redact_proxy(str(e), self.proxy), headers), attempts
+        finally:
+            await r.aclose()
+        try:
+            xml, unzip_truncated = _gunzip(raw, self.max_download_bytes)
+        except (OSError, zlib.error) as e:
+            return f"Invalid gzip body: {e}", attempts
+        urls, sitemaps = _parse_sitemap(xml.decode("utf-8", "replace"))
+        return (urls, sitemaps, truncated or unzip_truncated), attempts
+
+    async def _fetch_sitemap(
+        self, client: httpx.AsyncClient, url: str, filter: str | None, headers: dict[str, str], timeout: float
+    ) -> dict[str, Any]:
+        """The URLs of url's sitemap and an index's latest sitemaps, newest first, containing filter if given."""
+        sitemap_url = _sitemap_url(url)
+        refusal = await self._refusal(client, sitemap_url)
+        if refusal is not None:
+            return {**refusal, "url": url, "sitemapUrl": sitemap_url}
+        read_top, attempts = await self._read_sitemap(client, sitemap_url, headers, timeout)
+        if isinstance(read_top, str):
+            return {"error": read_top, "url": url, "sitemapUrl": sitemap_url, "attempts": attempts}
+        urls, sitemaps, download_truncated = read_top
+        sitemaps = _newest_first(sitemaps)
+        read, failed = [], []
+        for entry in sitemaps[:MAX_SITEMAPS]:
+            try:
+                self._check_domain(entry["url"])
+            except _DomainNotAllowed:
+                failed.append(entry["url"])
+                continue
+            if not _validate_url(entry["url"])[0] or await self._refusal(client, entry["url"]) is not None:
+                failed.append(entry["url"])
+                continue
+            child, child_attempts = await self._read_sitemap(client, entry["url"], headers, timeout)
+            attempts += child_attempts
+            if isinstance(child, str):
+                failed.append(entry["url"])
+                continue
+            urls += child[0]
+            download_truncated = download_truncated or child[2]
+            read.append(entry["url"])
+        if filter is not None:
+            urls = [entry for entry in urls if filter.lower() in entry["url"].lower()]
+        unique: dict[str, dict] = {}
+        for entry in urls:
+            unique.setdefault(entry["url"], entry)
+        urls = _newest_first(list(unique.values()))
+        result = {"url": url, "sitemapUrl": sitemap_url, "extractor": "sitemap", "totalUrls": len(urls)}
+        result.update({"truncated": len(urls) > self.max_links, "downloadTruncated": download_truncated})
+        result.update({"urls": urls[: self.max_links], "attempts": attempts})
+        if sitemaps:
+            result.update({"sitemaps": read, "totalSitemaps": len(sitemaps)})
+        if failed:
+            result["failedSitemaps"] = failed
+        return result
+
+    async def _refusal(self, client: httpx.AsyncClient, url: str) -> dict[str, Any] | None:
+        """Why url may not be fetched now, if it may not: robots.txt or the domain's rate limit."""
+        if self.respect_robots and not await self._robots_allow(client, url):
+            return {"error": "blocked_by_robots", "robotsUrl": urljoin(url, "/robots.txt")}
+        domain = _registered_domain(urlparse(url).hostname or "")
+        retry_after = await self._limiter.acquire(domain, self.rate_limit_wait, self.rate_limit_max_wait_s)
+        if retry_after:
+            return {"error": "rate_limited", "domain": domain, "retryAfterS": retry_after}
+        return None
 
-    async def execute(self, url: str, extractMode: str = "markdown", maxChars: int | None = None, **kwargs: Any) -> str:
+    async def _robots_allow(self, client: httpx.AsyncClient, url: str) -> bool:
+        """Whether the origin's robots.txt allows url; a missing or failing one allows everything."""
+        p = urlparse(url)
+        origin = f"{p.scheme}://{p.netloc}"
+        cached = self._robots.get(origin)
+        if cached is None or time.monotonic() - cached[0] >= ROBOTS_TTL_S:
+            rules = RobotFileParser()
+            try:
+                robots_url = urljoin(origin, "/robots.txt")
+                async with client.stream("GET", robots_url, headers={"User-Agent": USER_AGENT}, timeout=10.0) as r:
+                    if r.is_success:
+                        rules.parse(_decode_body((await _read_body(r, ROBOTS_MAX_BYTES))[0], "", False)[0].splitlines())
+                    else:
+                        rules.allow_all = True
+            except (httpx.HTTPError, _DomainNotAllowed):
+                rules.allow_all = True
+            cached = self._robots[origin] = (time.monotonic(), rules)
+        return cached[1].can_fetch(USER_AGENT, url)
+
+    @_structured
+    async def execute(
+        self,
+        url: str,
+        extractMode: str = "markdown",
+        maxChars: int | None = None,
+        headers: dict[str, str] | None = None,
+        method: str = "GET",
+        body: str | None = None,
+        contentType: str | None = None,
+        save_to: str | None = None,
+        sameDomainOnly: bool = False,
+        cache_bust: bool = False,
+        timeoutS: float | None = None,
+        maxTokens: int | None = None,
+        filter: str | None = None,
+        **kwargs: Any,
+    ) -> str:
         from readability import Document
 
+        head_only = extractMode == "head"
+        timeout = _call_timeout(min(self.timeout_s, HEAD_TIMEOUT_S) if head_only else self.timeout_s, timeoutS)
         max_chars = maxChars or self.max_chars
+        links = (self.max_links, sameDomainOnly)
+        if maxTokens is not None and maxTokens < 1:
+            return json.dumps({"error": "maxTokens must be positive", "url": url})
+        method = method.strip().upper()
+        if method not in METHODS:
+            error = f"Unsupported method '{method}', expected one of {', '.join(METHODS)}"
+            return json.dumps({"error": error, "url": url})
+        if method in WRITE_METHODS and not self.allow_write_methods:
+            message = f"{method} requests need allow_write_methods"
+            return json.dumps({"error": "method_not_allowed", "message": message, "url": url})
+        if head_only:
+            if method not in ("GET", "HEAD"):
+                return json.dumps({"error": f"extractMode head cannot be used with {method}", "url": url})
+            method = "HEAD"
+        if extractMode == "sitemap" and method != "GET":
+            return json.dumps({"error": f"extractMode sitemap cannot be used with {method}", "url": url})
+        try:
+            save_path = _resolve_save_path(self.workspace, save_to) if save_to is not None else None
+        except ValueError as e:
+            return json.dumps({"error": str(e), "url": url})
 
         # Validate URL before fetching
         is_valid, error_msg = _validate_url(url)
         if not is_valid:
             return json.dumps({"error": f"URL validation failed: {error_msg}", "url": url})
+        try:
+            self._check_domain(url)
+        except _DomainNotAllowed as e:
+            return json.dumps(e.result(url))
 
         try:
-            async with httpx.AsyncClient(follow_redirects=True, max_redirects=MAX_REDIRECTS, timeout=30.0) as client:
-                r = await client.get(url, headers={"User-Agent": USER_AGENT})
-                r.raise_for_status()
+            extra = _parse_headers(headers) if headers is not None else None
+        except ValueError as e:
+            return json.dumps({"error": str(e), "url": url})
+        # Pages fetched with their own headers may depend on them, so bypass the cache
+        use_cache = self.cache_ttl_s is not None and extra is None and method == "GET"
+        use_validators = self.conditional_requests and extra is None and method == "GET"
+        headers = {**self.headers, **(extra or {})}
+
+        if use_cache and not cache_bust:
+            page = self._cached(url, extractMode)
+            if page is not None:
+                return self._result(url, page, max_chars, links, 0, headers, maxTokens)
+        validated = self._validated(url, extractMode) if use_validators and not cache_bust else None
+
+        attempts = 0
+        cookies_sent = None  # Whether a Cookie header went with the request, with cookie_store
+        hops: list[str] = []  # Pages followed from ones sending readers on without a redirect
+
+        def sent(result: dict[str, Any]) -> str:
+            if hops:
+                result["refreshHops"] = hops
+            if cookies_sent is not None:
+                result["cookiesSent"] = cookies_sent
+            return json.dumps(result)
+
+        try:
+            proxy = _proxy_for(self.proxy, url)
+            async with httpx.AsyncClient(
+                follow_redirects=True,
+                max_redirects=MAX_REDIRECTS,
+                timeout=timeout,
+                proxy=proxy,
+                cookies=self._cookies,
+                event_hooks={"request": [self._check_request]},
+            ) as client:
+                request_headers = {"User-Agent": USER_AGENT, **headers}
+                # Sitemap mode checks the sitemaps it reads rather than the page
+                if extractMode == "sitemap":
+                    return json.dumps(await self._fetch_sitemap(client, url, filter, request_headers, timeout))
+                refusal = await self._refusal(client, url)
+                if refusal is not None:
+                    return json.dumps({**refusal, "url": url})
+                if body is not None and contentType is None:
+                    try:
+                        json.loads(body)
+                        contentType = "application/json"
+                    except ValueError:
+                        contentType = "text/plain; charset=utf-8"
+                if contentType is not None:
+                    request_headers["content-type"] = contentType
+                if validated is not None:
+                    etag, last_modified, _ = validated
+                    if etag is not None:
+                        request_headers["if-none-match"] = etag
+                    if last_modified is not None:
+                        request_headers["if-modified-since"] = last_modified
+                request = client.build_request(method, url, headers=request_headers, content=body)
+                if self._cookies is not None:
+                    cookies_sent = "cookie" in request.headers
+                # POST is not idempotent, so never retried
+                max_retries = 0 if method == "POST" else self.max_retries
+                # Articles on MediaWiki sites as plain text from the site's API, or else as any other page
+                api = _extracts_url(url, self.mediawiki_hosts) if method == "GET" else None
+                api_attempts = 0
+                if api is not None and extractMode in ("markdown", "text"):
+                    api_headers = {"User-Agent": USER_AGENT, **headers}
+                    page, api_attempts = await self._mediawiki_page(
+                        client, api, url, extractMode, api_headers, max_retries
+                    )
+                    if page is not None:
+                        if use_cache:
+                            self._store(url, extractMode, page)
+                        result = self._result(url, page, max_chars, links, api_attempts, headers, maxTokens)
+                        return sent(json.loads(result))
+                r, attempts = await _send_with_retry(client, request, max_retries, stream=True)
+                attempts += api_attempts
+                if head_only and isinstance(r, httpx.Response) and r.status_code in (405, 501):
+                    # Ask for the first byte only instead
+                    await r.aclose()
+                    method = "GET"
+                    ranged = client.build_request(method, url, headers={**request_headers, "range": "bytes=0-0"})
+                    r, ranged_attempts = await _send_with_retry(client, ranged, max_retries, stream=True)
+                    attempts += ranged_attempts
+                if isinstance(r, Exception):
+                    raise r
+                if r.status_code == 407:
+                    await r.aclose()
+                    error = f"{PROXY_AUTH_ERROR} (HTTP 407)"
+                    return sent({"error": error, "errorKind": "proxy_auth", "url": url, "attempts": attempts})
+                if head_only:
+                    await r.aclose()
+                    return sent({**self._head_result(url, r, method), "attempts": attempts})
+                if method == "HEAD":
+                    await r.aclose()
+                    response_headers = {k: REDACTED if _is_sensitive(k) else v for k, v in r.headers.items()}
+                    result = {"url": url, "finalUrl": str(r.url), "status": r.status_code, "method": "HEAD"}
+                    return sent({**result, "headers": response_headers, "attempts": attempts})
+                if r.status_code == 304 and validated is not None:
+                    # Unchanged since the validators were stored, so is their page
+                    await r.aclose()
+                    page = validated[2]
+                    if use_cache:
+                        self._store(url, extractMode, page)
+                    result = json.loads(self._result(url, page, max_chars, links, attempts, headers, maxTokens))
+                    return sent({**result, "status": 304, "notModified": True})
+                try:
+                    r.raise_for_status()
+                    raw, download_truncated = await _read_body(r, self.max_download_bytes)
+                finally:
+                    await r.aclose()
+                while (target := self._refresh_of(r, raw, download_truncated)) is not None:
+                    error = self._refresh_error(url, target, hops)
+                    if error is not None:
+                        return sent({**error, "attempts": attempts})
+                    refusal = await self._refusal(client, target)
+                    if refusal is not None:
+                        return sent({**refusal, "url": url, "refreshTo": target, "attempts": attempts})
+                    hops.append(target)
+                    hop = client.build_request("GET", target, headers={"User-Agent": USER_AGENT, **headers})
+                    r, hop_attempts = await _send_with_retry(client, hop, max_retries, stream=True)
+                    attempts += hop_attempts
+                    if isinstance(r, Exception):
+                        raise r
+                    try:
+                        r.raise_for_status()
+                        raw, download_truncated = await _read_body(r, self.max_download_bytes)
+                    finally:
+                        await r.aclose()
 
             ctype = r.headers.get("content-type", "")
+            if _is_binary(ctype, raw):
+                result = self._binary_result(url, r, raw, download_truncated, save_path)
+                if method != "GET":
+                    result["method"] = method
+                return sent({**result, "attempts": attempts})
+            body, charset = _decode_body(raw, ctype, download_truncated)
+            is_html = "application/json" not in ctype and (
+                "text/html" in ctype or body.lstrip()[:256].lower().startswith(("<!doctype", "<html"))
+            )
+            meta = _page_metadata(body, str(r.url)) if is_html else None
 
+            if extractMode in ("metadata", "links"):
+                text, extractor = "", extractMode
             # JSON
-            if "application/json" in ctype:
-                text, extractor = json.dumps(r.json(), indent=2), "json"
+            elif "application/json" in ctype:
+                try:
+                    text, extractor = json.dumps(json.loads(body), indent=2), "json"
+                except ValueError:
+                    text, extractor = body, "raw"
             # HTML
-            elif "text/html" in ctype or r.text[:256].lower().startswith(("<!doctype", "<html")):
-                doc = Document(r.text)
-                content = self._to_markdown(doc.summary()) if extractMode == "markdown" else _strip_tags(doc.summary())
+            elif is_html:
+                page_html = _trim_partial_html(body) if download_truncated else body
+                doc = Document(page_html)
+                # The main content, unless the whole page was asked for
+                if extractMode == "full":
+                    content, extractor = self._to_markdown(page_html), "full"
+                else:
+                    summary = doc.summary()
+                    content = _html_to_text(summary) if extractMode == "text" else self._to_markdown(summary)
+                    extractor = "readability"
                 text = f"# {doc.title()}\n\n{content}" if doc.title() else content
-                extractor = "readability"
             else:
-                text, extractor = r.text, "raw"
+                text, extractor = body, "raw"
 
-            truncated = len(text) > max_chars
-            if truncated:
-                text = text[:max_chars]
-
-            return json.dumps(
-                {
-                    "url": url,
-                    "finalUrl": str(r.url),
-                    "status": r.status_code,
-                    "extractor": extractor,
-                    "truncated": truncated,
-                    "length": len(text),
-                    "text": text,
-                }
-            )
+            page = {
+                "finalUrl": str(r.url),
+                "status": r.status_code,
+                "extractor": extractor,
+                "downloadTruncated": download_truncated,
+                "charset": charset,
+                "meta": meta if meta is not None or extractor != "metadata" else {},
+                "links": _page_links(body, str(r.url)) if is_html and extractor == "links" else [],
+                "text": text,
+            }
+            if use_cache:
+                self._store(url, extractMode, page)
+            etag, last_modified = r.headers.get("etag"), r.headers.get("last-modified")
+            if use_validators and not hops and (etag is not None or last_modified is not None):
+                self._store_validators(url, extractMode, etag, last_modified, page)
+            result = json.loads(self._result(url, page, max_chars, links, attempts, headers, maxTokens))
+            if method != "GET":
+                result["method"] = method
+            return sent(result)
         except Exception as e:
-            return json.dumps({"error": str(e), "url": url})
+            if isinstance(e, _DomainNotAllowed):
+                # Raised at a redirect, after the first request
+                return sent({**e.result(url), "attempts": max(attempts, 1)})
+            if isinstance(e, httpx.TimeoutException):
+                result = {"error": _timeout_error(timeout), "errorKind": "timeout"}
+                return sent({**result, "url": url, "attempts": attempts})
+            error = _redact_secrets(_redact_proxy(str(e), self.proxy), headers)
+            if _is_proxy_auth_error(e):
+                result = {"error": f"{PROXY_AUTH_ERROR}: {error}", "errorKind": "proxy_auth"}
+                return sent({**result, "url": url, "attempts": attempts})
+            return sent({"error": error, "url": url, "attempts": attempts})
 
     def _to_markdown(self, html: str) -> str:
         """Convert HTML to markdown."""
+        blocks: list[str] = []
+        return _restore_blocks(self._markdown_with_blocks(html, blocks, 0), blocks)
+
+    def _markdown_with_blocks(self, html: str, blocks: list[str], depth: int) -> str:
+        """Convert HTML to markdown, leaving code blocks, lists and blockquotes as markers for blocks.
+
+        `depth` counts the lists and blockquotes `html` sits in.
+        """
+        # Code blocks first, so nothing below touches their whitespace
+        text = _extract_code_blocks(html, blocks)
+        text = re.sub(r"<code[^>]*>([\s\S]*?)</code>", _inline_code, text, flags=re.I)
         # Convert links, headings, lists before stripping tags
         text = re.sub(
             r'<a\s+[^>]*href=["\']([^"\']+)["\'][^>]*>([\s\S]*?)</a>',
             lambda m: f"[{_strip_tags(m[2])}]({m[1]})",
-            html,
+            text,
             flags=re.I,
         )
+        # Convert tables, keeping the links in their cells
+        text = _convert_tables(text)
+
+        # Lists and blockquotes, whose content is converted on its own; past
+        # the depth limit they are left to the tag stripping below
+        def convert(fragment: str) -> str:
+            return _restore_blocks(self._markdown_with_blocks(fragment, blocks, depth + 1), blocks)
+
+        if depth < MAX_BLOCK_DEPTH:
+            text = _replace_blocks(
+                text, lambda tag, attrs, content: _mark_block(blocks, _block_to_markdown(tag, attrs, content, convert))
+            )
         text = re.sub(
             r"<h([1-6])[^>]*>([\s\S]*?)</h\1>",
             lambda m: f"\n{'#' * int(m[1])} {_strip_tags(m[2])}\n",
@@ -179,3 +2084,82 @@ class WebFetchTool(Tool):
         text = re.sub(r"</(p|div|section|article)>", "\n\n", text, flags=re.I)
         text = re.sub(r"<(br|hr)\s*/?>", "\n", text, flags=re.I)
         return _normalize(_strip_tags(text))
+
+
+class WebFetchManyTool(Tool):
+    """Fetch several URLs at once, as web_fetch does each."""
+
+    name = "web_fetch_many"
+    description = (
+        "Fetch several URLs at once and extract their readable content. "
+        "Returns a JSON array with each URL's result, as web_fetch gives it."
+    )
+
+    def __init__(
+        self,
+        fetch_tool: WebFetchTool | None = None,
+        max_urls: int = DEFAULT_MAX_URLS,
+        concurrency: int = DEFAULT_FETCH_CONCURRENCY,
+        max_chars_total: int = DEFAULT_MAX_CHARS_TOTAL,
+        structured_output: bool = False,
+    ):
+        if max_urls <= 0 or concurrency <= 0:
+            raise ValueError("max_urls and concurrency must be positive")
+        # Fetches each URL, with its cache, rate limits and other settings
+        self.fetch_tool = fetch_tool or WebFetchTool()
+        self.max_urls = max_urls
+        self.concurrency = concurrency
+        self.max_chars_total = max_chars_total
+        self.structured_output = structured_output  # Whether execute returns a list rather than JSON
+
+    @property
+    def parameters(self) -> dict[str, Any]:
+        return {
+            "type": "object",
+            "properties": {
+                "urls": {
+                    "type": "array",
+                    "description": "URLs to fetch",
+                    "items": {"type": "string"},
+                    "minItems": 1,
+                    "maxItems": self.max_urls,
+                },
+                "extractMode": {
+                    "type": "string",
+                    "enum": ["markdown", "text", "full", "metadata", "links"],
+                    "default": "markdown",
+                },
+                "maxCharsTotal": {
+                    "type": "integer",
+                    "description": "Characters of text for all pages, shared among those fetched",
+                    "minimum": 100,
+                },
+            },
+            "required": ["urls"],
+        }
+
+    @_structured
+    async def execute(
+        self, urls: list[str], extractMode: str = "markdown", maxCharsTotal: int | None = None, **kwargs: Any
+    ) -> str:
+        if not urls or len(urls) > self.max_urls:
+            return json.dumps({"error": f"Expected 1 to {self.max_urls} URLs, got {len(urls)}"})
+        max_chars_total = maxCharsTotal or self.max_chars_total
+        semaphore = asyncio.Semaphore(self.concurrency)
+
+        async def fetch(url: str) -> dict[str, Any]:
+            async with semaphore:
+                try:
+                    result = await self.fetch_tool.execute(url, extractMode, max_chars_total)
+                    return json.loads(result) if isinstance(result, str) else result
+                except Exception as e:
+                    return {"error": str(e), "url": url}
+
+        results = await asyncio.gather(*(fetch(url) for url in urls))
+        # The text budget is shared evenly by the pages fetched without error
+        share = max_chars_total // max(sum("error" not in r for r in results), 1)
+        for result in results:
+            text = result.get("text")
+            if isinstance(text, str) and len(text) > share:
+                result.update({"text": text[: share - 1] + TRUNCATION_MARKER, "length": share, "truncated": True})
+        return json.dumps(results)
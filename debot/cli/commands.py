@@ -269,7 +269,7 @@ def gateway(
     from debot.bus import MessageBus
     from debot.channels.manager import ChannelManager
     from debot.config.loader import get_data_dir, load_config
-    from debot.cron import CronJob, CronService
+    from debot.cron import CronService
     from debot.heartbeat import HeartbeatService
     from debot.providers.litellm_provider import LiteLLMProvider
 
@@ -314,17 +314,17 @@ def gateway(
     )
 
     # Create cron service
-    async def on_cron_job(job: CronJob) -> str | None:
+    async def on_cron_job(job: dict) -> str | None:
         """Execute a cron job through the agent."""
-        response = await agent.process_direct(job.payload.message, session_key=f"cron:{job.id}")
+        response = await agent.process_direct(job["message"], session_key=f"cron:{job['job_id']}")
         # Optionally deliver to channel
-        if job.payload.deliver and job.payload.to:
+        if job["deliver"] and job["to"]:
             from debot.bus import OutboundMessage
 
             await bus.publish_outbound(
                 OutboundMessage(
-                    channel=job.payload.channel or "whatsapp",
-                    chat_id=job.payload.to,
+                    channel=job["channel"] or "whatsapp",
+                    chat_id=job["to"],
                     content=response or "",
                 )
             )
@@ -372,7 +372,7 @@ def gateway(
             console.print("\nShutting down...")
         finally:
             heartbeat.stop()
-            cron.stop()
+            await cron.shutdown(timeout_s=10)
             agent.stop()
             await channels.stop_all()
 
@@ -698,7 +698,7 @@ def cron_list(
     store_path = get_data_dir() / "cron" / "jobs.json"
     service = CronService(store_path)
 
-    jobs = service.list_jobs(include_disabled=all)
+    jobs = asyncio.run(service.list_jobs(include_disabled=all))
 
     if not jobs:
         console.print("No scheduled jobs.")
@@ -767,13 +767,15 @@ def cron_add(
     store_path = get_data_dir() / "cron" / "jobs.json"
     service = CronService(store_path)
 
-    job = service.add_job(
-        name=name,
-        schedule=schedule,
-        message=message,
-        deliver=deliver,
-        to=to,
-        channel=channel,
+    job = asyncio.run(
+        service.add_job(
+            name=name,
+            schedule=schedule,
+            message=message,
+            deliver=deliver,
+            to=to,
+            channel=channel,
+        )
     )
 
     console.print(f"[green]✓[/green] Added job '{job.name}' ({job.id})")
@@ -790,7 +792,7 @@ def cron_remove(
     store_path = get_data_dir() / "cron" / "jobs.json"
     service = CronService(store_path)
 
-    if service.remove_job(job_id):
+    if asyncio.run(service.remove_job(job_id)):
         console.print(f"[green]✓[/green] Removed job {job_id}")
     else:
         console.print(f"[red]Job {job_id} not found[/red]")
@@ -808,7 +810,7 @@ def cron_enable(
     store_path = get_data_dir() / "cron" / "jobs.json"
     service = CronService(store_path)
 
-    job = service.enable_job(job_id, enabled=not disable)
+    job = asyncio.run(service.enable_job(job_id, enabled=not disable))
     if job:
         status = "disabled" if disable else "enabled"
         console.print(f"[green]✓[/green] Job '{job.name}' {status}")
@@ -829,12 +831,17 @@ def cron_run(
     service = CronService(store_path)
 
     async def run():
-        return await service.run_job(job_id, force=force)
+        result = await service.run_job(job_id, force=force)
+        await service.flush()
+        return result
 
-    if asyncio.run(run()):
-        console.print("[green]✓[/green] Job executed")
-    else:
+    result = asyncio.run(run())
+    if not result:
         console.print(f"[red]Failed to run job {job_id}[/red]")
+    elif isinstance(result, dict) and result.get("status") == "error":
+        console.print(f"[red]Job {job_id} failed: {result.get('error')}[/red]")
+    else:
+        console.print("[green]✓[/green] Job executed")
 
 
 # ============================================================================
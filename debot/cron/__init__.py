@@ -1,9 +1,9 @@
 """Cron service for scheduled agent tasks."""
 
 try:
-    from debot_rust import CronJob, CronSchedule, CronService
+    from debot_rust import CronJob, CronJobState, CronPayload, CronSchedule, CronService
 except ImportError:
     from debot.cron._service_py import CronService
-    from debot.cron._types_py import CronJob, CronSchedule
+    from debot.cron._types_py import CronJob, CronJobState, CronPayload, CronSchedule
 
-__all__ = ["CronService", "CronJob", "CronSchedule"]
+__all__ = ["CronService", "CronJob", "CronJobState", "CronPayload", "CronSchedule"]
@@ -0,0 +1,50 @@
+"""Lifecycle events for the cron service."""
+
+import time
+from typing import Any, Callable
+
+from loguru import logger
+
+
+class CronEvents:
+    """Dispatches lifecycle events to the optional `on_event` callback.
+
+    Events are dicts with at least `event` and `ts_ms` keys. Exceptions
+    raised by the callback are swallowed and counted. Without a callback,
+    events fall back to a log line. Events are stamped by `clock`, the
+    service's clock, if given.
+    """
+
+    def __init__(
+        self, callback: Callable[[dict[str, Any]], Any] | None = None, clock: Callable[[], int] | None = None
+    ):
+        self.callback = callback
+        self.clock = clock
+        self.error_count = 0
+
+    def emit(self, event: str, log: str, **fields: Any) -> None:
+        """Emit `event` with extra `fields`; `log` is used when no callback is set."""
+        if not self._send(event, fields):
+            logger.info(f"Cron: {log}")
+
+    def emit_quiet(self, event: str, **fields: Any) -> None:
+        """Emit `event` without a log fallback (for high-frequency events)."""
+        self._send(event, fields)
+
+    def _now_ms(self) -> int:
+        """The time by `clock`; a failing clock is reported by the service as `clock_error`."""
+        if self.clock is not None:
+            try:
+                return int(self.clock())
+            except Exception:
+                pass
+        return int(time.time() * 1000)
+
+    def _send(self, event: str, fields: dict[str, Any]) -> bool:
+        if self.callback is None:
+            return False
+        try:
+            self.callback({"event": event, "ts_ms": self._now_ms(), **fields})
+        except Exception:
+            self.error_count += 1
+        return True
@@ -0,0 +1,98 @@
+"""iCalendar (RFC 5545) rendering of the job schedule."""
+
+from datetime import datetime, timezone
+from typing import Callable
+
+from debot.cron._types_py import CronJob, CronSchedule
+
+DAY_MS = 24 * 60 * 60 * 1000
+HOUR_MS = 60 * 60 * 1000
+MINUTE_MS = 60 * 1000
+# Content lines longer than this many octets are folded
+MAX_LINE_OCTETS = 75
+
+
+def render(
+    jobs: list[CronJob],
+    occurrences: int,
+    now_ms: int,
+    next_occurrences: Callable[[CronSchedule, int, int], list[int]],
+) -> str:
+    """Render enabled jobs as a VCALENDAR.
+
+    `at` jobs become single events and `every` jobs a recurring event. Other
+    schedules cannot be expressed as an RRULE in general, so their next
+    `occurrences` runs are listed as separate events.
+    """
+    lines = ["BEGIN:VCALENDAR", "VERSION:2.0", "PRODID:-//debot//cron//EN", "CALSCALE:GREGORIAN"]
+
+    stamp = _format_utc(now_ms)
+    for job in jobs:
+        first = job.state.next_run_at_ms
+        if not job.enabled or first is None:
+            continue
+        if job.schedule.kind == "at":
+            lines += _event(job, job.id, first, None, stamp)
+        elif job.schedule.kind == "every":
+            every_ms = job.schedule.every_ms
+            rrule = _rrule_for_interval(every_ms) if every_ms is not None else None
+            lines += _event(job, job.id, first, rrule, stamp)
+        else:
+            try:
+                rest = next_occurrences(job.schedule, first, max(occurrences - 1, 0))
+            except ValueError:
+                rest = []
+            for fire in ([first] + rest)[:occurrences]:
+                lines += _event(job, f"{job.id}-{fire}", fire, None, stamp)
+
+    lines.append("END:VCALENDAR")
+    return "".join(_fold(line) for line in lines)
+
+
+def _event(job: CronJob, uid: str, start_ms: int, rrule: str | None, stamp: str) -> list[str]:
+    lines = ["BEGIN:VEVENT", f"UID:{uid}@debot", f"DTSTAMP:{stamp}", f"DTSTART:{_format_utc(start_ms)}"]
+    if rrule is not None:
+        lines.append(f"RRULE:{rrule}")
+    lines.append(f"SUMMARY:{_escape_text(job.name)}")
+    if job.payload.message:
+        lines.append(f"DESCRIPTION:{_escape_text(job.payload.message)}")
+    lines.append("END:VEVENT")
+    return lines
+
+
+def _rrule_for_interval(every_ms: int) -> str:
+    """Express a fixed interval in the coarsest unit that divides it exactly."""
+    if every_ms % DAY_MS == 0:
+        freq, unit = "DAILY", DAY_MS
+    elif every_ms % HOUR_MS == 0:
+        freq, unit = "HOURLY", HOUR_MS
+    elif every_ms % MINUTE_MS == 0:
+        freq, unit = "MINUTELY", MINUTE_MS
+    else:
+        freq, unit = "SECONDLY", 1000
+    return f"FREQ={freq};INTERVAL={max(every_ms // unit, 1)}"
+
+
+def _format_utc(ms: int) -> str:
+    return datetime.fromtimestamp(ms / 1000, timezone.utc).strftime("%Y%m%dT%H%M%SZ")
+
+
+def _escape_text(text: str) -> str:
+    """Escape a TEXT value."""
+    return text.replace("\\", "\\\\").replace(";", "\\;").replace(",", "\\,").replace("\r", "").replace("\n", "\\n")
+
+
+def _fold(line: str) -> str:
+    """A content line, folded at `MAX_LINE_OCTETS` octets."""
+    out = []
+    octets = 0
+    for c in line:
+        size = len(c.encode())
+        if octets + size > MAX_LINE_OCTETS:
+            out.append("\r\n ")
+            # The leading space counts towards the continuation line
+            octets = 1
+        out.append(c)
+        octets += size
+    out.append("\r\n")
+    return "".join(out)
@@ -0,0 +1,50 @@
+"""Execution metrics of a cron service: run, failure, timeout and overlap counts, overall and per job."""
+
+from dataclasses import dataclass, field
+from typing import Any
+
+
+@dataclass
+class _JobCounts:
+    executions: int = 0
+    failures: int = 0
+    timeouts: int = 0
+    skipped_overlap: int = 0
+
+
+@dataclass
+class CronMetrics:
+    executions: int = 0
+    failures: int = 0
+    timeouts: int = 0
+    skipped_overlap: int = 0
+    total_duration_ms: int = 0
+    jobs: dict[str, _JobCounts] = field(default_factory=dict)
+
+    def record_execution(self, job_id: str, duration_ms: int, failed: bool, timed_out: bool) -> None:
+        """Record a finished job execution."""
+        self.executions += 1
+        self.failures += failed
+        self.timeouts += timed_out
+        self.total_duration_ms += max(duration_ms, 0)
+
+        job = self.jobs.setdefault(job_id, _JobCounts())
+        job.executions += 1
+        job.failures += failed
+        job.timeouts += timed_out
+
+    def record_skipped_overlap(self, job_id: str) -> None:
+        """Record a run skipped because the job was already executing."""
+        self.skipped_overlap += 1
+        self.jobs.setdefault(job_id, _JobCounts()).skipped_overlap += 1
+
+    def to_dict(self) -> dict[str, Any]:
+        """The metrics as a dict, with the average run duration."""
+        return {
+            "executions": self.executions,
+            "failures": self.failures,
+            "timeouts": self.timeouts,
+            "skipped_overlap": self.skipped_overlap,
+            "avg_duration_ms": self.total_duration_ms / self.executions if self.executions else None,
+            "jobs": {job_id: vars(counts).copy() for job_id, counts in self.jobs.items()},
+        }
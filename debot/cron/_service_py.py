@@ -1,43 +1,357 @@
 """Cron service for scheduling agent tasks."""
 
 import asyncio
-import json
+import contextlib
+import inspect
+import math
 import time
 import uuid
+from collections import deque
+from dataclasses import dataclass
+from datetime import datetime, timedelta
 from pathlib import Path
 from typing import Any, Callable, Coroutine
 
-from loguru import logger
+from debot.cron import _ics_py as ics
+from debot.cron import _shell_py as shell
+from debot.cron import _webhook_py as webhook
+from debot.cron._events_py import CronEvents
+from debot.cron._metrics_py import CronMetrics
+from debot.cron._store_py import StoreFile, parse_store, store_to_json
+from debot.cron._types_py import CronJob, CronJobState, CronPayload, CronSchedule, CronStore, _parse_tz, _quiet_window
 
-from debot.cron._types_py import CronJob, CronJobState, CronPayload, CronSchedule, CronStore
+
+# How often the store file is polled for external changes
+STORE_WATCH_INTERVAL_MS = 2000
+# Delay used to coalesce successive saves into one write
+SAVE_DEBOUNCE_MS = 250
+# Number of recent runs considered when reporting the slowest job
+RECENT_RUNS_WINDOW = 50
+# Maximum number of occurrences `upcoming_jobs` lists for a single job
+MAX_UPCOMING_PER_JOB = 1000
+# Maximum number of follow-up jobs a single run may trigger in sequence
+MAX_CHAIN_DEPTH = 5
+# Runs missed by at most this long while the service was stopped still fire
+# on start; older ones are dropped and the next run recomputed
+CATCH_UP_WINDOW_MS = 5 * 60 * 1000
+# Default for the longest the scheduler sleeps before re-reading the clock
+DEFAULT_IDLE_INTERVAL_MS = 60_000
+# Drift between wall-clock and monotonic time over one sleep that counts as a
+# clock jump (suspend/resume, NTP step)
+CLOCK_JUMP_THRESHOLD_MS = 5000
+
+# Payload kinds a job can carry
+PAYLOAD_KINDS = ("agent_turn", "system_event", "webhook", "shell")
 
 
 def _now_ms() -> int:
     return int(time.time() * 1000)
 
 
-def _compute_next_run(schedule: CronSchedule, now_ms: int) -> int | None:
-    """Compute next run time in ms."""
+def _parse_cron_expr(expr: str) -> str:
+    """Parse a cron expression into croniter's field order.
+
+    Accepts the standard 5-field form (`"0 9 * * 1-5"`) as well as the
+    6/7-field form with a leading seconds field used by the Rust extension;
+    croniter expects the seconds after the day-of-week field instead.
+    """
+    from croniter import croniter
+
+    expr = expr.strip()
+    fields = expr.split()
+    if not expr.startswith("@") and len(fields) in (6, 7):
+        fields = fields[1:6] + fields[:1] + fields[6:]
+    normalized = " ".join(fields)
+    if not croniter.is_valid(normalized):
+        raise ValueError(f"Invalid cron expression '{expr}'")
+    return normalized
+
+
+def _next_occurrences(schedule: CronSchedule, from_ms: int, count: int) -> list[int]:
+    """Compute up to `count` fire times (epoch ms) strictly after `from_ms`.
+
+    Raises `ValueError` if the schedule is invalid.
+    """
     if schedule.kind == "at":
-        return schedule.at_ms if schedule.at_ms and schedule.at_ms > now_ms else None
+        at = schedule.at_ms
+        return [at] if at is not None and at > from_ms and count > 0 else []
 
     if schedule.kind == "every":
-        if not schedule.every_ms or schedule.every_ms <= 0:
-            return None
-        # Next interval from now
-        return now_ms + schedule.every_ms
+        every = schedule.every_ms
+        if not every or every <= 0:
+            return []
+        return [from_ms + every * i for i in range(1, count + 1)]
+
+    if schedule.kind == "cron":
+        if schedule.expr is None:
+            raise ValueError("Cron schedule requires 'expr'")
+        from croniter import croniter
+
+        expr = _parse_cron_expr(schedule.expr)
+        tz = _parse_tz(schedule.tz)
+        it = croniter(expr, datetime.fromtimestamp(from_ms / 1000, tz))
+        runs: list[int] = []
+        while len(runs) < count:
+            fire = round(it.get_next(float) * 1000)
+            if fire > from_ms:
+                runs.append(fire)
+        return runs
+
+    if schedule.kind in ("daily", "weekly"):
+        return _local_time_occurrences(schedule, from_ms, count)
+
+    raise ValueError(f"Unknown schedule kind '{schedule.kind}'")
+
+
+def _local_time_occurrences(schedule: CronSchedule, from_ms: int, count: int) -> list[int]:
+    """Fire times for `daily`/`weekly` schedules, computed in the schedule's timezone.
+
+    The local wall-clock time is kept across DST changes. A time skipped by
+    a DST jump (e.g. 02:30 on spring-forward day) fires shifted forward by
+    the gap; a repeated time fires on its first occurrence.
+    """
+    tz = _parse_tz(schedule.tz)
+    hour, minute = schedule.hour, schedule.minute or 0
+    if hour is None or not (0 <= hour <= 23 and 0 <= minute <= 59):
+        raise ValueError(f"Invalid time for '{schedule.kind}' schedule")
+    weekdays = (schedule.weekdays or 0) & 0x7F if schedule.kind == "weekly" else 0x7F
+    if weekdays == 0:
+        return []
+
+    day = datetime.fromtimestamp(from_ms / 1000, tz).date()
+    runs: list[int] = []
+    while len(runs) < count:
+        if weekdays & (1 << day.weekday()):
+            # With fold=0, a skipped time resolves with the offset before the
+            # gap and a repeated time resolves to its first occurrence.
+            local = datetime(day.year, day.month, day.day, hour, minute, tzinfo=tz)
+            fire = round(local.timestamp() * 1000)
+            if fire > from_ms:
+                runs.append(fire)
+        day += timedelta(days=1)
+    return runs
+
+
+def _compute_next_run(schedule: CronSchedule, now_ms: int) -> int | None:
+    """Compute next run time in ms.
+
+    With the `"defer"` quiet policy, a run inside the quiet window is moved
+    to the window's end.
+    """
+    try:
+        runs = _next_occurrences(schedule, now_ms, 1)
+        window = _quiet_window(schedule)
+    except ValueError:
+        return None
+    if not runs:
+        return None
+    if window is not None and window.policy == "defer":
+        return window.end_if_inside(runs[0]) or runs[0]
+    return runs[0]
+
+
+def _new_job_id() -> str:
+    """Generate a fresh job ID.
+
+    IDs are full UUIDs; older stores may still hold 8-character IDs.
+    """
+    return str(uuid.uuid4())
+
+
+def _validate_schedule(schedule: CronSchedule, now: int) -> None:
+    """Check that a schedule is well-formed, raising `ValueError` if not."""
+    if schedule.kind == "at" and schedule.at_ms is None:
+        raise ValueError("'at' schedule requires 'atMs'")
+    if schedule.kind == "every" and (schedule.every_ms is None or schedule.every_ms <= 0):
+        raise ValueError("'every' schedule requires a positive 'everyMs'")
+    if schedule.kind in ("daily", "weekly"):
+        if schedule.hour is None or not 0 <= schedule.hour <= 23:
+            raise ValueError(f"'{schedule.kind}' schedule requires an 'hour' between 0 and 23")
+        if schedule.minute is not None and not 0 <= schedule.minute <= 59:
+            raise ValueError("'minute' must be between 0 and 59")
+    if schedule.kind == "weekly" and (schedule.weekdays is None or not 1 <= schedule.weekdays <= 0x7F):
+        raise ValueError("'weekly' schedule requires a 'weekdays' mask between 1 and 127")
+    _quiet_window(schedule)
+    _next_occurrences(schedule, now, 0)
+
+
+def _validate_payload(payload: CronPayload) -> None:
+    """Check the kind and its kind-specific fields, raising `ValueError` if they are invalid."""
+    if payload.kind == "webhook":
+        webhook.validate(payload)
+    elif payload.kind == "shell":
+        shell.validate(payload)
+    elif payload.kind not in PAYLOAD_KINDS:
+        raise ValueError(f"Unknown payload kind '{payload.kind}' (expected one of: {', '.join(PAYLOAD_KINDS)})")
+
+
+def _callback_arg(job: CronJob, pass_job: bool) -> Any:
+    """The argument passed to a job callback."""
+    if pass_job:
+        return job
+    return {
+        "kind": job.payload.kind,
+        "message": job.payload.message,
+        "deliver": job.payload.deliver,
+        "channel": job.payload.channel,
+        "to": job.payload.to,
+        "job_id": job.id,
+        "job_name": job.name,
+    }
+
 
-    if schedule.kind == "cron" and schedule.expr:
+def _parse_import(text: str, now: int) -> list[CronJob]:
+    """Parse and validate an exported job set."""
+    jobs, _ = parse_store(text)
+    for job in jobs:
         try:
-            from croniter import croniter
+            _validate_schedule(job.schedule, now)
+            _validate_payload(job.payload)
+        except ValueError as e:
+            raise ValueError(f"Job '{job.name}': {e}") from None
+    return jobs
 
-            cron = croniter(schedule.expr, time.time())
-            next_time = cron.get_next()
-            return int(next_time * 1000)
-        except Exception:
-            return None
 
-    return None
+def _create_job(
+    name: str,
+    schedule: CronSchedule,
+    payload: CronPayload,
+    delete_after_run: bool,
+    tags: list[str],
+    disable_after_failures: int | None,
+    priority: int,
+    now: int,
+) -> CronJob:
+    """Build a new enabled job with a fresh ID and its first run scheduled after `now`."""
+    return CronJob(
+        id=_new_job_id(),
+        name=name,
+        enabled=True,
+        schedule=schedule,
+        payload=payload,
+        state=CronJobState(next_run_at_ms=_compute_next_run(schedule, now)),
+        created_at_ms=now,
+        updated_at_ms=now,
+        delete_after_run=delete_after_run,
+        tags=tags,
+        disable_after_failures=disable_after_failures,
+        priority=priority,
+    )
+
+
+def _set_chain(job: CronJob, on_success: str | None, on_failure: str | None) -> None:
+    """Set the follow-up jobs. `None` leaves a link unchanged and an empty string clears it."""
+    if on_success is not None:
+        job.on_success_job_id = on_success or None
+    if on_failure is not None:
+        job.on_failure_job_id = on_failure or None
+
+
+def _set_enabled(job: CronJob, enabled: bool, now: int) -> None:
+    """Enable or disable a job, updating its next run accordingly.
+
+    Enabling resets the consecutive failure count.
+    """
+    job.enabled = enabled
+    job.updated_at_ms = now
+    if enabled:
+        job.state.consecutive_failures = 0
+    job.state.snoozed_until_ms = None
+    job.state.next_run_at_ms = _compute_next_run(job.schedule, now) if enabled else None
+
+
+def _resumed_next_run(job: CronJob, now: int) -> int | None:
+    """The next run to resume with when the service starts.
+
+    An active snooze wins. Otherwise the stored next run is kept if it is
+    upcoming or was missed by at most `CATCH_UP_WINDOW_MS`, and recomputed
+    from the schedule if it is missing or older.
+    """
+    until = job.state.snoozed_until_ms
+    if until is not None and until > now:
+        return until
+    next_run = job.state.next_run_at_ms
+    if next_run is not None and next_run >= now - CATCH_UP_WINDOW_MS:
+        return next_run
+    return _compute_next_run(job.schedule, now)
+
+
+def _record_duration(state: CronJobState, duration_ms: int) -> None:
+    """Record the duration of a completed run."""
+    avg = state.avg_duration_ms or 0.0
+    state.run_count += 1
+    state.avg_duration_ms = avg + (duration_ms - avg) / state.run_count
+    state.last_duration_ms = duration_ms
+
+
+def _same_definition(a: CronJob, b: CronJob) -> bool:
+    """Compare user-defined fields, ignoring runtime state and timestamps."""
+    return (
+        a.name == b.name
+        and a.enabled == b.enabled
+        and a.schedule == b.schedule
+        and a.payload == b.payload
+        and a.delete_after_run == b.delete_after_run
+        and a.tags == b.tags
+        and a.disable_after_failures == b.disable_after_failures
+        and a.priority == b.priority
+        and a.on_success_job_id == b.on_success_job_id
+        and a.on_failure_job_id == b.on_failure_job_id
+    )
+
+
+def _merge_external_jobs(
+    current: list[CronJob], incoming: list[CronJob], saved_ids: set[str], now: int
+) -> list[CronJob]:
+    """Merge jobs read from disk into the in-memory set.
+
+    Jobs whose definition is unchanged keep their in-memory runtime state;
+    new or edited jobs get their next run recomputed. Jobs missing from disk
+    are dropped, unless they are not in `saved_ids`: those were created since
+    the last save and are kept, after the jobs from disk.
+    """
+    by_id = {j.id: j for j in current}
+    incoming_ids = {j.id for j in incoming}
+    unsaved = [j for j in current if j.id not in saved_ids and j.id not in incoming_ids]
+    merged = []
+    for job in incoming:
+        existing = by_id.get(job.id)
+        if existing is not None and _same_definition(existing, job):
+            merged.append(existing)
+        else:
+            job.state.snoozed_until_ms = None
+            job.state.next_run_at_ms = _compute_next_run(job.schedule, now) if job.enabled else None
+            merged.append(job)
+    return merged + unsaved
+
+
+@dataclass
+class _RunOutcome:
+    """What running a job's payload produced."""
+
+    error: str | None = None
+    output: str | None = None
+    exit_code: int | None = None
+    timed_out: bool = False
+
+
+@dataclass
+class _RunRecord:
+    """A completed run, kept in the service's recent-runs window."""
+
+    job_id: str
+    job_name: str
+    duration_ms: int
+
+
+# Orderings offered by `list_jobs`
+_SORT_KEYS: dict[str, Callable[[CronJob], Any]] = {
+    # Soonest first, then by descending priority
+    "next_run": lambda j: (j.state.next_run_at_ms or float("inf"), -j.priority),
+    "name": lambda j: j.name,
+    "created": lambda j: j.created_at_ms,
+    "last_run": lambda j: -(j.state.last_run_at_ms or float("-inf")),
+}
 
 
 class CronService:
@@ -46,136 +360,288 @@ class CronService:
     def __init__(
         self,
         store_path: Path,
-        on_job: Callable[[CronJob], Coroutine[Any, Any, str | None]] | None = None,
+        on_job: Callable[[Any], Coroutine[Any, Any, str | None]] | None = None,
+        on_load_error: Callable[[str], Any] | None = None,
+        watch_store: bool = False,
+        on_event: Callable[[dict[str, Any]], Any] | None = None,
+        allow_shell: bool = False,
+        disable_after_failures: int | None = None,
+        idle_interval_ms: int = DEFAULT_IDLE_INTERVAL_MS,
+        pass_job: bool = False,
+        dry_run: bool = False,
+        clock: Callable[[], int] | None = None,
     ):
+        """Create the service and load the existing store.
+
+        If the store cannot be loaded (corrupt, or written by a newer
+        version), `on_load_error(message)` is called, a `store_load_failed`
+        event is emitted, and the file is left untouched: the service will
+        not write to it.
+
+        With `watch_store=True`, the running service polls the store file and
+        merges in jobs added or edited by hand or by another process.
+
+        `on_event(event)` receives lifecycle events as dicts with at least
+        `event` and `ts_ms` keys: `job_started`, `job_finished`, `job_failed`
+        (with `error`), `store_saved`, and others. It must be a regular
+        (non-async) callable.
+
+        Jobs with a `shell` payload run arbitrary commands and are refused
+        unless `allow_shell=True`.
+
+        `disable_after_failures` is the default number of consecutive
+        failures after which a job is disabled; jobs may override it.
+
+        Callbacks receive a dict with the payload's `kind`, `message`,
+        `deliver`, `channel` and `to`, plus `job_id` and `job_name`. Set
+        `pass_job=True` to receive the `CronJob` itself, as older versions did.
+
+        The scheduler never sleeps longer than `idle_interval_ms`, so a wall
+        clock that jumps ahead (e.g. after the machine resumes from suspend)
+        is noticed within that interval. `clock`, a callable returning epoch
+        milliseconds, replaces the system clock the service schedules by.
+
+        With `dry_run=True` jobs go through the motions without side effects:
+        no callback, webhook or command runs. Each run emits a `job_dry_run`
+        event, sets `last_status` to `"dry_run"` and advances the schedule.
+        """
+        if idle_interval_ms <= 0:
+            raise ValueError("idle_interval_ms must be positive")
         self.store_path = store_path
         self.on_job = on_job  # Callback to execute job, returns response text
-        self._store: CronStore | None = None
+        # Callbacks bound to individual jobs. Not persisted.
+        self._job_callbacks: dict[str, Callable[[Any], Any]] = {}
+        self.on_load_error = on_load_error
+        self.watch_store = watch_store
+        self.allow_shell = allow_shell
+        self.disable_after_failures = disable_after_failures
+        self.idle_interval_ms = idle_interval_ms
+        self.pass_job = pass_job  # Pass the CronJob instead of a payload dict
+        self.dry_run = dry_run  # Record runs without running payloads
+        self._clock = clock
+        self._events = CronEvents(on_event, clock)
+        self._recent_runs: deque[_RunRecord] = deque(maxlen=RECENT_RUNS_WINDOW)
+        self._metrics = CronMetrics()
+        self._file = StoreFile(store_path, self._events)
+        self._store = CronStore(jobs=self._load_jobs())
         self._timer_task: asyncio.Task | None = None
+        # A debounced save is scheduled but has not run yet
+        self._save_handle: asyncio.TimerHandle | None = None
+        # Running job executions, awaited by shutdown()
+        self._in_flight: set[asyncio.Task] = set()
+        # IDs of the jobs currently executing, so a job never overlaps with itself
+        self._executing: set[str] = set()
+        self._watch_task: asyncio.Task | None = None
         self._running = False
 
-    def _load_store(self) -> CronStore:
-        """Load jobs from disk."""
-        if self._store:
-            return self._store
+    def _load_jobs(self) -> list[CronJob]:
+        """Load jobs from the store, reporting failures to `on_load_error`."""
+        try:
+            return self._file.load()
+        except ValueError as e:
+            self._report_load_error(str(e))
+            return []
 
-        if self.store_path.exists():
-            try:
-                data = json.loads(self.store_path.read_text())
-                jobs = []
-                for j in data.get("jobs", []):
-                    jobs.append(
-                        CronJob(
-                            id=j["id"],
-                            name=j["name"],
-                            enabled=j.get("enabled", True),
-                            schedule=CronSchedule(
-                                kind=j["schedule"]["kind"],
-                                at_ms=j["schedule"].get("atMs"),
-                                every_ms=j["schedule"].get("everyMs"),
-                                expr=j["schedule"].get("expr"),
-                                tz=j["schedule"].get("tz"),
-                            ),
-                            payload=CronPayload(
-                                kind=j["payload"].get("kind", "agent_turn"),
-                                message=j["payload"].get("message", ""),
-                                deliver=j["payload"].get("deliver", False),
-                                channel=j["payload"].get("channel"),
-                                to=j["payload"].get("to"),
-                            ),
-                            state=CronJobState(
-                                next_run_at_ms=j.get("state", {}).get("nextRunAtMs"),
-                                last_run_at_ms=j.get("state", {}).get("lastRunAtMs"),
-                                last_status=j.get("state", {}).get("lastStatus"),
-                                last_error=j.get("state", {}).get("lastError"),
-                            ),
-                            created_at_ms=j.get("createdAtMs", 0),
-                            updated_at_ms=j.get("updatedAtMs", 0),
-                            delete_after_run=j.get("deleteAfterRun", False),
-                        )
-                    )
-                self._store = CronStore(jobs=jobs)
-            except Exception as e:
-                logger.warning(f"Failed to load cron store: {e}")
-                self._store = CronStore()
-        else:
-            self._store = CronStore()
+    def _report_load_error(self, error: str) -> None:
+        """Emit `store_load_failed` and pass the error to `on_load_error`.
 
-        return self._store
+        The log fallback is only used when neither callback is set.
+        """
+        if not self.on_load_error:
+            self._events.emit("store_load_failed", f"failed to load store: {error}", error=error)
+            return
+        self._events.emit_quiet("store_load_failed", error=error)
+        try:
+            self.on_load_error(error)
+        except Exception as err:
+            self._events.emit("load_error_callback_failed", f"load error callback failed: {err}", error=str(err))
 
     def _save_store(self) -> None:
-        """Save jobs to disk."""
-        if not self._store:
+        """Schedule a save of the job set.
+
+        Saves are debounced: changes made within `SAVE_DEBOUNCE_MS` of each
+        other are written together. Use `_flush_store` to write immediately.
+        """
+        if self._save_handle is not None:
+            return
+        try:
+            loop = asyncio.get_running_loop()
+        except RuntimeError:
+            self._try_flush_store()
             return
+        self._save_handle = loop.call_later(SAVE_DEBOUNCE_MS / 1000, self._try_flush_store)
 
-        self.store_path.parent.mkdir(parents=True, exist_ok=True)
-
-        data = {
-            "version": self._store.version,
-            "jobs": [
-                {
-                    "id": j.id,
-                    "name": j.name,
-                    "enabled": j.enabled,
-                    "schedule": {
-                        "kind": j.schedule.kind,
-                        "atMs": j.schedule.at_ms,
-                        "everyMs": j.schedule.every_ms,
-                        "expr": j.schedule.expr,
-                        "tz": j.schedule.tz,
-                    },
-                    "payload": {
-                        "kind": j.payload.kind,
-                        "message": j.payload.message,
-                        "deliver": j.payload.deliver,
-                        "channel": j.payload.channel,
-                        "to": j.payload.to,
-                    },
-                    "state": {
-                        "nextRunAtMs": j.state.next_run_at_ms,
-                        "lastRunAtMs": j.state.last_run_at_ms,
-                        "lastStatus": j.state.last_status,
-                        "lastError": j.state.last_error,
-                    },
-                    "createdAtMs": j.created_at_ms,
-                    "updatedAtMs": j.updated_at_ms,
-                    "deleteAfterRun": j.delete_after_run,
-                }
-                for j in self._store.jobs
-            ],
-        }
+    def _flush_store(self) -> None:
+        """Write the job set to disk now, if it changed since the last write.
+
+        Write failures are emitted as `store_error` events and raised.
+        """
+        if self._save_handle is not None:
+            self._save_handle.cancel()
+            self._save_handle = None
+        try:
+            written = self._file.save(self._store.jobs)
+        except OSError as e:
+            self._events.emit("store_error", str(e), error=str(e))
+            raise
+        if written:
+            self._events.emit_quiet("store_saved", jobs=len(self._store.jobs))
 
-        self.store_path.write_text(json.dumps(data, indent=2))
+    def _try_flush_store(self) -> None:
+        """Flush the store; failures are already reported through the event callback."""
+        with contextlib.suppress(OSError):
+            self._flush_store()
+
+    def set_callback(self, callback: Callable[[Any], Any] | None) -> None:
+        """Set the callback function."""
+        self.on_job = callback
+
+    async def bind_callback(self, job_id: str, callback: Callable[[Any], Any] | None) -> bool:
+        """Bind `callback` to a single job, overriding the service callback for its runs.
+
+        `callback=None` removes the binding. Bindings are not persisted: after
+        a restart, jobs run with the service callback until bound again.
+        Returns `False` if the job does not exist.
+        """
+        if not any(j.id == job_id for j in self._store.jobs):
+            return False
+        self._bind(job_id, callback)
+        return True
+
+    def _bind(self, job_id: str, callback: Callable[[Any], Any] | None) -> None:
+        if callback is None:
+            self._job_callbacks.pop(job_id, None)
+        else:
+            self._job_callbacks[job_id] = callback
 
     async def start(self) -> None:
         """Start the cron service."""
         self._running = True
-        self._load_store()
-        self._recompute_next_runs()
+        # Write out pending changes before reloading
+        self._try_flush_store()
+        self._store.jobs = self._load_jobs()
+        self._resume_next_runs()
         self._save_store()
         self._arm_timer()
-        logger.info(f"Cron service started with {len(self._store.jobs if self._store else [])} jobs")
+        count = len(self._store.jobs)
+        self._events.emit("service_started", f"service started with {count} jobs", jobs=count)
+
+        if self.watch_store:
+            self._watch_task = asyncio.create_task(self._watch_store_changes())
 
     def stop(self) -> None:
-        """Stop the cron service."""
+        """Stop the cron service.
+
+        Returns immediately; use `shutdown()` to wait for running jobs.
+        """
         self._running = False
         if self._timer_task:
             self._timer_task.cancel()
             self._timer_task = None
+        if self._watch_task:
+            self._watch_task.cancel()
+            self._watch_task = None
 
-    def _recompute_next_runs(self) -> None:
-        """Recompute next run times for all enabled jobs."""
-        if not self._store:
-            return
-        now = _now_ms()
+    def close(self) -> None:
+        """Stop the service and write the store before returning.
+
+        Unlike `shutdown()` this does not wait for running jobs: they are
+        marked `"interrupted"` in the store, and the next `start()` counts
+        them as failed and runs them again.
+
+        Raises `OSError` if the store cannot be written.
+        """
+        self.stop()
+        for job in self._store.jobs:
+            if job.id in self._executing:
+                job.state.last_status = "interrupted"
+                job.state.last_error = "Service closed while the job was running"
+        self._flush_store()
+
+    async def shutdown(self, timeout_s: float | None = None) -> bool:
+        """Stop the service and wait for it to wind down.
+
+        Waits for in-flight job executions (up to `timeout_s` seconds if
+        given), then saves the store. Returns `False` if the timeout expired
+        with jobs still running, `True` otherwise. An infinite `timeout_s`
+        waits like `None`.
+        """
+        if timeout_s is not None and (math.isnan(timeout_s) or timeout_s < 0):
+            raise ValueError("timeout_s must be a non-negative number of seconds")
+        if timeout_s == math.inf:
+            timeout_s = None
+        self.stop()
+        drained = True
+        if self._in_flight:
+            _, pending = await asyncio.wait(set(self._in_flight), timeout=timeout_s)
+            drained = not pending
+
+        self._try_flush_store()
+        self._events.emit("service_stopped", "service stopped", drained=drained)
+        return drained
+
+    def _track(self, coro: Coroutine[Any, Any, Any]) -> asyncio.Task:
+        """Run a job execution as a task that `shutdown()` waits for."""
+        task = asyncio.create_task(coro)
+        self._in_flight.add(task)
+        task.add_done_callback(self._in_flight.discard)
+        return task
+
+    async def _watch_store_changes(self) -> None:
+        """Poll the store file and merge external edits into the in-memory jobs."""
+        while self._running:
+            await asyncio.sleep(STORE_WATCH_INTERVAL_MS / 1000)
+            # The file is about to be replaced by the in-memory jobs anyway
+            if self._save_handle is not None:
+                continue
+            # Taken before polling, which replaces it with the IDs on disk
+            saved_ids = self._file.saved_ids
+            result = self._file.poll_external_change()
+            if isinstance(result, ValueError):
+                self._report_load_error(str(result))
+            elif result is not None:
+                merged = _merge_external_jobs(self._store.jobs, result, saved_ids, self._wall_now_ms())
+                self._store.jobs = merged
+                self._arm_timer()
+                self._events.emit("store_reloaded", "reloaded store after external change", jobs=len(merged))
+                if len(merged) > len(result):
+                    self._save_store()
+
+    def _resume_next_runs(self) -> None:
+        """Resume next runs of enabled jobs, keeping snoozes and recently missed runs."""
+        now = self._wall_now_ms()
         for job in self._store.jobs:
             if job.enabled:
-                job.state.next_run_at_ms = _compute_next_run(job.schedule, now)
+                job.state.next_run_at_ms = _resumed_next_run(job, now)
+                if job.state.snoozed_until_ms is not None and job.state.snoozed_until_ms <= now:
+                    job.state.snoozed_until_ms = None
+                # The lost run counts as a failure and is retried now, unless
+                # that many failures disable the job
+                if job.state.last_status == "interrupted" and not self._record_failure(job, job.state.last_error or ""):
+                    job.state.next_run_at_ms = now
+                    self._events.emit(
+                        "job_interrupted",
+                        f"retrying job '{job.name}' after an interrupted run",
+                        job_id=job.id,
+                        job_name=job.name,
+                    )
+
+    def _wall_now_ms(self) -> int:
+        """The service's current time.
+
+        If `clock` raises, the error is reported as a `clock_error` event and
+        the system clock is read instead.
+        """
+        if self._clock is None:
+            return _now_ms()
+        try:
+            return int(self._clock())
+        except Exception as e:
+            self._events.emit("clock_error", f"Clock failed, reading the system clock: {e}", error=str(e))
+            return _now_ms()
 
     def _get_next_wake_ms(self) -> int | None:
         """Get the earliest next run time across all jobs."""
-        if not self._store:
-            return None
         times = [j.state.next_run_at_ms for j in self._store.jobs if j.enabled and j.state.next_run_at_ms]
         return min(times) if times else None
 
@@ -184,77 +650,394 @@ class CronService:
         if self._timer_task:
             self._timer_task.cancel()
 
-        next_wake = self._get_next_wake_ms()
-        if not next_wake or not self._running:
+        if not self._running:
             return
 
-        delay_ms = max(0, next_wake - _now_ms())
-        delay_s = delay_ms / 1000
+        # Cap the sleep so wall-clock time is re-read regularly
+        next_wake = self._get_next_wake_ms()
+        slept_from = self._wall_now_ms()
+        delay_ms = self.idle_interval_ms
+        if next_wake:
+            delay_ms = min(max(0, next_wake - slept_from), delay_ms)
 
         async def tick():
-            await asyncio.sleep(delay_s)
-            if self._running:
-                await self._on_timer()
+            slept_at = time.monotonic()
+            await asyncio.sleep(delay_ms / 1000)
+            if not self._running:
+                return
+            # The monotonic clock stops while suspended, so a wall clock that
+            # moved much further means a resume or a clock step
+            skew_ms = (self._wall_now_ms() - slept_from) - int((time.monotonic() - slept_at) * 1000)
+            if abs(skew_ms) > CLOCK_JUMP_THRESHOLD_MS:
+                self._events.emit("clock_jump", f"wall clock jumped by {skew_ms} ms, rescanning jobs", skew_ms=skew_ms)
+            await self._on_timer()
 
         self._timer_task = asyncio.create_task(tick())
 
     async def _on_timer(self) -> None:
         """Handle timer tick - run due jobs."""
-        if not self._store:
-            return
-
-        now = _now_ms()
+        now = self._wall_now_ms()
         due_jobs = [
             j for j in self._store.jobs if j.enabled and j.state.next_run_at_ms and now >= j.state.next_run_at_ms
         ]
+        # Highest priority first, then the longest overdue
+        due_jobs.sort(key=lambda j: (-j.priority, j.state.next_run_at_ms))
 
         for job in due_jobs:
-            await self._execute_job(job)
+            if not self._running:
+                break
+            if self._hold_for_quiet_hours(job, self._wall_now_ms()):
+                continue
+            # Shielded so that stop() cancelling the timer does not cut the run short
+            await asyncio.shield(self._track(self._execute_chain(job, dry_run=self.dry_run)))
 
         self._save_store()
         self._arm_timer()
 
-    async def _execute_job(self, job: CronJob) -> None:
-        """Execute a single job."""
-        start_ms = _now_ms()
-        logger.info(f"Cron: executing job '{job.name}' ({job.id})")
+    def _hold_for_quiet_hours(self, job: CronJob, now: int) -> bool:
+        """Keep a due job from running inside its quiet window.
 
+        With the `"defer"` policy the run moves to the end of the window; with
+        `"skip"` it is dropped and recorded as skipped. Returns whether the job
+        was held back.
+        """
         try:
-            if self.on_job:
-                await self.on_job(job)
+            window = _quiet_window(job.schedule)
+        except ValueError:
+            return False
+        quiet_until = window.end_if_inside(now) if window else None
+        if quiet_until is None:
+            return False
 
-            job.state.last_status = "ok"
-            job.state.last_error = None
-            logger.info(f"Cron: job '{job.name}' completed")
+        if window.policy == "defer":
+            job.state.next_run_at_ms = quiet_until
+            self._events.emit(
+                "job_deferred",
+                f"deferred job '{job.name}' until the end of quiet hours",
+                job_id=job.id,
+                job_name=job.name,
+                until_ms=quiet_until,
+            )
+        else:
+            job.state.last_status = "skipped"
+            job.updated_at_ms = now
+            if job.schedule.kind == "at":
+                job.enabled = False
+                job.state.next_run_at_ms = None
+            else:
+                job.state.next_run_at_ms = _compute_next_run(job.schedule, now)
+            self._events.emit(
+                "job_skipped",
+                f"skipped job '{job.name}' during quiet hours",
+                job_id=job.id,
+                job_name=job.name,
+                reason="quiet_hours",
+            )
+        return True
+
+    def _check_payload(self, payload: CronPayload) -> None:
+        """Validate a payload, refusing shell payloads unless enabled."""
+        if payload.kind == "shell" and not self.allow_shell:
+            raise ValueError("Shell payloads are disabled; create the service with allow_shell=True")
+        _validate_payload(payload)
+
+    async def _run_payload(self, job: CronJob) -> _RunOutcome:
+        """Run a job's payload: a webhook, a shell command, or the callback."""
+        kind = job.payload.kind
+        if kind == "webhook":
+            try:
+                return _RunOutcome(output=await webhook.send(job.payload))
+            except TimeoutError as e:
+                return _RunOutcome(error=str(e), output=str(e), timed_out=True)
+            except Exception as e:
+                return _RunOutcome(error=str(e), output=str(e))
+        if kind == "shell" and not self.allow_shell:
+            return _RunOutcome(error="Shell payloads are disabled (allow_shell=False)")
+        if kind == "shell":
+            try:
+                out = await shell.run(job.payload)
+            except TimeoutError as e:
+                return _RunOutcome(error=str(e), timed_out=True)
+            except Exception as e:
+                return _RunOutcome(error=str(e))
+            if out.exit_code == 0:
+                error = None
+            elif out.exit_code is None:
+                error = "Command terminated by signal"
+            else:
+                error = f"Command exited with code {out.exit_code}"
+            return _RunOutcome(error=error, output=out.output, exit_code=out.exit_code)
 
+        # Pass the job to the callback
+        try:
+            callback = self._job_callbacks.get(job.id, self.on_job)
+            if callback:
+                result = callback(_callback_arg(job, self.pass_job))
+                if inspect.isawaitable(result):
+                    await result
         except Exception as e:
+            return _RunOutcome(error=str(e))
+        return _RunOutcome()
+
+    def _skip_overlapping_run(self, job: CronJob, reschedule: bool, start_ms: int) -> dict[str, Any]:
+        """Report a run skipped because the job is still executing.
+
+        A due recurring job moves on to its next run so the scheduler does not
+        retry it in a tight loop; the execution in progress handles one-shot jobs.
+        Nothing moves unless `reschedule` is set, as for a forced manual run.
+        """
+        self._metrics.record_skipped_overlap(job.id)
+        self._events.emit(
+            "job_skipped",
+            f"skipped job '{job.name}': already running",
+            job_id=job.id,
+            job_name=job.name,
+            reason="overlap",
+        )
+        next_run = job.state.next_run_at_ms
+        if reschedule and next_run is not None and next_run <= start_ms:
+            job.state.next_run_at_ms = None if job.schedule.kind == "at" else _compute_next_run(job.schedule, start_ms)
+        return {
+            "status": "skipped",
+            "error": "Job is already running",
+            "duration_ms": None,
+            "started_at_ms": start_ms,
+            "next_run_at_ms": job.state.next_run_at_ms,
+        }
+
+    async def _execute_chain(self, job: CronJob, reschedule: bool = True, dry_run: bool = False) -> dict[str, Any]:
+        """Execute a job, then the follow-up jobs it chains to.
+
+        Each link runs `on_success_job_id` or `on_failure_job_id` of the job
+        before it, regardless of the follow-up's own schedule or enabled
+        state. At most `MAX_CHAIN_DEPTH` follow-ups run, which also breaks
+        cycles. Returns the report of the first job.
+        """
+        first = None
+        current: CronJob | None = job
+        parent: CronJob | None = None
+        depth = 0
+        while current is not None:
+            if depth > MAX_CHAIN_DEPTH:
+                self._events.emit(
+                    "chain_limit_reached",
+                    f"chain from job {job.id} stopped after {MAX_CHAIN_DEPTH} follow-ups",
+                    job_id=job.id,
+                    skipped_job_id=current.id,
+                )
+                break
+            if parent is not None:
+                self._events.emit(
+                    "job_chained",
+                    f"job {parent.id} triggered follow-up {current.id}",
+                    job_id=current.id,
+                    parent_job_id=parent.id,
+                )
+            # Read the links first: a one-shot job may delete itself when it runs
+            on_success, on_failure = current.on_success_job_id, current.on_failure_job_id
+
+            # A follow-up runs off its own schedule, which it keeps
+            report = await self._execute_job(current, reschedule and parent is None, dry_run)
+            if parent is not None:
+                current.state.last_triggered_by = parent.id
+            if first is None:
+                first = report
+
+            next_id = {"ok": on_success, "error": on_failure}.get(report["status"])
+            parent = current
+            current = next((j for j in self._store.jobs if j.id == next_id), None) if next_id else None
+            depth += 1
+        return first
+
+    async def _execute_job(self, job: CronJob, reschedule: bool = True, dry_run: bool = False) -> dict[str, Any]:
+        """Execute a single job, returning its run report.
+
+        If the job is already executing, the run is skipped and reported with
+        status `"skipped"`. Without `reschedule` the next run and enabled
+        state are left alone; with `dry_run` the payload is not run.
+        """
+        start_ms = self._wall_now_ms()
+        if job.id in self._executing:
+            return self._skip_overlapping_run(job, reschedule, start_ms)
+        if dry_run:
+            return self._dry_run_job(job, reschedule, start_ms)
+
+        started = time.monotonic()
+        self._events.emit("job_started", f"executing job '{job.name}' ({job.id})", job_id=job.id, job_name=job.name)
+
+        self._executing.add(job.id)
+        try:
+            outcome = await self._run_payload(job)
+        finally:
+            self._executing.discard(job.id)
+        duration_ms = int((time.monotonic() - started) * 1000)
+        self._metrics.record_execution(job.id, duration_ms, outcome.error is not None, outcome.timed_out)
+        self._recent_runs.append(_RunRecord(job.id, job.name, duration_ms))
+
+        job.state.last_exit_code = outcome.exit_code
+        job.state.last_output = outcome.output
+        _record_duration(job.state, duration_ms)
+        fields = {"job_id": job.id, "job_name": job.name, "duration_ms": duration_ms}
+        auto_disabled = False
+        if outcome.error is None:
+            job.state.last_status = "ok"
+            job.state.last_error = None
+            job.state.consecutive_failures = 0
+            self._events.emit("job_finished", f"job '{job.name}' completed", output=outcome.output, **fields)
+        else:
+            e = outcome.error
             job.state.last_status = "error"
-            job.state.last_error = str(e)
-            logger.error(f"Cron: job '{job.name}' failed: {e}")
+            job.state.last_error = e
+            self._events.emit("job_failed", f"job '{job.name}' failed: {e}", error=e, **fields)
+
+            auto_disabled = self._record_failure(job, e)
+
+        finished_ms = self._wall_now_ms()
+        job.state.last_run_at_ms = start_ms
+        job.state.last_triggered_by = None
+        job.updated_at_ms = finished_ms
+        return {
+            "status": job.state.last_status,
+            "error": outcome.error,
+            "duration_ms": duration_ms,
+            "started_at_ms": start_ms,
+            # Keep auto-disabled jobs, even if one-shot, so the failure stays visible
+            "next_run_at_ms": None if auto_disabled else self._advance_after_run(job, reschedule, finished_ms),
+        }
+
+    def _record_failure(self, job: CronJob, error: str) -> bool:
+        """Count a failed run of `job`, disabling it once its consecutive failures reach its threshold.
 
+        The job's own threshold applies, else the service's. Returns whether
+        the job was disabled.
+        """
+        job.state.consecutive_failures += 1
+        failures = job.state.consecutive_failures
+        threshold = job.disable_after_failures
+        if threshold is None:
+            threshold = self.disable_after_failures or 0
+        if threshold <= 0 or failures < threshold:
+            return False
+        job.enabled = False
+        job.state.next_run_at_ms = None
+        job.state.last_error = f"Auto-disabled after {failures} consecutive failures; last error: {error}"
+        self._events.emit(
+            "job_auto_disabled",
+            f"job '{job.name}' disabled after {failures} consecutive failures",
+            job_id=job.id,
+            job_name=job.name,
+            consecutive_failures=failures,
+            error=error,
+        )
+        return True
+
+    def _dry_run_job(self, job: CronJob, reschedule: bool, start_ms: int) -> dict[str, Any]:
+        """Record a dry run: the job's schedule advances but its payload never runs."""
         job.state.last_run_at_ms = start_ms
-        job.updated_at_ms = _now_ms()
+        job.state.last_status = "dry_run"
+        job.state.last_error = None
+        job.state.last_triggered_by = None
+        job.updated_at_ms = start_ms
+        self._events.emit(
+            "job_dry_run",
+            f"dry run of job '{job.name}' ({job.id})",
+            job_id=job.id,
+            job_name=job.name,
+            kind=job.payload.kind,
+        )
+        return {
+            "status": "dry_run",
+            "error": None,
+            "duration_ms": 0,
+            "started_at_ms": start_ms,
+            "next_run_at_ms": self._advance_after_run(job, reschedule, start_ms),
+        }
 
-        # Handle one-shot jobs
-        if job.schedule.kind == "at":
-            if job.delete_after_run:
-                self._store.jobs = [j for j in self._store.jobs if j.id != job.id]
-            else:
-                job.enabled = False
-                job.state.next_run_at_ms = None
+    def _advance_after_run(self, job: CronJob, reschedule: bool, now: int) -> int | None:
+        """Advance a job's schedule after a run finishing at `now`, returning its next run.
+
+        One-shot jobs are disabled or deleted. Nothing changes unless
+        `reschedule` is set.
+        """
+        if not reschedule:
+            return job.state.next_run_at_ms
+        # A snooze ends once the job runs on schedule
+        job.state.snoozed_until_ms = None
+        if job.schedule.kind != "at":
+            job.state.next_run_at_ms = _compute_next_run(job.schedule, now)
+            return job.state.next_run_at_ms
+        if job.delete_after_run:
+            self._store.jobs = [j for j in self._store.jobs if j.id != job.id]
         else:
-            # Compute next run
-            job.state.next_run_at_ms = _compute_next_run(job.schedule, _now_ms())
+            job.enabled = False
+            job.state.next_run_at_ms = None
+        return None
 
     # ========== Public API ==========
 
-    def list_jobs(self, include_disabled: bool = False) -> list[CronJob]:
-        """List all jobs."""
-        store = self._load_store()
-        jobs = store.jobs if include_disabled else [j for j in store.jobs if j.enabled]
-        return sorted(jobs, key=lambda j: j.state.next_run_at_ms or float("inf"))
+    async def list_jobs(
+        self,
+        include_disabled: bool = False,
+        tags: list[str] | None = None,
+        name_contains: str | None = None,
+        sort_by: str = "next_run",
+        offset: int = 0,
+        limit: int | None = None,
+        with_total: bool = False,
+    ) -> list[CronJob] | tuple[list[CronJob], int]:
+        """List all jobs.
+
+        When `tags` is given, only jobs carrying all of them are returned;
+        `name_contains` keeps jobs whose name contains it, ignoring case.
 
-    def add_job(
+        `sort_by` is `"next_run"` (default; soonest first), `"name"`,
+        `"created"` (oldest first) or `"last_run"` (most recent first). Jobs
+        without the sort time come last. `offset` and `limit` select a page of
+        the sorted list. With `with_total=True` a `(jobs, total)` tuple is
+        returned, where `total` counts all matching jobs before paging.
+        """
+        key = _SORT_KEYS.get(sort_by)
+        if key is None:
+            raise ValueError(f"Invalid sort_by '{sort_by}': expected 'next_run', 'name', 'created' or 'last_run'")
+        tags = tags or []
+        needle = name_contains.lower() if name_contains is not None else None
+        jobs = [
+            j
+            for j in self._store.jobs
+            if (include_disabled or j.enabled)
+            and all(t in j.tags for t in tags)
+            and (needle is None or needle in j.name.lower())
+        ]
+        # The sort is stable, so ties keep insertion order
+        jobs.sort(key=key)
+
+        page = jobs[offset:] if limit is None else jobs[offset : offset + limit]
+        return (page, len(jobs)) if with_total else page
+
+    async def upcoming_jobs(self, within_ms: int, now_ms: int | None = None) -> list[tuple[CronJob, int]]:
+        """List the runs of enabled jobs due within a time window.
+
+        Returns `(job, fire_at_ms)` pairs for every run due within `within_ms`
+        of `now_ms` (default: now), sorted by fire time. Jobs that fire repeatedly in the window are listed once per
+        occurrence, starting from their current next run.
+        """
+        end = (self._wall_now_ms() if now_ms is None else now_ms) + within_ms
+        upcoming: list[tuple[CronJob, int]] = []
+        for job in self._store.jobs:
+            fire = job.state.next_run_at_ms
+            if not job.enabled or fire is None or fire > end:
+                continue
+            for _ in range(MAX_UPCOMING_PER_JOB):
+                upcoming.append((job, fire))
+                nxt = _compute_next_run(job.schedule, fire)
+                if nxt is None or nxt <= fire or nxt > end:
+                    break
+                fire = nxt
+
+        upcoming.sort(key=lambda pair: (pair[1], -pair[0].priority))
+        return upcoming
+
+    async def add_job(
         self,
         name: str,
         schedule: CronSchedule,
@@ -263,84 +1046,409 @@ class CronService:
         channel: str | None = None,
         to: str | None = None,
         delete_after_run: bool = False,
+        tags: list[str] | None = None,
+        payload: CronPayload | None = None,
+        disable_after_failures: int | None = None,
+        priority: int | None = None,
+        on_success_job_id: str | None = None,
+        on_failure_job_id: str | None = None,
+        callback: Callable[[Any], Any] | None = None,
     ) -> CronJob:
-        """Add a new job."""
-        store = self._load_store()
-        now = _now_ms()
-
-        job = CronJob(
-            id=str(uuid.uuid4())[:8],
-            name=name,
-            enabled=True,
-            schedule=schedule,
-            payload=CronPayload(
-                kind="agent_turn",
-                message=message,
-                deliver=deliver,
-                channel=channel,
-                to=to,
-            ),
-            state=CronJobState(next_run_at_ms=_compute_next_run(schedule, now)),
-            created_at_ms=now,
-            updated_at_ms=now,
-            delete_after_run=delete_after_run,
+        """Add a new job.
+
+        `payload` replaces the agent-turn payload built from `message`,
+        `deliver`, `channel` and `to`, e.g. for webhook jobs.
+
+        `callback` handles this job's runs instead of the service callback;
+        see `bind_callback`.
+        """
+        if payload is None:
+            payload = CronPayload(kind="agent_turn", message=message, deliver=deliver, channel=channel, to=to)
+        _validate_schedule(schedule, self._wall_now_ms())
+        self._check_payload(payload)
+        job = _create_job(
+            name,
+            schedule,
+            payload,
+            delete_after_run,
+            list(tags or []),
+            disable_after_failures,
+            priority or 0,
+            self._wall_now_ms(),
         )
+        _set_chain(job, on_success_job_id, on_failure_job_id)
+        if callback is not None:
+            self._bind(job.id, callback)
 
-        store.jobs.append(job)
+        self._store.jobs.append(job)
         self._save_store()
         self._arm_timer()
 
-        logger.info(f"Cron: added job '{name}' ({job.id})")
+        self._events.emit("job_added", f"added job '{name}' ({job.id})", job_id=job.id, job_name=name)
         return job
 
-    def remove_job(self, job_id: str) -> bool:
+    async def upsert_job(
+        self,
+        name: str,
+        schedule: CronSchedule,
+        message: str,
+        deliver: bool = False,
+        channel: str | None = None,
+        to: str | None = None,
+        delete_after_run: bool = False,
+        tags: list[str] | None = None,
+        payload: CronPayload | None = None,
+        disable_after_failures: int | None = None,
+        priority: int | None = None,
+        on_success_job_id: str | None = None,
+        on_failure_job_id: str | None = None,
+    ) -> tuple[CronJob, bool]:
+        """Add a job, or update the enabled job with exactly the same name.
+
+        An existing job keeps its ID, creation time and run history; its
+        schedule, payload and `delete_after_run` are replaced, and `tags`,
+        `disable_after_failures` and `priority` are replaced when given.
+        Returns `(job, created)`.
+        """
+        if payload is None:
+            payload = CronPayload(kind="agent_turn", message=message, deliver=deliver, channel=channel, to=to)
+        _validate_schedule(schedule, self._wall_now_ms())
+        self._check_payload(payload)
+
+        job = next((j for j in self._store.jobs if j.enabled and j.name == name), None)
+        created = job is None
+        if job is None:
+            job = _create_job(
+                name,
+                schedule,
+                payload,
+                delete_after_run,
+                list(tags or []),
+                disable_after_failures,
+                priority or 0,
+                self._wall_now_ms(),
+            )
+            self._store.jobs.append(job)
+        else:
+            now = self._wall_now_ms()
+            if job.schedule != schedule:
+                job.state.next_run_at_ms = _compute_next_run(schedule, now)
+                job.state.snoozed_until_ms = None
+                job.schedule = schedule
+            job.payload = payload
+            job.delete_after_run = delete_after_run
+            if tags is not None:
+                job.tags = list(tags)
+            if disable_after_failures is not None:
+                job.disable_after_failures = disable_after_failures
+            if priority is not None:
+                job.priority = priority
+            job.updated_at_ms = now
+        _set_chain(job, on_success_job_id, on_failure_job_id)
+
+        self._save_store()
+        self._arm_timer()
+        event, verb = ("job_added", "added") if created else ("job_updated", "updated")
+        self._events.emit(event, f"{verb} job '{job.name}' ({job.id})", job_id=job.id, job_name=job.name)
+        return job, created
+
+    async def remove_job(self, job_id: str) -> bool:
         """Remove a job by ID."""
-        store = self._load_store()
+        store = self._store
         before = len(store.jobs)
         store.jobs = [j for j in store.jobs if j.id != job_id]
         removed = len(store.jobs) < before
 
+        if removed:
+            self._bind(job_id, None)
+            self._save_store()
+            self._arm_timer()
+            self._events.emit("job_removed", f"removed job {job_id}", job_id=job_id)
+
+        return removed
+
+    async def update_job(
+        self,
+        job_id: str,
+        name: str | None = None,
+        schedule: CronSchedule | None = None,
+        message: str | None = None,
+        deliver: bool | None = None,
+        channel: str | None = None,
+        to: str | None = None,
+        delete_after_run: bool | None = None,
+        tags: list[str] | None = None,
+        payload: CronPayload | None = None,
+        disable_after_failures: int | None = None,
+        priority: int | None = None,
+        on_success_job_id: str | None = None,
+        on_failure_job_id: str | None = None,
+    ) -> CronJob | None:
+        """Update fields of an existing job. Fields left as `None` are unchanged.
+
+        `payload` replaces the whole payload before the individual payload
+        fields are applied.
+        """
+        if schedule is not None:
+            _validate_schedule(schedule, self._wall_now_ms())
+        if payload is not None:
+            self._check_payload(payload)
+        store = self._store
+        job = next((j for j in store.jobs if j.id == job_id), None)
+        if job is None:
+            return None
+
+        now = self._wall_now_ms()
+        if name is not None:
+            job.name = name
+        if schedule is not None:
+            job.schedule = schedule
+            job.state.snoozed_until_ms = None
+            if job.enabled:
+                job.state.next_run_at_ms = _compute_next_run(schedule, now)
+        if payload is not None:
+            job.payload = payload
+        if message is not None:
+            job.payload.message = message
+        if deliver is not None:
+            job.payload.deliver = deliver
+        if channel is not None:
+            job.payload.channel = channel
+        if to is not None:
+            job.payload.to = to
+        if delete_after_run is not None:
+            job.delete_after_run = delete_after_run
+        if tags is not None:
+            job.tags = list(tags)
+        if disable_after_failures is not None:
+            job.disable_after_failures = disable_after_failures
+        if priority is not None:
+            job.priority = priority
+        _set_chain(job, on_success_job_id, on_failure_job_id)
+        job.updated_at_ms = now
+
+        self._save_store()
+        self._arm_timer()
+        return job
+
+    async def remove_jobs_by_tag(self, tag: str) -> list[str]:
+        """Remove every job carrying the given tag. Returns the removed job IDs."""
+        return await self.clear_jobs(tag)
+
+    async def remove_jobs(self, job_ids: list[str]) -> list[str]:
+        """Remove several jobs at once, saving the store once.
+
+        Returns the IDs that were actually removed.
+        """
+        removed = self._remove_jobs_where(lambda j: j.id in job_ids)
+        if removed:
+            self._save_store()
+            self._arm_timer()
+            self._events.emit("jobs_removed", f"removed {len(removed)} job(s)", job_ids=removed)
+        return removed
+
+    async def clear_jobs(self, tag: str | None = None) -> list[str]:
+        """Remove all jobs, or only those carrying `tag`. Returns the removed IDs."""
+        removed = self._remove_jobs_where(lambda j: tag is None or tag in j.tags)
         if removed:
             self._save_store()
             self._arm_timer()
-            logger.info(f"Cron: removed job {job_id}")
+            log = f"removed {len(removed)} job(s)" + (f" tagged '{tag}'" if tag is not None else "")
+            self._events.emit("jobs_removed", log, job_ids=removed, tag=tag)
+        return removed
 
+    def _remove_jobs_where(self, pred: Callable[[CronJob], bool]) -> list[str]:
+        """Remove jobs matching `pred`, returning their IDs."""
+        removed = [j.id for j in self._store.jobs if pred(j)]
+        self._store.jobs = [j for j in self._store.jobs if not pred(j)]
+        for job_id in removed:
+            self._bind(job_id, None)
         return removed
 
-    def enable_job(self, job_id: str, enabled: bool = True) -> CronJob | None:
+    async def enable_job(self, job_id: str, enabled: bool = True) -> CronJob | None:
         """Enable or disable a job."""
-        store = self._load_store()
+        store = self._store
         for job in store.jobs:
             if job.id == job_id:
-                job.enabled = enabled
-                job.updated_at_ms = _now_ms()
-                if enabled:
-                    job.state.next_run_at_ms = _compute_next_run(job.schedule, _now_ms())
-                else:
-                    job.state.next_run_at_ms = None
+                _set_enabled(job, enabled, self._wall_now_ms())
                 self._save_store()
                 self._arm_timer()
                 return job
         return None
 
-    async def run_job(self, job_id: str, force: bool = False) -> bool:
-        """Manually run a job."""
-        store = self._load_store()
-        for job in store.jobs:
-            if job.id == job_id:
-                if not force and not job.enabled:
-                    return False
-                await self._execute_job(job)
-                self._save_store()
-                self._arm_timer()
-                return True
-        return False
+    async def enable_jobs(self, job_ids: list[str], enabled: bool = True) -> list[str]:
+        """Enable or disable several jobs at once, saving the store once.
+
+        Returns the IDs that matched an existing job.
+        """
+        now = self._wall_now_ms()
+        affected = []
+        for job in self._store.jobs:
+            if job.id in job_ids:
+                _set_enabled(job, enabled, now)
+                affected.append(job.id)
+        if affected:
+            self._save_store()
+            self._arm_timer()
+        return affected
+
+    async def snooze_job(self, job_id: str, until_ms: int | None) -> CronJob | None:
+        """Hold an enabled job's next run until `until_ms`.
+
+        The snooze is persisted, survives restarts and ends when the job next
+        runs on schedule. Pass `until_ms=None` to cancel it and resume the
+        regular schedule. Returns the updated job, or `None` if the job does
+        not exist or is disabled.
+        """
+        now = self._wall_now_ms()
+        if until_ms is not None and until_ms <= now:
+            raise ValueError("until_ms must be in the future")
+        job = next((j for j in self._store.jobs if j.id == job_id and j.enabled), None)
+        if job is None:
+            return None
+
+        job.state.snoozed_until_ms = until_ms
+        job.state.next_run_at_ms = until_ms if until_ms is not None else _compute_next_run(job.schedule, now)
+        if until_ms is not None:
+            self._events.emit(
+                "job_snoozed", f"snoozed job '{job.name}'", job_id=job.id, job_name=job.name, until_ms=until_ms
+            )
+        else:
+            self._events.emit("job_unsnoozed", f"resumed job '{job.name}'", job_id=job.id, job_name=job.name)
+        self._save_store()
+        self._arm_timer()
+        return job
+
+    async def run_job(
+        self,
+        job_id: str,
+        force: bool = False,
+        wait: bool = True,
+        reschedule: bool = False,
+        dry_run: bool | None = None,
+    ) -> dict[str, Any] | None:
+        """Manually run a job.
+
+        Returns `None` if the job does not exist (or is disabled and `force`
+        is not set). Otherwise returns a dict with `status` (`"ok"`, `"error"`,
+        or `"skipped"` if the job is already executing), `error`,
+        `duration_ms`, `started_at_ms` and `next_run_at_ms`. With
+        `wait=False` the job runs in the background and
+        `{"status": "started", "started_at_ms": ...}` is returned immediately.
+
+        A manual run records its result and history but leaves
+        `next_run_at_ms` and the enabled state alone, so one-shot jobs still
+        fire at their scheduled time. Pass `reschedule=True` to advance the
+        schedule as if the scheduler had run the job.
+
+        `dry_run` overrides the service's dry-run setting for this run.
+        """
+        job = next((j for j in self._store.jobs if j.id == job_id), None)
+        if job is None or (not force and not job.enabled):
+            return None
+
+        async def run() -> dict[str, Any]:
+            report = await self._execute_chain(job, reschedule, self.dry_run if dry_run is None else dry_run)
+            self._save_store()
+            self._arm_timer()
+            return report
+
+        task = self._track(run())
+        if wait:
+            return await task
+        return {
+            "status": "started",
+            "error": None,
+            "duration_ms": None,
+            "started_at_ms": self._wall_now_ms(),
+            "next_run_at_ms": None,
+        }
+
+    async def flush(self) -> None:
+        """Write pending changes to the store now instead of after the debounce.
+
+        Raises `OSError` if the store cannot be written.
+        """
+        self._flush_store()
+
+    async def export_jobs(self) -> str:
+        """Export the full job set (including runtime state) as a JSON string."""
+        store = self._store
+        return store_to_json(store.jobs)
+
+    async def export_ics(self, occurrences: int = 10) -> str:
+        """Render enabled jobs as an iCalendar (`.ics`) document.
+
+        `every` jobs become recurring events; cron, daily and weekly jobs are
+        expanded to their next `occurrences` runs. Event UIDs derive from the
+        job ID, so re-importing the calendar updates existing events.
+        """
+        return ics.render(self._store.jobs, occurrences, self._wall_now_ms(), _next_occurrences)
+
+    async def import_jobs(self, json: str, merge: bool = True) -> list[CronJob]:
+        """Import jobs from a JSON string produced by `export_jobs`.
+
+        With `merge=True` the imported jobs are added alongside existing ones;
+        otherwise they replace them. Colliding IDs are regenerated and next run
+        times are recomputed. Returns the imported jobs.
+        """
+        incoming = _parse_import(json, self._wall_now_ms())
+        for job in incoming:
+            self._check_payload(job.payload)
+        store = self._store
+        if not merge:
+            store.jobs = []
+
+        now = self._wall_now_ms()
+        for job in incoming:
+            while not job.id or any(j.id == job.id for j in store.jobs):
+                job.id = _new_job_id()
+            job.state.snoozed_until_ms = None
+            job.state.next_run_at_ms = _compute_next_run(job.schedule, now) if job.enabled else None
+            store.jobs.append(job)
+
+        self._save_store()
+        self._arm_timer()
+        self._events.emit("jobs_imported", f"imported {len(incoming)} job(s)", job_ids=[j.id for j in incoming])
+        return incoming
 
     def status(self) -> dict:
         """Get service status."""
-        store = self._load_store()
+        store = self._store
+        slowest = max(self._recent_runs, key=lambda r: r.duration_ms, default=None)
         return {
             "enabled": self._running,
             "jobs": len(store.jobs),
             "next_wake_at_ms": self._get_next_wake_ms(),
+            "event_errors": self._events.error_count,
+            # Slowest run within the recent-runs window
+            "slowest_job": (
+                {"job_id": slowest.job_id, "job_name": slowest.job_name, "duration_ms": slowest.duration_ms}
+                if slowest
+                else None
+            ),
         }
+
+    def metrics(self) -> dict[str, Any]:
+        """Execution metrics of this service's jobs.
+
+        Counts of `executions`, `failures`, `timeouts` and `skipped_overlap`
+        runs, overall and under `jobs` by job ID, and `avg_duration_ms`
+        (`None` before any run).
+        """
+        return self._metrics.to_dict()
+
+    def reset_metrics(self) -> None:
+        """Reset this service's execution metrics."""
+        self._metrics = CronMetrics()
+
+    @staticmethod
+    def preview_schedule(schedule: CronSchedule, count: int = 5, from_ms: int | None = None) -> list[int]:
+        """Preview the next `count` fire times (epoch ms) of a schedule.
+
+        Pure: no job needs to exist. `at` schedules yield at most one entry.
+        """
+        if from_ms is None:
+            from_ms = _now_ms()
+        _validate_schedule(schedule, from_ms)
+        return _next_occurrences(schedule, from_ms, count)
@@ -0,0 +1,89 @@
+"""Shell payloads: jobs that run a command without involving the agent."""
+
+import asyncio
+import sys
+from dataclasses import dataclass
+
+from debot.cron._types_py import CronPayload
+
+DEFAULT_TIMEOUT_MS = 60_000
+# Maximum number of characters kept from each of stdout and stderr
+MAX_OUTPUT_CHARS = 4000
+# Bytes kept from each stream: MAX_OUTPUT_CHARS characters of any width
+MAX_OUTPUT_BYTES = MAX_OUTPUT_CHARS * 4
+# UTF-8 continuation bytes; every other byte starts a character
+_CONTINUATION_BYTES = bytes(range(0x80, 0xC0))
+
+
+@dataclass
+class ShellOutput:
+    """Result of running a shell payload."""
+
+    exit_code: int | None
+    output: str
+
+
+def validate(payload: CronPayload) -> None:
+    """Check that a shell payload has a command, raising `ValueError` if not."""
+    if not (payload.command or "").strip():
+        raise ValueError("Shell payload requires 'command'")
+
+
+def _truncate(text: str, dropped_chars: int) -> str:
+    """Keep at most `MAX_OUTPUT_CHARS` characters of `text`, which `dropped_chars` more followed."""
+    if len(text) <= MAX_OUTPUT_CHARS:
+        return text
+    return f"{text[:MAX_OUTPUT_CHARS]}... (truncated, {len(text) - MAX_OUTPUT_CHARS + dropped_chars} more chars)"
+
+
+async def _capture(stream: asyncio.StreamReader) -> tuple[str, int]:
+    """Read `stream` to its end, keeping only its first `MAX_OUTPUT_BYTES` bytes.
+
+    Returns the text kept and the number of characters read after it.
+    """
+    kept = bytearray()
+    dropped_chars = 0
+    while chunk := await stream.read(8192):
+        room = MAX_OUTPUT_BYTES - len(kept)
+        kept += chunk[:room]
+        dropped_chars += len(chunk[room:].translate(None, _CONTINUATION_BYTES))
+    return kept.decode(errors="replace"), dropped_chars
+
+
+async def run(payload: CronPayload) -> ShellOutput:
+    """Run the payload's command.
+
+    The process is killed if it exceeds the timeout. Output past what is
+    kept is read and discarded until the process exits. A missing exit code
+    means the process was terminated by a signal. Raises `RuntimeError` if
+    the command cannot be started, and `TimeoutError` if it times out.
+    """
+    validate(payload)
+    timeout_ms = payload.timeout_ms if payload.timeout_ms is not None else DEFAULT_TIMEOUT_MS
+    argv = ["cmd", "/C", payload.command] if sys.platform == "win32" else ["sh", "-c", payload.command]
+
+    try:
+        process = await asyncio.create_subprocess_exec(
+            *argv, cwd=payload.cwd, stdout=asyncio.subprocess.PIPE, stderr=asyncio.subprocess.PIPE
+        )
+    except OSError as e:
+        raise RuntimeError(f"Failed to run command: {e}") from None
+    try:
+        # Both pipes are read at once, so neither fills up and stalls the command
+        (out, out_dropped), (err, err_dropped), _ = await asyncio.wait_for(
+            asyncio.gather(_capture(process.stdout), _capture(process.stderr), process.wait()),
+            timeout=timeout_ms / 1000,
+        )
+    except asyncio.TimeoutError:
+        process.kill()
+        await process.wait()
+        raise TimeoutError(f"Command timed out after {timeout_ms} ms") from None
+
+    parts = []
+    if out.strip():
+        parts.append(_truncate(out, out_dropped))
+    if err.strip():
+        parts.append(f"STDERR:\n{_truncate(err, err_dropped)}")
+
+    code = process.returncode
+    return ShellOutput(exit_code=code if code is not None and code >= 0 else None, output="\n".join(parts))
@@ -0,0 +1,232 @@
+"""On-disk persistence for the cron job set.
+
+The store is a versioned JSON document. Older versions are migrated forward
+on load; newer (unknown) versions are refused so that a downgraded install
+never overwrites data it does not understand.
+"""
+
+import hashlib
+import json
+from pathlib import Path
+from typing import Any
+
+from debot.cron._events_py import CronEvents
+from debot.cron._types_py import CronJob
+
+# Current on-disk store format version.
+#
+# History:
+# - 1: initial format
+# - 2: jobs carry a `tags` list
+# - 3: payloads may be webhooks (`url`, `method`, `headers`, `body`)
+# - 4: payloads may be shell commands (`command`, `cwd`, `timeoutMs`);
+#   state records `lastExitCode` and `lastOutput`
+# - 5: jobs may set `disableAfterFailures`; state records
+#   `consecutiveFailures`
+# - 6: `daily`/`weekly` schedules (`hour`, `minute`, `weekdays`)
+# - 7: jobs carry a `priority`
+# - 8: schedules may set quiet hours (`quietStart`, `quietEnd`, `quietPolicy`)
+# - 9: jobs may chain follow-up jobs (`onSuccessJobId`, `onFailureJobId`);
+#   state records `lastTriggeredBy`
+# - 10: state records run statistics (`lastDurationMs`, `avgDurationMs`,
+#   `runCount`) and `snoozedUntilMs`
+STORE_VERSION = 10
+
+
+def store_to_json(jobs: list[CronJob]) -> str:
+    """Serialize jobs as a store document at the current version."""
+    return json.dumps({"version": STORE_VERSION, "jobs": [j.to_dict() for j in jobs]}, indent=2)
+
+
+def parse_store(text: str) -> tuple[list[CronJob], int]:
+    """Parse store JSON of any known version into jobs, migrating as needed.
+
+    Returns the jobs together with the version found in the document. Raises
+    `ValueError` if the document is invalid or from an unknown version.
+    """
+    try:
+        data = json.loads(text)
+    except json.JSONDecodeError as e:
+        raise ValueError(f"Invalid JSON: {e}") from None
+    if not isinstance(data, dict):
+        raise ValueError("Invalid cron store: expected an object")
+    # Documents predating the version field are treated as version 1.
+    version = data.get("version", 1)
+    if not isinstance(version, int) or isinstance(version, bool):
+        raise ValueError(f"Invalid 'version' field: {version!r}")
+    if not 1 <= version <= STORE_VERSION:
+        raise ValueError(f"Unsupported cron store version {version} (this build supports up to {STORE_VERSION})")
+
+    data = _migrate(data, version)
+    try:
+        jobs = [CronJob.from_dict(j) for j in data["jobs"]]
+    except (KeyError, TypeError, ValueError) as e:
+        raise ValueError(f"Invalid cron store: {e}") from None
+    return jobs, version
+
+
+def _migrate(data: dict[str, Any], version: int) -> dict[str, Any]:
+    """Migrate a store document from `version` up to `STORE_VERSION`."""
+    for source in range(version, STORE_VERSION):
+        if source in _MIGRATIONS:
+            _MIGRATIONS[source](data)
+    data["version"] = STORE_VERSION
+    return data
+
+
+def _migrate_v1_to_v2(data: dict[str, Any]) -> None:
+    """v1 -> v2: jobs gain an explicit (empty) `tags` list."""
+    for job in data.get("jobs") or []:
+        if isinstance(job, dict):
+            job.setdefault("tags", [])
+
+
+# Migration from each version to the next. Versions without an entry
+# (v3 to v10) only add optional fields.
+_MIGRATIONS = {1: _migrate_v1_to_v2}
+
+
+class StoreFile:
+    """Handle to the on-disk store.
+
+    If the store fails to load (unreadable, corrupt, or written by a newer
+    version), it is marked read-only so the service never clobbers it.
+    Migrations, and failures to rewrite a migrated file, are reported as
+    events.
+
+    The store remembers the fingerprint (modification time and content hash)
+    of what it last read or wrote, so that external edits can be told apart
+    from the service's own saves. It also keeps the IDs of the jobs in the
+    file, so a reload can tell jobs deleted on disk from jobs created since
+    the last save.
+    """
+
+    def __init__(self, path: Path, events: CronEvents):
+        self.path = path
+        self._events = events
+        self.writable = True
+        self._mtime: int | None = None
+        self._hash: str | None = None
+        # IDs of the jobs in the file as last read or written
+        self.saved_ids: set[str] = set()
+
+    def load(self) -> list[CronJob]:
+        """Load jobs from disk. A missing file is an empty store.
+
+        Old versions are migrated and the file rewritten at the current
+        version. Raises `ValueError` on failure, and the store becomes read-only.
+        """
+        if not self.path.exists():
+            self.writable = True
+            return []
+        try:
+            try:
+                content = self.path.read_text()
+            except OSError as e:
+                raise ValueError(f"Failed to read {self.path}: {e}") from None
+            self._remember(content)
+            jobs, migrated = self._parse(content)
+        except ValueError:
+            self.writable = False
+            raise
+        self.writable = True
+        self.saved_ids = {j.id for j in jobs}
+
+        if migrated:
+            # A failed rewrite is retried (and reported) by the next save
+            try:
+                self._write(store_to_json(jobs))
+            except OSError as e:
+                self._events.emit("store_error", str(e), error=str(e))
+        return jobs
+
+    def poll_external_change(self) -> list[CronJob] | ValueError | None:
+        """Check whether the file changed on disk since we last read or wrote it.
+
+        Returns `None` when nothing changed (including our own saves), and the
+        parsed jobs (or the parse error) when an external edit is detected.
+        """
+        mtime = _file_mtime(self.path)
+        if mtime is None or mtime == self._mtime:
+            return None
+        try:
+            content = self.path.read_text()
+        except OSError:
+            return None
+        digest = _content_hash(content)
+        self._mtime = mtime
+        if digest == self._hash:
+            return None
+        self._hash = digest
+
+        try:
+            jobs, _ = self._parse(content)
+        except ValueError as e:
+            self.writable = False
+            return e
+        self.writable = True
+        self.saved_ids = {j.id for j in jobs}
+        return jobs
+
+    def save(self, jobs: list[CronJob]) -> bool:
+        """Write jobs at the current version, unless the store is read-only.
+
+        The write is skipped if the file already holds exactly this content.
+        Returns whether the file was written; raises `OSError` on failure.
+        """
+        if not self.writable:
+            return False
+        content = store_to_json(jobs)
+        if _content_hash(content) == self._hash and self.path.exists():
+            return False
+        self._write(content)
+        self.saved_ids = {j.id for j in jobs}
+        return True
+
+    def _parse(self, content: str) -> tuple[list[CronJob], bool]:
+        """Parse content, reporting whether it was migrated from an older version."""
+        try:
+            jobs, version = parse_store(content)
+        except ValueError as e:
+            raise ValueError(f"{self.path}: {e}") from None
+        migrated = version < STORE_VERSION
+        if migrated:
+            self._events.emit(
+                "store_migrated",
+                f"migrated store from version {version} to {STORE_VERSION}",
+                from_version=version,
+                to_version=STORE_VERSION,
+            )
+            # Forget the old content so the next save rewrites the file
+            self._hash = None
+        return jobs, migrated
+
+    def _remember(self, content: str) -> None:
+        self._mtime = _file_mtime(self.path)
+        self._hash = _content_hash(content)
+
+    def _write(self, content: str) -> None:
+        """Write content, raising `OSError` on failure.
+
+        The content is written to a temporary file and renamed into place so
+        readers never see a partial store.
+        """
+        temp = self.path.with_name(self.path.name + ".tmp")
+        try:
+            self.path.parent.mkdir(parents=True, exist_ok=True)
+            temp.write_text(content)
+            temp.replace(self.path)
+        except OSError as e:
+            raise OSError(f"Failed to write {self.path}: {e}") from e
+        self._remember(content)
+
+
+def _content_hash(content: str) -> str:
+    return hashlib.sha256(content.encode()).hexdigest()
+
+
+def _file_mtime(path: Path) -> int | None:
+    try:
+        return path.stat().st_mtime_ns
+    except OSError:
+        return None
@@ -1,34 +1,245 @@
 """Cron types."""
 
-from dataclasses import dataclass, field
-from typing import Literal
+from contextlib import contextmanager
+from dataclasses import InitVar, dataclass, field
+from datetime import datetime, time, timedelta, timezone, tzinfo
+from typing import Any, Callable, Iterator, Literal
+from zoneinfo import ZoneInfo, ZoneInfoNotFoundError
+
+
+def _parse_tz(tz: str | None) -> tzinfo:
+    """Parse an IANA timezone name, defaulting to UTC."""
+    if not tz:
+        return timezone.utc
+    try:
+        return ZoneInfo(tz)
+    except (ZoneInfoNotFoundError, ValueError):
+        raise ValueError(f"Unknown timezone '{tz}'") from None
+
+
+def _parse_at_time(text: str, tz: str | None) -> int:
+    """Parse an `at` time given as an ISO-8601 string.
+
+    Strings with an offset (`"2025-07-01T09:00:00+02:00"`, `"...Z"`) are
+    absolute; naive strings (`"2025-07-01T09:00"`, `"2025-07-01 09:00:00"`)
+    are read as wall-clock time in `tz`, defaulting to UTC.
+    """
+    text = text.strip()
+    try:
+        dt = datetime.fromisoformat(text)
+    except ValueError:
+        raise ValueError(f"Invalid 'at' time '{text}': expected ISO-8601") from None
+    if dt.tzinfo is None:
+        # With fold=0, a time skipped by a DST jump is shifted forward by the
+        # gap and a repeated time resolves to its first occurrence.
+        dt = dt.replace(tzinfo=_parse_tz(tz))
+    return round(dt.timestamp() * 1000)
+
+
+def _optional_fields(**fields: Any) -> dict[str, Any]:
+    """The given fields, omitting those that are unset."""
+    return {k: v for k, v in fields.items() if v is not None}
+
+
+@dataclass
+class _QuietWindow:
+    """A daily window, in the schedule's timezone, during which jobs do not run."""
+
+    tz: tzinfo
+    start: time
+    end: time
+    # "defer" or "skip"
+    policy: str
+
+    def end_if_inside(self, ms: int) -> int | None:
+        """If `ms` falls inside the window, the time the window ends."""
+        local = datetime.fromtimestamp(ms / 1000, self.tz)
+        day, now = local.date(), local.time()
+        if self.start < self.end:
+            if not self.start <= now < self.end:
+                return None
+            end_day = day
+        elif now >= self.start:
+            # Spans midnight: ends tomorrow
+            end_day = day + timedelta(days=1)
+        elif now < self.end:
+            end_day = day
+        else:
+            return None
+        return round(datetime.combine(end_day, self.end, tzinfo=self.tz).timestamp() * 1000)
+
+
+def _quiet_window(schedule: "CronSchedule") -> _QuietWindow | None:
+    """Parse the schedule's quiet window, if it has one, raising `ValueError` if it is invalid."""
+
+    def parse(text: str) -> time:
+        try:
+            return datetime.strptime(text.strip(), "%H:%M").time()
+        except ValueError:
+            raise ValueError(f"Invalid quiet hours time '{text}': expected HH:MM") from None
+
+    policy = schedule.quiet_policy or "defer"
+    if policy not in ("defer", "skip"):
+        raise ValueError(f"Invalid quiet_policy '{policy}': expected 'defer' or 'skip'")
+    if schedule.quiet_start is None and schedule.quiet_end is None:
+        return None
+    if schedule.quiet_start is None or schedule.quiet_end is None:
+        raise ValueError("Quiet hours require both 'quiet_start' and 'quiet_end'")
+    start, end = parse(schedule.quiet_start), parse(schedule.quiet_end)
+    if start == end:
+        raise ValueError("Quiet hours start and end must differ")
+    return _QuietWindow(_parse_tz(schedule.tz), start, end, policy)
+
+
+@contextmanager
+def _reading(what: str) -> Iterator[None]:
+    """Turn errors from reading a malformed dict into `ValueError`."""
+    try:
+        yield
+    except (KeyError, TypeError, AttributeError) as e:
+        raise ValueError(f"Invalid {what}: {e!r}") from None
 
 
 @dataclass
 class CronSchedule:
     """Schedule definition for a cron job."""
 
-    kind: Literal["at", "every", "cron"]
+    kind: Literal["at", "every", "cron", "daily", "weekly"]
     # For "at": timestamp in ms
     at_ms: int | None = None
     # For "every": interval in ms
     every_ms: int | None = None
     # For "cron": cron expression (e.g. "0 9 * * *")
     expr: str | None = None
-    # Timezone for cron expressions
+    # Timezone for cron expressions and daily/weekly schedules
     tz: str | None = None
+    # Local hour (0-23) for daily/weekly schedules
+    hour: int | None = None
+    # Local minute (0-59) for daily/weekly schedules, defaults to 0
+    minute: int | None = None
+    # Weekday mask for weekly schedules: bit 0 is Monday, bit 6 Sunday
+    weekdays: int | None = None
+    # Convenience for `at_ms`: epoch milliseconds or an ISO-8601 string (naive
+    # strings are read in `tz`). It must lie in the future, as told by `clock`
+    # or else the system clock.
+    at: InitVar[int | str | None] = None
+    # Start of the daily quiet window ("HH:MM", local to `tz`)
+    quiet_start: str | None = None
+    # End of the daily quiet window ("HH:MM"); may be before the start to
+    # span midnight
+    quiet_end: str | None = None
+    # "defer" (default) moves runs to the end of the quiet window; "skip"
+    # drops them
+    quiet_policy: str | None = None
+    # Callable returning epoch milliseconds that `at` is checked against,
+    # such as the one given to the service
+    clock: InitVar[Callable[[], int] | None] = None
+
+    def __post_init__(self, at: int | str | None, clock: Callable[[], int] | None) -> None:
+        _quiet_window(self)
+        if at is None:
+            return
+        if self.at_ms is not None:
+            raise ValueError("Pass either 'at' or 'at_ms', not both")
+        ms = _parse_at_time(at, self.tz) if isinstance(at, str) else at
+        now = int(clock()) if clock is not None else int(datetime.now(timezone.utc).timestamp() * 1000)
+        if ms <= now:
+            raise ValueError(f"'at' time {ms} is in the past")
+        self.at_ms = ms
+
+    def to_dict(self) -> dict[str, Any]:
+        """Convert to a dict keyed by the store's camelCase field names."""
+        return {
+            "kind": self.kind,
+            "atMs": self.at_ms,
+            "everyMs": self.every_ms,
+            "expr": self.expr,
+            "tz": self.tz,
+            **_optional_fields(hour=self.hour, minute=self.minute, weekdays=self.weekdays),
+            **_optional_fields(quietStart=self.quiet_start, quietEnd=self.quiet_end, quietPolicy=self.quiet_policy),
+        }
+
+    @classmethod
+    def from_dict(cls, d: dict[str, Any]) -> "CronSchedule":
+        """Build from a dict as produced by `to_dict`."""
+        with _reading("schedule"):
+            return cls(
+                kind=d["kind"],
+                at_ms=d.get("atMs"),
+                every_ms=d.get("everyMs"),
+                expr=d.get("expr"),
+                tz=d.get("tz"),
+                hour=d.get("hour"),
+                minute=d.get("minute"),
+                weekdays=d.get("weekdays"),
+                quiet_start=d.get("quietStart"),
+                quiet_end=d.get("quietEnd"),
+                quiet_policy=d.get("quietPolicy"),
+            )
 
 
 @dataclass
 class CronPayload:
     """What to do when the job runs."""
 
-    kind: Literal["system_event", "agent_turn"] = "agent_turn"
+    kind: Literal["system_event", "agent_turn", "webhook", "shell"] = "agent_turn"
     message: str = ""
     # Deliver response to channel
     deliver: bool = False
     channel: str | None = None  # e.g. "whatsapp"
     to: str | None = None  # e.g. phone number
+    # Webhook target URL (http/https)
+    url: str | None = None
+    # Webhook HTTP method, defaults to POST
+    method: str | None = None
+    # Extra webhook request headers
+    headers: dict[str, str] = field(default_factory=dict)
+    # Webhook request body
+    body: str | None = None
+    # Shell command to run
+    command: str | None = None
+    # Working directory for the shell command
+    cwd: str | None = None
+    # Shell command timeout, defaults to 60 seconds
+    timeout_ms: int | None = None
+
+    def to_dict(self) -> dict[str, Any]:
+        """Convert to a dict keyed by the store's camelCase field names."""
+        return {
+            "kind": self.kind,
+            "message": self.message,
+            "deliver": self.deliver,
+            "channel": self.channel,
+            "to": self.to,
+            **_optional_fields(
+                url=self.url,
+                method=self.method,
+                headers=dict(self.headers) or None,
+                body=self.body,
+                command=self.command,
+                cwd=self.cwd,
+                timeoutMs=self.timeout_ms,
+            ),
+        }
+
+    @classmethod
+    def from_dict(cls, d: dict[str, Any]) -> "CronPayload":
+        """Build from a dict as produced by `to_dict`."""
+        with _reading("payload"):
+            return cls(
+                kind=d.get("kind", "agent_turn"),
+                message=d.get("message", ""),
+                deliver=d.get("deliver", False),
+                channel=d.get("channel"),
+                to=d.get("to"),
+                url=d.get("url"),
+                method=d.get("method"),
+                headers=dict(d.get("headers") or {}),
+                body=d.get("body"),
+                command=d.get("command"),
+                cwd=d.get("cwd"),
+                timeout_ms=d.get("timeoutMs"),
+            )
 
 
 @dataclass
@@ -37,13 +248,62 @@ class CronJobState:
 
     next_run_at_ms: int | None = None
     last_run_at_ms: int | None = None
-    last_status: Literal["ok", "error", "skipped"] | None = None
+    last_status: Literal["ok", "error", "skipped", "interrupted", "dry_run"] | None = None
     last_error: str | None = None
+    # Exit code of the last shell run (None if killed by a signal)
+    last_exit_code: int | None = None
+    # Captured output of the last shell or webhook run
+    last_output: str | None = None
+    # Wall-clock duration of the last run
+    last_duration_ms: int | None = None
+    # Mean duration over all recorded runs
+    avg_duration_ms: float | None = None
+    # Number of completed runs
+    run_count: int = 0
+    # Failed runs since the last success
+    consecutive_failures: int = 0
+    # ID of the job whose completion triggered the last run, if chained
+    last_triggered_by: str | None = None
+    # Time until which `snooze_job` holds the next run
+    snoozed_until_ms: int | None = None
 
+    def to_dict(self) -> dict[str, Any]:
+        """Convert to a dict keyed by the store's camelCase field names."""
+        return {
+            "nextRunAtMs": self.next_run_at_ms,
+            "lastRunAtMs": self.last_run_at_ms,
+            "lastStatus": self.last_status,
+            "lastError": self.last_error,
+            **_optional_fields(lastExitCode=self.last_exit_code, lastOutput=self.last_output),
+            **_optional_fields(lastDurationMs=self.last_duration_ms, avgDurationMs=self.avg_duration_ms),
+            "runCount": self.run_count,
+            "consecutiveFailures": self.consecutive_failures,
+            **_optional_fields(lastTriggeredBy=self.last_triggered_by, snoozedUntilMs=self.snoozed_until_ms),
+        }
 
-@dataclass
+    @classmethod
+    def from_dict(cls, d: dict[str, Any]) -> "CronJobState":
+        """Build from a dict as produced by `to_dict`."""
+        with _reading("job state"):
+            return cls(
+                next_run_at_ms=d.get("nextRunAtMs"),
+                last_run_at_ms=d.get("lastRunAtMs"),
+                last_status=d.get("lastStatus"),
+                last_error=d.get("lastError"),
+                last_exit_code=d.get("lastExitCode"),
+                last_output=d.get("lastOutput"),
+                last_duration_ms=d.get("lastDurationMs"),
+                avg_duration_ms=d.get("avgDurationMs"),
+                run_count=d.get("runCount", 0),
+                consecutive_failures=d.get("consecutiveFailures", 0),
+                last_triggered_by=d.get("lastTriggeredBy"),
+                snoozed_until_ms=d.get("snoozedUntilMs"),
+            )
+
+
+@dataclass(eq=False)
 class CronJob:
-    """A scheduled job."""
+    """A scheduled job. Jobs are equal when their IDs are."""
 
     id: str
     name: str
@@ -54,6 +314,65 @@ class CronJob:
     created_at_ms: int = 0
     updated_at_ms: int = 0
     delete_after_run: bool = False
+    tags: list[str] = field(default_factory=list)
+    # Disable the job after this many consecutive failures (0 = never).
+    # Falls back to the service default when unset.
+    disable_after_failures: int | None = None
+    # Jobs due at the same time run in descending priority order
+    priority: int = 0
+    # Jobs to run right after this one succeeds or fails
+    on_success_job_id: str | None = None
+    on_failure_job_id: str | None = None
+
+    @property
+    def short_id(self) -> str:
+        """Leading characters of the ID, for display."""
+        return self.id[:8]
+
+    def to_dict(self) -> dict[str, Any]:
+        """Convert to a dict keyed by the store's camelCase field names."""
+        return {
+            "id": self.id,
+            "name": self.name,
+            "enabled": self.enabled,
+            "schedule": self.schedule.to_dict(),
+            "payload": self.payload.to_dict(),
+            "state": self.state.to_dict(),
+            "createdAtMs": self.created_at_ms,
+            "updatedAtMs": self.updated_at_ms,
+            "deleteAfterRun": self.delete_after_run,
+            "tags": list(self.tags),
+            **_optional_fields(disableAfterFailures=self.disable_after_failures),
+            "priority": self.priority,
+            **_optional_fields(onSuccessJobId=self.on_success_job_id, onFailureJobId=self.on_failure_job_id),
+        }
+
+    @classmethod
+    def from_dict(cls, d: dict[str, Any]) -> "CronJob":
+        """Build from a dict as produced by `to_dict`."""
+        with _reading("job"):
+            return cls(
+                id=d["id"],
+                name=d["name"],
+                enabled=d.get("enabled", True),
+                schedule=CronSchedule.from_dict(d["schedule"]),
+                payload=CronPayload.from_dict(d["payload"]),
+                state=CronJobState.from_dict(d.get("state", {})),
+                created_at_ms=d.get("createdAtMs", 0),
+                updated_at_ms=d.get("updatedAtMs", 0),
+                delete_after_run=d.get("deleteAfterRun", False),
+                tags=list(d.get("tags", [])),
+                disable_after_failures=d.get("disableAfterFailures"),
+                priority=d.get("priority", 0),
+                on_success_job_id=d.get("onSuccessJobId"),
+                on_failure_job_id=d.get("onFailureJobId"),
+            )
+
+    def __eq__(self, other: object) -> bool:
+        return isinstance(other, CronJob) and self.id == other.id
+
+    def __hash__(self) -> int:
+        return hash(self.id)
 
 
 @dataclass
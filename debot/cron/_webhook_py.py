@@ -0,0 +1,74 @@
+"""Webhook payloads: jobs that call an HTTP endpoint instead of the agent."""
+
+import httpx
+
+from debot.agent.tools._web_py import _validate_url
+from debot.cron._types_py import CronPayload
+
+WEBHOOK_TIMEOUT_S = 30.0
+# Maximum number of characters of the response body kept for reporting
+SNIPPET_CHARS = 200
+# Most bytes of the response body read: enough for a snippet of
+# `SNIPPET_CHARS` characters and a sign that there was more
+MAX_BODY_BYTES = (SNIPPET_CHARS + 1) * 4
+
+
+def _parse_method(method: str | None) -> str:
+    method = (method or "POST").upper()
+    if not method.isalpha():
+        raise ValueError(f"Invalid HTTP method '{method}'")
+    return method
+
+
+def validate(payload: CronPayload) -> None:
+    """Check that a webhook payload has a usable URL and method, raising `ValueError` if not."""
+    if payload.url is None:
+        raise ValueError("Webhook payload requires 'url'")
+    ok, error = _validate_url(payload.url)
+    if not ok:
+        raise ValueError(f"Invalid webhook URL '{payload.url}': {error}")
+    _parse_method(payload.method)
+
+
+def _snippet(text: str) -> str:
+    text = text.strip()
+    return text[:SNIPPET_CHARS] + "..." if len(text) > SNIPPET_CHARS else text
+
+
+async def _read_head(response: httpx.Response) -> str:
+    """The start of the response body, up to `MAX_BODY_BYTES`.
+
+    The rest is never read, so a huge or endless response cannot fill memory.
+    """
+    body = bytearray()
+    try:
+        async for chunk in response.aiter_bytes():
+            room = MAX_BODY_BYTES - len(body)
+            if len(chunk) >= room:
+                body += chunk[:room]
+                break
+            body += chunk
+    except httpx.HTTPError:
+        pass
+    return body.decode(errors="replace")
+
+
+async def send(payload: CronPayload) -> str:
+    """Perform the webhook request.
+
+    Returns `"HTTP <status>: <snippet>"`; non-2xx responses raise `RuntimeError`
+    with the same summary. Raises `TimeoutError` if the request times out.
+    """
+    validate(payload)
+    try:
+        async with httpx.AsyncClient(timeout=WEBHOOK_TIMEOUT_S) as client:
+            async with client.stream(
+                _parse_method(payload.method), payload.url, headers=payload.headers, content=payload.body
+            ) as response:
+                body = await _read_head(response)
+    except httpx.TimeoutException as e:
+        raise TimeoutError(str(e)) from None
+    summary = f"HTTP {response.status_code} {response.reason_phrase}: {_snippet(body)}"
+    if not response.is_success:
+        raise RuntimeError(summary)
+    return summary
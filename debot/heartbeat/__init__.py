@@ -1,8 +1,8 @@
 """Heartbeat service for periodic agent wake-ups."""
 
 try:
-    from debot_rust import HeartbeatService
+    from debot_rust import HeartbeatManager, HeartbeatService
 except ImportError:
-    from debot.heartbeat._service_py import HeartbeatService
+    from debot.heartbeat._service_py import HeartbeatManager, HeartbeatService
 
-__all__ = ["HeartbeatService"]
+__all__ = ["HeartbeatManager", "HeartbeatService"]
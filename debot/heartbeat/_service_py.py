@@ -1,11 +1,23 @@
 """Heartbeat service - periodic agent wake-up to check for tasks."""
 
 import asyncio
+import fnmatch
+import hashlib
+import json
+import os
+import random
+import re
+import time as _time
+from collections import deque
+from datetime import datetime, time
 from pathlib import Path
+from zoneinfo import ZoneInfo
 from typing import Any, Callable, Coroutine
 
 from loguru import logger
 
+from debot.cron._service_py import _compute_next_run
+
 # Default interval: 30 minutes
 DEFAULT_HEARTBEAT_INTERVAL_S = 30 * 60
 
@@ -17,22 +29,289 @@ If nothing needs attention, reply with just: HEARTBEAT_OK"""
 # Token that indicates "nothing to do"
 HEARTBEAT_OK_TOKEN = "HEARTBEAT_OK"
 
+# How often watched task files are checked for changes
+WATCH_POLL_S = 0.5
+
+# Default limit on how long the callback may take: 10 minutes
+DEFAULT_TICK_TIMEOUT_S = 10 * 60
+
+# Longest interval that backing off after errors stretches ticks to
+MAX_BACKOFF_S = 6 * 60 * 60
+
+# Ticks kept in the history
+HISTORY_LIMIT = 400
+
+# Characters of the agent's response kept per history entry
+RESPONSE_SNIPPET_CHARS = 200
+
+# File in the workspace the tick counters persist to, when enabled
+METRICS_FILE = ".heartbeat_metrics.json"
+
+_COUNTER_KEYS = (
+    "total",
+    "ok",
+    "action",
+    "error",
+    "skipped_empty",
+    "skipped_inactive",
+    "skipped_busy",
+    "skipped_active",
+    "skipped_paused",
+    "current_ok_streak",
+    "longest_ok_streak",
+)
+
+# Task file read when none are configured
+DEFAULT_HEARTBEAT_FILE = "HEARTBEAT.md"
+
+# Characters of the agent's response kept per tick log entry
+LOG_RESPONSE_CHARS = 2000
+
+# Default size in bytes past which the tick log is rotated
+DEFAULT_LOG_MAX_BYTES = 1024 * 1024
+
+# File in the workspace the last runs of task file sections persist to
+SECTIONS_FILE = ".heartbeat_sections.json"
+
+_EVERY_UNITS = {"s": 1, "m": 60, "h": 60 * 60, "d": 24 * 60 * 60}
+
+
+def _normalize_ok(text: str) -> str:
+    """Normalize a response or token for OK detection."""
+    return text.upper().replace("_", "")
+
 
 def _is_heartbeat_empty(content: str | None) -> bool:
-    """Check if HEARTBEAT.md has no actionable content."""
+    """Check if a task file has no actionable content.
+
+    YAML frontmatter, HTML comments (including multi-line ones), headings,
+    horizontal rules, checked task items and unchecked items without text are
+    not actionable; any other text, such as ``- [ ] water the plants``, is.
+    """
     if not content:
         return True
 
-    # Lines to skip: empty, headers, HTML comments, empty checkboxes
-    skip_patterns = {"- [ ]", "* [ ]", "- [x]", "* [x]"}
+    body = _strip_html_comments(_strip_frontmatter(content))
+    return not any(_is_actionable_line(line) for line in body.split("\n"))
+
+
+def _strip_frontmatter(content: str) -> str:
+    """The content after a leading ``---`` frontmatter block, if it is closed."""
+    lines = content.split("\n")
+    if lines[0].rstrip() != "---":
+        return content
+    for i, line in enumerate(lines[1:], start=1):
+        if line.rstrip() in ("---", "..."):
+            return "\n".join(lines[i + 1 :])
+    return content
+
+
+def _strip_html_comments(content: str) -> str:
+    """Remove ``<!-- ... -->`` comments; an unclosed comment runs to the end."""
+    out = []
+    rest = content
+    while (start := rest.find("<!--")) != -1:
+        out.append(rest[:start])
+        end = rest.find("-->", start + 4)
+        if end == -1:
+            return "".join(out)
+        rest = rest[end + 3 :]
+    out.append(rest)
+    return "".join(out)
+
+
+def _is_actionable_line(line: str) -> bool:
+    line = line.strip()
+    if not line or line.startswith("#") or _is_horizontal_rule(line):
+        return False
+    item = _task_item(line)
+    if item is None:
+        return True
+    # A task item counts only if it is unchecked and says something
+    checked, text = item
+    return not checked and bool(text)
+
+
+def _is_horizontal_rule(line: str) -> bool:
+    """``---``, ``***`` or ``___``, possibly spaced out."""
+    marks = "".join(line.split())
+    return len(marks) >= 3 and marks[0] in "-*_" and marks == marks[0] * len(marks)
+
+
+def _task_item(line: str) -> tuple[bool, str] | None:
+    """Parse a list item with a checkbox into whether it is checked and its text.
+
+    Empty list items count as unchecked items without text.
+    """
+    if not line or line[0] not in "-*+" or (len(line) > 1 and not line[1].isspace()):
+        return None
+    rest = line[1:].lstrip()
+    if rest.startswith("[ ]"):
+        return False, rest[3:].strip()
+    if rest.startswith(("[x]", "[X]")):
+        return True, rest[3:].strip()
+    if not rest:
+        return False, ""
+    return None
+
+
+def _h2_title(line: str) -> tuple[str, str | None] | None:
+    """The title of a `## ` heading and its `every:` annotation, if any."""
+    line = line.rstrip()
+    if not line.startswith("##") or not line[2:3].isspace():
+        return None
+    heading = line[2:].strip()
+    start = heading.find("<!--")
+    if start < 0:
+        return heading, None
+    comment = heading[start + 4 :].rstrip()
+    comment = comment.removesuffix("-->").strip()
+    return heading[:start].strip(), comment if comment.startswith("every:") else None
+
+
+def _parse_every(annotation: str) -> int | None:
+    """Seconds in an `every: 24h` annotation, e.g. `1h30m`; None if malformed."""
+    spec = annotation.removeprefix("every:").strip()
+    parts = re.findall(r"(\d+)([smhd])", spec.lower())
+    if not parts or "".join(n + u for n, u in parts) != spec.lower():
+        return None
+    seconds = sum(int(n) * _EVERY_UNITS[u] for n, u in parts)
+    return seconds or None
+
+
+def _split_sections(content: str, file_name: str) -> tuple[str, list[dict]]:
+    """Split content into the text before the first `## ` heading and its sections."""
+    preamble, sections = "", []
+    for line in content.splitlines(keepends=True):
+        heading = _h2_title(line)
+        if heading is not None:
+            title, annotation = heading
+            every = _parse_every(annotation) if annotation else None
+            if annotation and every is None:
+                logger.warning(f"Heartbeat: ignoring malformed '{annotation}' on section '{title}' of {file_name}")
+            sections.append({"title": title, "every": every, "text": line})
+        elif sections:
+            sections[-1]["text"] += line
+        else:
+            preamble += line
+    return preamble, sections
+
+
+def _parse_hhmm(text: str) -> time:
+    """Parse a HH:MM time of day."""
+    try:
+        return datetime.strptime(text.strip(), "%H:%M").time()
+    except ValueError:
+        raise ValueError(f"Invalid time '{text}', expected HH:MM") from None
+
+
+def _active_hours(start: str | None, end: str | None) -> tuple[time, time] | None:
+    """Build the active-hours window from optional bounds given together."""
+    if start is None and end is None:
+        return None
+    if start is None or end is None:
+        raise ValueError("active_start and active_end must be given together")
+    window = (_parse_hhmm(start), _parse_hhmm(end))
+    if window[0] == window[1]:
+        raise ValueError("active_start and active_end must differ")
+    return window
+
+
+def _is_completed(line: str) -> bool:
+    """Whether a line is a checked checkbox item, e.g. `- [x] Water plants`."""
+    item = _task_item(line.lstrip())
+    return item is not None and item[0]
 
-    for line in content.split("\n"):
-        line = line.strip()
-        if not line or line.startswith("#") or line.startswith("<!--") or line in skip_patterns:
-            continue
-        return False  # Found actionable content
 
-    return True
+def _archive_path(path: Path) -> Path:
+    """The archive file for a task file: HEARTBEAT.md -> HEARTBEAT.archive.md."""
+    return path.with_name(f"{path.stem}.archive{path.suffix}")
+
+
+def _is_archive(path: Path) -> bool:
+    return path.stem.endswith(".archive") or path.name.endswith(".archive")
+
+
+def _is_log(path: Path) -> bool:
+    """Whether a file is named like a tick log, e.g. heartbeat.log.md, or a rotated one."""
+    return ".log." in path.name
+
+
+def _append_log(
+    path: Path, max_bytes: int, at_ms: int, outcome: str, response: str | None, error: str | None, tz: ZoneInfo
+) -> None:
+    """Append a tick to the log, first rotating it to `<name>.1` if it would grow past max_bytes."""
+    at = datetime.fromtimestamp(at_ms / 1000, tz)
+    entry = f"## {at.strftime('%Y-%m-%d %H:%M:%S %Z')} {outcome}\n\n"
+    if error is not None:
+        body = f"Error: {error}"
+    else:
+        body = (response or "").strip()
+        if len(body) > LOG_RESPONSE_CHARS:
+            body = body[:LOG_RESPONSE_CHARS] + "…"
+    if body:
+        entry += f"{body}\n\n"
+    size = path.stat().st_size if path.exists() else 0
+    if size and size + len(entry.encode()) > max_bytes:
+        os.replace(path, path.with_name(path.name + ".1"))
+    with path.open("a") as f:
+        f.write(entry)
+
+
+def _archive_completed(path: Path, tz: ZoneInfo) -> int:
+    """Move checked items in `path` to its archive; returns how many moved.
+
+    Nothing is written if the file changes while the new content is prepared.
+    """
+    raw = path.read_bytes()
+    content = raw.decode()
+    kept, completed = [], []
+    for line in content.splitlines(keepends=True):
+        if _is_completed(line):
+            completed.append(line.rstrip("\r\n"))
+        else:
+            kept.append(line)
+    if not completed:
+        return 0
+
+    archive = _archive_path(path)
+    archived = archive.read_text() if archive.exists() else ""
+    if archived and not archived.endswith("\n"):
+        archived += "\n"
+    if archived:
+        archived += "\n"
+    stamp = datetime.now(tz).strftime("%Y-%m-%d %H:%M %Z")
+    archived += f"## Archived {stamp}\n\n" + "".join(f"{line}\n" for line in completed)
+
+    temp = path.with_name(path.name + ".tmp")
+    temp.write_bytes("".join(kept).encode())
+    if hashlib.sha256(path.read_bytes()).digest() != hashlib.sha256(raw).digest():
+        temp.unlink()
+        raise RuntimeError(f"{path} changed while archiving, left as is")
+    archive_temp = archive.with_name(archive.name + ".tmp")
+    archive_temp.write_text(archived)
+    os.replace(archive_temp, archive)
+    os.replace(temp, path)
+    return len(completed)
+
+
+def _zone(tz: str | None) -> ZoneInfo:
+    try:
+        return ZoneInfo(tz or "UTC")
+    except Exception:
+        raise ValueError(f"Unknown timezone '{tz}'") from None
+
+
+def _check_schedule(schedule) -> None:
+    """Check that a schedule repeats."""
+    if schedule.kind == "at":
+        raise ValueError("A heartbeat schedule must repeat; 'at' schedules run once")
+    if _compute_next_run(schedule, _now_ms()) is None:
+        raise ValueError(f"Invalid '{schedule.kind}' schedule")
+
+
+def _now_ms() -> int:
+    return int(_time.time() * 1000)
 
 
 class HeartbeatService:
@@ -47,28 +326,340 @@ class HeartbeatService:
         self,
         workspace: Path,
         on_heartbeat: Callable[[str], Coroutine[Any, Any, str]] | None = None,
-        interval_s: int = DEFAULT_HEARTBEAT_INTERVAL_S,
+        interval_s: int | None = None,
         enabled: bool = True,
+        prompt: str | None = None,
+        ok_token: str | None = None,
+        heartbeat_file: str | list[str] | None = None,
+        active_start: str | None = None,
+        active_end: str | None = None,
+        tz: str | None = None,
+        watch: bool = False,
+        watch_debounce_ms: int = 2000,
+        watch_min_spacing_s: int = 60,
+        persist_metrics: bool = False,
+        tick_timeout_s: float = DEFAULT_TICK_TIMEOUT_S,
+        archive_completed: bool = False,
+        rich_callback: bool = False,
+        deliver: bool = False,
+        channel: str | None = None,
+        to: str | None = None,
+        on_deliver: Callable[[dict], Coroutine[Any, Any, Any]] | None = None,
+        schedule=None,
+        suppress_within_s: float | None = None,
+        on_result: Callable[[dict], Any] | None = None,
+        alert_after_failures: int | None = None,
+        on_alert: Callable[[dict], Coroutine[Any, Any, Any]] | None = None,
+        jitter_s: float = 0.0,
+        adaptive_interval: bool = False,
+        adaptive_factor: float = 2.0,
+        adaptive_after_oks: int = 3,
+        log_file: str | None = None,
+        log_max_bytes: int = DEFAULT_LOG_MAX_BYTES,
     ):
+        if heartbeat_file is None:
+            files = [DEFAULT_HEARTBEAT_FILE]
+        elif isinstance(heartbeat_file, str):
+            files = [heartbeat_file]
+        else:
+            files = list(heartbeat_file)
+        if not files:
+            raise ValueError("heartbeat_file must name at least one file")
+
         self.workspace = workspace
         self.on_heartbeat = on_heartbeat
-        self.interval_s = interval_s
+        if interval_s is not None and schedule is not None:
+            raise ValueError("Pass either 'interval_s' or 'schedule', not both")
+        if interval_s is None:
+            interval_s = DEFAULT_HEARTBEAT_INTERVAL_S
+        if interval_s <= 0:
+            raise ValueError("interval_s must be positive")
+        self._interval_s = interval_s
+        if schedule is not None:
+            _check_schedule(schedule)
+        self._schedule = schedule
+        self._wake = asyncio.Event()
         self.enabled = enabled
+        self.prompt = prompt or HEARTBEAT_PROMPT
+        self.ok_token = ok_token or HEARTBEAT_OK_TOKEN
+        self._files = files
+        self._active_hours = _active_hours(active_start, active_end)
+        self._tz = _zone(tz)
         self._running = False
         self._task: asyncio.Task | None = None
+        self._next_tick_at_ms: int | None = None
+        self._last_tick_at_ms: int | None = None
+        self._last_result: str | None = None
+        self._last_error: str | None = None
+        self._history: deque[dict] = deque(maxlen=HISTORY_LIMIT)
+        self._metrics_path = Path(workspace) / METRICS_FILE if persist_metrics else None
+        self._counters = self._load_counters()
+        self._consecutive_failures = 0
+        self._sections_path = Path(workspace) / SECTIONS_FILE
+        self._section_runs = self._load_section_runs()
+        if tick_timeout_s <= 0:
+            raise ValueError("tick_timeout_s must be a positive number of seconds")
+        self.tick_timeout_s = tick_timeout_s
+        self.archive_completed = archive_completed
+        if log_max_bytes <= 0:
+            raise ValueError("log_max_bytes must be positive")
+        self.log_file = log_file
+        self.log_max_bytes = log_max_bytes
+        self.rich_callback = rich_callback
+        self._ticks_started = 0
+        self.deliver = deliver
+        self.channel = channel
+        self.to = to
+        self.on_deliver = on_deliver
+        self.watch = watch
+        self._watch_debounce_s = watch_debounce_ms / 1000
+        self._watch_min_spacing_s = watch_min_spacing_s
+        self._watch_task: asyncio.Task | None = None
+        self._busy = asyncio.Lock()
+        self._fired_ms = 0
+        self.suppress_within_s = suppress_within_s
+        self.jitter_s = jitter_s
+        if not 1 < adaptive_factor < float("inf"):
+            raise ValueError("adaptive_factor must be a number greater than 1")
+        if adaptive_after_oks <= 0:
+            raise ValueError("adaptive_after_oks must be positive")
+        self._adaptive_interval = adaptive_interval
+        self._adaptive_factor = adaptive_factor
+        self._adaptive_after_oks = adaptive_after_oks
+        self._last_activity_ms: int | None = None
+        self._retry_at_ms: int | None = None
+        self.on_result = on_result
+        self._result_errors = 0
+        self._paused = False
+        self._resumed = False
+        self._stopping: set[asyncio.Task] = set()
+        self.alert_after_failures = alert_after_failures
+        self.on_alert = on_alert
+
+    @property
+    def interval_s(self) -> int:
+        return self._interval_s
+
+    @interval_s.setter
+    def interval_s(self, seconds: int) -> None:
+        self.set_interval(seconds)
+
+    @property
+    def effective_interval_s(self) -> int:
+        """The interval, stretched while the agent keeps replying OK if
+        adaptive_interval is set, then doubled per consecutive failure, up to
+        MAX_BACKOFF_S."""
+        cap = max(MAX_BACKOFF_S, self._interval_s)
+        interval_s = self._interval_s
+        if self._adaptive_interval:
+            steps = min(self._counters["current_ok_streak"] // self._adaptive_after_oks, 64)
+            interval_s = int(min(interval_s * self._adaptive_factor**steps, cap))
+        return min(interval_s * 2 ** min(self._consecutive_failures, 32), cap)
+
+    @property
+    def adaptive_interval(self) -> bool:
+        """Whether the interval lengthens while the agent keeps replying OK."""
+        return self._adaptive_interval
+
+    @adaptive_interval.setter
+    def adaptive_interval(self, adaptive_interval: bool) -> None:
+        self._adaptive_interval = adaptive_interval
+        self._wake.set()
+
+    def set_interval(self, seconds: int) -> None:
+        """Change the interval, replacing any schedule; a running loop applies it to the next tick."""
+        if seconds <= 0:
+            raise ValueError("interval_s must be positive")
+        self._interval_s = seconds
+        self._schedule = None
+        self._wake.set()
+
+    @property
+    def alert_after_failures(self) -> int | None:
+        """Failed ticks in a row after which on_alert is called, or None for never."""
+        return self._alert_after_failures
+
+    @alert_after_failures.setter
+    def alert_after_failures(self, failures: int | None) -> None:
+        if failures is not None and failures <= 0:
+            raise ValueError("alert_after_failures must be positive")
+        self._alert_after_failures = failures
+
+    @property
+    def suppress_within_s(self) -> float | None:
+        """Seconds after user activity during which ticks are put off."""
+        return self._suppress_within_s
+
+    @suppress_within_s.setter
+    def suppress_within_s(self, seconds: float | None) -> None:
+        if seconds is not None and seconds <= 0:
+            raise ValueError("suppress_within_s must be a positive number of seconds")
+        self._suppress_within_s = seconds
+
+    @property
+    def jitter_s(self) -> float:
+        """Upper bound, in seconds, of the random delay added before each periodic tick."""
+        return self._jitter_s
+
+    @jitter_s.setter
+    def jitter_s(self, seconds: float) -> None:
+        if not 0 <= seconds < float("inf"):
+            raise ValueError("jitter_s must be a non-negative number of seconds")
+        self._jitter_s = seconds
+
+    def notify_activity(self) -> None:
+        """Record that the user is active; ticks within suppress_within_s of it are put off."""
+        self._last_activity_ms = _now_ms()
+
+    def _suppressed_until(self) -> int | None:
+        """When the window after the user's last activity ends, if it has not yet."""
+        if self._suppress_within_s is None or self._last_activity_ms is None:
+            return None
+        until = self._last_activity_ms + int(self._suppress_within_s * 1000)
+        return until if until > _now_ms() else None
+
+    @property
+    def schedule(self):
+        """The schedule ticks follow, or None when they follow the interval."""
+        return self._schedule
+
+    def set_schedule(self, schedule=None) -> None:
+        """Follow `schedule` instead of the interval, or the interval again if None."""
+        if schedule is not None:
+            _check_schedule(schedule)
+        self._schedule = schedule
+        self._wake.set()
+
+    @property
+    def heartbeat_file(self) -> list[str]:
+        """The task file paths, with wildcards resolved against the workspace."""
+        paths: list[str] = []
+        for name in self._files:
+            path = Path(self.workspace) / name
+            if "*" in path.name or "?" in path.name:
+                try:
+                    matched = sorted(
+                        str(p)
+                        for p in path.parent.iterdir()
+                        if p.is_file()
+                        and fnmatch.fnmatchcase(p.name, path.name)
+                        and not _is_archive(p)
+                        and not _is_log(p)
+                    )
+                except OSError:
+                    matched = []
+            else:
+                matched = [str(path)]
+            paths.extend(p for p in matched if p not in paths)
+        return paths
+
+    @property
+    def active_start(self) -> str | None:
+        return self._active_hours[0].strftime("%H:%M") if self._active_hours else None
 
     @property
-    def heartbeat_file(self) -> Path:
-        return self.workspace / "HEARTBEAT.md"
+    def active_end(self) -> str | None:
+        return self._active_hours[1].strftime("%H:%M") if self._active_hours else None
 
-    def _read_heartbeat_file(self) -> str | None:
-        """Read HEARTBEAT.md content."""
-        if self.heartbeat_file.exists():
+    @property
+    def tz(self) -> str:
+        return self._tz.key
+
+    @property
+    def is_active_now(self) -> bool:
+        """Whether the current time falls in the active hours."""
+        if self._active_hours is None:
+            return True
+        start, end = self._active_hours
+        now = datetime.now(self._tz).time()
+        if start < end:
+            return start <= now < end
+        return now >= start or now < end
+
+    def set_active_hours(
+        self,
+        active_start: str | None = None,
+        active_end: str | None = None,
+        tz: str | None = None,
+    ) -> None:
+        """Change the active hours; the next tick uses the new window."""
+        active_hours = _active_hours(active_start, active_end)
+        if tz is not None:
+            self._tz = _zone(tz)
+        self._active_hours = active_hours
+
+    def _files_with_tasks(self, due_only: bool = False) -> list[dict]:
+        """The task files with actionable content: name (relative to the
+        workspace), path and content.
+
+        With `due_only`, only the part of each file that is due counts and is
+        kept as its content.
+        """
+        found = []
+        for path in map(Path, self.heartbeat_file):
             try:
-                return self.heartbeat_file.read_text()
+                content = path.read_text()
             except Exception:
-                return None
-        return None
+                content = None
+            name = str(path.relative_to(self.workspace))
+            timed, titles = [], None
+            if due_only and content is not None:
+                content, timed, titles = self._due(name, content)
+            if not _is_heartbeat_empty(content):
+                found.append(
+                    {"name": name, "path": str(path), "content": content, "_timed": timed, "_titles": titles}
+                )
+        return found
+
+    def _due(self, name: str, content: str) -> tuple[str, list[str], list[str] | None]:
+        """The part of a task file that is due, the keys of its timed sections,
+        and the titles of its sections if some were left out."""
+        preamble, sections = _split_sections(content, name)
+        now = _now_ms()
+        due, timed, titles, left_out = preamble, [], [], False
+        for section in sections:
+            if section["every"] is not None:
+                key = f"{name}#{section['title']}"
+                every_ms = section["every"] * 1000
+                last = self._section_runs.get(key)
+                if last is not None and now < last + every_ms - every_ms // 20:
+                    left_out = True
+                    continue
+                timed.append(key)
+            due += section["text"]
+            titles.append(section["title"])
+        return due, timed, titles if left_out else None
+
+    def _load_section_runs(self) -> dict[str, int]:
+        try:
+            return {k: int(v) for k, v in json.loads(self._sections_path.read_text()).items()}
+        except Exception:
+            return {}
+
+    def _mark_sections(self, files: list[dict], at_ms: int) -> None:
+        keys = [key for f in files for key in f["_timed"]]
+        if not keys:
+            return
+        self._section_runs.update(dict.fromkeys(keys, at_ms))
+        temp = self._sections_path.with_suffix(".json.tmp")
+        try:
+            temp.write_text(json.dumps(self._section_runs))
+            temp.replace(self._sections_path)
+        except OSError as e:
+            logger.error(f"Heartbeat: failed to save {self._sections_path}: {e}")
+
+    def _prompt_for(self, files: list[dict]) -> str:
+        """The prompt for a tick, naming the task files that have work in them
+        and, for files with sections not due yet, the sections that are."""
+        prompt = self.prompt
+        names = [f["name"] for f in files]
+        if names and names != [DEFAULT_HEARTBEAT_FILE]:
+            prompt += f"\n\nTask files: {', '.join(names)}"
+        for f in files:
+            if f["_titles"] is not None:
+                titles = ", ".join(f["_titles"]) or "none, only the text before them"
+                prompt += f"\n\nSections of {f['name']} due now: {titles}"
+        return prompt
 
     async def start(self) -> None:
         """Start the heartbeat service."""
@@ -78,53 +669,504 @@ class HeartbeatService:
 
         self._running = True
         self._task = asyncio.create_task(self._run_loop())
-        logger.info(f"Heartbeat started (every {self.interval_s}s)")
+        if self.watch:
+            self._watch_task = asyncio.create_task(self._watch_loop())
+        if self._schedule is not None:
+            logger.info(f"Heartbeat started (on {self._schedule.kind} schedule)")
+        else:
+            logger.info(f"Heartbeat started (every {self.interval_s}s)")
+
+    def pause(self) -> None:
+        """Skip the loop's ticks until resume(); manual triggers still run."""
+        if not self._paused:
+            self._paused = True
+            logger.info("Heartbeat paused")
+
+    def resume(self) -> None:
+        """Resume ticking; the next tick is a full interval from now."""
+        if self._paused:
+            self._paused = False
+            self._resumed = True
+            self._wake.set()
+            logger.info("Heartbeat resumed")
+
+    @property
+    def paused(self) -> bool:
+        return self._paused
 
     def stop(self) -> None:
         """Stop the heartbeat service."""
         self._running = False
-        if self._task:
-            self._task.cancel()
-            self._task = None
+        for task in (self._task, self._watch_task):
+            if task:
+                task.cancel()
+                self._stopping.add(task)
+        self._task = None
+        self._watch_task = None
+
+    async def wait_stopped(self, timeout_s: float | None = None) -> bool:
+        """Wait for the loop to exit after stop(); False if the timeout expired first."""
+        if timeout_s is not None and timeout_s <= 0:
+            raise ValueError("timeout_s must be a positive number of seconds")
+        pending = {t for t in self._stopping if not t.done()}
+        if pending:
+            _, pending = await asyncio.wait(pending, timeout=timeout_s)
+        self._stopping = set(pending)
+        return not pending
 
     async def _run_loop(self) -> None:
         """Main heartbeat loop."""
         while self._running:
             try:
-                await asyncio.sleep(self.interval_s)
+                await self._sleep_interval()
                 if self._running:
-                    await self._tick()
+                    outcome = await self._run_tick()
+                    # Retry a tick put off by user activity when the window ends
+                    self._retry_at_ms = self._suppressed_until() if outcome == "skipped_active" else None
             except asyncio.CancelledError:
                 break
+        self._next_tick_at_ms = None
+
+    async def _run_tick(self) -> str:
+        """Run a tick and record its outcome, or skip it if one is running."""
+        if self._busy.locked():
+            logger.info("Heartbeat: skipped, previous tick still running")
+            self._record(_now_ms(), "skipped_busy")
+            return "skipped_busy"
+        async with self._busy:
+            started_ms = _now_ms()
+            failures_before = self._consecutive_failures
+            try:
+                outcome, response = await self._tick()
+                self._record(started_ms, outcome, response)
             except Exception as e:
-                logger.error(f"Heartbeat error: {e}")
+                # Log only the first of a run of failures
+                if failures_before == 0:
+                    logger.error(f"Heartbeat error: {e} (backing off until a tick succeeds)")
+                self._record(started_ms, "error", error=str(e))
+                return "error"
+            else:
+                if failures_before and not self._consecutive_failures:
+                    logger.info(f"Heartbeat: recovered after {failures_before} failed ticks")
+                return outcome
 
-    async def _tick(self) -> None:
-        """Execute a single heartbeat tick."""
-        content = self._read_heartbeat_file()
+    def _fingerprint(self) -> list:
+        """Modification time and size of each task file; None if missing."""
+        result = []
+        for path in self.heartbeat_file:
+            try:
+                stat = Path(path).stat()
+                result.append((path, (stat.st_mtime_ns, stat.st_size)))
+            except OSError:
+                result.append((path, None))
+        return result
+
+    async def _watch_loop(self) -> None:
+        """Tick once a task file change has settled."""
+        loop = asyncio.get_running_loop()
+        last = self._fingerprint()
+        changed_at: float | None = None
+        last_tick: float | None = None
+        while self._running:
+            await asyncio.sleep(WATCH_POLL_S)
+            current = self._fingerprint()
+            if current != last:
+                last = current
+                changed_at = loop.time()
+                continue
+            if changed_at is None or loop.time() - changed_at < self._watch_debounce_s:
+                continue
+            if last_tick is not None and loop.time() - last_tick < self._watch_min_spacing_s:
+                continue
+            changed_at = None
+            last_tick = loop.time()
+            logger.info("Heartbeat: task files changed")
+            await self._run_tick()
+
+    async def _sleep_interval(self) -> None:
+        """Sleep one interval, or until the next scheduled time, re-armed from
+        the same start when either changes."""
+        loop = asyncio.get_running_loop()
+        started = loop.time()
+        slept_from = _now_ms()
+        # Drawn once per sleep, so re-arming keeps the offset
+        jitter_fraction = random.random()
+        while self._running:
+            if self._retry_at_ms is not None:
+                next_ms = self._retry_at_ms
+            elif self._schedule is not None:
+                next_ms = _compute_next_run(self._schedule, max(_now_ms(), self._fired_ms))
+            else:
+                next_ms = slept_from + self.effective_interval_s * 1000
+            if next_ms is not None:
+                next_ms += int(self._jitter_s * 1000 * jitter_fraction)
+            self._next_tick_at_ms = next_ms
+            self._wake.clear()
+            # A schedule with no further times waits to be changed
+            remaining = None if next_ms is None else started + (next_ms - slept_from) / 1000 - loop.time()
+            if remaining is not None and remaining <= 0:
+                self._fired_ms = next_ms
+                return
+            try:
+                await asyncio.wait_for(self._wake.wait(), remaining)
+            except asyncio.TimeoutError:
+                self._fired_ms = next_ms
+                return
+            if self._resumed:
+                # Count the next tick from the resume, not the ticks skipped while paused
+                self._resumed = False
+                started = loop.time()
+                slept_from = _now_ms()
+                self._retry_at_ms = None
+
+    def _record(
+        self,
+        started_ms: int,
+        result: str,
+        response: str | None = None,
+        error: str | None = None,
+    ) -> None:
+        """Record a tick, manual or scheduled, that started at `started_ms`."""
+        now = _now_ms()
+        self._last_tick_at_ms = now
+        self._last_result = result
+        self._last_error = error
+        if result == "error":
+            self._consecutive_failures += 1
+            if self._consecutive_failures == self._alert_after_failures:
+                asyncio.ensure_future(self._alert(self._consecutive_failures, error))
+        elif result in ("ok", "action"):
+            self._consecutive_failures = 0
+        if result == "ok":
+            self._counters["current_ok_streak"] += 1
+            self._counters["longest_ok_streak"] = max(
+                self._counters["longest_ok_streak"], self._counters["current_ok_streak"]
+            )
+        elif result in ("action", "error"):
+            self._counters["current_ok_streak"] = 0
+        self._counters["total"] += 1
+        self._counters[result] += 1
+        record = {
+            "at_ms": started_ms,
+            "result": result,
+            "duration_ms": now - started_ms,
+            "response": response[:RESPONSE_SNIPPET_CHARS] if response is not None else None,
+            "error": error,
+        }
+        self._history.append(record)
+        self._save_counters()
+        if self.log_file and result in ("ok", "action", "error"):
+            path = Path(self.workspace) / self.log_file
+            try:
+                _append_log(path, self.log_max_bytes, started_ms, result, response, error, self._tz)
+            except OSError as e:
+                logger.error(f"Heartbeat: failed to write {path}: {e}")
+        self._report(record)
 
-        # Skip if HEARTBEAT.md is empty or doesn't exist
-        if _is_heartbeat_empty(content):
-            logger.debug("Heartbeat: no tasks (HEARTBEAT.md empty)")
+    def _report(self, record: dict) -> None:
+        """Pass a tick's record to on_result in the background, counting exceptions."""
+        if not self.on_result:
             return
+        info = {"outcome": record["result"], **{k: v for k, v in record.items() if k != "result"}}
 
-        logger.info("Heartbeat: checking for tasks...")
+        async def call() -> None:
+            try:
+                result = self.on_result(info)
+                if asyncio.iscoroutine(result):
+                    await result
+            except Exception:
+                self._result_errors += 1
 
-        if self.on_heartbeat:
+        asyncio.ensure_future(call())
+
+    def _load_counters(self) -> dict[str, int]:
+        counters = dict.fromkeys(_COUNTER_KEYS, 0)
+        if self._metrics_path is not None:
             try:
-                response = await self.on_heartbeat(HEARTBEAT_PROMPT)
+                saved = json.loads(self._metrics_path.read_text())
+                counters.update({k: int(saved[k]) for k in _COUNTER_KEYS if k in saved})
+            except Exception:
+                pass
+        return counters
+
+    def _save_counters(self) -> None:
+        if self._metrics_path is None:
+            return
+        temp = self._metrics_path.with_suffix(".json.tmp")
+        try:
+            temp.write_text(json.dumps(self._counters))
+            temp.replace(self._metrics_path)
+        except OSError as e:
+            logger.error(f"Heartbeat: failed to save {self._metrics_path}: {e}")
+
+    def history(self, limit: int = 20) -> list[dict]:
+        """The most recent ticks, newest first."""
+        return [dict(tick) for tick in reversed(self._history)][:limit]
+
+    def metrics(self) -> dict[str, int]:
+        """Tick counts: total and one count per result, the current and longest
+        runs of OK replies, plus on_result_errors."""
+        return {**self._counters, "on_result_errors": self._result_errors}
+
+    def reset_metrics(self) -> None:
+        """Zero the tick counts and clear the history."""
+        self._counters = dict.fromkeys(_COUNTER_KEYS, 0)
+        self._history.clear()
+        self._save_counters()
+        self._result_errors = 0
+
+    def status(self) -> dict:
+        """The service's state and what the last tick did."""
+        return {
+            "running": self._running,
+            "paused": self._paused,
+            "interval_s": None if self._schedule is not None else self.interval_s,
+            "next_tick_at_ms": self._next_tick_at_ms if self._running else None,
+            "last_tick_at_ms": self._last_tick_at_ms,
+            "last_result": self._last_result,
+            "last_error": self._last_error,
+            "ticks_total": self._counters["total"],
+            "consecutive_failures": self._consecutive_failures,
+            "effective_interval_s": None if self._schedule is not None else self.effective_interval_s,
+        }
+
+    def _outcome(self, response: str | None) -> str:
+        if response is not None and _normalize_ok(self.ok_token) not in _normalize_ok(response):
+            return "action"
+        return "ok"
+
+    async def _tick(self) -> tuple[str, str | None]:
+        """Execute a single heartbeat tick."""
+        if self._paused:
+            return "skipped_paused", None
+
+        if not self.is_active_now:
+            logger.info(
+                f"Heartbeat: skipped, outside active hours "
+                f"({self.active_start}-{self.active_end} {self.tz})"
+            )
+            return "skipped_inactive", None
 
-                # Check if agent said "nothing to do"
-                if HEARTBEAT_OK_TOKEN.replace("_", "") in response.upper().replace("_", ""):
-                    logger.info("Heartbeat: OK (no action needed)")
-                else:
-                    logger.info("Heartbeat: completed task")
+        until = self._suppressed_until()
+        if until is not None:
+            logger.info(f"Heartbeat: skipped, user active, quiet for another {(until - _now_ms()) // 1000}s")
+            return "skipped_active", None
 
+        started_ms = _now_ms()
+        files = self._files_with_tasks(due_only=True)
+
+        # Skip if every task file is empty or missing, or has nothing due
+        if not files:
+            logger.debug("Heartbeat: no tasks (task files empty)")
+            return "skipped_empty", None
+
+        logger.info("Heartbeat: checking for tasks...")
+
+        if not self.on_heartbeat:
+            return "ok", None
+        try:
+            response = await self._call(files, self.tick_timeout_s, manual=False)
+        except Exception as e:
+            raise RuntimeError(f"Callback error: {e}") from e
+        self._mark_sections(files, started_ms)
+
+        # Check if agent said "nothing to do"
+        outcome = self._outcome(response)
+        if outcome == "ok":
+            logger.info("Heartbeat: OK (no action needed)")
+        else:
+            logger.info("Heartbeat: completed task")
+            self._archive(files)
+            await self._deliver(response, manual=False)
+        return outcome, response
+
+    def set_deliver_callback(self, callback) -> None:
+        """Set the callback that receives responses to deliver."""
+        self.on_deliver = callback
+
+    def set_result_callback(self, callback) -> None:
+        """Set the callback told the outcome of each tick."""
+        self.on_result = callback
+
+    def set_alert_callback(self, callback) -> None:
+        """Set the callback called when ticks keep failing."""
+        self.on_alert = callback
+
+    async def _alert(self, failures: int, error: str | None) -> None:
+        """Tell on_alert that `failures` ticks in a row have failed."""
+        logger.error(f"Heartbeat: {failures} failed ticks in a row")
+        if not self.on_alert:
+            return
+        try:
+            await asyncio.wait_for(self.on_alert({"failures": failures, "error": error}), self.tick_timeout_s)
+        except Exception as e:
+            logger.error(f"Heartbeat: alert failed: {e}")
+
+    async def _deliver(self, message: str, manual: bool) -> None:
+        """Pass a response that is not OK to on_deliver, if delivery is enabled."""
+        if not (self.deliver and self.on_deliver):
+            return
+        payload = {"message": message, "channel": self.channel, "to": self.to, "manual": manual}
+        try:
+            await asyncio.wait_for(self.on_deliver(payload), self.tick_timeout_s)
+        except Exception as e:
+            logger.error(f"Heartbeat: delivery failed: {e}")
+
+    def _archive(self, files: list[dict]) -> None:
+        """Move completed tasks out of `files` when archiving is enabled."""
+        if not self.archive_completed:
+            return
+        for name in (f["name"] for f in files):
+            try:
+                moved = _archive_completed(Path(self.workspace) / name, self._tz)
             except Exception as e:
-                logger.error(f"Heartbeat execution failed: {e}")
+                logger.error(f"Heartbeat: failed to archive {name}: {e}")
+                continue
+            if moved:
+                logger.info(f"Heartbeat: archived {moved} completed tasks from {name}")
+
+    async def _call(self, files: list[dict], timeout_s: float, manual: bool) -> str:
+        prompt = self._prompt_for(files)
+        if self.rich_callback:
+            self._ticks_started += 1
+            shown = [{k: f[k] for k in ("name", "path", "content")} for f in files]
+            context = {"tick": self._ticks_started, "manual": manual, "files": shown}
+            call = self.on_heartbeat(prompt, context)
+        else:
+            call = self.on_heartbeat(prompt)
+        try:
+            return await asyncio.wait_for(call, timeout_s)
+        except asyncio.TimeoutError:
+            raise TimeoutError(f"heartbeat callback timed out after {timeout_s}s") from None
+
+    async def trigger_now(
+        self, timeout_s: float | None = None, wait: bool = False, skip_if_empty: bool = True
+    ) -> dict | str | None:
+        """Manually trigger a heartbeat, raising TimeoutError past `timeout_s`.
+
+        Returns a dict of whether the tick was `skipped`, its `outcome`, the
+        `response` and `duration_ms`. With `skip_if_empty`, the agent is not
+        woken when every task file is empty; without it, the callback is always
+        called and the raw response is returned, or None if skipped.
 
-    async def trigger_now(self) -> str | None:
-        """Manually trigger a heartbeat."""
+        If another tick is running this one is skipped, unless `wait` is set.
+        """
+        started_ms = _now_ms()
+        if self._busy.locked() and not wait:
+            self._record(started_ms, "skipped_busy")
+            return self._trigger_result(skip_if_empty, started_ms, "skipped_busy", None)
+        async with self._busy:
+            files = self._files_with_tasks()
+            if skip_if_empty and not files:
+                self._record(started_ms, "skipped_empty")
+                return self._trigger_result(skip_if_empty, started_ms, "skipped_empty", None)
+            response = await self._trigger(started_ms, files, timeout_s)
+            return self._trigger_result(skip_if_empty, started_ms, self._outcome(response), response)
+
+    @staticmethod
+    def _trigger_result(skip_if_empty: bool, started_ms: int, outcome: str, response: str | None) -> dict | str | None:
+        if not skip_if_empty:
+            return response
+        return {
+            "skipped": outcome not in ("ok", "action"),
+            "outcome": outcome,
+            "response": response,
+            "duration_ms": _now_ms() - started_ms,
+        }
+
+    async def _trigger(self, started_ms: int, files: list[dict], timeout_s: float | None) -> str | None:
+        response = None
         if self.on_heartbeat:
-            return await self.on_heartbeat(HEARTBEAT_PROMPT)
-        return None
+            try:
+                response = await self._call(
+                    files,
+                    timeout_s if timeout_s is not None else self.tick_timeout_s,
+                    manual=True,
+                )
+            except Exception as e:
+                self._record(started_ms, "error", error=f"Callback error: {e}")
+                raise
+        outcome = self._outcome(response)
+        if outcome == "action":
+            self._archive(files)
+            await self._deliver(response or "", manual=True)
+        self._record(started_ms, outcome, response)
+        return response
+
+
+class HeartbeatManager:
+    """Named heartbeats started and stopped together, sharing one on_result hook."""
+
+    def __init__(self, on_result: Callable[[dict], Any] | None = None):
+        self.on_result = on_result
+        self._services: dict[str, HeartbeatService] = {}
+        self._running = False
+        self._stopped = asyncio.Event()
+
+    def add(self, name: str, workspace: Path, interval_s: int | None = None, **kwargs) -> HeartbeatService:
+        """Add a heartbeat and return its service; it starts at once if the manager runs."""
+        if name in self._services:
+            raise ValueError(f"A heartbeat named '{name}' already exists")
+        if "on_result" in kwargs:
+            raise ValueError("on_result is set on the manager, not per heartbeat")
+        service = HeartbeatService(workspace, interval_s=interval_s, **kwargs)
+        service.on_result = lambda info: self._report(name, info)
+        if self._running:
+            asyncio.ensure_future(service.start())
+        self._services[name] = service
+        return service
+
+    def _report(self, name: str, info: dict):
+        if self.on_result:
+            return self.on_result({"name": name, **info})
+
+    def remove(self, name: str) -> bool:
+        """Stop and remove a heartbeat; returns whether it existed."""
+        service = self._services.pop(name, None)
+        if service is None:
+            return False
+        service.stop()
+        return True
+
+    def get(self, name: str) -> HeartbeatService | None:
+        return self._services.get(name)
+
+    def names(self) -> list[str]:
+        return sorted(self._services)
+
+    def status_all(self) -> dict[str, dict]:
+        """Each heartbeat's status(), keyed by name."""
+        return {name: self._services[name].status() for name in self.names()}
+
+    def set_result_callback(self, callback) -> None:
+        """Set the callback told the outcome of each tick of every heartbeat."""
+        self.on_result = callback
+
+    async def start(self) -> None:
+        """Start every heartbeat; returns once the manager is stopped and their loops exited."""
+        if self._running:
+            raise RuntimeError("The heartbeat manager is already running")
+        self._running = True
+        self._stopped.clear()
+        for service in self._services.values():
+            await service.start()
+        await self._stopped.wait()
+        for service in list(self._services.values()):
+            await service.wait_stopped()
+
+    def stop(self) -> None:
+        """Stop every heartbeat."""
+        self._running = False
+        for service in self._services.values():
+            service.stop()
+        self._stopped.set()
+
+    @property
+    def is_running(self) -> bool:
+        return self._running
+
+    def __len__(self) -> int:
+        return len(self._services)
+
+    def __contains__(self, name: str) -> bool:
+        return name in self._services
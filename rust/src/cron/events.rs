@@ -0,0 +1,82 @@
+//! Lifecycle events for the cron service.
+
+use pyo3::prelude::*;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+use super::{now_ms, ClockSource};
+
+/// Dispatches lifecycle events to the optional Python `on_event` callback.
+///
+/// Events are queued on a channel drained by a background task, so emitting
+/// never blocks the scheduler and callbacks see events in order. Exceptions
+/// raised by the callback are swallowed and counted. Without a callback,
+/// events fall back to a `[cron]` line on stderr. Events are stamped by the
+/// service's clock.
+#[derive(Clone)]
+pub(super) struct CronEvents {
+    tx: Option<mpsc::UnboundedSender<Value>>,
+    errors: Arc<AtomicU64>,
+    clock: ClockSource,
+}
+
+impl CronEvents {
+    pub(super) fn new(callback: Option<PyObject>, clock: ClockSource) -> Self {
+        let errors = Arc::new(AtomicU64::new(0));
+        let tx = callback.map(|cb| {
+            let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+            let errors = errors.clone();
+            pyo3_async_runtimes::tokio::get_runtime().spawn(async move {
+                while let Some(event) = rx.recv().await {
+                    let result = Python::with_gil(|py| -> PyResult<()> {
+                        let dict = py
+                            .import("json")?
+                            .call_method1("loads", (event.to_string(),))?;
+                        cb.call1(py, (dict,))?;
+                        Ok(())
+                    });
+                    if result.is_err() {
+                        errors.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+            tx
+        });
+        Self { tx, errors, clock }
+    }
+
+    /// Emit `event` with extra `fields` (a JSON object).
+    ///
+    /// `log` builds the stderr line used when no callback is set.
+    pub(super) fn emit(&self, event: &str, fields: Value, log: impl FnOnce() -> String) {
+        if !self.send(event, fields) {
+            eprintln!("[cron] {}", log());
+        }
+    }
+
+    /// Emit `event` without a stderr fallback (for high-frequency events).
+    pub(super) fn emit_quiet(&self, event: &str, fields: Value) {
+        self.send(event, fields);
+    }
+
+    fn send(&self, event: &str, fields: Value) -> bool {
+        let Some(tx) = &self.tx else {
+            return false;
+        };
+        // A failing clock is reported by the service as `clock_error`
+        let ts_ms = self.clock.read().unwrap_or_else(|_| now_ms());
+        let mut payload = json!({ "event": event, "ts_ms": ts_ms });
+        if let (Some(dst), Value::Object(src)) = (payload.as_object_mut(), fields) {
+            dst.extend(src);
+        }
+        let _ = tx.send(payload);
+        true
+    }
+
+    /// Number of exceptions raised by the event callback.
+    pub(super) fn error_count(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,126 @@
+//! iCalendar (RFC 5545) rendering of the job schedule.
+
+use chrono::{TimeZone, Utc};
+
+use super::{next_occurrences, CronJob};
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+const HOUR_MS: i64 = 60 * 60 * 1000;
+const MINUTE_MS: i64 = 60 * 1000;
+/// Content lines longer than this many octets are folded.
+const MAX_LINE_OCTETS: usize = 75;
+
+/// Render enabled jobs as a VCALENDAR.
+///
+/// `at` jobs become single events and `every` jobs a recurring event. Other
+/// schedules cannot be expressed as an RRULE in general, so their next
+/// `occurrences` runs are listed as separate events.
+pub(super) fn render(jobs: &[CronJob], occurrences: usize, now_ms: i64) -> String {
+    let mut out = String::new();
+    push_line(&mut out, "BEGIN:VCALENDAR");
+    push_line(&mut out, "VERSION:2.0");
+    push_line(&mut out, "PRODID:-//debot//cron//EN");
+    push_line(&mut out, "CALSCALE:GREGORIAN");
+
+    let stamp = format_utc(now_ms);
+    for job in jobs.iter().filter(|j| j.enabled) {
+        let Some(first) = job.state.next_run_at_ms else {
+            continue;
+        };
+        match job.schedule.kind.as_str() {
+            "at" => push_event(&mut out, job, &job.id, first, None, &stamp),
+            "every" => {
+                let rrule = job.schedule.every_ms.map(rrule_for_interval);
+                push_event(&mut out, job, &job.id, first, rrule.as_deref(), &stamp);
+            }
+            _ => {
+                let rest = next_occurrences(&job.schedule, first, occurrences.saturating_sub(1))
+                    .unwrap_or_default();
+                for fire in std::iter::once(first).chain(rest).take(occurrences) {
+                    let uid = format!("{}-{}", job.id, fire);
+                    push_event(&mut out, job, &uid, fire, None, &stamp);
+                }
+            }
+        }
+    }
+
+    push_line(&mut out, "END:VCALENDAR");
+    out
+}
+
+fn push_event(
+    out: &mut String,
+    job: &CronJob,
+    uid: &str,
+    start_ms: i64,
+    rrule: Option<&str>,
+    stamp: &str,
+) {
+    push_line(out, "BEGIN:VEVENT");
+    push_line(out, &format!("UID:{}@debot", uid));
+    push_line(out, &format!("DTSTAMP:{}", stamp));
+    push_line(out, &format!("DTSTART:{}", format_utc(start_ms)));
+    if let Some(rrule) = rrule {
+        push_line(out, &format!("RRULE:{}", rrule));
+    }
+    push_line(out, &format!("SUMMARY:{}", escape_text(&job.name)));
+    if !job.payload.message.is_empty() {
+        push_line(
+            out,
+            &format!("DESCRIPTION:{}", escape_text(&job.payload.message)),
+        );
+    }
+    push_line(out, "END:VEVENT");
+}
+
+/// Express a fixed interval in the coarsest unit that divides it exactly.
+fn rrule_for_interval(every_ms: i64) -> String {
+    let (freq, unit) = if every_ms % DAY_MS == 0 {
+        ("DAILY", DAY_MS)
+    } else if every_ms % HOUR_MS == 0 {
+        ("HOURLY", HOUR_MS)
+    } else if every_ms % MINUTE_MS == 0 {
+        ("MINUTELY", MINUTE_MS)
+    } else {
+        ("SECONDLY", 1000)
+    };
+    format!("FREQ={};INTERVAL={}", freq, (every_ms / unit).max(1))
+}
+
+fn format_utc(ms: i64) -> String {
+    Utc.timestamp_millis_opt(ms)
+        .single()
+        .map(|dt| dt.format("%Y%m%dT%H%M%SZ").to_string())
+        .unwrap_or_default()
+}
+
+/// Escape a TEXT value.
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Append a content line, folding it at `MAX_LINE_OCTETS` octets.
+fn push_line(out: &mut String, line: &str) {
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            // The leading space counts towards the continuation line
+            octets = 1;
+        }
+        out.push(c);
+        octets += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
@@ -0,0 +1,90 @@
+//! Execution metrics of a cron service: run, failure, timeout and overlap
+//! counts, overall and per job.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Metrics shared between a service and the runs it spawns.
+pub(super) type SharedMetrics = Arc<parking_lot::Mutex<CronMetrics>>;
+
+#[derive(Default)]
+struct JobCounts {
+    executions: u64,
+    failures: u64,
+    timeouts: u64,
+    skipped_overlap: u64,
+}
+
+#[derive(Default)]
+pub(super) struct CronMetrics {
+    executions: u64,
+    failures: u64,
+    timeouts: u64,
+    skipped_overlap: u64,
+    total_duration_ms: u64,
+    jobs: HashMap<String, JobCounts>,
+}
+
+impl CronMetrics {
+    /// Record a finished job execution.
+    pub(super) fn record_execution(
+        &mut self,
+        job_id: &str,
+        duration_ms: i64,
+        failed: bool,
+        timed_out: bool,
+    ) {
+        self.executions += 1;
+        self.failures += failed as u64;
+        self.timeouts += timed_out as u64;
+        self.total_duration_ms += duration_ms.max(0) as u64;
+
+        let job = self.jobs.entry(job_id.to_string()).or_default();
+        job.executions += 1;
+        job.failures += failed as u64;
+        job.timeouts += timed_out as u64;
+    }
+
+    /// Record a run skipped because the job was already executing.
+    pub(super) fn record_skipped_overlap(&mut self, job_id: &str) {
+        self.skipped_overlap += 1;
+        self.jobs
+            .entry(job_id.to_string())
+            .or_default()
+            .skipped_overlap += 1;
+    }
+
+    /// The metrics as a JSON object, with the average run duration.
+    pub(super) fn to_json(&self) -> Value {
+        let avg_duration_ms = if self.executions > 0 {
+            Some(self.total_duration_ms as f64 / self.executions as f64)
+        } else {
+            None
+        };
+        let jobs: HashMap<&str, _> = self
+            .jobs
+            .iter()
+            .map(|(id, c)| {
+                (
+                    id.as_str(),
+                    json!({
+                        "executions": c.executions,
+                        "failures": c.failures,
+                        "timeouts": c.timeouts,
+                        "skipped_overlap": c.skipped_overlap,
+                    }),
+                )
+            })
+            .collect();
+
+        json!({
+            "executions": self.executions,
+            "failures": self.failures,
+            "timeouts": self.timeouts,
+            "skipped_overlap": self.skipped_overlap,
+            "avg_duration_ms": avg_duration_ms,
+            "jobs": jobs,
+        })
+    }
+}
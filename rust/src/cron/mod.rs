@@ -0,0 +1,3090 @@
+//! Cron service for scheduling agent tasks.
+
+mod events;
+mod ics;
+mod metrics;
+mod shell;
+mod store;
+mod webhook;
+
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3::IntoPyObjectExt;
+use pyo3_async_runtimes::tokio::future_into_py;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{Mutex, Notify};
+
+use crate::session::{json_to_python, python_to_json};
+use events::CronEvents;
+use metrics::{CronMetrics, SharedMetrics};
+use serde_json::json;
+use store::{
+    flush_store, lock_blocking, parse_store, save_store, CronStore, CronStoreJson, StoreJson,
+    BLOCKING_LOCK_TIMEOUT,
+};
+
+/// How often the store file is polled for external changes.
+const STORE_WATCH_INTERVAL_MS: u64 = 2000;
+/// Number of recent runs considered when reporting the slowest job.
+const RECENT_RUNS_WINDOW: usize = 50;
+/// Maximum number of follow-up jobs a single run may trigger in sequence.
+const MAX_CHAIN_DEPTH: usize = 5;
+/// Maximum number of occurrences `upcoming_jobs` lists for a single job.
+const MAX_UPCOMING_PER_JOB: usize = 1000;
+/// Runs missed by at most this long while the service was stopped still
+/// fire on start; older ones are dropped and the next run recomputed.
+const CATCH_UP_WINDOW_MS: i64 = 5 * 60 * 1000;
+/// Default for the longest the scheduler sleeps before re-reading the clock.
+const DEFAULT_IDLE_INTERVAL_MS: u64 = 60_000;
+/// Drift between wall-clock and monotonic time over one sleep that counts as
+/// a clock jump (suspend/resume, NTP step).
+const CLOCK_JUMP_THRESHOLD_MS: i64 = 5000;
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Read a Python clock callable returning epoch milliseconds.
+fn call_clock(py: Python<'_>, clock: &PyObject) -> PyResult<i64> {
+    clock.call0(py)?.extract::<i64>(py)
+}
+
+/// The system clock, or a Python callable returning epoch milliseconds in
+/// its place.
+#[derive(Clone, Default)]
+struct ClockSource(Option<Arc<PyObject>>);
+
+impl ClockSource {
+    /// The current time, or the error the callable raised.
+    fn read(&self) -> PyResult<i64> {
+        match &self.0 {
+            Some(clock) => Python::with_gil(|py| call_clock(py, clock)),
+            None => Ok(now_ms()),
+        }
+    }
+}
+
+/// The wall clock a service schedules by.
+#[derive(Clone)]
+struct WallClock {
+    source: ClockSource,
+    events: CronEvents,
+}
+
+impl WallClock {
+    /// The current time. If the callable fails, the error is reported as a
+    /// `clock_error` event and the system clock is read instead.
+    fn now_ms(&self) -> i64 {
+        self.source.read().unwrap_or_else(|e| {
+            self.events
+                .emit("clock_error", json!({ "error": e.to_string() }), || {
+                    format!("Clock failed, reading the system clock: {}", e)
+                });
+            now_ms()
+        })
+    }
+}
+
+/// Schedule definition for a cron job.
+#[pyclass]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CronSchedule {
+    #[pyo3(get, set)]
+    pub kind: String, // "at", "every", "cron", "daily", "weekly"
+    #[pyo3(get, set)]
+    pub at_ms: Option<i64>,
+    #[pyo3(get, set)]
+    pub every_ms: Option<i64>,
+    #[pyo3(get, set)]
+    pub expr: Option<String>,
+    #[pyo3(get, set)]
+    pub tz: Option<String>,
+    /// Local hour (0-23) for `daily`/`weekly` schedules.
+    #[pyo3(get, set)]
+    pub hour: Option<u32>,
+    /// Local minute (0-59) for `daily`/`weekly` schedules, defaults to 0.
+    #[pyo3(get, set)]
+    pub minute: Option<u32>,
+    /// Weekday mask for `weekly` schedules: bit 0 is Monday, bit 6 Sunday.
+    #[pyo3(get, set)]
+    pub weekdays: Option<u8>,
+    /// Start of the daily quiet window (`"HH:MM"`, local to `tz`).
+    #[pyo3(get, set)]
+    pub quiet_start: Option<String>,
+    /// End of the daily quiet window (`"HH:MM"`); may be before the start
+    /// to span midnight.
+    #[pyo3(get, set)]
+    pub quiet_end: Option<String>,
+    /// `"defer"` (default) moves runs to the end of the quiet window;
+    /// `"skip"` drops them.
+    #[pyo3(get, set)]
+    pub quiet_policy: Option<String>,
+}
+
+/// An `at` time given either as epoch milliseconds or an ISO-8601 string.
+#[derive(FromPyObject)]
+enum AtTime {
+    Ms(i64),
+    Text(String),
+}
+
+#[pymethods]
+impl CronSchedule {
+    /// `at` is a convenience for `at_ms`: epoch milliseconds or an ISO-8601
+    /// string (naive strings are read in `tz`). It must lie in the future,
+    /// as told by `clock` (a callable returning epoch milliseconds, such as
+    /// the one given to the service) or else the system clock.
+    #[new]
+    #[pyo3(signature = (kind, at_ms=None, every_ms=None, expr=None, tz=None, hour=None, minute=None, weekdays=None, at=None, quiet_start=None, quiet_end=None, quiet_policy=None, clock=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn py_new(
+        kind: String,
+        at_ms: Option<i64>,
+        every_ms: Option<i64>,
+        expr: Option<String>,
+        tz: Option<String>,
+        hour: Option<u32>,
+        minute: Option<u32>,
+        weekdays: Option<u8>,
+        at: Option<AtTime>,
+        quiet_start: Option<String>,
+        quiet_end: Option<String>,
+        quiet_policy: Option<String>,
+        clock: Option<PyObject>,
+    ) -> PyResult<Self> {
+        let at_ms = match at {
+            None => at_ms,
+            Some(_) if at_ms.is_some() => {
+                return Err(pyo3::exceptions::PyValueError::new_err(
+                    "Pass either 'at' or 'at_ms', not both",
+                ))
+            }
+            Some(at) => {
+                let ms = match at {
+                    AtTime::Ms(ms) => ms,
+                    AtTime::Text(text) => parse_at_time(&text, tz.as_deref())
+                        .map_err(pyo3::exceptions::PyValueError::new_err)?,
+                };
+                let now = match &clock {
+                    Some(clock) => Python::with_gil(|py| call_clock(py, clock))?,
+                    None => now_ms(),
+                };
+                if ms <= now {
+                    return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                        "'at' time {} is in the past",
+                        ms
+                    )));
+                }
+                Some(ms)
+            }
+        };
+        let mut schedule = Self::new(kind, at_ms, every_ms, expr, tz, hour, minute, weekdays);
+        schedule.quiet_start = quiet_start;
+        schedule.quiet_end = quiet_end;
+        schedule.quiet_policy = quiet_policy;
+        quiet_window(&schedule).map_err(pyo3::exceptions::PyValueError::new_err)?;
+        Ok(schedule)
+    }
+
+    /// Convert to a dict keyed by the store's camelCase field names.
+    fn to_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
+        json_to_python(py, &self.to_store_json())
+    }
+
+    /// Build from a dict as produced by `to_dict`.
+    #[staticmethod]
+    fn from_dict(d: Bound<'_, PyAny>) -> PyResult<Self> {
+        Self::from_store_json(python_to_json(d)?).map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self == other
+    }
+
+    fn __repr__(&self) -> String {
+        format!("CronSchedule(kind={:?})", self.kind)
+    }
+}
+
+impl CronSchedule {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        kind: String,
+        at_ms: Option<i64>,
+        every_ms: Option<i64>,
+        expr: Option<String>,
+        tz: Option<String>,
+        hour: Option<u32>,
+        minute: Option<u32>,
+        weekdays: Option<u8>,
+    ) -> Self {
+        Self {
+            kind,
+            at_ms,
+            every_ms,
+            expr,
+            tz,
+            hour,
+            minute,
+            weekdays,
+            quiet_start: None,
+            quiet_end: None,
+            quiet_policy: None,
+        }
+    }
+}
+
+/// What to do when the job runs.
+#[pyclass]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CronPayload {
+    #[pyo3(get, set)]
+    pub kind: String, // "system_event", "agent_turn", "webhook", "shell"
+    #[pyo3(get, set)]
+    pub message: String,
+    #[pyo3(get, set)]
+    pub deliver: bool,
+    #[pyo3(get, set)]
+    pub channel: Option<String>,
+    #[pyo3(get, set)]
+    pub to: Option<String>,
+    /// Webhook target URL (http/https).
+    #[pyo3(get, set)]
+    pub url: Option<String>,
+    /// Webhook HTTP method, defaults to POST.
+    #[pyo3(get, set)]
+    pub method: Option<String>,
+    /// Extra webhook request headers.
+    #[pyo3(get, set)]
+    pub headers: HashMap<String, String>,
+    /// Webhook request body.
+    #[pyo3(get, set)]
+    pub body: Option<String>,
+    /// Shell command to run.
+    #[pyo3(get, set)]
+    pub command: Option<String>,
+    /// Working directory for the shell command.
+    #[pyo3(get, set)]
+    pub cwd: Option<String>,
+    /// Shell command timeout, defaults to 60 seconds.
+    #[pyo3(get, set)]
+    pub timeout_ms: Option<u64>,
+}
+
+#[pymethods]
+impl CronPayload {
+    #[new]
+    #[pyo3(signature = (kind="agent_turn", message="", deliver=false, channel=None, to=None, url=None, method=None, headers=None, body=None, command=None, cwd=None, timeout_ms=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        kind: &str,
+        message: &str,
+        deliver: bool,
+        channel: Option<String>,
+        to: Option<String>,
+        url: Option<String>,
+        method: Option<String>,
+        headers: Option<HashMap<String, String>>,
+        body: Option<String>,
+        command: Option<String>,
+        cwd: Option<String>,
+        timeout_ms: Option<u64>,
+    ) -> Self {
+        Self {
+            kind: kind.to_string(),
+            message: message.to_string(),
+            deliver,
+            channel,
+            to,
+            url,
+            method,
+            headers: headers.unwrap_or_default(),
+            body,
+            command,
+            cwd,
+            timeout_ms,
+        }
+    }
+
+    /// Convert to a dict keyed by the store's camelCase field names.
+    fn to_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
+        json_to_python(py, &self.to_store_json())
+    }
+
+    /// Build from a dict as produced by `to_dict`.
+    #[staticmethod]
+    fn from_dict(d: Bound<'_, PyAny>) -> PyResult<Self> {
+        Self::from_store_json(python_to_json(d)?).map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl Default for CronPayload {
+    fn default() -> Self {
+        Self {
+            kind: "agent_turn".to_string(),
+            message: String::new(),
+            deliver: false,
+            channel: None,
+            to: None,
+            url: None,
+            method: None,
+            headers: HashMap::new(),
+            body: None,
+            command: None,
+            cwd: None,
+            timeout_ms: None,
+        }
+    }
+}
+
+impl CronPayload {
+    /// Check the kind and its kind-specific fields.
+    fn validate(&self) -> Result<(), String> {
+        match self.kind.as_str() {
+            "agent_turn" | "system_event" => Ok(()),
+            "webhook" => webhook::validate(self),
+            "shell" => shell::validate(self),
+            other => Err(format!(
+                "Unknown payload kind '{}' (expected one of: {})",
+                other,
+                PAYLOAD_KINDS.join(", ")
+            )),
+        }
+    }
+}
+
+/// Payload kinds a job can carry.
+const PAYLOAD_KINDS: [&str; 4] = ["agent_turn", "system_event", "webhook", "shell"];
+
+/// Runtime state of a job.
+#[pyclass]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CronJobState {
+    #[pyo3(get, set)]
+    pub next_run_at_ms: Option<i64>,
+    #[pyo3(get, set)]
+    pub last_run_at_ms: Option<i64>,
+    #[pyo3(get, set)]
+    pub last_status: Option<String>, // "ok", "error", "skipped", "interrupted", "dry_run"
+    #[pyo3(get, set)]
+    pub last_error: Option<String>,
+    /// Exit code of the last shell run (`None` if killed by a signal).
+    #[pyo3(get, set)]
+    pub last_exit_code: Option<i32>,
+    /// Captured output of the last shell or webhook run.
+    #[pyo3(get, set)]
+    pub last_output: Option<String>,
+    /// Wall-clock duration of the last run.
+    #[pyo3(get, set)]
+    pub last_duration_ms: Option<i64>,
+    /// Mean duration over all recorded runs.
+    #[pyo3(get, set)]
+    pub avg_duration_ms: Option<f64>,
+    /// Number of completed runs.
+    #[pyo3(get, set)]
+    pub run_count: u64,
+    /// Failed runs since the last success.
+    #[pyo3(get, set)]
+    pub consecutive_failures: u32,
+    /// ID of the job whose completion triggered the last run, if chained.
+    #[pyo3(get, set)]
+    pub last_triggered_by: Option<String>,
+    /// Time until which `snooze_job` holds the next run.
+    #[pyo3(get, set)]
+    pub snoozed_until_ms: Option<i64>,
+}
+
+#[pymethods]
+impl CronJobState {
+    #[new]
+    #[pyo3(signature = (next_run_at_ms=None, last_run_at_ms=None, last_status=None, last_error=None, last_exit_code=None, last_output=None, last_duration_ms=None, avg_duration_ms=None, run_count=0, consecutive_failures=0, last_triggered_by=None, snoozed_until_ms=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        next_run_at_ms: Option<i64>,
+        last_run_at_ms: Option<i64>,
+        last_status: Option<String>,
+        last_error: Option<String>,
+        last_exit_code: Option<i32>,
+        last_output: Option<String>,
+        last_duration_ms: Option<i64>,
+        avg_duration_ms: Option<f64>,
+        run_count: u64,
+        consecutive_failures: u32,
+        last_triggered_by: Option<String>,
+        snoozed_until_ms: Option<i64>,
+    ) -> Self {
+        Self {
+            next_run_at_ms,
+            last_run_at_ms,
+            last_status,
+            last_error,
+            last_exit_code,
+            last_output,
+            last_duration_ms,
+            avg_duration_ms,
+            run_count,
+            consecutive_failures,
+            last_triggered_by,
+            snoozed_until_ms,
+        }
+    }
+
+    /// Convert to a dict keyed by the store's camelCase field names.
+    fn to_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
+        json_to_python(py, &self.to_store_json())
+    }
+
+    /// Build from a dict as produced by `to_dict`.
+    #[staticmethod]
+    fn from_dict(d: Bound<'_, PyAny>) -> PyResult<Self> {
+        Self::from_store_json(python_to_json(d)?).map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl CronJobState {
+    /// Record the duration of a completed run.
+    fn record_duration(&mut self, duration_ms: i64) {
+        let avg = self.avg_duration_ms.unwrap_or(0.0);
+        self.run_count += 1;
+        self.avg_duration_ms = Some(avg + (duration_ms as f64 - avg) / self.run_count as f64);
+        self.last_duration_ms = Some(duration_ms);
+    }
+}
+
+/// A completed run, kept in the service's recent-runs window.
+struct RunRecord {
+    job_id: String,
+    job_name: String,
+    duration_ms: i64,
+}
+
+type RecentRuns = Arc<parking_lot::Mutex<VecDeque<RunRecord>>>;
+
+/// Counts in-flight job executions so shutdown can wait for them, and
+/// tracks which jobs are executing so a job never overlaps with itself.
+#[derive(Default)]
+struct InFlight {
+    count: AtomicUsize,
+    idle: Notify,
+    job_ids: parking_lot::Mutex<HashSet<String>>,
+}
+
+impl InFlight {
+    /// Mark an execution as started until the returned guard is dropped.
+    fn enter(self: &Arc<Self>) -> InFlightGuard {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard(self.clone())
+    }
+
+    /// Mark `job_id` as executing until the returned claim is dropped.
+    ///
+    /// Returns `None` if the job is already executing.
+    fn claim(self: &Arc<Self>, job_id: &str) -> Option<JobClaim> {
+        if !self.job_ids.lock().insert(job_id.to_string()) {
+            return None;
+        }
+        Some(JobClaim(self.clone(), job_id.to_string()))
+    }
+
+    /// Wait until no executions are in flight.
+    async fn wait_idle(&self) {
+        loop {
+            // Register before checking so a concurrent exit is not missed
+            let idle = self.idle.notified();
+            if self.count.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+}
+
+struct InFlightGuard(Arc<InFlight>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if self.0.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
+struct JobClaim(Arc<InFlight>, String);
+
+impl Drop for JobClaim {
+    fn drop(&mut self) {
+        self.0.job_ids.lock().remove(&self.1);
+    }
+}
+
+/// The service-wide `on_job` callback and per-job overrides.
+///
+/// Only locked while holding the GIL, so it never blocks Python threads.
+type JobCallback = Arc<parking_lot::Mutex<JobCallbacks>>;
+
+#[derive(Default)]
+struct JobCallbacks {
+    default: Option<PyObject>,
+    /// Callbacks bound to individual jobs. Not persisted.
+    by_job: HashMap<String, PyObject>,
+}
+
+impl JobCallbacks {
+    /// The callback for `job_id`: its own, else the service-wide default.
+    fn for_job(&self, py: Python<'_>, job_id: &str) -> Option<PyObject> {
+        self.by_job
+            .get(job_id)
+            .or(self.default.as_ref())
+            .map(|cb| cb.clone_ref(py))
+    }
+
+    fn bind(&mut self, job_id: String, callback: Option<PyObject>) {
+        match callback {
+            Some(cb) => self.by_job.insert(job_id, cb),
+            None => self.by_job.remove(&job_id),
+        };
+    }
+
+    fn unbind_all(&mut self, job_ids: &[String]) {
+        for id in job_ids {
+            self.by_job.remove(id);
+        }
+    }
+}
+
+/// Settings that affect how jobs execute.
+#[derive(Clone, Copy)]
+struct ExecConfig {
+    allow_shell: bool,
+    disable_after_failures: Option<u32>,
+    /// Advance the schedule after a run. Cleared for forced manual runs.
+    reschedule: bool,
+    /// Pass the `CronJob` to callbacks instead of a payload dict.
+    pass_job: bool,
+    /// Record runs and advance schedules without running payloads.
+    dry_run: bool,
+}
+
+/// A scheduled job.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct CronJob {
+    #[pyo3(get, set)]
+    pub id: String,
+    #[pyo3(get, set)]
+    pub name: String,
+    #[pyo3(get, set)]
+    pub enabled: bool,
+    #[pyo3(get)]
+    pub schedule: CronSchedule,
+    #[pyo3(get)]
+    pub payload: CronPayload,
+    #[pyo3(get)]
+    pub state: CronJobState,
+    #[pyo3(get, set)]
+    pub created_at_ms: i64,
+    #[pyo3(get, set)]
+    pub updated_at_ms: i64,
+    #[pyo3(get, set)]
+    pub delete_after_run: bool,
+    #[pyo3(get, set)]
+    pub tags: Vec<String>,
+    /// Disable the job after this many consecutive failures (0 = never).
+    /// Falls back to the service default when unset.
+    #[pyo3(get, set)]
+    pub disable_after_failures: Option<u32>,
+    /// Jobs due at the same time run in descending priority order.
+    #[pyo3(get, set)]
+    pub priority: i32,
+    /// Job to run right after this one succeeds.
+    #[pyo3(get, set)]
+    pub on_success_job_id: Option<String>,
+    /// Job to run right after this one fails.
+    #[pyo3(get, set)]
+    pub on_failure_job_id: Option<String>,
+}
+
+#[pymethods]
+impl CronJob {
+    #[new]
+    #[pyo3(signature = (id, name, enabled=true, schedule=None, payload=None, state=None, created_at_ms=0, updated_at_ms=0, delete_after_run=false, tags=None, disable_after_failures=None, priority=0, on_success_job_id=None, on_failure_job_id=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        id: String,
+        name: String,
+        enabled: bool,
+        schedule: Option<CronSchedule>,
+        payload: Option<CronPayload>,
+        state: Option<CronJobState>,
+        created_at_ms: i64,
+        updated_at_ms: i64,
+        delete_after_run: bool,
+        tags: Option<Vec<String>>,
+        disable_after_failures: Option<u32>,
+        priority: i32,
+        on_success_job_id: Option<String>,
+        on_failure_job_id: Option<String>,
+    ) -> Self {
+        Self {
+            id,
+            name,
+            enabled,
+            schedule: schedule.unwrap_or_else(|| {
+                CronSchedule::new(
+                    "every".to_string(),
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                )
+            }),
+            payload: payload.unwrap_or_default(),
+            state: state.unwrap_or_default(),
+            created_at_ms,
+            updated_at_ms,
+            delete_after_run,
+            tags: tags.unwrap_or_default(),
+            disable_after_failures,
+            priority,
+            on_success_job_id,
+            on_failure_job_id,
+        }
+    }
+
+    /// Leading characters of the ID, for display.
+    #[getter]
+    fn short_id(&self) -> String {
+        self.id.chars().take(SHORT_ID_LEN).collect()
+    }
+
+    /// Convert to a dict keyed by the store's camelCase field names.
+    fn to_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
+        json_to_python(py, &self.to_store_json())
+    }
+
+    /// Build from a dict as produced by `to_dict`.
+    #[staticmethod]
+    fn from_dict(d: Bound<'_, PyAny>) -> PyResult<Self> {
+        Self::from_store_json(python_to_json(d)?).map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    /// Jobs are equal when their IDs are.
+    fn __eq__(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+
+    fn __hash__(&self) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.id.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "CronJob(id={:?}, name={:?}, enabled={})",
+            self.id, self.name, self.enabled
+        )
+    }
+}
+
+impl CronJob {
+    /// Build a new enabled job with a fresh ID and its first run scheduled
+    /// after `now`.
+    #[allow(clippy::too_many_arguments)]
+    fn create(
+        name: String,
+        schedule: CronSchedule,
+        payload: CronPayload,
+        delete_after_run: bool,
+        tags: Vec<String>,
+        disable_after_failures: Option<u32>,
+        priority: i32,
+        now: i64,
+    ) -> Self {
+        Self {
+            id: new_job_id(),
+            name,
+            enabled: true,
+            state: CronJobState {
+                next_run_at_ms: compute_next_run(&schedule, now),
+                ..Default::default()
+            },
+            schedule,
+            payload,
+            created_at_ms: now,
+            updated_at_ms: now,
+            delete_after_run,
+            tags,
+            disable_after_failures,
+            priority,
+            on_success_job_id: None,
+            on_failure_job_id: None,
+        }
+    }
+
+    /// Set the follow-up jobs. `None` leaves a link unchanged and an empty
+    /// string clears it.
+    fn set_chain(&mut self, on_success: Option<String>, on_failure: Option<String>) {
+        if let Some(id) = on_success {
+            self.on_success_job_id = (!id.is_empty()).then_some(id);
+        }
+        if let Some(id) = on_failure {
+            self.on_failure_job_id = (!id.is_empty()).then_some(id);
+        }
+    }
+
+    fn has_all_tags(&self, tags: &[String]) -> bool {
+        tags.iter().all(|t| self.tags.contains(t))
+    }
+
+    /// Enable or disable the job, updating its next run accordingly.
+    ///
+    /// Enabling resets the consecutive failure count.
+    fn set_enabled(&mut self, enabled: bool, now: i64) {
+        self.enabled = enabled;
+        self.updated_at_ms = now;
+        if enabled {
+            self.state.consecutive_failures = 0;
+        }
+        self.state.snoozed_until_ms = None;
+        self.state.next_run_at_ms = if enabled {
+            compute_next_run(&self.schedule, now)
+        } else {
+            None
+        };
+    }
+
+    /// The next run to resume with when the service starts.
+    ///
+    /// An active snooze wins. Otherwise the stored next run is kept if it is
+    /// upcoming or was missed by at most `CATCH_UP_WINDOW_MS`, and recomputed
+    /// from the schedule if it is missing or older.
+    fn resumed_next_run(&self, now: i64) -> Option<i64> {
+        if let Some(until) = self.state.snoozed_until_ms.filter(|&until| until > now) {
+            return Some(until);
+        }
+        match self.state.next_run_at_ms {
+            Some(next) if next >= now - CATCH_UP_WINDOW_MS => Some(next),
+            _ => compute_next_run(&self.schedule, now),
+        }
+    }
+
+    /// Compare user-defined fields, ignoring runtime state and timestamps.
+    fn same_definition(&self, other: &CronJob) -> bool {
+        self.name == other.name
+            && self.enabled == other.enabled
+            && self.schedule == other.schedule
+            && self.payload == other.payload
+            && self.delete_after_run == other.delete_after_run
+            && self.tags == other.tags
+            && self.disable_after_failures == other.disable_after_failures
+            && self.priority == other.priority
+            && self.on_success_job_id == other.on_success_job_id
+            && self.on_failure_job_id == other.on_failure_job_id
+    }
+}
+
+/// Generate a fresh job ID.
+///
+/// IDs are full UUIDs; older stores may still hold 8-character IDs.
+fn new_job_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// Number of leading ID characters shown by `CronJob.short_id`.
+const SHORT_ID_LEN: usize = 8;
+
+/// Check that a schedule is well-formed.
+pub(crate) fn validate_schedule(schedule: &CronSchedule, now: i64) -> Result<(), String> {
+    match schedule.kind.as_str() {
+        "at" if schedule.at_ms.is_none() => Err("'at' schedule requires 'atMs'".to_string()),
+        "every" if schedule.every_ms.is_none_or(|e| e <= 0) => {
+            Err("'every' schedule requires a positive 'everyMs'".to_string())
+        }
+        "daily" | "weekly" if schedule.hour.is_none_or(|h| h > 23) => Err(format!(
+            "'{}' schedule requires an 'hour' between 0 and 23",
+            schedule.kind
+        )),
+        "daily" | "weekly" if schedule.minute.is_some_and(|m| m > 59) => {
+            Err("'minute' must be between 0 and 59".to_string())
+        }
+        "weekly" if schedule.weekdays.is_none_or(|w| w == 0 || w > 0x7f) => {
+            Err("'weekly' schedule requires a 'weekdays' mask between 1 and 127".to_string())
+        }
+        _ => quiet_window(schedule).and_then(|_| next_occurrences(schedule, now, 0).map(|_| ())),
+    }
+}
+
+/// Parse a cron expression.
+///
+/// Accepts the standard 5-field form (`"0 9 * * 1-5"`) as well as the
+/// 6/7-field form with a leading seconds field used by the `cron` crate.
+fn parse_cron_expr(expr: &str) -> Result<cron::Schedule, String> {
+    let expr = expr.trim();
+    let normalized = if !expr.starts_with('@') && expr.split_whitespace().count() == 5 {
+        format!("0 {}", expr)
+    } else {
+        expr.to_string()
+    };
+    cron::Schedule::from_str(&normalized)
+        .map_err(|e| format!("Invalid cron expression '{}': {}", expr, e))
+}
+
+/// Parse an IANA timezone name, defaulting to UTC.
+pub(crate) fn parse_tz(tz: Option<&str>) -> Result<Tz, String> {
+    match tz {
+        Some(name) if !name.is_empty() => name
+            .parse::<Tz>()
+            .map_err(|_| format!("Unknown timezone '{}'", name)),
+        _ => Ok(chrono_tz::UTC),
+    }
+}
+
+/// Compute up to `count` fire times (epoch ms) strictly after `from_ms`.
+fn next_occurrences(
+    schedule: &CronSchedule,
+    from_ms: i64,
+    count: usize,
+) -> Result<Vec<i64>, String> {
+    match schedule.kind.as_str() {
+        "at" => Ok(schedule
+            .at_ms
+            .filter(|&at| at > from_ms && count > 0)
+            .into_iter()
+            .collect()),
+        "every" => match schedule.every_ms {
+            Some(every) if every > 0 => {
+                Ok((1..=count as i64).map(|i| from_ms + every * i).collect())
+            }
+            _ => Ok(Vec::new()),
+        },
+        "cron" => {
+            let expr = schedule
+                .expr
+                .as_deref()
+                .ok_or_else(|| "Cron schedule requires 'expr'".to_string())?;
+            let cron_schedule = parse_cron_expr(expr)?;
+            let tz = parse_tz(schedule.tz.as_deref())?;
+            let from = Utc
+                .timestamp_millis_opt(from_ms)
+                .single()
+                .ok_or_else(|| format!("Invalid timestamp {}", from_ms))?
+                .with_timezone(&tz);
+            Ok(cron_schedule
+                .after(&from)
+                .take(count)
+                .map(|dt| dt.timestamp_millis())
+                .collect())
+        }
+        "daily" | "weekly" => local_time_occurrences(schedule, from_ms, count),
+        other => Err(format!("Unknown schedule kind '{}'", other)),
+    }
+}
+
+/// Convert a wall-clock time in `tz` to epoch milliseconds.
+///
+/// A time skipped by a DST jump (e.g. 02:30 on spring-forward day) is
+/// shifted forward by the gap; a repeated time resolves to its first
+/// occurrence.
+fn resolve_local_time(tz: Tz, local: NaiveDateTime) -> Result<i64, String> {
+    use chrono::{Duration, LocalResult, Offset};
+
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(dt) => Ok(dt.timestamp_millis()),
+        LocalResult::Ambiguous(earliest, _) => Ok(earliest.timestamp_millis()),
+        LocalResult::None => {
+            // Apply the offset in effect the day before the gap
+            let before = tz
+                .offset_from_local_datetime(&(local - Duration::days(1)))
+                .earliest()
+                .map(|o| o.fix())
+                .ok_or_else(|| format!("Cannot resolve local time {}", local))?;
+            Ok((local - before).and_utc().timestamp_millis())
+        }
+    }
+}
+
+/// Parse an `at` time given as an RFC 3339 / ISO-8601 string.
+///
+/// Strings with an offset (`"2025-07-01T09:00:00+02:00"`, `"...Z"`) are
+/// absolute; naive strings (`"2025-07-01T09:00"`, `"2025-07-01 09:00:00"`)
+/// are read as wall-clock time in `tz`, defaulting to UTC.
+fn parse_at_time(text: &str, tz: Option<&str>) -> Result<i64, String> {
+    let text = text.trim();
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(text) {
+        return Ok(dt.timestamp_millis());
+    }
+
+    let naive = [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|fmt| NaiveDateTime::parse_from_str(text, fmt).ok())
+    .ok_or_else(|| format!("Invalid 'at' time '{}': expected ISO-8601", text))?;
+    resolve_local_time(parse_tz(tz)?, naive)
+}
+
+/// Fire times for `daily`/`weekly` schedules, computed in the schedule's
+/// timezone so the local wall-clock time is kept across DST changes (see
+/// `resolve_local_time`).
+fn local_time_occurrences(
+    schedule: &CronSchedule,
+    from_ms: i64,
+    count: usize,
+) -> Result<Vec<i64>, String> {
+    use chrono::{Datelike, NaiveTime};
+
+    let tz = parse_tz(schedule.tz.as_deref())?;
+    let time = schedule
+        .hour
+        .and_then(|h| NaiveTime::from_hms_opt(h, schedule.minute.unwrap_or(0), 0))
+        .ok_or_else(|| format!("Invalid time for '{}' schedule", schedule.kind))?;
+    let weekdays = match schedule.kind.as_str() {
+        "weekly" => schedule.weekdays.unwrap_or(0) & 0x7f,
+        _ => 0x7f,
+    };
+    if weekdays == 0 {
+        return Ok(Vec::new());
+    }
+
+    let from = Utc
+        .timestamp_millis_opt(from_ms)
+        .single()
+        .ok_or_else(|| format!("Invalid timestamp {}", from_ms))?;
+    let mut date = from.with_timezone(&tz).date_naive();
+    let mut runs = Vec::with_capacity(count);
+    while runs.len() < count {
+        if weekdays & (1 << date.weekday().num_days_from_monday()) != 0 {
+            let fire = resolve_local_time(tz, date.and_time(time))?;
+            if fire > from_ms {
+                runs.push(fire);
+            }
+        }
+        date = date
+            .succ_opt()
+            .ok_or_else(|| "Date out of range".to_string())?;
+    }
+    Ok(runs)
+}
+
+/// Compute next run time in ms.
+///
+/// With the `"defer"` quiet policy, a run inside the quiet window is moved
+/// to the window's end.
+pub(crate) fn compute_next_run(schedule: &CronSchedule, now_ms: i64) -> Option<i64> {
+    let next = next_occurrences(schedule, now_ms, 1)
+        .ok()
+        .and_then(|runs| runs.first().copied())?;
+    match quiet_window(schedule) {
+        Ok(Some(window)) if window.policy == QuietPolicy::Defer => {
+            Some(window.end_if_inside(next).unwrap_or(next))
+        }
+        _ => Some(next),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum QuietPolicy {
+    Defer,
+    Skip,
+}
+
+/// A daily window, in the schedule's timezone, during which jobs do not run.
+struct QuietWindow {
+    tz: Tz,
+    start: chrono::NaiveTime,
+    end: chrono::NaiveTime,
+    policy: QuietPolicy,
+}
+
+impl QuietWindow {
+    /// If `ms` falls inside the window, the time the window ends.
+    fn end_if_inside(&self, ms: i64) -> Option<i64> {
+        let local = Utc
+            .timestamp_millis_opt(ms)
+            .single()?
+            .with_timezone(&self.tz);
+        let (date, time) = (local.date_naive(), local.time());
+        let end_date = if self.start < self.end {
+            (time >= self.start && time < self.end).then_some(date)?
+        } else if time >= self.start {
+            // Spans midnight: ends tomorrow
+            date.succ_opt()?
+        } else if time < self.end {
+            date
+        } else {
+            return None;
+        };
+        resolve_local_time(self.tz, end_date.and_time(self.end)).ok()
+    }
+}
+
+/// Parse the schedule's quiet window, if it has one.
+fn quiet_window(schedule: &CronSchedule) -> Result<Option<QuietWindow>, String> {
+    let parse = |text: &str| {
+        chrono::NaiveTime::parse_from_str(text.trim(), "%H:%M")
+            .map_err(|_| format!("Invalid quiet hours time '{}': expected HH:MM", text))
+    };
+    let policy = match schedule.quiet_policy.as_deref() {
+        None | Some("defer") => QuietPolicy::Defer,
+        Some("skip") => QuietPolicy::Skip,
+        Some(other) => {
+            return Err(format!(
+                "Invalid quiet_policy '{}': expected 'defer' or 'skip'",
+                other
+            ))
+        }
+    };
+    match (
+        schedule.quiet_start.as_deref(),
+        schedule.quiet_end.as_deref(),
+    ) {
+        (None, None) => Ok(None),
+        (Some(start), Some(end)) => {
+            let (start, end) = (parse(start)?, parse(end)?);
+            if start == end {
+                return Err("Quiet hours start and end must differ".to_string());
+            }
+            Ok(Some(QuietWindow {
+                tz: parse_tz(schedule.tz.as_deref())?,
+                start,
+                end,
+                policy,
+            }))
+        }
+        _ => Err("Quiet hours require both 'quiet_start' and 'quiet_end'".to_string()),
+    }
+}
+
+/// Service for managing and executing scheduled jobs.
+#[pyclass]
+#[allow(dead_code)]
+pub struct CronService {
+    store: Arc<CronStore>,
+    callback: JobCallback,
+    load_error_callback: Arc<Option<PyObject>>,
+    jobs: Arc<Mutex<Vec<CronJob>>>,
+    running: Arc<AtomicBool>,
+    watch_store: bool,
+    config: ExecConfig,
+    events: CronEvents,
+    recent_runs: RecentRuns,
+    metrics: SharedMetrics,
+    /// Wakes the scheduler loop when jobs change.
+    wake: Arc<Notify>,
+    in_flight: Arc<InFlight>,
+    /// Longest the scheduler sleeps before re-reading the clock.
+    idle_interval_ms: u64,
+    clock: WallClock,
+}
+
+#[pymethods]
+impl CronService {
+    /// Create the service and load the existing store.
+    ///
+    /// If the store cannot be loaded (corrupt, or written by a newer
+    /// version), `on_load_error(message)` is called, a `store_load_failed`
+    /// event is emitted, and the file is left untouched: the service will
+    /// not write to it.
+    ///
+    /// With `watch_store=True`, the running service polls the store file and
+    /// merges in jobs added or edited by hand or by another process.
+    ///
+    /// `on_event(event)` receives lifecycle events as dicts with at least
+    /// `event` and `ts_ms` keys: `job_started`, `job_finished`, `job_failed`
+    /// (with `error`), `store_saved`, and others. It is called from a
+    /// background task and must be a regular (non-async) callable.
+    ///
+    /// Jobs with a `shell` payload run arbitrary commands and are refused
+    /// unless `allow_shell=True`.
+    ///
+    /// `disable_after_failures` is the default number of consecutive
+    /// failures after which a job is disabled; jobs may override it.
+    ///
+    /// Callbacks receive a dict with the payload's `kind`, `message`,
+    /// `deliver`, `channel` and `to`, plus `job_id` and `job_name`. Set
+    /// `pass_job=True` to receive the `CronJob` itself, as older versions did.
+    ///
+    /// The scheduler never sleeps longer than `idle_interval_ms`, so a wall
+    /// clock that jumps ahead (e.g. after the machine resumes from suspend)
+    /// is noticed within that interval. `clock`, a callable returning epoch
+    /// milliseconds, replaces the system clock the service schedules by.
+    ///
+    /// With `dry_run=True` jobs go through the motions without side effects:
+    /// no callback, webhook or command runs. Each run emits a `job_dry_run`
+    /// event, sets `last_status` to `"dry_run"` and advances the schedule.
+    #[new]
+    #[pyo3(signature = (store_path, on_job=None, on_load_error=None, watch_store=false, on_event=None, allow_shell=false, disable_after_failures=None, idle_interval_ms=DEFAULT_IDLE_INTERVAL_MS, pass_job=false, dry_run=false, clock=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        store_path: PathBuf,
+        on_job: Option<PyObject>,
+        on_load_error: Option<PyObject>,
+        watch_store: bool,
+        on_event: Option<PyObject>,
+        allow_shell: bool,
+        disable_after_failures: Option<u32>,
+        idle_interval_ms: u64,
+        pass_job: bool,
+        dry_run: bool,
+        clock: Option<PyObject>,
+    ) -> PyResult<Self> {
+        if idle_interval_ms == 0 {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "idle_interval_ms must be positive",
+            ));
+        }
+        let clock = ClockSource(clock.map(Arc::new));
+        let events = CronEvents::new(on_event, clock.clone());
+        let store = Arc::new(CronStore::new(store_path, events.clone()));
+        let load_error_callback = Arc::new(on_load_error);
+        let jobs = jobs_or_report(store.load_blocking(), &load_error_callback, &events);
+        store.update_summary(&jobs);
+        Ok(Self {
+            store,
+            callback: Arc::new(parking_lot::Mutex::new(JobCallbacks {
+                default: on_job,
+                ..Default::default()
+            })),
+            load_error_callback,
+            jobs: Arc::new(Mutex::new(jobs)),
+            running: Arc::new(AtomicBool::new(false)),
+            watch_store,
+            config: ExecConfig {
+                allow_shell,
+                disable_after_failures,
+                reschedule: true,
+                pass_job,
+                dry_run,
+            },
+            events: events.clone(),
+            recent_runs: RecentRuns::default(),
+            metrics: SharedMetrics::default(),
+            wake: Arc::new(Notify::new()),
+            in_flight: Arc::default(),
+            idle_interval_ms,
+            clock: WallClock {
+                source: clock,
+                events,
+            },
+        })
+    }
+
+    /// Set the callback function.
+    fn set_callback(&self, callback: Option<PyObject>) {
+        self.callback.lock().default = callback;
+    }
+
+    /// Bind `callback` to a single job, overriding the service callback for
+    /// its runs. `callback=None` removes the binding.
+    ///
+    /// Bindings are not persisted: after a restart, jobs run with the
+    /// service callback until bound again. Returns `False` if the job does
+    /// not exist.
+    fn bind_callback<'py>(
+        &self,
+        py: Python<'py>,
+        job_id: String,
+        callback: Option<PyObject>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let jobs = self.jobs.clone();
+        let callbacks = self.callback.clone();
+
+        future_into_py(py, async move {
+            if !jobs.lock().await.iter().any(|j| j.id == job_id) {
+                return Ok(false);
+            }
+            Python::with_gil(|_| callbacks.lock().bind(job_id, callback));
+            Ok(true)
+        })
+    }
+
+    /// Start the cron service.
+    fn start<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.running.store(true, Ordering::Relaxed);
+
+        let store = self.store.clone();
+        let events = self.events.clone();
+        let jobs = self.jobs.clone();
+        let callback = self.callback.clone();
+        let load_error_callback = self.load_error_callback.clone();
+        let running = self.running.clone();
+        let watch_store = self.watch_store;
+        let config = self.config;
+        let recent_runs = self.recent_runs.clone();
+        let metrics = self.metrics.clone();
+        let wake = self.wake.clone();
+        let in_flight = self.in_flight.clone();
+        let idle_interval_ms = self.idle_interval_ms;
+        let clock = self.clock.clone();
+
+        future_into_py(py, async move {
+            // Load jobs from disk, writing out pending changes first
+            let _ = flush_store(&store, &jobs, &events).await;
+            {
+                let loaded = jobs_or_report(store.load().await, &load_error_callback, &events);
+                let mut guard = jobs.lock().await;
+                *guard = loaded;
+            }
+
+            // Resume next runs, keeping snoozes and recently missed runs
+            {
+                let now = clock.now_ms();
+                let mut guard = jobs.lock().await;
+                for job in guard.iter_mut().filter(|j| j.enabled) {
+                    job.state.next_run_at_ms = job.resumed_next_run(now);
+                    if job.state.snoozed_until_ms.is_some_and(|until| until <= now) {
+                        job.state.snoozed_until_ms = None;
+                    }
+                    if job.state.last_status.as_deref() != Some("interrupted") {
+                        continue;
+                    }
+                    // The lost run counts as a failure and is retried now,
+                    // unless that many failures disable the job
+                    let error = job.state.last_error.clone().unwrap_or_default();
+                    if !record_failure(job, &error, config, &events) {
+                        job.state.next_run_at_ms = Some(now);
+                        events.emit(
+                            "job_interrupted",
+                            json!({ "job_id": job.id, "job_name": job.name }),
+                            || format!("Retrying job '{}' after an interrupted run", job.name),
+                        );
+                    }
+                }
+            }
+
+            // Save store
+            save_store(&store, &jobs, &events).await;
+
+            let job_count = jobs.lock().await.len();
+            events.emit("service_started", json!({ "jobs": job_count }), || {
+                format!("Service started with {} jobs", job_count)
+            });
+
+            if watch_store {
+                tokio::spawn(watch_store_changes(
+                    store.clone(),
+                    jobs.clone(),
+                    load_error_callback.clone(),
+                    running.clone(),
+                    events.clone(),
+                    wake.clone(),
+                    clock.clone(),
+                ));
+            }
+
+            // Main loop
+            while running.load(Ordering::Relaxed) {
+                let next_wake = {
+                    let guard = jobs.lock().await;
+                    guard
+                        .iter()
+                        .filter(|j| j.enabled && j.state.next_run_at_ms.is_some())
+                        .filter_map(|j| j.state.next_run_at_ms)
+                        .min()
+                };
+
+                // Cap the sleep so wall-clock time is re-read regularly
+                let slept_from = clock.now_ms();
+                let delay_ms = match next_wake {
+                    Some(wake) => ((wake - slept_from).max(0) as u64).min(idle_interval_ms),
+                    None => idle_interval_ms,
+                };
+
+                // Sleep until the next job is due or the job set changes
+                let slept_at = Instant::now();
+                let notified = tokio::select! {
+                    _ = tokio::time::sleep(tokio::time::Duration::from_millis(delay_ms)) => false,
+                    _ = wake.notified() => true,
+                };
+
+                if !running.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                // The monotonic clock stops while suspended, so a wall clock
+                // that moved much further means a resume or a clock step
+                let skew_ms = (clock.now_ms() - slept_from) - slept_at.elapsed().as_millis() as i64;
+                let clock_jumped = skew_ms.abs() > CLOCK_JUMP_THRESHOLD_MS;
+                if clock_jumped {
+                    events.emit("clock_jump", json!({ "skew_ms": skew_ms }), || {
+                        format!("Wall clock jumped by {} ms, rescanning jobs", skew_ms)
+                    });
+                } else if notified {
+                    continue;
+                }
+
+                // Execute due jobs
+                let now = clock.now_ms();
+                let due_job_ids: Vec<String> = {
+                    let guard = jobs.lock().await;
+                    let mut due: Vec<&CronJob> = guard
+                        .iter()
+                        .filter(|j| {
+                            j.enabled
+                                && j.state.next_run_at_ms.is_some()
+                                && now >= j.state.next_run_at_ms.unwrap()
+                        })
+                        .collect();
+                    // Highest priority first, then the longest overdue
+                    due.sort_by_key(|j| (std::cmp::Reverse(j.priority), j.state.next_run_at_ms));
+                    due.into_iter().map(|j| j.id.clone()).collect()
+                };
+
+                for job_id in due_job_ids {
+                    // Entered before checking `running`, so a shutdown either
+                    // stops this run or waits for it
+                    let _in_flight = in_flight.enter();
+                    if !running.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if hold_for_quiet_hours(&jobs, &events, &job_id, clock.now_ms()).await {
+                        continue;
+                    }
+                    execute_chain(
+                        &jobs,
+                        &callback,
+                        &events,
+                        &recent_runs,
+                        &metrics,
+                        &in_flight,
+                        config,
+                        &clock,
+                        &job_id,
+                    )
+                    .await;
+                }
+
+                save_store(&store, &jobs, &events).await;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Stop the cron service.
+    ///
+    /// Returns immediately; use `shutdown()` to wait for running jobs.
+    fn stop(&self) {
+        self.running.store(false, Ordering::SeqCst);
+        self.wake.notify_one();
+    }
+
+    /// Stop the service and write the store before returning.
+    ///
+    /// Unlike `shutdown()` this does not wait for running jobs: they are
+    /// marked `"interrupted"` in the store, and the next `start()` counts
+    /// them as failed and runs them again. Also runs, best effort, when the
+    /// service is garbage collected with jobs running or a save pending.
+    ///
+    /// Raises `OSError` if the store cannot be written.
+    fn close(&self, py: Python<'_>) -> PyResult<()> {
+        self.stop();
+        // Waits for the runtime's tasks, which may need the GIL meanwhile
+        py.allow_threads(|| {
+            // Taken before the jobs, like every save. Both are only held
+            // briefly, but never block forever in a finalizer
+            let writing = self.store.lock_writing_blocking()?;
+            let mut guard = lock_blocking(&self.jobs, BLOCKING_LOCK_TIMEOUT)
+                .ok_or_else(|| "Timed out waiting for the job list".to_string())?;
+            mark_interrupted(&mut guard, &self.in_flight.job_ids.lock());
+            self.store.flush_blocking(&guard, &writing)
+        })
+        .map_err(pyo3::exceptions::PyOSError::new_err)
+    }
+
+    /// Stop the service and wait for it to wind down.
+    ///
+    /// Waits for in-flight job executions (up to `timeout_s` seconds if
+    /// given), then saves the store. Resolves to `False` if the timeout
+    /// expired with jobs still running, `True` otherwise. An infinite
+    /// `timeout_s` waits like `None`.
+    #[pyo3(signature = (timeout_s=None))]
+    fn shutdown<'py>(
+        &self,
+        py: Python<'py>,
+        timeout_s: Option<f64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        if timeout_s.is_some_and(|secs| secs.is_nan() || secs < 0.0) {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "timeout_s must be a non-negative number of seconds",
+            ));
+        }
+        // Too long to represent (including infinity) means no timeout
+        let timeout = timeout_s.and_then(|secs| std::time::Duration::try_from_secs_f64(secs).ok());
+        self.stop();
+
+        let jobs = self.jobs.clone();
+        let store = self.store.clone();
+        let events = self.events.clone();
+        let in_flight = self.in_flight.clone();
+
+        future_into_py(py, async move {
+            let drained = match timeout {
+                Some(timeout) => tokio::time::timeout(timeout, in_flight.wait_idle())
+                    .await
+                    .is_ok(),
+                None => {
+                    in_flight.wait_idle().await;
+                    true
+                }
+            };
+
+            let _ = flush_store(&store, &jobs, &events).await;
+            events.emit("service_stopped", json!({ "drained": drained }), || {
+                "Service stopped".to_string()
+            });
+            Ok(drained)
+        })
+    }
+
+    /// List all jobs.
+    ///
+    /// When `tags` is given, only jobs carrying all of them are returned;
+    /// `name_contains` keeps jobs whose name contains it, ignoring case.
+    ///
+    /// `sort_by` is `"next_run"` (default; soonest first), `"name"`,
+    /// `"created"` (oldest first) or `"last_run"` (most recent first). Jobs
+    /// without the sort time come last. `offset` and `limit` select a page of
+    /// the sorted list. With `with_total=True` a `(jobs, total)` tuple is
+    /// returned, where `total` counts all matching jobs before paging.
+    #[pyo3(signature = (include_disabled=false, tags=None, name_contains=None, sort_by="next_run", offset=0, limit=None, with_total=false))]
+    #[allow(clippy::too_many_arguments)]
+    fn list_jobs<'py>(
+        &self,
+        py: Python<'py>,
+        include_disabled: bool,
+        tags: Option<Vec<String>>,
+        name_contains: Option<String>,
+        sort_by: &str,
+        offset: usize,
+        limit: Option<usize>,
+        with_total: bool,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let jobs = self.jobs.clone();
+        let tags = tags.unwrap_or_default();
+        let needle = name_contains.map(|n| n.to_lowercase());
+        let sort = JobSort::parse(sort_by).map_err(pyo3::exceptions::PyValueError::new_err)?;
+
+        future_into_py(py, async move {
+            let guard = jobs.lock().await;
+            let mut matching: Vec<&CronJob> = guard
+                .iter()
+                .filter(|j| (include_disabled || j.enabled) && j.has_all_tags(&tags))
+                .filter(|j| {
+                    needle
+                        .as_ref()
+                        .is_none_or(|n| j.name.to_lowercase().contains(n))
+                })
+                .collect();
+            sort.apply(&mut matching);
+
+            let total = matching.len();
+            let page: Vec<CronJob> = matching
+                .into_iter()
+                .skip(offset)
+                .take(limit.unwrap_or(usize::MAX))
+                .cloned()
+                .collect();
+            drop(guard);
+
+            Python::with_gil(|py| {
+                if with_total {
+                    (page, total).into_py_any(py)
+                } else {
+                    page.into_py_any(py)
+                }
+            })
+        })
+    }
+
+    /// List `(job, fire_at_ms)` pairs for every run of an enabled job due
+    /// within `within_ms` of `now_ms` (default: now), sorted by fire time.
+    ///
+    /// Jobs that fire repeatedly in the window are listed once per
+    /// occurrence, starting from their current next run.
+    #[pyo3(signature = (within_ms, now_ms=None))]
+    fn upcoming_jobs<'py>(
+        &self,
+        py: Python<'py>,
+        within_ms: i64,
+        now_ms: Option<i64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let jobs = self.jobs.clone();
+        let end = now_ms
+            .unwrap_or_else(|| self.clock.now_ms())
+            .saturating_add(within_ms);
+
+        future_into_py(py, async move {
+            let guard = jobs.lock().await;
+            let mut upcoming: Vec<(CronJob, i64)> = Vec::new();
+            for job in guard.iter().filter(|j| j.enabled) {
+                let mut fire = match job.state.next_run_at_ms {
+                    Some(t) if t <= end => t,
+                    _ => continue,
+                };
+                for _ in 0..MAX_UPCOMING_PER_JOB {
+                    upcoming.push((job.clone(), fire));
+                    match compute_next_run(&job.schedule, fire) {
+                        Some(next) if next > fire && next <= end => fire = next,
+                        _ => break,
+                    }
+                }
+            }
+
+            upcoming.sort_by_key(|(job, fire)| (*fire, std::cmp::Reverse(job.priority)));
+            Ok(upcoming)
+        })
+    }
+
+    /// Add a new job.
+    ///
+    /// `payload` replaces the agent-turn payload built from `message`,
+    /// `deliver`, `channel` and `to`, e.g. for webhook jobs.
+    ///
+    /// `callback` handles this job's runs instead of the service callback;
+    /// see `bind_callback`.
+    #[pyo3(signature = (name, schedule, message, deliver=false, channel=None, to=None, delete_after_run=false, tags=None, payload=None, disable_after_failures=None, priority=None, on_success_job_id=None, on_failure_job_id=None, callback=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn add_job<'py>(
+        &self,
+        py: Python<'py>,
+        name: String,
+        schedule: CronSchedule,
+        message: String,
+        deliver: bool,
+        channel: Option<String>,
+        to: Option<String>,
+        delete_after_run: bool,
+        tags: Option<Vec<String>>,
+        payload: Option<CronPayload>,
+        disable_after_failures: Option<u32>,
+        priority: Option<i32>,
+        on_success_job_id: Option<String>,
+        on_failure_job_id: Option<String>,
+        callback: Option<PyObject>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let jobs = self.jobs.clone();
+        let store = self.store.clone();
+        let events = self.events.clone();
+        let wake = self.wake.clone();
+        let callbacks = self.callback.clone();
+        let now = self.clock.now_ms();
+
+        let payload = payload.unwrap_or_else(|| CronPayload {
+            message,
+            deliver,
+            channel,
+            to,
+            ..CronPayload::default()
+        });
+        self.check_schedule(&schedule)?;
+        self.check_payload(&payload)?;
+
+        future_into_py(py, async move {
+            let mut job = CronJob::create(
+                name,
+                schedule,
+                payload,
+                delete_after_run,
+                tags.unwrap_or_default(),
+                disable_after_failures,
+                priority.unwrap_or(0),
+                now,
+            );
+            job.set_chain(on_success_job_id, on_failure_job_id);
+            if callback.is_some() {
+                Python::with_gil(|_| callbacks.lock().bind(job.id.clone(), callback));
+            }
+
+            {
+                let mut guard = jobs.lock().await;
+                guard.push(job.clone());
+            }
+
+            wake.notify_one();
+            save_store(&store, &jobs, &events).await;
+            events.emit(
+                "job_added",
+                json!({ "job_id": job.id, "job_name": job.name }),
+                || format!("Added job '{}' ({})", job.name, job.id),
+            );
+
+            Ok(job)
+        })
+    }
+
+    /// Add a job, or update the enabled job with exactly the same name.
+    ///
+    /// An existing job keeps its ID, creation time and run history; its
+    /// schedule, payload and `delete_after_run` are replaced, and `tags`,
+    /// `disable_after_failures` and `priority` are replaced when given. Returns
+    /// `(job, created)`.
+    #[pyo3(signature = (name, schedule, message, deliver=false, channel=None, to=None, delete_after_run=false, tags=None, payload=None, disable_after_failures=None, priority=None, on_success_job_id=None, on_failure_job_id=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn upsert_job<'py>(
+        &self,
+        py: Python<'py>,
+        name: String,
+        schedule: CronSchedule,
+        message: String,
+        deliver: bool,
+        channel: Option<String>,
+        to: Option<String>,
+        delete_after_run: bool,
+        tags: Option<Vec<String>>,
+        payload: Option<CronPayload>,
+        disable_after_failures: Option<u32>,
+        priority: Option<i32>,
+        on_success_job_id: Option<String>,
+        on_failure_job_id: Option<String>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let jobs = self.jobs.clone();
+        let store = self.store.clone();
+        let events = self.events.clone();
+        let wake = self.wake.clone();
+        let now = self.clock.now_ms();
+
+        let payload = payload.unwrap_or_else(|| CronPayload {
+            message,
+            deliver,
+            channel,
+            to,
+            ..CronPayload::default()
+        });
+        self.check_schedule(&schedule)?;
+        self.check_payload(&payload)?;
+
+        future_into_py(py, async move {
+            let (job, created) = {
+                let mut guard = jobs.lock().await;
+                match guard.iter_mut().find(|j| j.enabled && j.name == name) {
+                    Some(job) => {
+                        if job.schedule != schedule {
+                            job.state.next_run_at_ms = compute_next_run(&schedule, now);
+                            job.state.snoozed_until_ms = None;
+                            job.schedule = schedule;
+                        }
+                        job.payload = payload;
+                        job.delete_after_run = delete_after_run;
+                        if let Some(tags) = tags {
+                            job.tags = tags;
+                        }
+                        if disable_after_failures.is_some() {
+                            job.disable_after_failures = disable_after_failures;
+                        }
+                        if let Some(priority) = priority {
+                            job.priority = priority;
+                        }
+                        job.set_chain(on_success_job_id, on_failure_job_id);
+                        job.updated_at_ms = now;
+                        (job.clone(), false)
+                    }
+                    None => {
+                        let mut job = CronJob::create(
+                            name,
+                            schedule,
+                            payload,
+                            delete_after_run,
+                            tags.unwrap_or_default(),
+                            disable_after_failures,
+                            priority.unwrap_or(0),
+                            now,
+                        );
+                        job.set_chain(on_success_job_id, on_failure_job_id);
+                        guard.push(job.clone());
+                        (job, true)
+                    }
+                }
+            };
+
+            wake.notify_one();
+            save_store(&store, &jobs, &events).await;
+            let event = if created { "job_added" } else { "job_updated" };
+            events.emit(
+                event,
+                json!({ "job_id": job.id, "job_name": job.name }),
+                || {
+                    let verb = if created { "Added" } else { "Updated" };
+                    format!("{} job '{}' ({})", verb, job.name, job.id)
+                },
+            );
+
+            Ok((job, created))
+        })
+    }
+
+    /// Remove a job by ID.
+    fn remove_job<'py>(&self, py: Python<'py>, job_id: String) -> PyResult<Bound<'py, PyAny>> {
+        let jobs = self.jobs.clone();
+        let store = self.store.clone();
+        let events = self.events.clone();
+        let wake = self.wake.clone();
+        let callbacks = self.callback.clone();
+
+        future_into_py(py, async move {
+            let removed = {
+                let mut guard = jobs.lock().await;
+                let before = guard.len();
+                guard.retain(|j| j.id != job_id);
+                guard.len() < before
+            };
+
+            if removed {
+                Python::with_gil(|_| callbacks.lock().bind(job_id.clone(), None));
+                wake.notify_one();
+                save_store(&store, &jobs, &events).await;
+                events.emit("job_removed", json!({ "job_id": job_id }), || {
+                    format!("Removed job {}", job_id)
+                });
+            }
+
+            Ok(removed)
+        })
+    }
+
+    /// Update fields of an existing job. Fields left as `None` are unchanged.
+    ///
+    /// `payload` replaces the whole payload before the individual payload
+    /// fields are applied.
+    #[pyo3(signature = (job_id, name=None, schedule=None, message=None, deliver=None, channel=None, to=None, delete_after_run=None, tags=None, payload=None, disable_after_failures=None, priority=None, on_success_job_id=None, on_failure_job_id=None))]
+    #[allow(clippy::too_many_arguments)]
+    fn update_job<'py>(
+        &self,
+        py: Python<'py>,
+        job_id: String,
+        name: Option<String>,
+        schedule: Option<CronSchedule>,
+        message: Option<String>,
+        deliver: Option<bool>,
+        channel: Option<String>,
+        to: Option<String>,
+        delete_after_run: Option<bool>,
+        tags: Option<Vec<String>>,
+        payload: Option<CronPayload>,
+        disable_after_failures: Option<u32>,
+        priority: Option<i32>,
+        on_success_job_id: Option<String>,
+        on_failure_job_id: Option<String>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let jobs = self.jobs.clone();
+        let store = self.store.clone();
+        let events = self.events.clone();
+        let wake = self.wake.clone();
+        let now = self.clock.now_ms();
+
+        if let Some(schedule) = &schedule {
+            self.check_schedule(schedule)?;
+        }
+        if let Some(payload) = &payload {
+            self.check_payload(payload)?;
+        }
+
+        future_into_py(py, async move {
+            let updated = {
+                let mut guard = jobs.lock().await;
+                guard.iter_mut().find(|j| j.id == job_id).map(|job| {
+                    if let Some(name) = name {
+                        job.name = name;
+                    }
+                    if let Some(schedule) = schedule {
+                        job.schedule = schedule;
+                        job.state.snoozed_until_ms = None;
+                        if job.enabled {
+                            job.state.next_run_at_ms = compute_next_run(&job.schedule, now);
+                        }
+                    }
+                    if let Some(payload) = payload {
+                        job.payload = payload;
+                    }
+                    if let Some(message) = message {
+                        job.payload.message = message;
+                    }
+                    if let Some(deliver) = deliver {
+                        job.payload.deliver = deliver;
+                    }
+                    if channel.is_some() {
+                        job.payload.channel = channel;
+                    }
+                    if to.is_some() {
+                        job.payload.to = to;
+                    }
+                    if let Some(delete_after_run) = delete_after_run {
+                        job.delete_after_run = delete_after_run;
+                    }
+                    if let Some(tags) = tags {
+                        job.tags = tags;
+                    }
+                    if disable_after_failures.is_some() {
+                        job.disable_after_failures = disable_after_failures;
+                    }
+                    if let Some(priority) = priority {
+                        job.priority = priority;
+                    }
+                    job.set_chain(on_success_job_id, on_failure_job_id);
+                    job.updated_at_ms = now;
+                    job.clone()
+                })
+            };
+
+            if updated.is_some() {
+                wake.notify_one();
+                save_store(&store, &jobs, &events).await;
+            }
+
+            Ok(updated)
+        })
+    }
+
+    /// Remove every job carrying the given tag. Returns the removed job IDs.
+    fn remove_jobs_by_tag<'py>(&self, py: Python<'py>, tag: String) -> PyResult<Bound<'py, PyAny>> {
+        self.clear_jobs(py, Some(tag))
+    }
+
+    /// Remove several jobs at once, saving the store once.
+    ///
+    /// Returns the IDs that were actually removed.
+    fn remove_jobs<'py>(
+        &self,
+        py: Python<'py>,
+        job_ids: Vec<String>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let jobs = self.jobs.clone();
+        let store = self.store.clone();
+        let events = self.events.clone();
+        let wake = self.wake.clone();
+        let callbacks = self.callback.clone();
+
+        future_into_py(py, async move {
+            let removed = remove_jobs_where(&jobs, |j| job_ids.contains(&j.id)).await;
+
+            if !removed.is_empty() {
+                Python::with_gil(|_| callbacks.lock().unbind_all(&removed));
+                wake.notify_one();
+                save_store(&store, &jobs, &events).await;
+                events.emit("jobs_removed", json!({ "job_ids": removed }), || {
+                    format!("Removed {} job(s)", removed.len())
+                });
+            }
+
+            Ok(removed)
+        })
+    }
+
+    /// Remove all jobs, or only those carrying `tag`. Returns the removed IDs.
+    #[pyo3(signature = (tag=None))]
+    fn clear_jobs<'py>(&self, py: Python<'py>, tag: Option<String>) -> PyResult<Bound<'py, PyAny>> {
+        let jobs = self.jobs.clone();
+        let store = self.store.clone();
+        let events = self.events.clone();
+        let wake = self.wake.clone();
+        let callbacks = self.callback.clone();
+
+        future_into_py(py, async move {
+            let removed =
+                remove_jobs_where(&jobs, |j| tag.as_ref().is_none_or(|t| j.tags.contains(t))).await;
+
+            if !removed.is_empty() {
+                Python::with_gil(|_| callbacks.lock().unbind_all(&removed));
+                wake.notify_one();
+                save_store(&store, &jobs, &events).await;
+                events.emit(
+                    "jobs_removed",
+                    json!({ "job_ids": removed, "tag": tag }),
+                    || match &tag {
+                        Some(tag) => format!("Removed {} job(s) tagged '{}'", removed.len(), tag),
+                        None => format!("Removed {} job(s)", removed.len()),
+                    },
+                );
+            }
+
+            Ok(removed)
+        })
+    }
+
+    /// Enable or disable a job.
+    #[pyo3(signature = (job_id, enabled=true))]
+    fn enable_job<'py>(
+        &self,
+        py: Python<'py>,
+        job_id: String,
+        enabled: bool,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let jobs = self.jobs.clone();
+        let store = self.store.clone();
+        let events = self.events.clone();
+        let wake = self.wake.clone();
+        let now = self.clock.now_ms();
+
+        future_into_py(py, async move {
+            let mut guard = jobs.lock().await;
+            for job in guard.iter_mut() {
+                if job.id == job_id {
+                    job.set_enabled(enabled, now);
+                    let job_clone = job.clone();
+                    drop(guard);
+                    wake.notify_one();
+                    save_store(&store, &jobs, &events).await;
+                    return Ok(Some(job_clone));
+                }
+            }
+            Ok(None)
+        })
+    }
+
+    /// Enable or disable several jobs at once, saving the store once.
+    ///
+    /// Returns the IDs that matched an existing job.
+    #[pyo3(signature = (job_ids, enabled=true))]
+    fn enable_jobs<'py>(
+        &self,
+        py: Python<'py>,
+        job_ids: Vec<String>,
+        enabled: bool,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let jobs = self.jobs.clone();
+        let store = self.store.clone();
+        let events = self.events.clone();
+        let wake = self.wake.clone();
+        let now = self.clock.now_ms();
+
+        future_into_py(py, async move {
+            let affected: Vec<String> = {
+                let mut guard = jobs.lock().await;
+                guard
+                    .iter_mut()
+                    .filter(|j| job_ids.contains(&j.id))
+                    .map(|job| {
+                        job.set_enabled(enabled, now);
+                        job.id.clone()
+                    })
+                    .collect()
+            };
+
+            if !affected.is_empty() {
+                wake.notify_one();
+                save_store(&store, &jobs, &events).await;
+            }
+
+            Ok(affected)
+        })
+    }
+
+    /// Hold an enabled job's next run until `until_ms`.
+    ///
+    /// The snooze is persisted, survives restarts and ends when the job next
+    /// runs on schedule. Pass `until_ms=None` to cancel it and resume the
+    /// regular schedule. Returns the updated job, or `None` if the job does
+    /// not exist or is disabled.
+    #[pyo3(signature = (job_id, until_ms))]
+    fn snooze_job<'py>(
+        &self,
+        py: Python<'py>,
+        job_id: String,
+        until_ms: Option<i64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let now = self.clock.now_ms();
+        if until_ms.is_some_and(|until| until <= now) {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "until_ms must be in the future",
+            ));
+        }
+        let jobs = self.jobs.clone();
+        let store = self.store.clone();
+        let events = self.events.clone();
+        let wake = self.wake.clone();
+
+        future_into_py(py, async move {
+            let snoozed = {
+                let mut guard = jobs.lock().await;
+                guard
+                    .iter_mut()
+                    .find(|j| j.id == job_id && j.enabled)
+                    .map(|job| {
+                        job.state.snoozed_until_ms = until_ms;
+                        job.state.next_run_at_ms =
+                            until_ms.or_else(|| compute_next_run(&job.schedule, now));
+                        job.clone()
+                    })
+            };
+
+            if let Some(job) = &snoozed {
+                match until_ms {
+                    Some(until) => events.emit(
+                        "job_snoozed",
+                        json!({ "job_id": job.id, "job_name": job.name, "until_ms": until }),
+                        || format!("Snoozed job '{}'", job.name),
+                    ),
+                    None => events.emit(
+                        "job_unsnoozed",
+                        json!({ "job_id": job.id, "job_name": job.name }),
+                        || format!("Resumed job '{}'", job.name),
+                    ),
+                }
+                wake.notify_one();
+                save_store(&store, &jobs, &events).await;
+            }
+            Ok(snoozed)
+        })
+    }
+
+    /// Manually run a job.
+    ///
+    /// Returns `None` if the job does not exist (or is disabled and `force`
+    /// is not set). Otherwise returns a dict with `status` (`"ok"`, `"error"`,
+    /// or `"skipped"` if the job is already executing), `error`,
+    /// `duration_ms`, `started_at_ms` and `next_run_at_ms`. With `wait=False`
+    /// the job runs in the background and
+    /// `{"status": "started", "started_at_ms": ...}` is returned immediately.
+    ///
+    /// A manual run records its result and history but leaves
+    /// `next_run_at_ms` and the enabled state alone, so one-shot jobs still
+    /// fire at their scheduled time. Pass `reschedule=True` to advance the
+    /// schedule as if the scheduler had run the job.
+    ///
+    /// `dry_run` overrides the service's dry-run setting for this run.
+    #[pyo3(signature = (job_id, force=false, wait=true, reschedule=false, dry_run=None))]
+    fn run_job<'py>(
+        &self,
+        py: Python<'py>,
+        job_id: String,
+        force: bool,
+        wait: bool,
+        reschedule: bool,
+        dry_run: Option<bool>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let jobs = self.jobs.clone();
+        let callback = self.callback.clone();
+        let store = self.store.clone();
+        let events = self.events.clone();
+        let config = ExecConfig {
+            reschedule,
+            dry_run: dry_run.unwrap_or(self.config.dry_run),
+            ..self.config
+        };
+        let recent_runs = self.recent_runs.clone();
+        let metrics = self.metrics.clone();
+        let in_flight = self.in_flight.clone();
+        let clock = self.clock.clone();
+
+        future_into_py(py, async move {
+            let job_exists = {
+                let guard = jobs.lock().await;
+                guard.iter().any(|j| j.id == job_id && (force || j.enabled))
+            };
+
+            if !job_exists {
+                return Ok(None);
+            }
+
+            let guard = in_flight.enter();
+            let started_at_ms = clock.now_ms();
+            let run = async move {
+                let _in_flight = guard;
+                let report = execute_chain(
+                    &jobs,
+                    &callback,
+                    &events,
+                    &recent_runs,
+                    &metrics,
+                    &in_flight,
+                    config,
+                    &clock,
+                    &job_id,
+                )
+                .await;
+                save_store(&store, &jobs, &events).await;
+                report
+            };
+
+            let report = if wait {
+                run.await
+            } else {
+                // Keep the event loop so async callbacks can be awaited
+                let locals = Python::with_gil(pyo3_async_runtimes::tokio::get_current_locals)?;
+                tokio::spawn(pyo3_async_runtimes::tokio::scope(locals, run));
+                Some(RunReport {
+                    status: "started".to_string(),
+                    started_at_ms,
+                    ..Default::default()
+                })
+            };
+
+            match report {
+                Some(report) => Python::with_gil(|py| report.to_py(py).map(Some)),
+                None => Ok(None),
+            }
+        })
+    }
+
+    /// Write pending changes to the store now instead of after the debounce.
+    ///
+    /// Raises `OSError` if the store cannot be written.
+    fn flush<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let jobs = self.jobs.clone();
+        let store = self.store.clone();
+        let events = self.events.clone();
+
+        future_into_py(py, async move {
+            flush_store(&store, &jobs, &events)
+                .await
+                .map_err(pyo3::exceptions::PyOSError::new_err)
+        })
+    }
+
+    /// Export the full job set (including runtime state) as a JSON string.
+    fn export_jobs<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let jobs = self.jobs.clone();
+
+        future_into_py(py, async move {
+            let guard = jobs.lock().await;
+            serde_json::to_string_pretty(&CronStoreJson::from_jobs(&guard))
+                .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))
+        })
+    }
+
+    /// Render enabled jobs as an iCalendar (`.ics`) document.
+    ///
+    /// `every` jobs become recurring events; cron, daily and weekly jobs are
+    /// expanded to their next `occurrences` runs. Event UIDs derive from the
+    /// job ID, so re-importing the calendar updates existing events.
+    #[pyo3(signature = (occurrences=10))]
+    fn export_ics<'py>(&self, py: Python<'py>, occurrences: usize) -> PyResult<Bound<'py, PyAny>> {
+        let jobs = self.jobs.clone();
+        let now = self.clock.now_ms();
+
+        future_into_py(py, async move {
+            let guard = jobs.lock().await;
+            Ok(ics::render(&guard, occurrences, now))
+        })
+    }
+
+    /// Import jobs from a JSON string produced by `export_jobs`.
+    ///
+    /// With `merge=True` the imported jobs are added alongside existing ones;
+    /// otherwise they replace them. Colliding IDs are regenerated and next run
+    /// times are recomputed. Returns the imported jobs.
+    #[pyo3(signature = (json, merge=true))]
+    fn import_jobs<'py>(
+        &self,
+        py: Python<'py>,
+        json: String,
+        merge: bool,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let jobs = self.jobs.clone();
+        let store = self.store.clone();
+        let events = self.events.clone();
+        let wake = self.wake.clone();
+        let now = self.clock.now_ms();
+
+        let incoming = parse_import(&json, now).map_err(pyo3::exceptions::PyValueError::new_err)?;
+        for job in &incoming {
+            self.check_payload(&job.payload)?;
+        }
+
+        future_into_py(py, async move {
+            let imported: Vec<CronJob> = {
+                let mut guard = jobs.lock().await;
+                if !merge {
+                    guard.clear();
+                }
+
+                let mut imported = Vec::with_capacity(incoming.len());
+                for mut job in incoming {
+                    while job.id.is_empty() || guard.iter().any(|j| j.id == job.id) {
+                        job.id = new_job_id();
+                    }
+                    job.state.snoozed_until_ms = None;
+                    job.state.next_run_at_ms = if job.enabled {
+                        compute_next_run(&job.schedule, now)
+                    } else {
+                        None
+                    };
+                    guard.push(job.clone());
+                    imported.push(job);
+                }
+                imported
+            };
+
+            wake.notify_one();
+            save_store(&store, &jobs, &events).await;
+            let ids: Vec<&str> = imported.iter().map(|j| j.id.as_str()).collect();
+            events.emit("jobs_imported", json!({ "job_ids": ids }), || {
+                format!("Imported {} job(s)", ids.len())
+            });
+
+            Ok(imported)
+        })
+    }
+
+    /// Get service status.
+    fn status<'py>(&self, py: Python<'py>) -> PyResult<PyObject> {
+        let dict = PyDict::new(py);
+        dict.set_item("enabled", self.running.load(Ordering::Relaxed))?;
+
+        // Read the summary kept by the store rather than waiting on the jobs
+        // lock, which may be held by the scheduler while it needs the GIL.
+        let summary = self.store.summary();
+        dict.set_item("jobs", summary.jobs)?;
+        dict.set_item("next_wake_at_ms", summary.next_wake_at_ms)?;
+        dict.set_item("event_errors", self.events.error_count())?;
+
+        // Slowest run within the recent-runs window
+        let slowest = match self.recent_runs.lock().iter().max_by_key(|r| r.duration_ms) {
+            Some(r) => {
+                let d = PyDict::new(py);
+                d.set_item("job_id", &r.job_id)?;
+                d.set_item("job_name", &r.job_name)?;
+                d.set_item("duration_ms", r.duration_ms)?;
+                Some(d)
+            }
+            None => None,
+        };
+        dict.set_item("slowest_job", slowest)?;
+
+        Ok(dict.into())
+    }
+
+    /// Execution metrics of this service's jobs: counts of `executions`,
+    /// `failures`, `timeouts` and `skipped_overlap` runs, overall and under
+    /// `jobs` by job ID, and `avg_duration_ms` (`None` before any run).
+    fn metrics(&self, py: Python<'_>) -> PyResult<PyObject> {
+        json_to_python(py, &self.metrics.lock().to_json())
+    }
+
+    /// Reset this service's execution metrics.
+    fn reset_metrics(&self) {
+        *self.metrics.lock() = CronMetrics::default();
+    }
+
+    /// Preview the next `count` fire times (epoch ms) of a schedule.
+    ///
+    /// Pure: no job needs to exist. `at` schedules yield at most one entry.
+    #[staticmethod]
+    #[pyo3(signature = (schedule, count=5, from_ms=None))]
+    fn preview_schedule(
+        schedule: CronSchedule,
+        count: usize,
+        from_ms: Option<i64>,
+    ) -> PyResult<Vec<i64>> {
+        let from_ms = from_ms.unwrap_or_else(now_ms);
+        validate_schedule(&schedule, from_ms)
+            .and_then(|_| next_occurrences(&schedule, from_ms, count))
+            .map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    fn __repr__(&self) -> String {
+        let running = self.running.load(Ordering::Relaxed);
+        format!(
+            "CronService(store_path={:?}, running={})",
+            self.store.path, running
+        )
+    }
+}
+
+impl CronService {
+    /// Validate a schedule given to add or update a job.
+    fn check_schedule(&self, schedule: &CronSchedule) -> PyResult<()> {
+        validate_schedule(schedule, self.clock.now_ms())
+            .map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+
+    /// Validate a payload, refusing shell payloads unless enabled.
+    fn check_payload(&self, payload: &CronPayload) -> PyResult<()> {
+        if payload.kind == "shell" && !self.config.allow_shell {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "Shell payloads are disabled; create the service with allow_shell=True",
+            ));
+        }
+        payload
+            .validate()
+            .map_err(pyo3::exceptions::PyValueError::new_err)
+    }
+}
+
+/// Parse and validate an exported job set.
+///
+/// Older store versions are migrated; newer ones are rejected.
+fn parse_import(json: &str, now: i64) -> Result<Vec<CronJob>, String> {
+    let (jobs, _) = parse_store(json)?;
+    for job in &jobs {
+        validate_schedule(&job.schedule, now)
+            .and_then(|_| job.payload.validate())
+            .map_err(|e| format!("Job '{}': {}", job.name, e))?;
+    }
+    Ok(jobs)
+}
+
+/// Unwrap loaded jobs, reporting failures to the load error callback.
+fn jobs_or_report(
+    loaded: Result<Vec<CronJob>, String>,
+    on_load_error: &Option<PyObject>,
+    events: &CronEvents,
+) -> Vec<CronJob> {
+    match loaded {
+        Ok(jobs) => jobs,
+        Err(e) => {
+            report_load_error(on_load_error, events, &e);
+            Vec::new()
+        }
+    }
+}
+
+/// Emit `store_load_failed` and pass the error to the load error callback.
+/// The stderr fallback is only used when neither callback is set.
+fn report_load_error(on_load_error: &Option<PyObject>, events: &CronEvents, error: &str) {
+    let fields = json!({ "error": error });
+    let Some(cb) = on_load_error else {
+        events.emit("store_load_failed", fields, || {
+            format!("Failed to load store: {}", error)
+        });
+        return;
+    };
+    events.emit_quiet("store_load_failed", fields);
+    if let Err(err) = Python::with_gil(|py| cb.call1(py, (error,))) {
+        events.emit(
+            "load_error_callback_failed",
+            json!({ "error": err.to_string() }),
+            || format!("Load error callback failed: {}", err),
+        );
+    }
+}
+
+/// Poll the store file and merge external edits into the in-memory jobs.
+async fn watch_store_changes(
+    store: Arc<CronStore>,
+    jobs: Arc<Mutex<Vec<CronJob>>>,
+    on_load_error: Arc<Option<PyObject>>,
+    running: Arc<AtomicBool>,
+    events: CronEvents,
+    wake: Arc<Notify>,
+    clock: WallClock,
+) {
+    while running.load(Ordering::Relaxed) {
+        tokio::time::sleep(tokio::time::Duration::from_millis(STORE_WATCH_INTERVAL_MS)).await;
+
+        // Taken before polling, which replaces it with the IDs on disk
+        let saved_ids = store.saved_ids();
+        match store.poll_external_change().await {
+            None => {}
+            Some(Ok(incoming)) => {
+                let now = clock.now_ms();
+                let mut guard = jobs.lock().await;
+                let kept_unsaved = merge_external_jobs(&mut guard, incoming, &saved_ids, now);
+                store.update_summary(&guard);
+                wake.notify_one();
+                events.emit("store_reloaded", json!({ "jobs": guard.len() }), || {
+                    "Reloaded store after external change".to_string()
+                });
+                drop(guard);
+                if kept_unsaved {
+                    save_store(&store, &jobs, &events).await;
+                }
+            }
+            Some(Err(e)) => report_load_error(&on_load_error, &events, &e),
+        }
+    }
+}
+
+/// Merge jobs read from disk into the in-memory set.
+///
+/// Jobs whose definition is unchanged keep their in-memory runtime state;
+/// new or edited jobs get their next run recomputed. Jobs missing from disk
+/// are dropped, unless they are not in `saved_ids`: those were created since
+/// the last save and are kept. Returns whether any such job was kept.
+fn merge_external_jobs(
+    current: &mut Vec<CronJob>,
+    incoming: Vec<CronJob>,
+    saved_ids: &HashSet<String>,
+    now: i64,
+) -> bool {
+    let mut merged = Vec::with_capacity(incoming.len());
+    let unsaved: Vec<CronJob> = current
+        .iter()
+        .filter(|j| !saved_ids.contains(&j.id) && !incoming.iter().any(|i| i.id == j.id))
+        .cloned()
+        .collect();
+    for mut job in incoming {
+        match current.iter().find(|j| j.id == job.id) {
+            Some(existing) if existing.same_definition(&job) => merged.push(existing.clone()),
+            _ => {
+                job.state.snoozed_until_ms = None;
+                job.state.next_run_at_ms = if job.enabled {
+                    compute_next_run(&job.schedule, now)
+                } else {
+                    None
+                };
+                merged.push(job);
+            }
+        }
+    }
+    let kept_unsaved = !unsaved.is_empty();
+    merged.extend(unsaved);
+    *current = merged;
+    kept_unsaved
+}
+
+/// Keep a due job from running inside its quiet window.
+///
+/// With the `"defer"` policy the run moves to the end of the window; with
+/// `"skip"` it is dropped and recorded as skipped. Returns whether the job
+/// was held back.
+async fn hold_for_quiet_hours(
+    jobs: &Arc<Mutex<Vec<CronJob>>>,
+    events: &CronEvents,
+    job_id: &str,
+    now: i64,
+) -> bool {
+    let mut guard = jobs.lock().await;
+    let Some(job) = guard.iter_mut().find(|j| j.id == job_id) else {
+        return false;
+    };
+    let Ok(Some(window)) = quiet_window(&job.schedule) else {
+        return false;
+    };
+    let Some(quiet_until) = window.end_if_inside(now) else {
+        return false;
+    };
+
+    match window.policy {
+        QuietPolicy::Defer => {
+            job.state.next_run_at_ms = Some(quiet_until);
+            events.emit(
+                "job_deferred",
+                json!({ "job_id": job.id, "job_name": job.name, "until_ms": quiet_until }),
+                || format!("Deferred job '{}' until the end of quiet hours", job.name),
+            );
+        }
+        QuietPolicy::Skip => {
+            job.state.last_status = Some("skipped".to_string());
+            job.updated_at_ms = now;
+            if job.schedule.kind == "at" {
+                job.enabled = false;
+                job.state.next_run_at_ms = None;
+            } else {
+                job.state.next_run_at_ms = compute_next_run(&job.schedule, now);
+            }
+            events.emit(
+                "job_skipped",
+                json!({ "job_id": job.id, "job_name": job.name, "reason": "quiet_hours" }),
+                || format!("Skipped job '{}' during quiet hours", job.name),
+            );
+        }
+    }
+    true
+}
+
+/// Report a run skipped because the job is still executing.
+///
+/// A due recurring job moves on to its next run so the scheduler does not
+/// retry it in a tight loop; the execution in progress handles one-shot jobs.
+/// Nothing moves unless `config.reschedule` is set, as for a forced manual
+/// run.
+async fn skip_overlapping_run(
+    jobs: &Arc<Mutex<Vec<CronJob>>>,
+    events: &CronEvents,
+    metrics: &SharedMetrics,
+    config: ExecConfig,
+    job: &CronJob,
+    start_ms: i64,
+) -> RunReport {
+    metrics.lock().record_skipped_overlap(&job.id);
+    events.emit(
+        "job_skipped",
+        json!({ "job_id": job.id, "job_name": job.name, "reason": "overlap" }),
+        || format!("Skipped job '{}': already running", job.name),
+    );
+
+    let mut next_run_at_ms = job.state.next_run_at_ms;
+    let mut guard = jobs.lock().await;
+    if let Some(j) = guard.iter_mut().find(|j| j.id == job.id) {
+        let due = j.state.next_run_at_ms.is_some_and(|t| t <= start_ms);
+        if due && config.reschedule {
+            j.state.next_run_at_ms = if j.schedule.kind == "at" {
+                None
+            } else {
+                compute_next_run(&j.schedule, start_ms)
+            };
+        }
+        next_run_at_ms = j.state.next_run_at_ms;
+    }
+
+    RunReport {
+        status: "skipped".to_string(),
+        error: Some("Job is already running".to_string()),
+        duration_ms: None,
+        started_at_ms: start_ms,
+        next_run_at_ms,
+    }
+}
+
+/// Remove jobs matching `pred`, returning their IDs.
+async fn remove_jobs_where(
+    jobs: &Arc<Mutex<Vec<CronJob>>>,
+    pred: impl Fn(&CronJob) -> bool,
+) -> Vec<String> {
+    let mut guard = jobs.lock().await;
+    let ids = guard
+        .iter()
+        .filter(|j| pred(j))
+        .map(|j| j.id.clone())
+        .collect();
+    guard.retain(|j| !pred(j));
+    ids
+}
+
+impl Drop for CronService {
+    fn drop(&mut self) {
+        let busy = !self.in_flight.job_ids.lock().is_empty();
+        if busy || self.store.has_pending_save() {
+            // Python drops the service under the GIL, so this only borrows it
+            if let Err(e) = Python::with_gil(|py| self.close(py)) {
+                let error = e.to_string();
+                self.events
+                    .emit("store_error", json!({ "error": error }), || error.clone());
+            }
+        }
+    }
+}
+
+/// Mark jobs that are still executing as interrupted.
+fn mark_interrupted(jobs: &mut [CronJob], running: &HashSet<String>) {
+    for job in jobs.iter_mut().filter(|j| running.contains(&j.id)) {
+        job.state.last_status = Some("interrupted".to_string());
+        job.state.last_error = Some("Service closed while the job was running".to_string());
+    }
+}
+
+/// Orderings offered by `list_jobs`.
+#[derive(Clone, Copy)]
+enum JobSort {
+    NextRun,
+    Name,
+    Created,
+    LastRun,
+}
+
+impl JobSort {
+    fn parse(text: &str) -> Result<Self, String> {
+        match text {
+            "next_run" => Ok(Self::NextRun),
+            "name" => Ok(Self::Name),
+            "created" => Ok(Self::Created),
+            "last_run" => Ok(Self::LastRun),
+            other => Err(format!(
+                "Invalid sort_by '{}': expected 'next_run', 'name', 'created' or 'last_run'",
+                other
+            )),
+        }
+    }
+
+    /// Sort `jobs`; the sort is stable, so ties keep insertion order.
+    fn apply(self, jobs: &mut [&CronJob]) {
+        match self {
+            // Soonest first, then by descending priority
+            Self::NextRun => jobs.sort_by_key(|j| {
+                (
+                    j.state.next_run_at_ms.unwrap_or(i64::MAX),
+                    std::cmp::Reverse(j.priority),
+                )
+            }),
+            Self::Name => jobs.sort_by(|a, b| a.name.cmp(&b.name)),
+            Self::Created => jobs.sort_by_key(|j| j.created_at_ms),
+            Self::LastRun => {
+                jobs.sort_by_key(|j| std::cmp::Reverse(j.state.last_run_at_ms.unwrap_or(i64::MIN)))
+            }
+        }
+    }
+}
+
+/// Call a Python callback and await the result if it is awaitable.
+///
+/// The awaitable is converted to a Rust future under the GIL and awaited
+/// outside it, so this must run inside a pyo3-async-runtimes task.
+pub(crate) async fn call_py<F>(call: F) -> PyResult<PyObject>
+where
+    F: for<'py> FnOnce(Python<'py>) -> PyResult<PyObject>,
+{
+    let pending = Python::with_gil(|py| -> PyResult<Result<PyObject, _>> {
+        let result = call(py)?;
+        let bound = result.bind(py);
+        if bound.hasattr("__await__")? {
+            Ok(Err(pyo3_async_runtimes::tokio::into_future(bound.clone())?))
+        } else {
+            Ok(Ok(result))
+        }
+    })?;
+    match pending {
+        Ok(value) => Ok(value),
+        Err(future) => future.await,
+    }
+}
+
+/// Outcome of a single job execution, as returned by `run_job`.
+#[derive(Default)]
+struct RunReport {
+    status: String,
+    error: Option<String>,
+    duration_ms: Option<i64>,
+    started_at_ms: i64,
+    next_run_at_ms: Option<i64>,
+}
+
+impl RunReport {
+    fn to_py(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let dict = PyDict::new(py);
+        dict.set_item("status", &self.status)?;
+        dict.set_item("error", &self.error)?;
+        dict.set_item("duration_ms", self.duration_ms)?;
+        dict.set_item("started_at_ms", self.started_at_ms)?;
+        dict.set_item("next_run_at_ms", self.next_run_at_ms)?;
+        Ok(dict.into())
+    }
+}
+
+/// What running a job's payload produced.
+#[derive(Default)]
+struct RunOutcome {
+    error: Option<String>,
+    output: Option<String>,
+    exit_code: Option<i32>,
+    timed_out: bool,
+}
+
+/// Why a webhook or shell payload failed.
+struct PayloadError {
+    message: String,
+    timed_out: bool,
+}
+
+impl PayloadError {
+    fn timeout(message: String) -> Self {
+        Self {
+            message,
+            timed_out: true,
+        }
+    }
+}
+
+impl From<String> for PayloadError {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            timed_out: false,
+        }
+    }
+}
+
+/// The argument passed to a job callback.
+fn callback_arg(py: Python<'_>, job: &CronJob, pass_job: bool) -> PyResult<PyObject> {
+    if pass_job {
+        return Ok(job.clone().into_pyobject(py)?.into_any().unbind());
+    }
+    let dict = PyDict::new(py);
+    dict.set_item("kind", &job.payload.kind)?;
+    dict.set_item("message", &job.payload.message)?;
+    dict.set_item("deliver", job.payload.deliver)?;
+    dict.set_item("channel", &job.payload.channel)?;
+    dict.set_item("to", &job.payload.to)?;
+    dict.set_item("job_id", &job.id)?;
+    dict.set_item("job_name", &job.name)?;
+    Ok(dict.into())
+}
+
+/// Run a job's payload: a webhook, a shell command, or the Python callback.
+async fn run_payload(job: &CronJob, callback: &JobCallback, config: ExecConfig) -> RunOutcome {
+    match job.payload.kind.as_str() {
+        "webhook" => match webhook::send(&job.payload).await {
+            Ok(summary) => RunOutcome {
+                output: Some(summary),
+                ..Default::default()
+            },
+            Err(e) => RunOutcome {
+                error: Some(e.message.clone()),
+                output: Some(e.message),
+                timed_out: e.timed_out,
+                ..Default::default()
+            },
+        },
+        "shell" if !config.allow_shell => RunOutcome {
+            error: Some("Shell payloads are disabled (allow_shell=False)".to_string()),
+            ..Default::default()
+        },
+        "shell" => match shell::run(&job.payload).await {
+            Ok(out) => RunOutcome {
+                error: match out.exit_code {
+                    Some(0) => None,
+                    Some(code) => Some(format!("Command exited with code {}", code)),
+                    None => Some("Command terminated by signal".to_string()),
+                },
+                output: Some(out.output),
+                exit_code: out.exit_code,
+                ..Default::default()
+            },
+            Err(e) => RunOutcome {
+                error: Some(e.message),
+                timed_out: e.timed_out,
+                ..Default::default()
+            },
+        },
+        _ => {
+            // Take the GIL before the lock, matching set_callback
+            let cb = Python::with_gil(|py| callback.lock().for_job(py, &job.id));
+            let result = match cb {
+                Some(cb) => call_py(|py| cb.call1(py, (callback_arg(py, job, config.pass_job)?,)))
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| e.to_string()),
+                None => Ok(()),
+            };
+            RunOutcome {
+                error: result.err(),
+                ..Default::default()
+            }
+        }
+    }
+}
+
+/// Execute a job, then the follow-up jobs it chains to.
+///
+/// Each link runs `on_success_job_id` or `on_failure_job_id` of the job
+/// before it, regardless of the follow-up's own schedule or enabled state.
+/// At most `MAX_CHAIN_DEPTH` follow-ups run, which also breaks cycles.
+/// Returns the report of the first job.
+#[allow(clippy::too_many_arguments)]
+async fn execute_chain(
+    jobs: &Arc<Mutex<Vec<CronJob>>>,
+    callback: &JobCallback,
+    events: &CronEvents,
+    recent_runs: &RecentRuns,
+    metrics: &SharedMetrics,
+    in_flight: &Arc<InFlight>,
+    config: ExecConfig,
+    clock: &WallClock,
+    job_id: &str,
+) -> Option<RunReport> {
+    let mut first = None;
+    let mut current = job_id.to_string();
+    let mut parent: Option<String> = None;
+    for depth in 0.. {
+        // Read the links first: a one-shot job may delete itself when it runs
+        let (on_success, on_failure) = {
+            let guard = jobs.lock().await;
+            let Some(job) = guard.iter().find(|j| j.id == current) else {
+                break;
+            };
+            (job.on_success_job_id.clone(), job.on_failure_job_id.clone())
+        };
+        if depth > MAX_CHAIN_DEPTH {
+            events.emit(
+                "chain_limit_reached",
+                json!({ "job_id": job_id, "skipped_job_id": current }),
+                || {
+                    format!(
+                        "Chain from job {} stopped after {} follow-ups",
+                        job_id, MAX_CHAIN_DEPTH
+                    )
+                },
+            );
+            break;
+        }
+        if let Some(parent) = &parent {
+            events.emit(
+                "job_chained",
+                json!({ "job_id": current, "parent_job_id": parent }),
+                || format!("Job {} triggered follow-up {}", parent, current),
+            );
+        }
+
+        // A follow-up runs off its own schedule, which it keeps
+        let run_config = match parent {
+            Some(_) => ExecConfig {
+                reschedule: false,
+                ..config
+            },
+            None => config,
+        };
+        let Some(report) = execute_job(
+            jobs,
+            callback,
+            events,
+            recent_runs,
+            metrics,
+            in_flight,
+            run_config,
+            clock,
+            &current,
+        )
+        .await
+        else {
+            break;
+        };
+
+        if let Some(parent) = parent.take() {
+            let mut guard = jobs.lock().await;
+            if let Some(job) = guard.iter_mut().find(|j| j.id == current) {
+                job.state.last_triggered_by = Some(parent);
+            }
+        }
+
+        let next = match report.status.as_str() {
+            "ok" => on_success,
+            "error" => on_failure,
+            _ => None,
+        };
+        first.get_or_insert(report);
+        match next {
+            Some(next) => parent = Some(std::mem::replace(&mut current, next)),
+            None => break,
+        }
+    }
+    first
+}
+
+/// Execute a single job, returning `None` if it no longer exists.
+///
+/// If the job is already executing, the run is skipped and reported with
+/// status `"skipped"`.
+#[allow(clippy::too_many_arguments)]
+async fn execute_job(
+    jobs: &Arc<Mutex<Vec<CronJob>>>,
+    callback: &JobCallback,
+    events: &CronEvents,
+    recent_runs: &RecentRuns,
+    metrics: &SharedMetrics,
+    in_flight: &Arc<InFlight>,
+    config: ExecConfig,
+    clock: &WallClock,
+    job_id: &str,
+) -> Option<RunReport> {
+    let start_ms = clock.now_ms();
+    let started = std::time::Instant::now();
+
+    // Get job info
+    let job_info = {
+        let guard = jobs.lock().await;
+        guard.iter().find(|j| j.id == job_id).cloned()
+    };
+
+    let job = job_info?;
+
+    let Some(_claim) = in_flight.claim(job_id) else {
+        return Some(skip_overlapping_run(jobs, events, metrics, config, &job, start_ms).await);
+    };
+    if config.dry_run {
+        return dry_run_job(jobs, events, config, job_id, start_ms).await;
+    }
+
+    events.emit(
+        "job_started",
+        json!({ "job_id": job.id, "job_name": job.name }),
+        || format!("Executing job '{}' ({})", job.name, job.id),
+    );
+
+    let outcome = run_payload(&job, callback, config).await;
+    let duration_ms = started.elapsed().as_millis() as i64;
+    metrics.lock().record_execution(
+        &job.id,
+        duration_ms,
+        outcome.error.is_some(),
+        outcome.timed_out,
+    );
+
+    {
+        let mut recent = recent_runs.lock();
+        if recent.len() == RECENT_RUNS_WINDOW {
+            recent.pop_front();
+        }
+        recent.push_back(RunRecord {
+            job_id: job.id.clone(),
+            job_name: job.name.clone(),
+            duration_ms,
+        });
+    }
+
+    // Update job state
+    let finished_ms = clock.now_ms();
+    let mut guard = jobs.lock().await;
+    let job = guard.iter_mut().find(|j| j.id == job_id)?;
+    job.state.last_run_at_ms = Some(start_ms);
+    job.state.last_triggered_by = None;
+    job.state.last_exit_code = outcome.exit_code;
+    job.state.last_output = outcome.output.clone();
+    job.state.record_duration(duration_ms);
+    job.updated_at_ms = finished_ms;
+
+    let output = &outcome.output;
+    let mut auto_disabled = false;
+    match &outcome.error {
+        None => {
+            job.state.last_status = Some("ok".to_string());
+            job.state.last_error = None;
+            job.state.consecutive_failures = 0;
+            events.emit(
+                "job_finished",
+                json!({
+                    "job_id": job.id,
+                    "job_name": job.name,
+                    "output": output,
+                    "duration_ms": duration_ms,
+                }),
+                || format!("Job '{}' completed", job.name),
+            );
+        }
+        Some(e) => {
+            job.state.last_status = Some("error".to_string());
+            job.state.last_error = Some(e.clone());
+            events.emit(
+                "job_failed",
+                json!({
+                    "job_id": job.id,
+                    "job_name": job.name,
+                    "error": e,
+                    "duration_ms": duration_ms,
+                }),
+                || format!("Job '{}' failed: {}", job.name, e),
+            );
+
+            auto_disabled = record_failure(job, e, config, events);
+        }
+    }
+
+    let status = job.state.last_status.clone().unwrap_or_default();
+    // Keep auto-disabled jobs, even if one-shot, so the failure stays visible
+    let next_run_at_ms = if auto_disabled {
+        None
+    } else {
+        advance_after_run(&mut guard, job_id, config, finished_ms)
+    };
+
+    Some(RunReport {
+        status,
+        error: outcome.error,
+        duration_ms: Some(duration_ms),
+        started_at_ms: start_ms,
+        next_run_at_ms,
+    })
+}
+
+/// Record a dry run: the job's schedule advances but its payload never runs.
+async fn dry_run_job(
+    jobs: &Arc<Mutex<Vec<CronJob>>>,
+    events: &CronEvents,
+    config: ExecConfig,
+    job_id: &str,
+    start_ms: i64,
+) -> Option<RunReport> {
+    let mut guard = jobs.lock().await;
+    let job = guard.iter_mut().find(|j| j.id == job_id)?;
+    job.state.last_run_at_ms = Some(start_ms);
+    job.state.last_status = Some("dry_run".to_string());
+    job.state.last_error = None;
+    job.state.last_triggered_by = None;
+    job.updated_at_ms = start_ms;
+    events.emit(
+        "job_dry_run",
+        json!({ "job_id": job.id, "job_name": job.name, "kind": job.payload.kind }),
+        || format!("Dry run of job '{}' ({})", job.name, job.id),
+    );
+
+    let next_run_at_ms = advance_after_run(&mut guard, job_id, config, start_ms);
+    Some(RunReport {
+        status: "dry_run".to_string(),
+        duration_ms: Some(0),
+        started_at_ms: start_ms,
+        next_run_at_ms,
+        ..Default::default()
+    })
+}
+
+/// Count a failed run of `job`, disabling it once its consecutive failures
+/// reach its threshold (or the service's). Returns whether it was disabled.
+fn record_failure(job: &mut CronJob, error: &str, config: ExecConfig, events: &CronEvents) -> bool {
+    job.state.consecutive_failures += 1;
+    let failures = job.state.consecutive_failures;
+    let threshold = job
+        .disable_after_failures
+        .or(config.disable_after_failures)
+        .unwrap_or(0);
+    if threshold == 0 || failures < threshold {
+        return false;
+    }
+    job.enabled = false;
+    job.state.next_run_at_ms = None;
+    job.state.last_error = Some(format!(
+        "Auto-disabled after {} consecutive failures; last error: {}",
+        failures, error
+    ));
+    events.emit(
+        "job_auto_disabled",
+        json!({
+            "job_id": job.id,
+            "job_name": job.name,
+            "consecutive_failures": failures,
+            "error": error,
+        }),
+        || {
+            format!(
+                "Job '{}' disabled after {} consecutive failures",
+                job.name, failures
+            )
+        },
+    );
+    true
+}
+
+/// Advance a job's schedule after a run finishing at `now`, returning its
+/// next run.
+///
+/// One-shot jobs are disabled or deleted. Nothing changes unless
+/// `config.reschedule` is set.
+fn advance_after_run(
+    jobs: &mut Vec<CronJob>,
+    job_id: &str,
+    config: ExecConfig,
+    now: i64,
+) -> Option<i64> {
+    let job = jobs.iter_mut().find(|j| j.id == job_id)?;
+    if !config.reschedule {
+        return job.state.next_run_at_ms;
+    }
+    // A snooze ends once the job runs on schedule
+    job.state.snoozed_until_ms = None;
+    if job.schedule.kind != "at" {
+        job.state.next_run_at_ms = compute_next_run(&job.schedule, now);
+        return job.state.next_run_at_ms;
+    }
+    if job.delete_after_run {
+        jobs.retain(|j| j.id != job_id);
+    } else {
+        job.enabled = false;
+        job.state.next_run_at_ms = None;
+    }
+    None
+}
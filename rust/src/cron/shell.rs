@@ -0,0 +1,136 @@
+//! Shell payloads: jobs that run a command without involving the agent.
+
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+use tokio::time::timeout;
+
+use super::{CronPayload, PayloadError};
+
+const DEFAULT_TIMEOUT_MS: u64 = 60_000;
+/// Maximum number of characters kept from each of stdout and stderr.
+const MAX_OUTPUT_CHARS: usize = 4000;
+/// Bytes kept from each stream: `MAX_OUTPUT_CHARS` characters of any width.
+const MAX_OUTPUT_BYTES: usize = MAX_OUTPUT_CHARS * 4;
+
+/// Result of running a shell payload.
+pub(super) struct ShellOutput {
+    pub(super) exit_code: Option<i32>,
+    pub(super) output: String,
+}
+
+/// Check that a shell payload has a command.
+pub(super) fn validate(payload: &CronPayload) -> Result<(), String> {
+    match payload.command.as_deref() {
+        Some(c) if !c.trim().is_empty() => Ok(()),
+        _ => Err("Shell payload requires 'command'".to_string()),
+    }
+}
+
+/// Output read from one of the command's streams.
+#[derive(Default)]
+struct Captured {
+    /// The first `MAX_OUTPUT_BYTES` bytes.
+    kept: Vec<u8>,
+    /// Characters read after those and thrown away.
+    dropped_chars: usize,
+}
+
+impl Captured {
+    /// At most `MAX_OUTPUT_CHARS` characters of the output.
+    fn text(&self) -> String {
+        let text = String::from_utf8_lossy(&self.kept);
+        match text.char_indices().nth(MAX_OUTPUT_CHARS) {
+            Some((idx, _)) => format!(
+                "{}... (truncated, {} more chars)",
+                &text[..idx],
+                text[idx..].chars().count() + self.dropped_chars
+            ),
+            None => text.into_owned(),
+        }
+    }
+}
+
+/// Read `stream` to its end, keeping only its first `MAX_OUTPUT_BYTES`
+/// bytes so a chatty command cannot fill memory.
+async fn capture(stream: Option<impl AsyncRead + Unpin>) -> std::io::Result<Captured> {
+    let mut captured = Captured::default();
+    let Some(mut stream) = stream else {
+        return Ok(captured);
+    };
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(captured);
+        }
+        let room = (MAX_OUTPUT_BYTES - captured.kept.len()).min(n);
+        captured.kept.extend_from_slice(&buf[..room]);
+        // Every byte but a UTF-8 continuation byte starts a character
+        captured.dropped_chars += buf[room..n].iter().filter(|&&b| b & 0xC0 != 0x80).count();
+    }
+}
+
+/// Run the payload's command.
+///
+/// The process is killed if it exceeds the timeout. Output past what is
+/// kept is read and discarded until the process exits. A missing exit code
+/// means the process was terminated by a signal.
+pub(super) async fn run(payload: &CronPayload) -> Result<ShellOutput, PayloadError> {
+    validate(payload)?;
+    let command = payload.command.as_deref().unwrap_or_default();
+    let timeout_ms = payload.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS);
+
+    let mut cmd = if cfg!(target_os = "windows") {
+        let mut c = Command::new("cmd");
+        c.args(["/C", command]);
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.args(["-c", command]);
+        c
+    };
+    if let Some(cwd) = &payload.cwd {
+        cmd.current_dir(PathBuf::from(cwd));
+    }
+    cmd.stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to run command: {}", e))?;
+    let (stdout, stderr) = (child.stdout.take(), child.stderr.take());
+    // Both pipes are read at once, so neither fills up and stalls the command
+    let run = async {
+        let (stdout, stderr) = tokio::try_join!(capture(stdout), capture(stderr))?;
+        Ok::<_, std::io::Error>((child.wait().await?, stdout, stderr))
+    };
+    let (status, stdout, stderr) = match timeout(Duration::from_millis(timeout_ms), run).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => return Err(format!("Failed to run command: {}", e).into()),
+        Err(_) => {
+            return Err(PayloadError::timeout(format!(
+                "Command timed out after {} ms",
+                timeout_ms
+            )))
+        }
+    };
+
+    let mut parts = Vec::new();
+    let stdout = stdout.text();
+    if !stdout.trim().is_empty() {
+        parts.push(stdout);
+    }
+    let stderr = stderr.text();
+    if !stderr.trim().is_empty() {
+        parts.push(format!("STDERR:\n{}", stderr));
+    }
+
+    Ok(ShellOutput {
+        exit_code: status.code(),
+        output: parts.join("\n"),
+    })
+}
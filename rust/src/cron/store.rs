@@ -0,0 +1,850 @@
+//! On-disk persistence for the cron job set.
+//!
+//! The store is a versioned JSON document. Older versions are migrated
+//! forward on load; newer (unknown) versions are refused so that a
+//! downgraded binary never overwrites data it does not understand.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::{Mutex, MutexGuard};
+
+use super::events::CronEvents;
+use super::{CronJob, CronJobState, CronPayload, CronSchedule};
+
+/// JSON structure for serialization
+#[derive(Serialize, Deserialize)]
+pub(super) struct CronStoreJson {
+    version: i32,
+    jobs: Vec<CronJobJson>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CronJobJson {
+    id: String,
+    name: String,
+    enabled: bool,
+    schedule: CronScheduleJson,
+    payload: CronPayloadJson,
+    state: CronJobStateJson,
+    created_at_ms: i64,
+    updated_at_ms: i64,
+    delete_after_run: bool,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    disable_after_failures: Option<u32>,
+    #[serde(default)]
+    priority: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    on_success_job_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    on_failure_job_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CronScheduleJson {
+    kind: String,
+    at_ms: Option<i64>,
+    every_ms: Option<i64>,
+    expr: Option<String>,
+    tz: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hour: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    minute: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    weekdays: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    quiet_start: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    quiet_end: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    quiet_policy: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CronPayloadJson {
+    kind: String,
+    message: String,
+    deliver: bool,
+    channel: Option<String>,
+    to: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    method: Option<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    headers: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cwd: Option<String>,
+    #[serde(default, rename = "timeoutMs", skip_serializing_if = "Option::is_none")]
+    timeout_ms: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct CronJobStateJson {
+    next_run_at_ms: Option<i64>,
+    last_run_at_ms: Option<i64>,
+    last_status: Option<String>,
+    last_error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_exit_code: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_output: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_duration_ms: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    avg_duration_ms: Option<f64>,
+    #[serde(default)]
+    run_count: u64,
+    #[serde(default)]
+    consecutive_failures: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_triggered_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    snoozed_until_ms: Option<i64>,
+}
+
+/// Delay used to coalesce successive saves into one write.
+const SAVE_DEBOUNCE_MS: u64 = 250;
+
+/// Longest blocking code waits for a lock the runtime holds.
+pub(super) const BLOCKING_LOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Lock `mutex` from blocking code, giving up after `timeout`.
+///
+/// Polls rather than blocking on the mutex, which would panic inside the
+/// runtime and could wait forever on a task that cannot run meanwhile.
+pub(super) fn lock_blocking<T>(mutex: &Mutex<T>, timeout: Duration) -> Option<MutexGuard<'_, T>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        match mutex.try_lock() {
+            Ok(guard) => return Some(guard),
+            Err(_) if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(5)),
+            Err(_) => return None,
+        }
+    }
+}
+
+/// Current on-disk store format version.
+///
+/// History:
+/// - 1: initial format
+/// - 2: jobs carry a `tags` list
+/// - 3: payloads may be webhooks (`url`, `method`, `headers`, `body`)
+/// - 4: payloads may be shell commands (`command`, `cwd`, `timeoutMs`);
+///   state records `lastExitCode` and `lastOutput`
+/// - 5: jobs may set `disableAfterFailures`; state records
+///   `consecutiveFailures`
+/// - 6: `daily`/`weekly` schedules (`hour`, `minute`, `weekdays`)
+/// - 7: jobs carry a `priority`
+/// - 8: schedules may set quiet hours (`quietStart`, `quietEnd`,
+///   `quietPolicy`)
+/// - 9: jobs may chain follow-up jobs (`onSuccessJobId`, `onFailureJobId`);
+///   state records `lastTriggeredBy`
+/// - 10: state records run statistics (`lastDurationMs`, `avgDurationMs`,
+///   `runCount`) and `snoozedUntilMs`
+pub(super) const STORE_VERSION: i32 = 10;
+
+impl From<&CronSchedule> for CronScheduleJson {
+    fn from(s: &CronSchedule) -> Self {
+        Self {
+            kind: s.kind.clone(),
+            at_ms: s.at_ms,
+            every_ms: s.every_ms,
+            expr: s.expr.clone(),
+            tz: s.tz.clone(),
+            hour: s.hour,
+            minute: s.minute,
+            weekdays: s.weekdays,
+            quiet_start: s.quiet_start.clone(),
+            quiet_end: s.quiet_end.clone(),
+            quiet_policy: s.quiet_policy.clone(),
+        }
+    }
+}
+
+impl From<CronScheduleJson> for CronSchedule {
+    fn from(s: CronScheduleJson) -> Self {
+        Self {
+            kind: s.kind,
+            at_ms: s.at_ms,
+            every_ms: s.every_ms,
+            expr: s.expr,
+            tz: s.tz,
+            hour: s.hour,
+            minute: s.minute,
+            weekdays: s.weekdays,
+            quiet_start: s.quiet_start,
+            quiet_end: s.quiet_end,
+            quiet_policy: s.quiet_policy,
+        }
+    }
+}
+
+impl From<&CronPayload> for CronPayloadJson {
+    fn from(p: &CronPayload) -> Self {
+        Self {
+            kind: p.kind.clone(),
+            message: p.message.clone(),
+            deliver: p.deliver,
+            channel: p.channel.clone(),
+            to: p.to.clone(),
+            url: p.url.clone(),
+            method: p.method.clone(),
+            headers: p.headers.clone(),
+            body: p.body.clone(),
+            command: p.command.clone(),
+            cwd: p.cwd.clone(),
+            timeout_ms: p.timeout_ms,
+        }
+    }
+}
+
+impl From<CronPayloadJson> for CronPayload {
+    fn from(p: CronPayloadJson) -> Self {
+        Self {
+            kind: p.kind,
+            message: p.message,
+            deliver: p.deliver,
+            channel: p.channel,
+            to: p.to,
+            url: p.url,
+            method: p.method,
+            headers: p.headers,
+            body: p.body,
+            command: p.command,
+            cwd: p.cwd,
+            timeout_ms: p.timeout_ms,
+        }
+    }
+}
+
+impl From<&CronJobState> for CronJobStateJson {
+    fn from(s: &CronJobState) -> Self {
+        Self {
+            next_run_at_ms: s.next_run_at_ms,
+            last_run_at_ms: s.last_run_at_ms,
+            last_status: s.last_status.clone(),
+            last_error: s.last_error.clone(),
+            last_exit_code: s.last_exit_code,
+            last_output: s.last_output.clone(),
+            last_duration_ms: s.last_duration_ms,
+            avg_duration_ms: s.avg_duration_ms,
+            run_count: s.run_count,
+            consecutive_failures: s.consecutive_failures,
+            last_triggered_by: s.last_triggered_by.clone(),
+            snoozed_until_ms: s.snoozed_until_ms,
+        }
+    }
+}
+
+impl From<CronJobStateJson> for CronJobState {
+    fn from(s: CronJobStateJson) -> Self {
+        Self {
+            next_run_at_ms: s.next_run_at_ms,
+            last_run_at_ms: s.last_run_at_ms,
+            last_status: s.last_status,
+            last_error: s.last_error,
+            last_exit_code: s.last_exit_code,
+            last_output: s.last_output,
+            last_duration_ms: s.last_duration_ms,
+            avg_duration_ms: s.avg_duration_ms,
+            run_count: s.run_count,
+            consecutive_failures: s.consecutive_failures,
+            last_triggered_by: s.last_triggered_by,
+            snoozed_until_ms: s.snoozed_until_ms,
+        }
+    }
+}
+
+impl From<&CronJob> for CronJobJson {
+    fn from(j: &CronJob) -> Self {
+        Self {
+            id: j.id.clone(),
+            name: j.name.clone(),
+            enabled: j.enabled,
+            schedule: (&j.schedule).into(),
+            payload: (&j.payload).into(),
+            state: (&j.state).into(),
+            created_at_ms: j.created_at_ms,
+            updated_at_ms: j.updated_at_ms,
+            delete_after_run: j.delete_after_run,
+            tags: j.tags.clone(),
+            disable_after_failures: j.disable_after_failures,
+            priority: j.priority,
+            on_success_job_id: j.on_success_job_id.clone(),
+            on_failure_job_id: j.on_failure_job_id.clone(),
+        }
+    }
+}
+
+impl From<CronJobJson> for CronJob {
+    fn from(j: CronJobJson) -> Self {
+        Self {
+            id: j.id,
+            name: j.name,
+            enabled: j.enabled,
+            schedule: j.schedule.into(),
+            payload: j.payload.into(),
+            state: j.state.into(),
+            created_at_ms: j.created_at_ms,
+            updated_at_ms: j.updated_at_ms,
+            delete_after_run: j.delete_after_run,
+            tags: j.tags,
+            disable_after_failures: j.disable_after_failures,
+            priority: j.priority,
+            on_success_job_id: j.on_success_job_id,
+            on_failure_job_id: j.on_failure_job_id,
+        }
+    }
+}
+
+/// Conversion to and from the store's JSON representation of a value.
+pub(super) trait StoreJson: Sized {
+    fn to_store_json(&self) -> Value;
+    fn from_store_json(value: Value) -> Result<Self, String>;
+}
+
+fn to_value(json: impl Serialize) -> Value {
+    serde_json::to_value(json).unwrap_or(Value::Null)
+}
+
+fn from_value<J: DeserializeOwned, T: From<J>>(value: Value) -> Result<T, String> {
+    serde_json::from_value::<J>(value)
+        .map(T::from)
+        .map_err(|e| e.to_string())
+}
+
+impl StoreJson for CronJob {
+    fn to_store_json(&self) -> Value {
+        to_value(CronJobJson::from(self))
+    }
+
+    fn from_store_json(value: Value) -> Result<Self, String> {
+        from_value::<CronJobJson, _>(value)
+    }
+}
+
+impl StoreJson for CronSchedule {
+    fn to_store_json(&self) -> Value {
+        to_value(CronScheduleJson::from(self))
+    }
+
+    fn from_store_json(value: Value) -> Result<Self, String> {
+        from_value::<CronScheduleJson, _>(value)
+    }
+}
+
+impl StoreJson for CronPayload {
+    fn to_store_json(&self) -> Value {
+        to_value(CronPayloadJson::from(self))
+    }
+
+    fn from_store_json(value: Value) -> Result<Self, String> {
+        from_value::<CronPayloadJson, _>(value)
+    }
+}
+
+impl StoreJson for CronJobState {
+    fn to_store_json(&self) -> Value {
+        to_value(CronJobStateJson::from(self))
+    }
+
+    fn from_store_json(value: Value) -> Result<Self, String> {
+        from_value::<CronJobStateJson, _>(value)
+    }
+}
+
+impl CronStoreJson {
+    pub(super) fn from_jobs(jobs: &[CronJob]) -> Self {
+        Self {
+            version: STORE_VERSION,
+            jobs: jobs.iter().map(CronJobJson::from).collect(),
+        }
+    }
+}
+
+/// Parse store JSON of any known version into jobs, migrating as needed.
+///
+/// Returns the jobs together with the version found in the document.
+pub(super) fn parse_store(content: &str) -> Result<(Vec<CronJob>, i32), String> {
+    let value: Value = serde_json::from_str(content).map_err(|e| format!("Invalid JSON: {}", e))?;
+    // Documents predating the version field are treated as version 1.
+    let version = match value.get("version") {
+        None => 1,
+        Some(v) => v
+            .as_i64()
+            .and_then(|v| i32::try_from(v).ok())
+            .ok_or_else(|| format!("Invalid 'version' field: {}", v))?,
+    };
+    if !(1..=STORE_VERSION).contains(&version) {
+        return Err(format!(
+            "Unsupported cron store version {} (this build supports up to {})",
+            version, STORE_VERSION
+        ));
+    }
+
+    let value = migrate(value, version);
+    let store: CronStoreJson =
+        serde_json::from_value(value).map_err(|e| format!("Invalid cron store: {}", e))?;
+    Ok((store.jobs.into_iter().map(CronJob::from).collect(), version))
+}
+
+/// Migrate a store document from `version` up to `STORE_VERSION`.
+fn migrate(mut value: Value, version: i32) -> Value {
+    for from in version..STORE_VERSION {
+        match from {
+            1 => migrate_v1_to_v2(&mut value),
+            // v3 to v10 only add optional fields
+            2..=9 => {}
+            _ => unreachable!("no migration from store version {}", from),
+        }
+    }
+    value["version"] = Value::from(STORE_VERSION);
+    value
+}
+
+/// v1 -> v2: jobs gain an explicit (empty) `tags` list.
+fn migrate_v1_to_v2(value: &mut Value) {
+    if let Some(jobs) = value.get_mut("jobs").and_then(|j| j.as_array_mut()) {
+        for job in jobs.iter_mut().filter_map(|j| j.as_object_mut()) {
+            job.entry("tags")
+                .or_insert_with(|| Value::Array(Vec::new()));
+        }
+    }
+}
+
+/// Job set summary for `status()`, readable without the async jobs lock.
+#[derive(Clone, Copy, Default)]
+pub(super) struct JobsSummary {
+    pub(super) jobs: usize,
+    pub(super) next_wake_at_ms: Option<i64>,
+}
+
+impl JobsSummary {
+    pub(super) fn of(jobs: &[CronJob]) -> Self {
+        Self {
+            jobs: jobs.len(),
+            next_wake_at_ms: jobs
+                .iter()
+                .filter(|j| j.enabled)
+                .filter_map(|j| j.state.next_run_at_ms)
+                .min(),
+        }
+    }
+}
+
+/// What we last saw on disk: modification time and content hash.
+#[derive(Clone, Copy, Default, PartialEq)]
+struct Fingerprint {
+    mtime: Option<SystemTime>,
+    hash: Option<u64>,
+}
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+async fn file_mtime(path: &Path) -> Option<SystemTime> {
+    tokio::fs::metadata(path)
+        .await
+        .and_then(|m| m.modified())
+        .ok()
+}
+
+/// Path of the temporary file written before being renamed over the store.
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Handle to the on-disk store.
+///
+/// If the store fails to load (unreadable, corrupt, or written by a newer
+/// version), it is marked read-only so the service never clobbers it.
+/// Migrations, and failures to rewrite a migrated file, are reported as
+/// events.
+///
+/// The store remembers the fingerprint of the content it last read or wrote,
+/// so that external edits can be told apart from the service's own saves.
+/// It also keeps a summary of the job set as of the last save, and the IDs
+/// of the jobs in the file, so a reload can tell jobs deleted on disk from
+/// jobs created since the last save.
+pub(super) struct CronStore {
+    pub(super) path: PathBuf,
+    writable: AtomicBool,
+    fingerprint: std::sync::Mutex<Fingerprint>,
+    summary: parking_lot::Mutex<JobsSummary>,
+    /// IDs of the jobs in the file as last read or written.
+    saved_ids: parking_lot::Mutex<HashSet<String>>,
+    /// A debounced save is scheduled but has not run yet.
+    save_pending: AtomicBool,
+    /// Held from reading the jobs to save until they are written, so saves
+    /// never share the temporary file and never replace a newer job set
+    /// with an older one.
+    writing: Mutex<()>,
+    events: CronEvents,
+}
+
+impl CronStore {
+    pub(super) fn new(path: PathBuf, events: CronEvents) -> Self {
+        Self {
+            path,
+            writable: AtomicBool::new(true),
+            fingerprint: std::sync::Mutex::new(Fingerprint::default()),
+            summary: parking_lot::Mutex::new(JobsSummary::default()),
+            saved_ids: parking_lot::Mutex::default(),
+            save_pending: AtomicBool::new(false),
+            writing: Mutex::new(()),
+            events,
+        }
+    }
+
+    pub(super) fn summary(&self) -> JobsSummary {
+        *self.summary.lock()
+    }
+
+    pub(super) fn update_summary(&self, jobs: &[CronJob]) {
+        *self.summary.lock() = JobsSummary::of(jobs);
+    }
+
+    /// IDs of the jobs in the file as last read or written.
+    pub(super) fn saved_ids(&self) -> HashSet<String> {
+        self.saved_ids.lock().clone()
+    }
+
+    fn remember_ids<'a>(&self, ids: impl IntoIterator<Item = &'a str>) {
+        *self.saved_ids.lock() = ids.into_iter().map(str::to_string).collect();
+    }
+
+    /// Load jobs from disk synchronously, for use at construction time.
+    ///
+    /// Behaves like `load`, but with blocking I/O.
+    pub(super) fn load_blocking(&self) -> Result<Vec<CronJob>, String> {
+        if !self.path.exists() {
+            self.writable.store(true, Ordering::Relaxed);
+            return Ok(Vec::new());
+        }
+
+        let result = std::fs::read_to_string(&self.path)
+            .map_err(|e| format!("Failed to read {}: {}", self.path.display(), e))
+            .and_then(|content| {
+                let mtime = std::fs::metadata(&self.path)
+                    .and_then(|m| m.modified())
+                    .ok();
+                self.remember(&content, mtime);
+                self.parse(&content)
+            });
+        self.writable.store(result.is_ok(), Ordering::Relaxed);
+
+        let (jobs, migrated) = result?;
+        self.remember_ids(jobs.iter().map(|j| j.id.as_str()));
+        if migrated {
+            // A failed rewrite is retried (and reported) by the next save
+            let written = self.lock_writing_blocking().and_then(|writing| {
+                self.write_blocking(&CronStoreJson::from_jobs(&jobs), &writing)
+            });
+            if let Err(e) = written {
+                self.report_write_error(e);
+            }
+        }
+        Ok(jobs)
+    }
+
+    /// Load jobs from disk, migrating old versions and rewriting the file at
+    /// the current version. On error the store becomes read-only.
+    pub(super) async fn load(&self) -> Result<Vec<CronJob>, String> {
+        if !tokio::fs::try_exists(&self.path).await.unwrap_or(false) {
+            self.writable.store(true, Ordering::Relaxed);
+            return Ok(Vec::new());
+        }
+
+        let content = tokio::fs::read_to_string(&self.path)
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", self.path.display(), e));
+        let result = match content {
+            Ok(content) => {
+                self.remember(&content, file_mtime(&self.path).await);
+                self.parse(&content)
+            }
+            Err(e) => Err(e),
+        };
+        self.writable.store(result.is_ok(), Ordering::Relaxed);
+
+        let (jobs, migrated) = result?;
+        self.remember_ids(jobs.iter().map(|j| j.id.as_str()));
+        if migrated {
+            // A failed rewrite is retried (and reported) by the next save
+            let writing = self.lock_writing().await;
+            if let Err(e) = self.write(&CronStoreJson::from_jobs(&jobs), &writing).await {
+                self.report_write_error(e);
+            }
+        }
+        Ok(jobs)
+    }
+
+    /// Check whether the file changed on disk since we last read or wrote it.
+    ///
+    /// Returns `None` when nothing changed (including our own saves), and the
+    /// parsed jobs (or the parse error) when an external edit is detected.
+    /// Nothing is read while a save is pending or being written: the file is
+    /// about to be replaced by the in-memory jobs anyway.
+    pub(super) async fn poll_external_change(&self) -> Option<Result<Vec<CronJob>, String>> {
+        if self.has_pending_save() {
+            return None;
+        }
+        let _writing = self.writing.try_lock().ok()?;
+        let mtime = file_mtime(&self.path).await?;
+        {
+            let fp = self.fingerprint.lock().ok()?;
+            if fp.mtime == Some(mtime) {
+                return None;
+            }
+        }
+
+        let content = tokio::fs::read_to_string(&self.path).await.ok()?;
+        let hash = content_hash(&content);
+        {
+            let mut fp = self.fingerprint.lock().ok()?;
+            fp.mtime = Some(mtime);
+            if fp.hash == Some(hash) {
+                return None;
+            }
+            fp.hash = Some(hash);
+        }
+
+        let result = self.parse(&content);
+        self.writable.store(result.is_ok(), Ordering::Relaxed);
+        if let Ok((jobs, _)) = &result {
+            self.remember_ids(jobs.iter().map(|j| j.id.as_str()));
+        }
+        Some(result.map(|(jobs, _)| jobs))
+    }
+
+    pub(super) fn is_writable(&self) -> bool {
+        self.writable.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn has_pending_save(&self) -> bool {
+        self.save_pending.load(Ordering::SeqCst)
+    }
+
+    /// Hold off other saves until the returned guard is dropped.
+    ///
+    /// Saves take it before reading the jobs and keep it through the write,
+    /// so a save that read the jobs earlier never writes after a later one.
+    pub(super) async fn lock_writing(&self) -> MutexGuard<'_, ()> {
+        self.writing.lock().await
+    }
+
+    /// Blocking counterpart of `lock_writing`, giving up after
+    /// `BLOCKING_LOCK_TIMEOUT`.
+    pub(super) fn lock_writing_blocking(&self) -> Result<MutexGuard<'_, ()>, String> {
+        lock_blocking(&self.writing, BLOCKING_LOCK_TIMEOUT).ok_or_else(|| {
+            format!(
+                "Failed to write {}: timed out waiting for a save in progress",
+                self.path.display()
+            )
+        })
+    }
+
+    /// Parse content, reporting whether it was migrated from an older version.
+    fn parse(&self, content: &str) -> Result<(Vec<CronJob>, bool), String> {
+        let (jobs, version) =
+            parse_store(content).map_err(|e| format!("{}: {}", self.path.display(), e))?;
+        let migrated = version < STORE_VERSION;
+        if migrated {
+            self.events.emit(
+                "store_migrated",
+                json!({ "from_version": version, "to_version": STORE_VERSION }),
+                || {
+                    format!(
+                        "Migrated store from version {} to {}",
+                        version, STORE_VERSION
+                    )
+                },
+            );
+            // Forget the old content so the next save rewrites the file
+            if let Ok(mut fp) = self.fingerprint.lock() {
+                fp.hash = None;
+            }
+        }
+        Ok((jobs, migrated))
+    }
+
+    fn report_write_error(&self, e: String) {
+        self.events
+            .emit("store_error", json!({ "error": e }), || e.clone());
+    }
+
+    fn remember(&self, content: &str, mtime: Option<SystemTime>) {
+        if let Ok(mut fp) = self.fingerprint.lock() {
+            *fp = Fingerprint {
+                mtime,
+                hash: Some(content_hash(content)),
+            };
+        }
+    }
+
+    /// Write `data` unless the file already holds exactly this content.
+    ///
+    /// The content is written to a temporary file and renamed into place so
+    /// readers never see a partial store. Returns whether the file was written.
+    async fn write(
+        &self,
+        data: &CronStoreJson,
+        _writing: &MutexGuard<'_, ()>,
+    ) -> Result<bool, String> {
+        let content = serde_json::to_string_pretty(data)
+            .map_err(|e| format!("Failed to serialize store: {}", e))?;
+
+        let unchanged = self
+            .fingerprint
+            .lock()
+            .is_ok_and(|fp| fp.hash == Some(content_hash(&content)));
+        if unchanged && tokio::fs::try_exists(&self.path).await.unwrap_or(false) {
+            return Ok(false);
+        }
+
+        let io_error =
+            |e: std::io::Error| format!("Failed to write {}: {}", self.path.display(), e);
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(io_error)?;
+        }
+        let temp = temp_path(&self.path);
+        tokio::fs::write(&temp, &content).await.map_err(io_error)?;
+        tokio::fs::rename(&temp, &self.path)
+            .await
+            .map_err(io_error)?;
+
+        self.remember(&content, file_mtime(&self.path).await);
+        self.remember_ids(data.jobs.iter().map(|j| j.id.as_str()));
+        Ok(true)
+    }
+
+    /// Write the job set now with blocking I/O, for use outside the runtime.
+    /// `writing` must have been taken before `jobs` was locked.
+    ///
+    /// Cancels any pending debounced save, since the file is then current.
+    pub(super) fn flush_blocking(
+        &self,
+        jobs: &[CronJob],
+        writing: &MutexGuard<'_, ()>,
+    ) -> Result<(), String> {
+        self.save_pending.store(false, Ordering::SeqCst);
+        self.update_summary(jobs);
+        if !self.is_writable() {
+            return Ok(());
+        }
+        self.write_blocking(&CronStoreJson::from_jobs(jobs), writing)
+    }
+
+    /// Blocking counterpart of `write`, used by `load_blocking` and
+    /// `flush_blocking`.
+    fn write_blocking(
+        &self,
+        data: &CronStoreJson,
+        _writing: &MutexGuard<'_, ()>,
+    ) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(data)
+            .map_err(|e| format!("Failed to serialize store: {}", e))?;
+
+        let io_error =
+            |e: std::io::Error| format!("Failed to write {}: {}", self.path.display(), e);
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        let temp = temp_path(&self.path);
+        std::fs::write(&temp, &content).map_err(io_error)?;
+        std::fs::rename(&temp, &self.path).map_err(io_error)?;
+
+        let mtime = std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .ok();
+        self.remember(&content, mtime);
+        self.remember_ids(data.jobs.iter().map(|j| j.id.as_str()));
+        Ok(())
+    }
+}
+
+/// Schedule a save of the job set.
+///
+/// Saves are debounced: changes made within `SAVE_DEBOUNCE_MS` of each other
+/// are written together. Use `flush_store` to write immediately.
+pub(super) async fn save_store(
+    store: &Arc<CronStore>,
+    jobs: &Arc<Mutex<Vec<CronJob>>>,
+    events: &CronEvents,
+) {
+    store.update_summary(&jobs.lock().await);
+    if store.save_pending.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let store = store.clone();
+    let jobs = jobs.clone();
+    let events = events.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(tokio::time::Duration::from_millis(SAVE_DEBOUNCE_MS)).await;
+        // Failures are already reported through the event callback
+        let _ = flush_store(&store, &jobs, &events).await;
+    });
+}
+
+/// Write the job set to disk now, if it changed since the last write.
+///
+/// Write failures are emitted as `store_error` events and returned.
+pub(super) async fn flush_store(
+    store: &CronStore,
+    jobs: &Arc<Mutex<Vec<CronJob>>>,
+    events: &CronEvents,
+) -> Result<(), String> {
+    // Cleared before reading so later changes schedule another save
+    store.save_pending.store(false, Ordering::SeqCst);
+
+    // Taken before the jobs so a save that read them earlier, and is still
+    // waiting to write, cannot write over this one
+    let writing = store.lock_writing().await;
+    let guard = jobs.lock().await;
+    store.update_summary(&guard);
+    if !store.is_writable() {
+        return Ok(());
+    }
+
+    let data = CronStoreJson::from_jobs(&guard);
+    let job_count = guard.len();
+
+    drop(guard);
+
+    match store.write(&data, &writing).await {
+        Ok(written) => {
+            if written {
+                events.emit_quiet("store_saved", json!({ "jobs": job_count }));
+            }
+            Ok(())
+        }
+        Err(e) => {
+            events.emit("store_error", json!({ "error": e }), || e.clone());
+            Err(e)
+        }
+    }
+}
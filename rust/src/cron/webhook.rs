@@ -0,0 +1,97 @@
+//! Webhook payloads: jobs that call an HTTP endpoint instead of the agent.
+
+use reqwest::Method;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use super::{CronPayload, PayloadError};
+use crate::tools::web::validate_url;
+
+const WEBHOOK_TIMEOUT_SECS: u64 = 30;
+/// Maximum number of characters of the response body kept for reporting.
+const SNIPPET_CHARS: usize = 200;
+/// Most bytes of the response body read: enough for a snippet of
+/// `SNIPPET_CHARS` characters and a sign that there was more.
+const MAX_BODY_BYTES: usize = (SNIPPET_CHARS + 1) * 4;
+
+/// Shared client so connections are pooled across job runs.
+fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(WEBHOOK_TIMEOUT_SECS))
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+fn parse_method(method: Option<&str>) -> Result<Method, String> {
+    let method = method.unwrap_or("POST").to_ascii_uppercase();
+    Method::from_bytes(method.as_bytes()).map_err(|_| format!("Invalid HTTP method '{}'", method))
+}
+
+/// Check that a webhook payload has a usable URL and method.
+pub(super) fn validate(payload: &CronPayload) -> Result<(), String> {
+    let url = payload
+        .url
+        .as_deref()
+        .ok_or_else(|| "Webhook payload requires 'url'".to_string())?;
+    validate_url(url).map_err(|e| format!("Invalid webhook URL '{}': {}", url, e))?;
+    parse_method(payload.method.as_deref())?;
+    Ok(())
+}
+
+/// Truncate `text` to `SNIPPET_CHARS` characters.
+fn snippet(text: &str) -> String {
+    let text = text.trim();
+    match text.char_indices().nth(SNIPPET_CHARS) {
+        Some((idx, _)) => format!("{}...", &text[..idx]),
+        None => text.to_string(),
+    }
+}
+
+/// The start of the response body, up to `MAX_BODY_BYTES`. The rest is
+/// never read, so a huge or endless response cannot fill memory.
+async fn read_head(mut response: reqwest::Response) -> String {
+    let mut body = Vec::new();
+    while let Ok(Some(chunk)) = response.chunk().await {
+        let room = MAX_BODY_BYTES - body.len();
+        if chunk.len() >= room {
+            body.extend_from_slice(&chunk[..room]);
+            break;
+        }
+        body.extend_from_slice(&chunk);
+    }
+    String::from_utf8_lossy(&body).into_owned()
+}
+
+/// Perform the webhook request.
+///
+/// Returns `"HTTP <status>: <snippet>"`; non-2xx responses are errors.
+pub(super) async fn send(payload: &CronPayload) -> Result<String, PayloadError> {
+    validate(payload)?;
+    let url = payload.url.as_deref().unwrap_or_default();
+    let method = parse_method(payload.method.as_deref())?;
+
+    let mut request = client().request(method, url);
+    for (name, value) in &payload.headers {
+        request = request.header(name, value);
+    }
+    if let Some(body) = &payload.body {
+        request = request.body(body.clone());
+    }
+
+    let response = request.send().await.map_err(|e| PayloadError {
+        timed_out: e.is_timeout(),
+        message: e.to_string(),
+    })?;
+    let status = response.status();
+    let body = read_head(response).await;
+    let summary = format!("HTTP {}: {}", status, snippet(&body));
+
+    if status.is_success() {
+        Ok(summary)
+    } else {
+        Err(summary.into())
+    }
+}
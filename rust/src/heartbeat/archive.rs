@@ -0,0 +1,156 @@
+//! Moving completed checkbox tasks out of a task file into its archive.
+
+use super::files::task_item;
+use chrono::Utc;
+use chrono_tz::Tz;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// Infix of archive file names: `HEARTBEAT.md` archives to
+/// `HEARTBEAT.archive.md`.
+const ARCHIVE_INFIX: &str = ".archive";
+
+/// Whether `line` is a checked checkbox item, e.g. `- [x] Water plants`.
+fn is_completed(line: &str) -> bool {
+    matches!(task_item(line.trim_start()), Some((true, _)))
+}
+
+/// Split `content` into the text to keep and the completed lines, leaving
+/// every kept byte as it was.
+fn split_completed(content: &str) -> (String, Vec<&str>) {
+    let mut kept = String::with_capacity(content.len());
+    let mut completed = Vec::new();
+    for line in content.split_inclusive('\n') {
+        if is_completed(line) {
+            completed.push(line.trim_end_matches(['\r', '\n']));
+        } else {
+            kept.push_str(line);
+        }
+    }
+    (kept, completed)
+}
+
+/// The archive file for a task file.
+pub(super) fn archive_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{}{}.{}", stem, ARCHIVE_INFIX, ext.to_string_lossy()),
+        None => format!("{}{}", stem, ARCHIVE_INFIX),
+    };
+    path.with_file_name(name)
+}
+
+/// Whether `path` is an archive file, which wildcards never match.
+pub(super) fn is_archive(path: &Path) -> bool {
+    path.file_stem()
+        .is_some_and(|stem| stem.to_string_lossy().ends_with(ARCHIVE_INFIX))
+        || path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().ends_with(ARCHIVE_INFIX))
+}
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Write `content` to a temporary file and rename it over `path`.
+fn write_atomic(path: &Path, content: &str) -> std::io::Result<()> {
+    let temp = temp_path(path);
+    std::fs::write(&temp, content)?;
+    std::fs::rename(&temp, path)
+}
+
+/// Move the completed tasks in `path` to its archive under a header with
+/// the current time in `tz`. Returns how many lines were moved.
+///
+/// Nothing is written if the file changes while the new content is being
+/// prepared, so edits made meanwhile are never lost.
+pub(super) fn archive_completed(path: &Path, tz: Tz) -> Result<usize, String> {
+    let io_error = |e: std::io::Error| format!("Failed to archive {}: {}", path.display(), e);
+    let content = std::fs::read_to_string(path).map_err(io_error)?;
+    let (kept, completed) = split_completed(&content);
+    if completed.is_empty() {
+        return Ok(0);
+    }
+
+    let archive = archive_path(path);
+    let mut archived = match std::fs::read_to_string(&archive) {
+        Ok(existing) => existing,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(io_error(e)),
+    };
+    if !archived.is_empty() && !archived.ends_with('\n') {
+        archived.push('\n');
+    }
+    if !archived.is_empty() {
+        archived.push('\n');
+    }
+    let now = Utc::now().with_timezone(&tz);
+    archived.push_str(&format!(
+        "## Archived {}\n\n",
+        now.format("%Y-%m-%d %H:%M %Z")
+    ));
+    for line in &completed {
+        archived.push_str(line);
+        archived.push('\n');
+    }
+
+    let temp = temp_path(path);
+    std::fs::write(&temp, &kept).map_err(io_error)?;
+    let current = std::fs::read_to_string(path).map_err(io_error)?;
+    if content_hash(&current) != content_hash(&content) {
+        let _ = std::fs::remove_file(&temp);
+        return Err(format!(
+            "{} changed while archiving, left as is",
+            path.display()
+        ));
+    }
+    write_atomic(&archive, &archived).map_err(io_error)?;
+    std::fs::rename(&temp, path).map_err(io_error)?;
+    Ok(completed.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_completed() {
+        let content = "# Tasks\r\n- [x] Done\r\n- [ ] Open\n  * [X] Nested done\n+  [x] Plus\nNotes - [x] inline\n- [x] Last";
+        let (kept, completed) = split_completed(content);
+        assert_eq!(kept, "# Tasks\r\n- [ ] Open\nNotes - [x] inline\n");
+        assert_eq!(
+            completed,
+            vec![
+                "- [x] Done",
+                "  * [X] Nested done",
+                "+  [x] Plus",
+                "- [x] Last"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_archive_path() {
+        assert_eq!(
+            archive_path(Path::new("/w/HEARTBEAT.md")),
+            PathBuf::from("/w/HEARTBEAT.archive.md")
+        );
+        assert_eq!(
+            archive_path(Path::new("/w/TODO")),
+            PathBuf::from("/w/TODO.archive")
+        );
+        assert!(is_archive(Path::new("/w/HEARTBEAT.archive.md")));
+        assert!(is_archive(Path::new("/w/TODO.archive")));
+        assert!(!is_archive(Path::new("/w/HEARTBEAT.md")));
+    }
+}
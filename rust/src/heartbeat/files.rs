@@ -0,0 +1,271 @@
+//! Heartbeat task files: resolving the configured names and checking them
+//! for actionable content.
+
+use std::path::{Path, PathBuf};
+
+use super::sections::SectionLog;
+
+/// File read when no task files are configured.
+pub(super) const DEFAULT_HEARTBEAT_FILE: &str = "HEARTBEAT.md";
+
+/// Check if a task file has no actionable content.
+///
+/// YAML frontmatter, HTML comments (including multi-line ones), headings,
+/// horizontal rules, checked task items and unchecked items without text
+/// are not actionable; any other text, such as `- [ ] water the plants`,
+/// is.
+pub(super) fn is_heartbeat_empty(content: Option<&str>) -> bool {
+    let content = match content {
+        Some(c) if !c.is_empty() => c,
+        _ => return true,
+    };
+
+    let body = strip_html_comments(strip_frontmatter(content));
+    !body.lines().any(is_actionable_line)
+}
+
+/// The content after a leading `---` frontmatter block, if it is closed.
+fn strip_frontmatter(content: &str) -> &str {
+    let mut lines = content.split_inclusive('\n');
+    if lines.next().map(str::trim_end) != Some("---") {
+        return content;
+    }
+    let mut offset = content.find('\n').map_or(content.len(), |i| i + 1);
+    for line in lines {
+        offset += line.len();
+        if matches!(line.trim_end(), "---" | "...") {
+            return &content[offset..];
+        }
+    }
+    content
+}
+
+/// Remove `<!-- ... -->` comments; an unclosed comment runs to the end.
+fn strip_html_comments(content: &str) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find("<!--") {
+        out.push_str(&rest[..start]);
+        match rest[start + 4..].find("-->") {
+            Some(end) => rest = &rest[start + 4 + end + 3..],
+            None => return out,
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn is_actionable_line(line: &str) -> bool {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') || is_horizontal_rule(line) {
+        return false;
+    }
+    match task_item(line) {
+        // A task item counts only if it is unchecked and says something
+        Some((checked, text)) => !checked && !text.is_empty(),
+        None => true,
+    }
+}
+
+/// `---`, `***` or `___`, possibly spaced out.
+fn is_horizontal_rule(line: &str) -> bool {
+    let marks: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3 && ['-', '*', '_'].contains(&marks[0]) && marks.iter().all(|&c| c == marks[0])
+}
+
+/// Parse a list item with a checkbox, e.g. `- [x] text`, into whether it is
+/// checked and its text. Empty list items count as unchecked items without
+/// text.
+pub(super) fn task_item(line: &str) -> Option<(bool, &str)> {
+    let rest = line
+        .strip_prefix(['-', '*', '+'])
+        .filter(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))?
+        .trim_start();
+    let (checked, text) = if let Some(text) = rest.strip_prefix("[ ]") {
+        (false, text)
+    } else if let Some(text) = rest.strip_prefix("[x]").or(rest.strip_prefix("[X]")) {
+        (true, text)
+    } else if rest.is_empty() {
+        (false, rest)
+    } else {
+        return None;
+    };
+    Some((checked, text.trim()))
+}
+
+/// Resolve configured task file names against the workspace.
+///
+/// Names are relative to the workspace. `*` and `?` in the last path
+/// component match against the files present, except archives of completed
+/// tasks and tick logs; other names resolve as is, whether or not the file
+/// exists. The result is deduplicated and, for each pattern, sorted.
+pub(super) fn resolve(workspace: &Path, names: &[String]) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for name in names {
+        let path = workspace.join(name);
+        let pattern = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if !pattern.contains(['*', '?']) {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+            continue;
+        }
+
+        let dir = path.parent().unwrap_or(workspace);
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        let mut matched: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+            .filter(|e| wildcard_match(&pattern, &e.file_name().to_string_lossy()))
+            .map(|e| e.path())
+            .filter(|path| !super::archive::is_archive(path) && !super::ticklog::is_log(path))
+            .collect();
+        matched.sort();
+        for path in matched {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+    }
+    paths
+}
+
+/// A task file with actionable content.
+pub(super) struct TaskFile {
+    /// Path relative to the workspace.
+    pub(super) name: String,
+    pub(super) path: PathBuf,
+    /// The file's content, or only the part that is due when sections are
+    /// taken into account.
+    pub(super) content: String,
+    /// Keys of the included sections that have their own cadence.
+    pub(super) timed_sections: Vec<String>,
+    /// Titles of the included sections, when some were left out as not due
+    /// yet.
+    pub(super) due_sections: Option<Vec<String>>,
+}
+
+/// The task files with actionable content.
+///
+/// With a `SectionLog`, only the part of each file that is due counts and
+/// is kept as its content.
+pub(super) fn with_tasks(
+    workspace: &Path,
+    names: &[String],
+    sections: Option<&SectionLog>,
+) -> Vec<TaskFile> {
+    let now_ms = super::status::now_ms();
+    resolve(workspace, names)
+        .into_iter()
+        .filter_map(|path| {
+            let content = std::fs::read_to_string(&path).ok()?;
+            let name = path
+                .strip_prefix(workspace)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .to_string();
+            let (content, timed_sections, due_sections) = match sections {
+                Some(log) => {
+                    let due = log.due(&name, &content, now_ms);
+                    (due.content, due.timed, due.titles)
+                }
+                None => (content, Vec::new(), None),
+            };
+            if is_heartbeat_empty(Some(&content)) {
+                return None;
+            }
+            Some(TaskFile {
+                name,
+                path,
+                content,
+                timed_sections,
+                due_sections,
+            })
+        })
+        .collect()
+}
+
+/// Match `name` against a pattern where `*` matches any run of characters
+/// and `?` a single character.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` and the name index it was tried at
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the last `*` swallow one more character
+                Some((sp, sn)) => {
+                    p = sp + 1;
+                    n = sn + 1;
+                    star = Some((sp, sn + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_heartbeat_empty() {
+        assert!(is_heartbeat_empty(None));
+        assert!(is_heartbeat_empty(Some("")));
+        assert!(is_heartbeat_empty(Some("# Header\n\n")));
+        assert!(is_heartbeat_empty(Some("<!-- comment -->\n")));
+        assert!(is_heartbeat_empty(Some("- [ ]")));
+        assert!(!is_heartbeat_empty(Some("Do something")));
+        assert!(!is_heartbeat_empty(Some("# Header\nDo something")));
+
+        // Task items
+        assert!(is_heartbeat_empty(Some("- [x] Watered the plants")));
+        assert!(is_heartbeat_empty(Some("* [X] Done\n+ [ ]\n-")));
+        assert!(!is_heartbeat_empty(Some("- [ ] Water the plants")));
+        assert!(!is_heartbeat_empty(Some("- [x] Done\n  - [ ] Still open")));
+        assert!(!is_heartbeat_empty(Some("- Call mom")));
+        assert!(!is_heartbeat_empty(Some(
+            "-5 degrees tonight, cover plants"
+        )));
+
+        // Frontmatter, comments and rules
+        assert!(is_heartbeat_empty(Some(
+            "---\ntitle: Tasks\n---\n# Tasks\n"
+        )));
+        assert!(!is_heartbeat_empty(Some("---\ntitle: Tasks\n---\nDo it")));
+        assert!(is_heartbeat_empty(Some(
+            "<!--\nAdd tasks below.\n- [ ] example\n-->\n"
+        )));
+        assert!(!is_heartbeat_empty(Some("<!-- note --> Do it")));
+        assert!(is_heartbeat_empty(Some("<!-- unclosed\nDo it")));
+        assert!(is_heartbeat_empty(Some("# Tasks\n---\n* * *\n___\n")));
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("HEARTBEAT*.md", "HEARTBEAT.md"));
+        assert!(wildcard_match("HEARTBEAT*.md", "HEARTBEAT.work.md"));
+        assert!(wildcard_match("TASKS-?.md", "TASKS-1.md"));
+        assert!(!wildcard_match("TASKS-?.md", "TASKS-10.md"));
+        assert!(!wildcard_match("HEARTBEAT*.md", "HEARTBEAT.md.bak"));
+        assert!(wildcard_match("*", "anything"));
+    }
+}
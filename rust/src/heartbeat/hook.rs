@@ -0,0 +1,89 @@
+//! The `on_result` hook: told the outcome of every tick, manual or
+//! scheduled.
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use super::status::TickRecord;
+use crate::cron::call_py;
+
+/// Passes each tick's record to a Python callback without waiting for it,
+/// so a slow or broken hook never holds up a tick. Exceptions raised by
+/// the callback are swallowed and counted.
+#[derive(Clone, Default)]
+pub(super) struct ResultHook {
+    callback: Arc<parking_lot::Mutex<Option<PyObject>>>,
+    errors: Arc<AtomicU64>,
+    /// Heartbeat name added to each record, for hooks shared by a manager.
+    name: Option<String>,
+}
+
+impl ResultHook {
+    pub(super) fn new(callback: Option<PyObject>) -> Self {
+        Self {
+            callback: Arc::new(parking_lot::Mutex::new(callback)),
+            ..Self::default()
+        }
+    }
+
+    /// A hook sharing this one's callback and error count that tags records
+    /// with `name`.
+    pub(super) fn named(&self, name: &str) -> Self {
+        Self {
+            name: Some(name.to_string()),
+            ..self.clone()
+        }
+    }
+
+    pub(super) fn set(&self, callback: Option<PyObject>) {
+        *self.callback.lock() = callback;
+    }
+
+    /// Calls to the callback that raised.
+    pub(super) fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn reset_errors(&self) {
+        self.errors.store(0, Ordering::Relaxed);
+    }
+
+    /// Call the callback with `record` in the background, if one is set.
+    pub(super) fn report(&self, record: TickRecord) {
+        let Some((callback, locals)) = Python::with_gil(|py| {
+            let callback = self.callback.lock().as_ref()?.clone_ref(py);
+            // Keep the event loop so an async callback can be awaited
+            let locals = pyo3_async_runtimes::tokio::get_current_locals(py).ok();
+            Some((callback, locals))
+        }) else {
+            return;
+        };
+        let errors = self.errors.clone();
+        let name = self.name.clone();
+        let call = async move {
+            let result = call_py(|py| {
+                let dict = PyDict::new(py);
+                if let Some(name) = name {
+                    dict.set_item("name", name)?;
+                }
+                dict.set_item("outcome", record.result)?;
+                dict.set_item("at_ms", record.at_ms)?;
+                dict.set_item("duration_ms", record.duration_ms)?;
+                dict.set_item("response", record.response.as_deref())?;
+                dict.set_item("error", record.error.as_deref())?;
+                callback.call1(py, (dict,))
+            })
+            .await;
+            if result.is_err() {
+                errors.fetch_add(1, Ordering::Relaxed);
+            }
+        };
+        let runtime = pyo3_async_runtimes::tokio::get_runtime();
+        match locals {
+            Some(locals) => runtime.spawn(pyo3_async_runtimes::tokio::scope(locals, call)),
+            None => runtime.spawn(call),
+        };
+    }
+}
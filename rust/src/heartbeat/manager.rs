@@ -0,0 +1,211 @@
+//! Several named heartbeats run together, e.g. one per agent.
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3_async_runtimes::tokio::{future_into_py, get_current_locals};
+use pyo3_async_runtimes::TaskLocals;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+use super::hook::ResultHook;
+use super::{HeartbeatService, LoopTracker};
+
+/// Named heartbeats started and stopped together, sharing one `on_result`
+/// hook.
+///
+/// Each heartbeat is a `HeartbeatService` with its own workspace and
+/// cadence. Their loops run as tasks on the shared runtime, so a single
+/// `start()` runs them all.
+#[pyclass]
+pub struct HeartbeatManager {
+    services: parking_lot::Mutex<BTreeMap<String, Py<HeartbeatService>>>,
+    on_result: ResultHook,
+    running: Arc<AtomicBool>,
+    /// Wakes `start()` when the manager is stopped.
+    stopped: Arc<Notify>,
+    loops: LoopTracker,
+}
+
+#[pymethods]
+impl HeartbeatManager {
+    /// Create the manager.
+    ///
+    /// `on_result` is called after every tick of every heartbeat, as for
+    /// `HeartbeatService`, with the heartbeat's `name` added to the dict.
+    #[new]
+    #[pyo3(signature = (on_result=None))]
+    fn new(on_result: Option<PyObject>) -> Self {
+        Self {
+            services: parking_lot::Mutex::new(BTreeMap::new()),
+            on_result: ResultHook::new(on_result),
+            running: Arc::new(AtomicBool::new(false)),
+            stopped: Arc::new(Notify::new()),
+            loops: LoopTracker::default(),
+        }
+    }
+
+    /// Add a heartbeat and return its service.
+    ///
+    /// Other keyword arguments are passed to `HeartbeatService`, except
+    /// `on_result`, which is the manager's. If the manager is running, the
+    /// heartbeat starts at once. Raises `ValueError` if `name` is taken.
+    #[pyo3(signature = (name, workspace, interval_s=None, **kwargs))]
+    fn add(
+        &self,
+        py: Python<'_>,
+        name: String,
+        workspace: PathBuf,
+        interval_s: Option<u64>,
+        kwargs: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<Py<HeartbeatService>> {
+        if self.services.lock().contains_key(&name) {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "A heartbeat named '{}' already exists",
+                name
+            )));
+        }
+        let kwargs = match kwargs {
+            Some(kwargs) => kwargs.copy()?,
+            None => PyDict::new(py),
+        };
+        if kwargs.contains("on_result")? {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "on_result is set on the manager, not per heartbeat",
+            ));
+        }
+        if interval_s.is_some() {
+            kwargs.set_item("interval_s", interval_s)?;
+        }
+        let service = py
+            .get_type::<HeartbeatService>()
+            .call((workspace,), Some(&kwargs))?
+            .downcast_into::<HeartbeatService>()?;
+        service.borrow_mut().on_result = self.on_result.named(&name);
+
+        if self.running.load(Ordering::Relaxed) {
+            self.spawn(get_current_locals(py)?, &service.borrow());
+        }
+        let service = service.unbind();
+        self.services.lock().insert(name, service.clone_ref(py));
+        Ok(service)
+    }
+
+    /// Stop and remove a heartbeat. Returns whether it existed.
+    fn remove(&self, py: Python<'_>, name: &str) -> bool {
+        let Some(service) = self.services.lock().remove(name) else {
+            return false;
+        };
+        service.borrow(py).stop();
+        true
+    }
+
+    /// The service of the heartbeat called `name`, if any.
+    fn get(&self, py: Python<'_>, name: &str) -> Option<Py<HeartbeatService>> {
+        self.services
+            .lock()
+            .get(name)
+            .map(|service| service.clone_ref(py))
+    }
+
+    /// The heartbeats' names, sorted.
+    fn names(&self) -> Vec<String> {
+        self.services.lock().keys().cloned().collect()
+    }
+
+    /// Each heartbeat's `status()`, keyed by name.
+    fn status_all<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        for (name, service) in self.services.lock().iter() {
+            dict.set_item(name, service.borrow(py).status(py)?)?;
+        }
+        Ok(dict)
+    }
+
+    /// Set the callback told the outcome of each tick of every heartbeat.
+    fn set_result_callback(&self, callback: Option<PyObject>) {
+        self.on_result.set(callback);
+    }
+
+    /// Start every heartbeat. Resolves once the manager is stopped and all
+    /// their loops have exited.
+    fn start<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        // Keep the event loop so async callbacks can be awaited
+        let locals = get_current_locals(py)?;
+        if self.running.swap(true, Ordering::Relaxed) {
+            return Err(pyo3::exceptions::PyRuntimeError::new_err(
+                "The heartbeat manager is already running",
+            ));
+        }
+        for service in self.services.lock().values() {
+            self.spawn(locals.clone_ref(py), &service.borrow(py));
+        }
+
+        let running = self.running.clone();
+        let stopped = self.stopped.clone();
+        let loops = self.loops.clone();
+        future_into_py(py, async move {
+            loop {
+                // Register before checking so a concurrent stop is not missed
+                let notified = stopped.notified();
+                if !running.load(Ordering::Relaxed) {
+                    break;
+                }
+                notified.await;
+            }
+            loops.wait_exited().await;
+            Ok(())
+        })
+    }
+
+    /// Stop every heartbeat.
+    fn stop(&self, py: Python<'_>) {
+        self.running.store(false, Ordering::Relaxed);
+        for service in self.services.lock().values() {
+            service.borrow(py).stop();
+        }
+        self.stopped.notify_waiters();
+    }
+
+    /// Whether the manager is running.
+    #[getter]
+    fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    fn __len__(&self) -> usize {
+        self.services.lock().len()
+    }
+
+    fn __contains__(&self, name: &str) -> bool {
+        self.services.lock().contains_key(name)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "HeartbeatManager(heartbeats={:?}, running={})",
+            self.names(),
+            self.is_running()
+        )
+    }
+}
+
+impl HeartbeatManager {
+    /// Run a heartbeat's loop as a task on the event loop of `locals`, if
+    /// it is enabled.
+    fn spawn(&self, locals: TaskLocals, service: &HeartbeatService) {
+        let Some(run) = service.run() else {
+            return;
+        };
+        let guard = self.loops.enter();
+        pyo3_async_runtimes::tokio::get_runtime().spawn(pyo3_async_runtimes::tokio::scope(
+            locals,
+            async move {
+                run.await;
+                drop(guard);
+            },
+        ));
+    }
+}
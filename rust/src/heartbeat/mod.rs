@@ -0,0 +1,1636 @@
+//! Heartbeat service - periodic agent wake-up to check for tasks.
+
+mod archive;
+mod files;
+mod hook;
+mod manager;
+mod persist;
+mod sections;
+mod status;
+mod ticklog;
+mod watch;
+mod window;
+
+use chrono_tz::Tz;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use pyo3_async_runtimes::tokio::future_into_py;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, Notify};
+
+use crate::cron::{call_py, compute_next_run, parse_tz, validate_schedule, CronSchedule};
+use files::{TaskFile, DEFAULT_HEARTBEAT_FILE};
+use hook::ResultHook;
+pub use manager::HeartbeatManager;
+use sections::SectionLog;
+use status::{now_ms, AdaptiveInterval, HeartbeatStatus, SharedStatus, TickOutcome};
+use ticklog::DEFAULT_LOG_MAX_BYTES;
+use watch::{WatchConfig, DEFAULT_WATCH_DEBOUNCE_MS, DEFAULT_WATCH_MIN_SPACING_S};
+use window::{format_hhmm, ActiveHours};
+
+/// Default interval: 30 minutes
+const DEFAULT_HEARTBEAT_INTERVAL_S: u64 = 30 * 60;
+
+/// The default prompt sent to agent during heartbeat
+const HEARTBEAT_PROMPT: &str = r#"Read HEARTBEAT.md in your workspace (if it exists).
+Follow any instructions or tasks listed there.
+If nothing needs attention, reply with just: HEARTBEAT_OK"#;
+
+/// Default token that indicates "nothing to do"
+const HEARTBEAT_OK_TOKEN: &str = "HEARTBEAT_OK";
+
+/// Default limit on how long the callback may take: 10 minutes
+const DEFAULT_TICK_TIMEOUT_S: f64 = 10.0 * 60.0;
+
+/// Settings that can be changed while the service runs.
+#[derive(Clone)]
+struct HeartbeatSettings {
+    prompt: String,
+    ok_token: String,
+    /// Task file names or wildcard patterns, relative to the workspace.
+    files: Vec<String>,
+    /// Local times at which periodic ticks run; `None` means always.
+    active_hours: Option<ActiveHours>,
+    /// Timezone the active hours are read in.
+    tz: Tz,
+    /// How long the callback may take before the tick fails.
+    tick_timeout: Duration,
+    /// Whether completed tasks are moved to the archive after a tick that
+    /// acted.
+    archive_completed: bool,
+    /// Whether the callback also receives a `TickContext`.
+    rich_callback: bool,
+    /// Whether responses that are not OK are passed to `on_deliver`.
+    deliver: bool,
+    channel: Option<String>,
+    to: Option<String>,
+    /// When set, scheduled ticks follow it instead of the interval.
+    schedule: Option<CronSchedule>,
+    /// How long after user activity ticks are put off.
+    suppress_within: Option<Duration>,
+    /// Failed ticks in a row after which `on_alert` is called.
+    alert_after_failures: Option<u32>,
+    /// Upper bound of the random delay added to each sleep.
+    jitter: Duration,
+    /// Whether the interval lengthens while the agent keeps replying OK.
+    adaptive_interval: bool,
+    adaptive: AdaptiveInterval,
+    /// Log in the workspace that ticks are appended to, if any.
+    log_file: Option<String>,
+    /// Size past which the log is rotated.
+    log_max_bytes: u64,
+}
+
+impl HeartbeatSettings {
+    fn adaptive(&self) -> Option<AdaptiveInterval> {
+        self.adaptive_interval.then_some(self.adaptive)
+    }
+
+    /// Whether a scheduled tick at the current time falls in the active hours.
+    fn is_active_now(&self) -> bool {
+        self.active_hours
+            .is_none_or(|hours| hours.contains(&chrono::Utc::now(), self.tz))
+    }
+}
+
+/// One file name or several, as accepted from Python.
+#[derive(FromPyObject)]
+enum FileNames {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// The prompt for a tick, naming the task files that have work in them.
+///
+/// The prompt is unchanged when the only such file is the default
+/// HEARTBEAT.md, which the default prompt already names. Files of which
+/// some sections are not due yet get a line naming the sections that are.
+fn prompt_with_files(prompt: &str, files: &[TaskFile]) -> String {
+    let mut prompt = prompt.to_string();
+    let names: Vec<&str> = files.iter().map(|f| f.name.as_str()).collect();
+    if !names.is_empty() && names != [DEFAULT_HEARTBEAT_FILE] {
+        prompt.push_str(&format!("\n\nTask files: {}", names.join(", ")));
+    }
+    for file in files {
+        if let Some(titles) = &file.due_sections {
+            let titles = if titles.is_empty() {
+                "none, only the text before them".to_string()
+            } else {
+                titles.join(", ")
+            };
+            prompt.push_str(&format!(
+                "\n\nSections of {} due now: {}",
+                file.name, titles
+            ));
+        }
+    }
+    prompt
+}
+
+/// What a tick passes to a callback taking a second argument.
+struct TickContext<'a> {
+    tick: u64,
+    manual: bool,
+    files: &'a [TaskFile],
+}
+
+impl TickContext<'_> {
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let files = self
+            .files
+            .iter()
+            .map(|file| {
+                let dict = PyDict::new(py);
+                dict.set_item("name", &file.name)?;
+                dict.set_item("path", file.path.to_string_lossy())?;
+                dict.set_item("content", &file.content)?;
+                Ok(dict)
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+        let dict = PyDict::new(py);
+        dict.set_item("tick", self.tick)?;
+        dict.set_item("manual", self.manual)?;
+        dict.set_item("files", files)?;
+        Ok(dict)
+    }
+}
+
+type SharedSettings = Arc<parking_lot::Mutex<HeartbeatSettings>>;
+
+/// Build the active-hours window from optional `HH:MM` bounds, which must be
+/// given together.
+fn active_hours(start: Option<&str>, end: Option<&str>) -> PyResult<Option<ActiveHours>> {
+    match (start, end) {
+        (None, None) => Ok(None),
+        (Some(start), Some(end)) => ActiveHours::parse(start, end)
+            .map(Some)
+            .map_err(pyo3::exceptions::PyValueError::new_err),
+        _ => Err(pyo3::exceptions::PyValueError::new_err(
+            "active_start and active_end must be given together",
+        )),
+    }
+}
+
+fn check_tz(tz: Option<&str>) -> PyResult<Tz> {
+    parse_tz(tz).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+/// Normalize a response or token for OK detection: case and underscores
+/// are ignored.
+fn normalize_ok(text: &str) -> String {
+    text.to_uppercase().replace('_', "")
+}
+
+/// Check whether the agent replied that nothing needs attention.
+fn is_ok_response(response: &str, ok_token: &str) -> bool {
+    normalize_ok(response).contains(&normalize_ok(ok_token))
+}
+
+/// Classify the agent's response; no response (no callback) counts as OK.
+fn response_outcome(response: Option<&str>, ok_token: &str) -> TickOutcome {
+    match response {
+        Some(response) if !is_ok_response(response, ok_token) => TickOutcome::Action,
+        _ => TickOutcome::Ok,
+    }
+}
+
+/// A callback that is set from Python and only ever cloned out to be
+/// called, so its lock is never held across an await or while waiting for
+/// the GIL.
+type HookCallback = Arc<parking_lot::Mutex<Option<PyObject>>>;
+
+/// The callback of `hook`, if one is set.
+fn hook_callback(hook: &HookCallback) -> Option<PyObject> {
+    Python::with_gil(|py| hook.lock().as_ref().map(|cb| cb.clone_ref(py)))
+}
+
+/// What a tick needs, shared by the periodic loop and the file watcher.
+#[derive(Clone)]
+struct Ticker {
+    workspace: PathBuf,
+    callback: HookCallback,
+    on_deliver: HookCallback,
+    on_result: ResultHook,
+    on_alert: HookCallback,
+    settings: SharedSettings,
+    status: SharedStatus,
+    /// Held while a tick runs, so ticks never overlap.
+    busy: Arc<Mutex<()>>,
+    /// When the user was last active, in epoch ms; `i64::MIN` if never.
+    last_activity_ms: Arc<AtomicI64>,
+    paused: Arc<AtomicBool>,
+    sections: Arc<parking_lot::Mutex<SectionLog>>,
+}
+
+impl Ticker {
+    /// When the window after the user's last activity ends, if it has not
+    /// yet.
+    fn suppressed_until(&self) -> Option<i64> {
+        let window = self.settings.lock().suppress_within?;
+        let until = self
+            .last_activity_ms
+            .load(Ordering::Relaxed)
+            .saturating_add(window.as_millis() as i64);
+        (until > now_ms()).then_some(until)
+    }
+
+    /// Record a tick, report it to `on_result`, and alert if it makes the
+    /// run of failures reach `alert_after_failures`. Returns the number of
+    /// consecutive failures before and after the tick.
+    async fn record(
+        &self,
+        started_ms: i64,
+        result: &Result<TickOutcome, String>,
+        response: Option<&str>,
+    ) -> (u32, u32) {
+        let (record, failures_before, failures) = {
+            let mut status = self.status.lock();
+            let failures_before = status.consecutive_failures;
+            let record = status.record(started_ms, result, response);
+            (record, failures_before, status.consecutive_failures)
+        };
+        self.save_counters().await;
+        let settings = self.settings.lock().clone();
+        if let (Some(name), Ok(TickOutcome::Ok | TickOutcome::Action) | Err(_)) =
+            (&settings.log_file, result)
+        {
+            let path = self.workspace.join(name);
+            let entry = ticklog::entry(
+                record.at_ms,
+                record.result,
+                response,
+                record.error.as_deref(),
+                settings.tz,
+            );
+            let max_bytes = settings.log_max_bytes;
+            let written = {
+                let path = path.clone();
+                tokio::task::spawn_blocking(move || ticklog::append(&path, max_bytes, &entry))
+                    .await
+                    .map_err(std::io::Error::other)
+                    .and_then(|written| written)
+            };
+            if let Err(e) = written {
+                eprintln!("[heartbeat] Failed to write {}: {}", path.display(), e);
+            }
+        }
+        self.on_result.report(record);
+        if let (Some(threshold), Err(error)) = (settings.alert_after_failures, result) {
+            if failures_before < threshold && failures >= threshold {
+                send_alert(&self.on_alert, &settings, failures, error).await;
+            }
+        }
+        (failures_before, failures)
+    }
+
+    /// Save the tick counters, if they persist, off the runtime.
+    async fn save_counters(&self) {
+        let Some(file) = self.status.lock().metrics_file() else {
+            return;
+        };
+        let status = self.status.clone();
+        file.save(move || status.lock().counters_json()).await;
+    }
+
+    /// Save when the sections last ran, if that persists, off the runtime.
+    async fn save_sections(&self) {
+        let Some(file) = self.sections.lock().file() else {
+            return;
+        };
+        let sections = self.sections.clone();
+        file.save(move || sections.lock().to_json()).await;
+    }
+
+    /// Run a tick and record its outcome, or record it as skipped if
+    /// another tick is still running. Returns the outcome, or `None` if the
+    /// tick failed.
+    ///
+    /// Errors are logged when ticks start failing and recovery when they
+    /// succeed again, not for every failure in between.
+    async fn tick(&self) -> Option<TickOutcome> {
+        let started_ms = now_ms();
+        let Ok(_busy) = self.busy.try_lock() else {
+            eprintln!("[heartbeat] Skipped: previous tick still running");
+            self.record(started_ms, &Ok(TickOutcome::SkippedBusy), None)
+                .await;
+            return Some(TickOutcome::SkippedBusy);
+        };
+        let (result, response) = match tick_inner(self).await {
+            Ok((outcome, response)) => (Ok(outcome), response),
+            Err(e) => (Err(e), None),
+        };
+        let (failures_before, failures) =
+            self.record(started_ms, &result, response.as_deref()).await;
+        match &result {
+            Err(e) if failures_before == 0 => {
+                eprintln!(
+                    "[heartbeat] Error: {} (backing off until a tick succeeds)",
+                    e
+                )
+            }
+            Ok(_) if failures_before > 0 && failures == 0 => {
+                eprintln!(
+                    "[heartbeat] Recovered after {} failed ticks",
+                    failures_before
+                )
+            }
+            _ => {}
+        }
+        result.ok()
+    }
+}
+
+/// Counts running loops so `wait_stopped` can tell when they have exited.
+#[derive(Clone, Default)]
+struct LoopTracker {
+    count: Arc<AtomicUsize>,
+    exited: Arc<Notify>,
+}
+
+impl LoopTracker {
+    /// Mark a loop as running until the returned guard is dropped.
+    fn enter(&self) -> LoopGuard {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        LoopGuard(self.clone())
+    }
+
+    /// Wait until no loop is running.
+    async fn wait_exited(&self) {
+        loop {
+            // Register before checking so a concurrent exit is not missed
+            let exited = self.exited.notified();
+            if self.count.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            exited.await;
+        }
+    }
+}
+
+struct LoopGuard(LoopTracker);
+
+impl Drop for LoopGuard {
+    fn drop(&mut self) {
+        if self.0.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.exited.notify_waiters();
+        }
+    }
+}
+
+/// Periodic heartbeat service that wakes the agent to check for tasks.
+///
+/// The agent reads HEARTBEAT.md from the workspace and executes any
+/// tasks listed there. If nothing needs attention, it replies HEARTBEAT_OK.
+#[pyclass]
+pub struct HeartbeatService {
+    workspace: PathBuf,
+    callback: HookCallback,
+    on_deliver: HookCallback,
+    on_result: ResultHook,
+    on_alert: HookCallback,
+    interval_s: Arc<AtomicU64>,
+    enabled: bool,
+    running: Arc<AtomicBool>,
+    settings: SharedSettings,
+    status: SharedStatus,
+    /// Wakes the sleeping loop when the interval changes or it is stopped.
+    wake: Arc<Notify>,
+    /// Set when edits to the task files trigger ticks.
+    watch: Option<WatchConfig>,
+    busy: Arc<Mutex<()>>,
+    last_activity_ms: Arc<AtomicI64>,
+    paused: Arc<AtomicBool>,
+    /// Set by `resume()` so the sleeping loop restarts its sleep from then.
+    resumed: Arc<AtomicBool>,
+    loops: LoopTracker,
+    /// When each task file section with its own cadence last ran.
+    sections: Arc<parking_lot::Mutex<SectionLog>>,
+}
+
+#[pymethods]
+impl HeartbeatService {
+    /// Create the service.
+    ///
+    /// `prompt` replaces the default prompt sent to `on_heartbeat`, and
+    /// `ok_token` the reply meaning "nothing to do". The token is matched
+    /// ignoring case and underscores.
+    ///
+    /// `heartbeat_file` names the task file, or a list of them, relative to
+    /// the workspace (default `HEARTBEAT.md`). Names may use `*` and `?`
+    /// wildcards, e.g. `HEARTBEAT*.md`. A tick is skipped unless some file
+    /// has actionable content, and the prompt lists the files that do.
+    ///
+    /// `active_start` and `active_end` (`HH:MM`, read in `tz`, default UTC)
+    /// restrict scheduled ticks to a daily window; ticks outside it are
+    /// skipped. A window ending before it starts wraps past midnight.
+    ///
+    /// With `watch`, the task files are polled for changes and an edit
+    /// triggers a tick once no further edit has been seen for
+    /// `watch_debounce_ms`, at most once per `watch_min_spacing_s`. The
+    /// periodic tick keeps running as a backstop.
+    ///
+    /// With `persist_metrics`, the tick counters are saved to
+    /// `.heartbeat_metrics.json` in the workspace and survive restarts.
+    ///
+    /// A `## ` section of a task file can run on its own cadence, given in
+    /// its heading as `## Daily <!-- every: 24h -->` (units `s`, `m`, `h`
+    /// and `d`, e.g. `1h30m`). Scheduled ticks show the agent only the
+    /// sections that are due, plus the text before the first section;
+    /// sections without a cadence, or with a malformed one, are due on
+    /// every tick. When each section last ran is saved to
+    /// `.heartbeat_sections.json` in the workspace. Manual triggers show
+    /// every section.
+    ///
+    /// A tick whose callback takes longer than `tick_timeout_s` fails with
+    /// a timeout error and the loop carries on.
+    ///
+    /// With `archive_completed`, after a tick in which the agent acted the
+    /// checked items (`- [x] ...`) in each task file are moved to a sibling
+    /// archive, e.g. `HEARTBEAT.archive.md`, under a timestamp header.
+    ///
+    /// With `rich_callback`, `on_heartbeat` is called as
+    /// `on_heartbeat(prompt, context)`, where `context` is a dict with the
+    /// `tick` number, whether it is a `manual` trigger, and the task `files`
+    /// with actionable content, each a dict with `name`, `path` and
+    /// `content`.
+    ///
+    /// With `deliver`, each response that is not the OK token is passed to
+    /// `on_deliver` as a dict with the `message`, the `channel` and `to`
+    /// settings, and whether the tick was `manual`, so the host can forward
+    /// it to a chat. OK responses are never delivered.
+    ///
+    /// `schedule`, a repeating `CronSchedule`, replaces `interval_s`: ticks
+    /// fire at its times, e.g. `CronSchedule(kind="cron", expr="0 9,13,17 *
+    /// * MON-FRI", tz="Europe/Berlin")`. Pass one or the other, not both.
+    ///
+    /// With `suppress_within_s`, periodic and watch ticks within that many
+    /// seconds of the last `notify_activity()` are skipped, so the agent is
+    /// not interrupted mid-conversation. A skipped periodic tick is retried
+    /// when the window ends.
+    ///
+    /// `on_result` is called after every tick, manual or scheduled, with a
+    /// dict of its `outcome` (as `last_result` in `status()`), `at_ms`,
+    /// `duration_ms`, `response` (the start of the agent's reply) and
+    /// `error`. It runs in the background; exceptions it raises are counted
+    /// in `metrics()` as `on_result_errors`.
+    ///
+    /// With `alert_after_failures`, `on_alert` is called once when that
+    /// many ticks in a row have failed, with a dict of the number of
+    /// `failures` and the last `error`. It is called again only after a
+    /// tick succeeds and a new run of failures reaches the threshold.
+    ///
+    /// With `jitter_s`, each sleep before a periodic tick is lengthened by a
+    /// random amount below that many seconds, drawn afresh for every tick,
+    /// so bots restarted together drift apart instead of ticking in step.
+    ///
+    /// With `adaptive_interval`, the interval is multiplied by
+    /// `adaptive_factor` for every `adaptive_after_oks` OK replies in a row,
+    /// up to six hours, and snaps back to `interval_s` once the agent acts
+    /// or a tick fails. It has no effect with a schedule.
+    ///
+    /// With `log_file`, e.g. `"heartbeat.log.md"`, each tick that woke the
+    /// agent or failed is appended to that file in the workspace as a
+    /// heading with its time and outcome, followed by the start of the
+    /// response or the error. Once the log would grow past `log_max_bytes`
+    /// it is moved to `heartbeat.log.md.1`, replacing the previous copy.
+    /// Failing to write the log never fails the tick. Task file wildcards
+    /// skip files named like `*.log.*`.
+    #[new]
+    #[pyo3(signature = (
+        workspace,
+        on_heartbeat=None,
+        interval_s=None,
+        enabled=true,
+        prompt=None,
+        ok_token=None,
+        heartbeat_file=None,
+        active_start=None,
+        active_end=None,
+        tz=None,
+        watch=false,
+        watch_debounce_ms=DEFAULT_WATCH_DEBOUNCE_MS,
+        watch_min_spacing_s=DEFAULT_WATCH_MIN_SPACING_S,
+        persist_metrics=false,
+        tick_timeout_s=DEFAULT_TICK_TIMEOUT_S,
+        archive_completed=false,
+        rich_callback=false,
+        deliver=false,
+        channel=None,
+        to=None,
+        on_deliver=None,
+        schedule=None,
+        suppress_within_s=None,
+        on_result=None,
+        alert_after_failures=None,
+        on_alert=None,
+        jitter_s=0.0,
+        adaptive_interval=false,
+        adaptive_factor=2.0,
+        adaptive_after_oks=3,
+        log_file=None,
+        log_max_bytes=DEFAULT_LOG_MAX_BYTES,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        workspace: PathBuf,
+        on_heartbeat: Option<PyObject>,
+        interval_s: Option<u64>,
+        enabled: bool,
+        prompt: Option<String>,
+        ok_token: Option<String>,
+        heartbeat_file: Option<FileNames>,
+        active_start: Option<String>,
+        active_end: Option<String>,
+        tz: Option<String>,
+        watch: bool,
+        watch_debounce_ms: u64,
+        watch_min_spacing_s: u64,
+        persist_metrics: bool,
+        tick_timeout_s: f64,
+        archive_completed: bool,
+        rich_callback: bool,
+        deliver: bool,
+        channel: Option<String>,
+        to: Option<String>,
+        on_deliver: Option<PyObject>,
+        schedule: Option<CronSchedule>,
+        suppress_within_s: Option<f64>,
+        on_result: Option<PyObject>,
+        alert_after_failures: Option<u32>,
+        on_alert: Option<PyObject>,
+        jitter_s: f64,
+        adaptive_interval: bool,
+        adaptive_factor: f64,
+        adaptive_after_oks: u32,
+        log_file: Option<String>,
+        log_max_bytes: u64,
+    ) -> PyResult<Self> {
+        let files = match heartbeat_file {
+            None => vec![DEFAULT_HEARTBEAT_FILE.to_string()],
+            Some(FileNames::One(name)) => vec![name],
+            Some(FileNames::Many(names)) => names,
+        };
+        if files.is_empty() {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "heartbeat_file must name at least one file",
+            ));
+        }
+        if interval_s.is_some() && schedule.is_some() {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "Pass either 'interval_s' or 'schedule', not both",
+            ));
+        }
+        let interval_s = interval_s.unwrap_or(DEFAULT_HEARTBEAT_INTERVAL_S);
+        check_interval(interval_s)?;
+        if let Some(schedule) = &schedule {
+            check_schedule(schedule)?;
+        }
+        let ok_token = ok_token.unwrap_or_else(|| HEARTBEAT_OK_TOKEN.to_string());
+        check_ok_token(&ok_token)?;
+        let active_hours = active_hours(active_start.as_deref(), active_end.as_deref())?;
+        let tz = check_tz(tz.as_deref())?;
+        let tick_timeout = timeout_duration(tick_timeout_s, "tick_timeout_s")?;
+        let suppress_within = suppress_within_s
+            .map(|seconds| timeout_duration(seconds, "suppress_within_s"))
+            .transpose()?;
+        check_alert_after_failures(alert_after_failures)?;
+        let jitter = jitter_duration(jitter_s)?;
+        let adaptive = adaptive_settings(adaptive_factor, adaptive_after_oks)?;
+        if log_max_bytes == 0 {
+            return Err(pyo3::exceptions::PyValueError::new_err(
+                "log_max_bytes must be positive",
+            ));
+        }
+        let status = if persist_metrics {
+            HeartbeatStatus::persisted(&workspace)
+        } else {
+            HeartbeatStatus::default()
+        };
+        let sections = SectionLog::persisted(&workspace);
+        Ok(Self {
+            workspace,
+            callback: Arc::new(parking_lot::Mutex::new(on_heartbeat)),
+            on_deliver: Arc::new(parking_lot::Mutex::new(on_deliver)),
+            on_result: ResultHook::new(on_result),
+            on_alert: Arc::new(parking_lot::Mutex::new(on_alert)),
+            interval_s: Arc::new(AtomicU64::new(interval_s)),
+            enabled,
+            running: Arc::new(AtomicBool::new(false)),
+            settings: Arc::new(parking_lot::Mutex::new(HeartbeatSettings {
+                prompt: prompt.unwrap_or_else(|| HEARTBEAT_PROMPT.to_string()),
+                ok_token,
+                files,
+                active_hours,
+                tz,
+                tick_timeout,
+                archive_completed,
+                rich_callback,
+                deliver,
+                channel,
+                to,
+                schedule,
+                suppress_within,
+                alert_after_failures,
+                jitter,
+                adaptive_interval,
+                adaptive,
+                log_file,
+                log_max_bytes,
+            })),
+            status: Arc::new(parking_lot::Mutex::new(status)),
+            wake: Arc::new(Notify::new()),
+            watch: watch.then_some(WatchConfig {
+                debounce: Duration::from_millis(watch_debounce_ms),
+                min_spacing: Duration::from_secs(watch_min_spacing_s),
+            }),
+            busy: Arc::new(Mutex::new(())),
+            last_activity_ms: Arc::new(AtomicI64::new(i64::MIN)),
+            paused: Arc::new(AtomicBool::new(false)),
+            resumed: Arc::new(AtomicBool::new(false)),
+            loops: LoopTracker::default(),
+            sections: Arc::new(parking_lot::Mutex::new(sections)),
+        })
+    }
+
+    /// The prompt sent to the callback on each heartbeat.
+    #[getter]
+    fn prompt(&self) -> String {
+        self.settings.lock().prompt.clone()
+    }
+
+    #[setter]
+    fn set_prompt(&self, prompt: String) {
+        self.settings.lock().prompt = prompt;
+    }
+
+    /// The reply that means "nothing to do".
+    #[getter]
+    fn ok_token(&self) -> String {
+        self.settings.lock().ok_token.clone()
+    }
+
+    #[setter]
+    fn set_ok_token(&self, ok_token: String) -> PyResult<()> {
+        check_ok_token(&ok_token)?;
+        self.settings.lock().ok_token = ok_token;
+        Ok(())
+    }
+
+    /// The task file paths, with wildcards resolved against the workspace.
+    #[getter]
+    fn heartbeat_file(&self) -> Vec<String> {
+        let names = self.settings.lock().files.clone();
+        files::resolve(&self.workspace, &names)
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect()
+    }
+
+    /// The log ticks are appended to, relative to the workspace, if any.
+    #[getter]
+    fn log_file(&self) -> Option<String> {
+        self.settings.lock().log_file.clone()
+    }
+
+    #[setter]
+    fn set_log_file(&self, log_file: Option<String>) {
+        self.settings.lock().log_file = log_file;
+    }
+
+    /// Whether completed tasks are archived after a tick that acted.
+    #[getter]
+    fn archive_completed(&self) -> bool {
+        self.settings.lock().archive_completed
+    }
+
+    #[setter]
+    fn set_archive_completed(&self, archive_completed: bool) {
+        self.settings.lock().archive_completed = archive_completed;
+    }
+
+    /// How long the callback may take, in seconds.
+    #[getter]
+    fn tick_timeout_s(&self) -> f64 {
+        self.settings.lock().tick_timeout.as_secs_f64()
+    }
+
+    #[setter]
+    fn set_tick_timeout_s(&self, seconds: f64) -> PyResult<()> {
+        self.settings.lock().tick_timeout = timeout_duration(seconds, "tick_timeout_s")?;
+        Ok(())
+    }
+
+    /// Start of the active hours as `HH:MM`, or `None` if ticks always run.
+    #[getter]
+    fn active_start(&self) -> Option<String> {
+        self.settings
+            .lock()
+            .active_hours
+            .map(|hours| format_hhmm(hours.start))
+    }
+
+    /// End of the active hours as `HH:MM`, or `None` if ticks always run.
+    #[getter]
+    fn active_end(&self) -> Option<String> {
+        self.settings
+            .lock()
+            .active_hours
+            .map(|hours| format_hhmm(hours.end))
+    }
+
+    /// Timezone the active hours are read in.
+    #[getter]
+    fn tz(&self) -> String {
+        self.settings.lock().tz.name().to_string()
+    }
+
+    /// Whether the current time falls in the active hours.
+    #[getter]
+    fn is_active_now(&self) -> bool {
+        self.settings.lock().is_active_now()
+    }
+
+    /// Change the active hours; the next tick uses the new window.
+    ///
+    /// Passing no bounds removes the window. `tz` keeps its current value
+    /// when omitted.
+    #[pyo3(signature = (active_start=None, active_end=None, tz=None))]
+    fn set_active_hours(
+        &self,
+        active_start: Option<String>,
+        active_end: Option<String>,
+        tz: Option<String>,
+    ) -> PyResult<()> {
+        let active_hours = active_hours(active_start.as_deref(), active_end.as_deref())?;
+        let tz = tz.map(|tz| check_tz(Some(&tz))).transpose()?;
+        let mut settings = self.settings.lock();
+        settings.active_hours = active_hours;
+        if let Some(tz) = tz {
+            settings.tz = tz;
+        }
+        Ok(())
+    }
+
+    /// Set the callback function.
+    fn set_callback(&self, callback: Option<PyObject>) {
+        *self.callback.lock() = callback;
+    }
+
+    /// Set the callback that receives responses to deliver.
+    fn set_deliver_callback(&self, callback: Option<PyObject>) {
+        *self.on_deliver.lock() = callback;
+    }
+
+    /// Set the callback told the outcome of each tick.
+    fn set_result_callback(&self, callback: Option<PyObject>) {
+        self.on_result.set(callback);
+    }
+
+    /// Set the callback called when ticks keep failing.
+    fn set_alert_callback(&self, callback: Option<PyObject>) {
+        *self.on_alert.lock() = callback;
+    }
+
+    /// Failed ticks in a row after which `on_alert` is called, or `None`
+    /// for never.
+    #[getter]
+    fn alert_after_failures(&self) -> Option<u32> {
+        self.settings.lock().alert_after_failures
+    }
+
+    #[setter]
+    fn set_alert_after_failures(&self, failures: Option<u32>) -> PyResult<()> {
+        check_alert_after_failures(failures)?;
+        self.settings.lock().alert_after_failures = failures;
+        Ok(())
+    }
+
+    /// Whether responses that are not OK are passed to `on_deliver`.
+    #[getter]
+    fn deliver(&self) -> bool {
+        self.settings.lock().deliver
+    }
+
+    #[setter]
+    fn set_deliver(&self, deliver: bool) {
+        self.settings.lock().deliver = deliver;
+    }
+
+    /// The channel passed along with delivered responses.
+    #[getter]
+    fn channel(&self) -> Option<String> {
+        self.settings.lock().channel.clone()
+    }
+
+    #[setter]
+    fn set_channel(&self, channel: Option<String>) {
+        self.settings.lock().channel = channel;
+    }
+
+    /// The recipient passed along with delivered responses.
+    #[getter]
+    fn to(&self) -> Option<String> {
+        self.settings.lock().to.clone()
+    }
+
+    #[setter]
+    fn set_to(&self, to: Option<String>) {
+        self.settings.lock().to = to;
+    }
+
+    /// Start the heartbeat service.
+    fn start<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        // Resolves at once if disabled
+        let run = self.run();
+        future_into_py(py, async move {
+            if let Some(run) = run {
+                run.await;
+            }
+            Ok(())
+        })
+    }
+
+    /// Stop the heartbeat service.
+    ///
+    /// The sleeping loop wakes at once; a tick already running is finished
+    /// first. Use `wait_stopped()` to wait for the loop to exit.
+    fn stop(&self) {
+        self.running.store(false, Ordering::Relaxed);
+        self.wake.notify_one();
+    }
+
+    /// Wait for the loop to exit after `stop()`, up to `timeout_s` seconds
+    /// if given. Resolves to `False` if the timeout expired first, `True`
+    /// otherwise, including when the service never started.
+    #[pyo3(signature = (timeout_s=None))]
+    fn wait_stopped<'py>(
+        &self,
+        py: Python<'py>,
+        timeout_s: Option<f64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let timeout = match timeout_s {
+            Some(seconds) => Some(timeout_duration(seconds, "timeout_s")?),
+            None => None,
+        };
+        let loops = self.loops.clone();
+        future_into_py(py, async move {
+            Ok(match timeout {
+                Some(timeout) => tokio::time::timeout(timeout, loops.wait_exited())
+                    .await
+                    .is_ok(),
+                None => {
+                    loops.wait_exited().await;
+                    true
+                }
+            })
+        })
+    }
+
+    /// Pause the service: the loop keeps running, but its ticks are skipped
+    /// until `resume()`. Manual triggers still run.
+    fn pause(&self) {
+        if !self.paused.swap(true, Ordering::Relaxed) {
+            eprintln!("[heartbeat] Paused");
+        }
+    }
+
+    /// Resume a paused service. The next tick is a full interval (or the
+    /// next scheduled time) from now.
+    fn resume(&self) {
+        if self.paused.swap(false, Ordering::Relaxed) {
+            eprintln!("[heartbeat] Resumed");
+            self.resumed.store(true, Ordering::Relaxed);
+            self.wake.notify_one();
+        }
+    }
+
+    /// Whether the service is paused.
+    #[getter]
+    fn paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Check if the service is running.
+    #[getter]
+    fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    /// Manually trigger a heartbeat.
+    ///
+    /// Resolves to a dict of whether the tick was `skipped`, its `outcome`
+    /// (as `last_result` in `status()`), the agent's `response` and the
+    /// tick's `duration_ms`. With `skip_if_empty`, the default, the agent is
+    /// not woken when every task file is empty or missing. Without it, the
+    /// callback is always called and the trigger resolves to the raw
+    /// response instead, or `None` if it was skipped.
+    ///
+    /// Raises `TimeoutError` if the callback takes longer than `timeout_s`
+    /// seconds, which defaults to `tick_timeout_s`. If another tick is still
+    /// running, this one is skipped, unless `wait` is set, in which case it
+    /// runs once the other tick finishes.
+    #[pyo3(signature = (timeout_s=None, wait=false, skip_if_empty=true))]
+    fn trigger_now<'py>(
+        &self,
+        py: Python<'py>,
+        timeout_s: Option<f64>,
+        wait: bool,
+        skip_if_empty: bool,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let callback = self.callback.clone();
+        let settings = self.settings.clone();
+        let timeout = match timeout_s {
+            Some(seconds) => Some(timeout_duration(seconds, "timeout_s")?),
+            None => None,
+        };
+        let status = self.status.clone();
+        let workspace = self.workspace.clone();
+        let busy = self.busy.clone();
+        let on_deliver = self.on_deliver.clone();
+        let ticker = self.ticker();
+
+        future_into_py(py, async move {
+            let started_ms = now_ms();
+            let _busy = if wait {
+                busy.lock_owned().await
+            } else {
+                match busy.try_lock_owned() {
+                    Ok(guard) => guard,
+                    Err(_) => {
+                        let skipped = TickOutcome::SkippedBusy;
+                        ticker.record(started_ms, &Ok(skipped), None).await;
+                        return trigger_result(skip_if_empty, started_ms, skipped, None);
+                    }
+                }
+            };
+
+            // Read the settings and task files only once it is this tick's turn
+            let settings = settings.lock().clone();
+            let timeout = timeout.unwrap_or(settings.tick_timeout);
+            let with_tasks = files::with_tasks(&workspace, &settings.files, None);
+            if skip_if_empty && with_tasks.is_empty() {
+                let skipped = TickOutcome::SkippedEmpty;
+                ticker.record(started_ms, &Ok(skipped), None).await;
+                return trigger_result(skip_if_empty, started_ms, skipped, None);
+            }
+            let prompt = prompt_with_files(&settings.prompt, &with_tasks);
+            let context = settings.rich_callback.then(|| TickContext {
+                tick: status.lock().start_tick(),
+                manual: true,
+                files: &with_tasks,
+            });
+            let response = call_heartbeat(&callback, &prompt, context, timeout).await;
+            let (result, text) = match &response {
+                Ok(text) => (
+                    Ok(response_outcome(text.as_deref(), &settings.ok_token)),
+                    text.as_deref(),
+                ),
+                Err(e) => (Err(format!("Callback error: {}", e)), None),
+            };
+            if result == Ok(TickOutcome::Action) {
+                if settings.archive_completed {
+                    archive_task_files(&with_tasks, settings.tz);
+                }
+                deliver_response(&on_deliver, &settings, text.unwrap_or_default(), true).await;
+            }
+            ticker.record(started_ms, &result, text).await;
+            let response = response?;
+            let outcome = response_outcome(response.as_deref(), &settings.ok_token);
+            trigger_result(skip_if_empty, started_ms, outcome, response)
+        })
+    }
+
+    /// The service's state: whether it runs or is paused, when the next
+    /// scheduled tick fires and what the last tick did.
+    ///
+    /// `last_result` is one of "ok", "action", "error", "skipped_empty",
+    /// "skipped_inactive", "skipped_busy", "skipped_active" or
+    /// "skipped_paused", or `None` before the first tick. After failed
+    /// ticks the interval doubles per failure, up to six hours, until a
+    /// tick succeeds; `consecutive_failures` and `effective_interval_s`
+    /// show this, as does `effective_interval_s` for `adaptive_interval`.
+    /// With a schedule, ticks keep to it and `interval_s` and
+    /// `effective_interval_s` are `None`. `next_tick_at_ms` includes the
+    /// jitter drawn for the next tick.
+    fn status<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let (interval_s, adaptive) = {
+            let settings = self.settings.lock();
+            let interval_s = match settings.schedule {
+                Some(_) => None,
+                None => Some(self.interval_s()),
+            };
+            (interval_s, settings.adaptive())
+        };
+        let dict = self
+            .status
+            .lock()
+            .to_dict(py, self.is_running(), interval_s, adaptive)?;
+        dict.set_item("paused", self.paused())?;
+        Ok(dict)
+    }
+
+    /// The most recent ticks, newest first, each a dict with `at_ms`,
+    /// `result`, `duration_ms`, `response` (the start of the agent's reply)
+    /// and `error`. Up to 400 ticks are kept.
+    #[pyo3(signature = (limit=20))]
+    fn history<'py>(&self, py: Python<'py>, limit: usize) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        self.status.lock().history(py, limit)
+    }
+
+    /// Tick counts: `total` and one count per result, plus
+    /// `on_result_errors`, the calls to `on_result` that raised.
+    ///
+    /// `current_ok_streak` counts the OK replies since the agent last acted
+    /// or a tick failed, and `longest_ok_streak` the longest such run. With
+    /// `ok` and `action` they show how many wake-ups were wasted.
+    fn metrics<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = self.status.lock().metrics(py)?;
+        dict.set_item("on_result_errors", self.on_result.errors())?;
+        Ok(dict)
+    }
+
+    /// Zero the tick counts and clear the history.
+    fn reset_metrics(&self, py: Python<'_>) {
+        let file = {
+            let mut status = self.status.lock();
+            status.reset_metrics();
+            status.metrics_file()
+        };
+        if let Some(file) = file {
+            py.allow_threads(|| file.save_blocking(|| self.status.lock().counters_json()));
+        }
+        self.on_result.reset_errors();
+    }
+
+    /// Get interval in seconds.
+    #[getter]
+    fn interval_s(&self) -> u64 {
+        self.interval_s.load(Ordering::Relaxed)
+    }
+
+    #[setter(interval_s)]
+    fn set_interval_s(&self, seconds: u64) -> PyResult<()> {
+        self.set_interval(seconds)
+    }
+
+    /// Change the interval, replacing any schedule. A running loop applies
+    /// it to the next tick, which fires `seconds` after the previous one (or
+    /// at once if that time has already passed).
+    fn set_interval(&self, seconds: u64) -> PyResult<()> {
+        check_interval(seconds)?;
+        self.interval_s.store(seconds, Ordering::Relaxed);
+        self.settings.lock().schedule = None;
+        self.wake.notify_one();
+        Ok(())
+    }
+
+    /// The schedule ticks follow, or `None` when they follow the interval.
+    #[getter]
+    fn schedule(&self) -> Option<CronSchedule> {
+        self.settings.lock().schedule.clone()
+    }
+
+    /// Follow `schedule` instead of the interval, or the interval again if
+    /// `None`. A running loop applies it to the next tick.
+    #[pyo3(signature = (schedule=None))]
+    fn set_schedule(&self, schedule: Option<CronSchedule>) -> PyResult<()> {
+        if let Some(schedule) = &schedule {
+            check_schedule(schedule)?;
+        }
+        self.settings.lock().schedule = schedule;
+        self.wake.notify_one();
+        Ok(())
+    }
+
+    /// Record that the user is active, e.g. because a message arrived.
+    /// Ticks within `suppress_within_s` of it are put off.
+    fn notify_activity(&self) {
+        self.last_activity_ms.store(now_ms(), Ordering::Relaxed);
+    }
+
+    /// Seconds after user activity during which ticks are put off, or
+    /// `None` if they never are.
+    #[getter]
+    fn suppress_within_s(&self) -> Option<f64> {
+        self.settings
+            .lock()
+            .suppress_within
+            .map(|window| window.as_secs_f64())
+    }
+
+    #[setter]
+    fn set_suppress_within_s(&self, seconds: Option<f64>) -> PyResult<()> {
+        let window = seconds
+            .map(|seconds| timeout_duration(seconds, "suppress_within_s"))
+            .transpose()?;
+        self.settings.lock().suppress_within = window;
+        Ok(())
+    }
+
+    /// Upper bound, in seconds, of the random delay added before each
+    /// periodic tick; 0 for none.
+    #[getter]
+    fn jitter_s(&self) -> f64 {
+        self.settings.lock().jitter.as_secs_f64()
+    }
+
+    #[setter]
+    fn set_jitter_s(&self, seconds: f64) -> PyResult<()> {
+        self.settings.lock().jitter = jitter_duration(seconds)?;
+        Ok(())
+    }
+
+    /// Whether the interval lengthens while the agent keeps replying OK.
+    #[getter]
+    fn adaptive_interval(&self) -> bool {
+        self.settings.lock().adaptive_interval
+    }
+
+    #[setter]
+    fn set_adaptive_interval(&self, adaptive_interval: bool) {
+        self.settings.lock().adaptive_interval = adaptive_interval;
+        // Re-arm the sleeping loop with the new interval
+        self.wake.notify_one();
+    }
+
+    /// Check if enabled.
+    #[getter]
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Whether edits to the task files trigger ticks.
+    #[getter]
+    fn watch(&self) -> bool {
+        self.watch.is_some()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "HeartbeatService(workspace={:?}, interval={}s, enabled={}, running={})",
+            self.workspace,
+            self.interval_s(),
+            self.enabled,
+            self.is_running()
+        )
+    }
+}
+
+impl HeartbeatService {
+    /// The loop `start()` runs, or `None` if the service is disabled.
+    fn run(&self) -> Option<impl Future<Output = ()> + Send + 'static> {
+        if !self.enabled {
+            return None;
+        }
+
+        self.running.store(true, Ordering::Relaxed);
+        // Counted from now, so `wait_stopped` waits even before the loop is
+        // first polled
+        let loop_guard = self.loops.enter();
+
+        let ticker = self.ticker();
+        let interval_s = self.interval_s.clone();
+        let running = self.running.clone();
+        let settings = self.settings.clone();
+        let status = self.status.clone();
+        let wake = self.wake.clone();
+        let watch = self.watch;
+        let resumed = self.resumed.clone();
+
+        Some(async move {
+            let every = match &settings.lock().schedule {
+                Some(schedule) => format!("on {} schedule", describe_schedule(schedule)),
+                None => format!("every {}s", interval_s.load(Ordering::Relaxed)),
+            };
+            eprintln!(
+                "[heartbeat] Started ({}{})",
+                every,
+                if watch.is_some() {
+                    ", watching task files"
+                } else {
+                    ""
+                }
+            );
+
+            let watcher = async {
+                if let Some(config) = watch {
+                    watch::watch_loop(&ticker, &settings, &running, config).await;
+                }
+            };
+            let periodic = async {
+                // Scheduled times already ticked at, so a tick that ends
+                // early by the clock does not fire again for the same time
+                let mut fired_ms = i64::MIN;
+                // Set after a tick put off by user activity: when to retry
+                let mut retry_at_ms = None;
+                while running.load(Ordering::Relaxed) {
+                    // Sleep first (heartbeat fires after interval). An interval
+                    // change re-arms the sleep, still counted from its start.
+                    let mut slept_from = now_ms();
+                    let mut slept_at = tokio::time::Instant::now();
+                    // Drawn once per sleep, so re-arming keeps the offset
+                    let jitter_fraction = random_fraction();
+                    loop {
+                        let (schedule, jitter, adaptive) = {
+                            let settings = settings.lock();
+                            (
+                                settings.schedule.clone(),
+                                settings.jitter,
+                                settings.adaptive(),
+                            )
+                        };
+                        let jitter_ms = (jitter.as_millis() as f64 * jitter_fraction) as i64;
+                        let next_ms = match (retry_at_ms, &schedule) {
+                            (Some(retry_at_ms), _) => Some(retry_at_ms),
+                            (None, Some(schedule)) => {
+                                compute_next_run(schedule, now_ms().max(fired_ms))
+                            }
+                            (None, None) => {
+                                let interval_s = status.lock().effective_interval_s(
+                                    interval_s.load(Ordering::Relaxed),
+                                    adaptive,
+                                );
+                                Some(slept_from + interval_s as i64 * 1000)
+                            }
+                        }
+                        .map(|ms| ms + jitter_ms);
+                        status.lock().next_tick_at_ms = next_ms;
+                        let deadline = next_ms.map(|ms| {
+                            let ms = (ms - slept_from).max(0) as u64;
+                            slept_at + Duration::from_millis(ms)
+                        });
+                        let woken = tokio::select! {
+                            // A schedule with no further times waits to be changed
+                            _ = sleep_until_deadline(deadline) => false,
+                            _ = wake.notified() => true,
+                        };
+                        if !woken {
+                            fired_ms = next_ms.unwrap_or(fired_ms);
+                        }
+                        if !woken || !running.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        if resumed.swap(false, Ordering::Relaxed) {
+                            // Count the next tick from the resume, not the
+                            // ticks skipped while paused
+                            slept_from = now_ms();
+                            slept_at = tokio::time::Instant::now();
+                            retry_at_ms = None;
+                        }
+                    }
+
+                    if !running.load(Ordering::Relaxed) {
+                        break;
+                    }
+
+                    // Execute tick
+                    retry_at_ms = match ticker.tick().await {
+                        Some(TickOutcome::SkippedActive) => ticker.suppressed_until(),
+                        _ => None,
+                    };
+                }
+                status.lock().next_tick_at_ms = None;
+            };
+            tokio::join!(periodic, watcher);
+            drop(loop_guard);
+        })
+    }
+
+    fn ticker(&self) -> Ticker {
+        Ticker {
+            workspace: self.workspace.clone(),
+            callback: self.callback.clone(),
+            on_deliver: self.on_deliver.clone(),
+            on_result: self.on_result.clone(),
+            on_alert: self.on_alert.clone(),
+            settings: self.settings.clone(),
+            status: self.status.clone(),
+            busy: self.busy.clone(),
+            last_activity_ms: self.last_activity_ms.clone(),
+            paused: self.paused.clone(),
+            sections: self.sections.clone(),
+        }
+    }
+}
+
+/// What `trigger_now` resolves to: the raw response without
+/// `skip_if_empty`, otherwise a dict describing the tick.
+fn trigger_result(
+    skip_if_empty: bool,
+    started_ms: i64,
+    outcome: TickOutcome,
+    response: Option<String>,
+) -> PyResult<PyObject> {
+    Python::with_gil(|py| {
+        if !skip_if_empty {
+            return Ok(response.into_pyobject(py)?.unbind());
+        }
+        let skipped = !matches!(outcome, TickOutcome::Ok | TickOutcome::Action);
+        let dict = PyDict::new(py);
+        dict.set_item("skipped", skipped)?;
+        dict.set_item("outcome", outcome.as_str())?;
+        dict.set_item("response", response)?;
+        dict.set_item("duration_ms", now_ms() - started_ms)?;
+        Ok(dict.into_any().unbind())
+    })
+}
+
+fn timeout_duration(seconds: f64, name: &str) -> PyResult<Duration> {
+    if !(seconds.is_finite() && seconds > 0.0) {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "{} must be a positive number of seconds",
+            name
+        )));
+    }
+    Ok(Duration::from_secs_f64(seconds))
+}
+
+fn check_interval(seconds: u64) -> PyResult<()> {
+    if seconds == 0 {
+        return Err(pyo3::exceptions::PyValueError::new_err(
+            "interval_s must be positive",
+        ));
+    }
+    Ok(())
+}
+
+/// Check that a schedule is well-formed and repeats.
+fn check_schedule(schedule: &CronSchedule) -> PyResult<()> {
+    if schedule.kind == "at" {
+        return Err(pyo3::exceptions::PyValueError::new_err(
+            "A heartbeat schedule must repeat; 'at' schedules run once",
+        ));
+    }
+    validate_schedule(schedule, now_ms()).map_err(pyo3::exceptions::PyValueError::new_err)
+}
+
+fn describe_schedule(schedule: &CronSchedule) -> String {
+    match &schedule.expr {
+        Some(expr) if schedule.kind == "cron" => format!("'{}'", expr),
+        _ => schedule.kind.clone(),
+    }
+}
+
+/// Sleep until `deadline`, or forever if there is none.
+async fn sleep_until_deadline(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+fn jitter_duration(seconds: f64) -> PyResult<Duration> {
+    if !(seconds.is_finite() && seconds >= 0.0) {
+        return Err(pyo3::exceptions::PyValueError::new_err(
+            "jitter_s must be a non-negative number of seconds",
+        ));
+    }
+    Ok(Duration::from_secs_f64(seconds))
+}
+
+fn adaptive_settings(factor: f64, after_oks: u32) -> PyResult<AdaptiveInterval> {
+    if !(factor.is_finite() && factor > 1.0) {
+        return Err(pyo3::exceptions::PyValueError::new_err(
+            "adaptive_factor must be a number greater than 1",
+        ));
+    }
+    if after_oks == 0 {
+        return Err(pyo3::exceptions::PyValueError::new_err(
+            "adaptive_after_oks must be positive",
+        ));
+    }
+    Ok(AdaptiveInterval { factor, after_oks })
+}
+
+/// A random number in `[0, 1)`, different on every call and in every
+/// process.
+fn random_fraction() -> f64 {
+    use std::hash::{BuildHasher, Hasher};
+    // Each `RandomState` is keyed afresh from a per-process random seed
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_i64(now_ms());
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+fn check_alert_after_failures(failures: Option<u32>) -> PyResult<()> {
+    if failures == Some(0) {
+        return Err(pyo3::exceptions::PyValueError::new_err(
+            "alert_after_failures must be positive",
+        ));
+    }
+    Ok(())
+}
+
+fn check_ok_token(ok_token: &str) -> PyResult<()> {
+    if normalize_ok(ok_token).is_empty() {
+        return Err(pyo3::exceptions::PyValueError::new_err(
+            "ok_token must contain characters other than underscores",
+        ));
+    }
+    Ok(())
+}
+
+/// Call the heartbeat callback with `prompt`, and `context` if given,
+/// returning its response.
+///
+/// Returns `None` if no callback is set, and raises `TimeoutError` if the
+/// callback does not finish within `timeout`. A timed-out coroutine is not
+/// cancelled; its result is ignored.
+async fn call_heartbeat(
+    callback: &HookCallback,
+    prompt: &str,
+    context: Option<TickContext<'_>>,
+    timeout: Duration,
+) -> PyResult<Option<String>> {
+    let Some(cb) = hook_callback(callback) else {
+        return Ok(None);
+    };
+
+    let call = call_py(|py| match &context {
+        Some(context) => cb.call1(py, (prompt, context.to_dict(py)?)),
+        None => cb.call1(py, (prompt,)),
+    });
+    let result = tokio::time::timeout(timeout, call).await.map_err(|_| {
+        pyo3::exceptions::PyTimeoutError::new_err(format!(
+            "heartbeat callback timed out after {}s",
+            timeout.as_secs_f64()
+        ))
+    })??;
+    Python::with_gil(|py| result.extract::<String>(py)).map(Some)
+}
+
+/// Pass a response that is not OK to the delivery callback, if delivery is
+/// enabled. Failures are logged, not raised, since the tick itself worked.
+async fn deliver_response(
+    on_deliver: &HookCallback,
+    settings: &HeartbeatSettings,
+    message: &str,
+    manual: bool,
+) {
+    if !settings.deliver {
+        return;
+    }
+    let Some(cb) = hook_callback(on_deliver) else {
+        return;
+    };
+
+    let call = call_py(|py| {
+        let dict = PyDict::new(py);
+        dict.set_item("message", message)?;
+        dict.set_item("channel", &settings.channel)?;
+        dict.set_item("to", &settings.to)?;
+        dict.set_item("manual", manual)?;
+        cb.call1(py, (dict,))
+    });
+    match tokio::time::timeout(settings.tick_timeout, call).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => eprintln!("[heartbeat] Delivery failed: {}", e),
+        Err(_) => eprintln!("[heartbeat] Delivery timed out"),
+    }
+}
+
+/// Tell `on_alert` that `failures` ticks in a row have failed.
+///
+/// Errors raised by the callback are only logged.
+async fn send_alert(
+    on_alert: &HookCallback,
+    settings: &HeartbeatSettings,
+    failures: u32,
+    error: &str,
+) {
+    eprintln!("[heartbeat] Alert: {} failed ticks in a row", failures);
+    let Some(cb) = hook_callback(on_alert) else {
+        return;
+    };
+
+    let call = call_py(|py| {
+        let dict = PyDict::new(py);
+        dict.set_item("failures", failures)?;
+        dict.set_item("error", error)?;
+        cb.call1(py, (dict,))
+    });
+    match tokio::time::timeout(settings.tick_timeout, call).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => eprintln!("[heartbeat] Alert failed: {}", e),
+        Err(_) => eprintln!("[heartbeat] Alert timed out"),
+    }
+}
+
+/// Move completed tasks out of `files` into their archives.
+fn archive_task_files(files: &[TaskFile], tz: Tz) {
+    for file in files {
+        match archive::archive_completed(&file.path, tz) {
+            Ok(0) => {}
+            Ok(moved) => eprintln!(
+                "[heartbeat] Archived {} completed tasks from {}",
+                moved, file.name
+            ),
+            Err(e) => eprintln!("[heartbeat] {}", e),
+        }
+    }
+}
+
+/// Execute a single heartbeat tick.
+async fn tick_inner(ticker: &Ticker) -> Result<(TickOutcome, Option<String>), String> {
+    let Ticker {
+        workspace,
+        callback,
+        status,
+        ..
+    } = ticker;
+    if ticker.paused.load(Ordering::Relaxed) {
+        return Ok((TickOutcome::SkippedPaused, None));
+    }
+    let settings = ticker.settings.lock().clone();
+    if !settings.is_active_now() {
+        if let Some(hours) = settings.active_hours {
+            eprintln!(
+                "[heartbeat] Skipped: outside active hours ({}-{} {})",
+                format_hhmm(hours.start),
+                format_hhmm(hours.end),
+                settings.tz.name()
+            );
+        }
+        return Ok((TickOutcome::SkippedInactive, None));
+    }
+    if let Some(until) = ticker.suppressed_until() {
+        eprintln!(
+            "[heartbeat] Skipped: user active, quiet for another {}s",
+            (until - now_ms()).max(0) / 1000
+        );
+        return Ok((TickOutcome::SkippedActive, None));
+    }
+    let HeartbeatSettings {
+        prompt,
+        ok_token,
+        files: names,
+        tick_timeout,
+        tz,
+        archive_completed,
+        rich_callback,
+        ..
+    } = settings.clone();
+
+    // Skip if every task file is empty or missing, or has nothing due
+    let started_ms = now_ms();
+    let with_tasks = files::with_tasks(workspace, &names, Some(&ticker.sections.lock()));
+    if with_tasks.is_empty() {
+        return Ok((TickOutcome::SkippedEmpty, None));
+    }
+
+    eprintln!("[heartbeat] Checking for tasks...");
+
+    let prompt = prompt_with_files(&prompt, &with_tasks);
+    let context = rich_callback.then(|| TickContext {
+        tick: status.lock().start_tick(),
+        manual: false,
+        files: &with_tasks,
+    });
+    let response = call_heartbeat(callback, &prompt, context, tick_timeout)
+        .await
+        .map_err(|e| format!("Callback error: {}", e))?;
+    let timed = with_tasks.iter().flat_map(|file| &file.timed_sections);
+    if ticker.sections.lock().mark_run(timed, started_ms) {
+        ticker.save_sections().await;
+    }
+
+    let outcome = response_outcome(response.as_deref(), &ok_token);
+    if response.is_some() {
+        // Check if agent said "nothing to do"
+        match outcome {
+            TickOutcome::Action => eprintln!("[heartbeat] Completed task"),
+            _ => eprintln!("[heartbeat] OK (no action needed)"),
+        }
+    }
+
+    if outcome == TickOutcome::Action {
+        if archive_completed {
+            archive_task_files(&with_tasks, tz);
+        }
+        let message = response.as_deref().unwrap_or_default();
+        deliver_response(&ticker.on_deliver, &settings, message, false).await;
+    }
+
+    Ok((outcome, response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_ok_response() {
+        assert!(is_ok_response("HEARTBEAT_OK", HEARTBEAT_OK_TOKEN));
+        assert!(is_ok_response(
+            "heartbeat ok? heartbeatok.",
+            HEARTBEAT_OK_TOKEN
+        ));
+        assert!(!is_ok_response("Watered the plants", HEARTBEAT_OK_TOKEN));
+        assert!(is_ok_response("Alles gut: ALLES_OK", "alles_ok"));
+        assert!(!is_ok_response("HEARTBEAT_OK", "ALLES_OK"));
+    }
+}
@@ -0,0 +1,55 @@
+//! JSON files in the workspace that heartbeat state persists to.
+
+use serde::de::DeserializeOwned;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// A JSON file replaced whole on each save.
+///
+/// Saves run one at a time and each takes its snapshot only once it is its
+/// turn, so the file always ends up with the latest state.
+#[derive(Clone)]
+pub(super) struct JsonFile {
+    path: PathBuf,
+    writing: Arc<parking_lot::Mutex<()>>,
+}
+
+impl JsonFile {
+    pub(super) fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            writing: Arc::default(),
+        }
+    }
+
+    /// The saved value, if the file exists and parses.
+    pub(super) fn load<T: DeserializeOwned>(&self) -> Option<T> {
+        let content = std::fs::read_to_string(&self.path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Save the JSON `snapshot` returns, with blocking I/O; `None` saves
+    /// nothing. Async code uses `save` instead.
+    pub(super) fn save_blocking(&self, snapshot: impl FnOnce() -> Option<String>) {
+        let _writing = self.writing.lock();
+        let Some(content) = snapshot() else {
+            return;
+        };
+        // Write a temporary file and rename it so a crash never leaves a
+        // truncated file
+        let temp = self.path.with_extension("json.tmp");
+        if let Err(e) =
+            std::fs::write(&temp, content).and_then(|_| std::fs::rename(&temp, &self.path))
+        {
+            eprintln!("[heartbeat] Failed to save {}: {}", self.path.display(), e);
+        }
+    }
+
+    /// `save_blocking` on the runtime's blocking threads.
+    pub(super) async fn save(&self, snapshot: impl FnOnce() -> Option<String> + Send + 'static) {
+        let file = self.clone();
+        if let Err(e) = tokio::task::spawn_blocking(move || file.save_blocking(snapshot)).await {
+            eprintln!("[heartbeat] Failed to save {}: {}", self.path.display(), e);
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! Sections of a task file with their own cadence, such as
+//! `## Daily <!-- every: 24h -->`, and when each last ran.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use super::persist::JsonFile;
+
+/// File in the workspace the sections' last runs persist to.
+pub(super) const SECTIONS_FILE: &str = ".heartbeat_sections.json";
+
+/// A `## ` section of a task file, from its heading to the next one.
+struct Section<'a> {
+    title: String,
+    /// Cadence from the heading's `every:` annotation; `None` means every
+    /// tick.
+    every: Option<Duration>,
+    text: &'a str,
+}
+
+/// Split `content` into the text before the first `## ` heading and the
+/// sections that follow. Headings of other levels stay inside the
+/// section they appear in.
+fn split_sections<'a>(content: &'a str, file_name: &str) -> (&'a str, Vec<Section<'a>>) {
+    let mut starts = Vec::new();
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        if h2_title(line).is_some() {
+            starts.push(offset);
+        }
+        offset += line.len();
+    }
+    let Some(&first) = starts.first() else {
+        return (content, Vec::new());
+    };
+
+    let ends = starts.iter().skip(1).copied().chain([content.len()]);
+    let sections = starts
+        .iter()
+        .zip(ends)
+        .map(|(&start, end)| {
+            let text = &content[start..end];
+            let heading = text.lines().next().unwrap_or_default();
+            let (title, every) = h2_title(heading).unwrap_or_default();
+            let every = every.and_then(|annotation| {
+                let every = parse_every(annotation);
+                if every.is_none() {
+                    eprintln!(
+                        "[heartbeat] Ignoring malformed '{}' on section '{}' of {}",
+                        annotation, title, file_name
+                    );
+                }
+                every
+            });
+            Section {
+                title: title.to_string(),
+                every,
+                text,
+            }
+        })
+        .collect();
+    (&content[..first], sections)
+}
+
+/// The title of a `## ` heading and its `every:` annotation, if any.
+fn h2_title(line: &str) -> Option<(&str, Option<&str>)> {
+    let heading = line
+        .trim_end()
+        .strip_prefix("##")
+        .filter(|rest| rest.starts_with(char::is_whitespace))?
+        .trim();
+    let Some(start) = heading.find("<!--") else {
+        return Some((heading, None));
+    };
+    let comment = heading[start + 4..].trim_end();
+    let comment = comment.strip_suffix("-->").unwrap_or(comment).trim();
+    let title = heading[..start].trim();
+    Some((title, comment.starts_with("every:").then_some(comment)))
+}
+
+/// Parse an `every: 24h` annotation. The duration is one or more numbers
+/// with a unit, `s`, `m`, `h` or `d`, as in `1h30m`.
+fn parse_every(annotation: &str) -> Option<Duration> {
+    let spec = annotation.strip_prefix("every:")?.trim();
+    let mut seconds: u64 = 0;
+    let mut number = String::new();
+    for c in spec.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            _ => return None,
+        };
+        let value: u64 = std::mem::take(&mut number).parse().ok()?;
+        seconds = seconds.checked_add(value.checked_mul(unit)?)?;
+    }
+    (number.is_empty() && seconds > 0).then(|| Duration::from_secs(seconds))
+}
+
+/// The part of a task file a scheduled tick shows the agent.
+pub(super) struct DueContent {
+    /// The text before the first section and the sections that are due.
+    pub(super) content: String,
+    /// Keys of the included sections that have their own cadence.
+    pub(super) timed: Vec<String>,
+    /// Titles of the included sections, when some were left out as not due
+    /// yet.
+    pub(super) titles: Option<Vec<String>>,
+}
+
+/// When each section with its own cadence last ran, keyed by file and
+/// title.
+#[derive(Default)]
+pub(super) struct SectionLog {
+    last_run_ms: BTreeMap<String, i64>,
+    /// Where the log is saved after each change, if anywhere.
+    file: Option<JsonFile>,
+}
+
+impl SectionLog {
+    /// The log persisted to `SECTIONS_FILE` in `workspace`, starting from the
+    /// runs saved there.
+    pub(super) fn persisted(workspace: &Path) -> Self {
+        let file = JsonFile::new(workspace.join(SECTIONS_FILE));
+        Self {
+            last_run_ms: file.load().unwrap_or_default(),
+            file: Some(file),
+        }
+    }
+
+    /// The text of `content` that is due at `now_ms`.
+    ///
+    /// A section with its own cadence is due once that long has passed since
+    /// it last ran, less a twentieth so ticks on the same cadence never miss
+    /// it by a few milliseconds. Sections without one, or with a malformed
+    /// one, are due on every tick, as is the text before the first section.
+    pub(super) fn due(&self, file_name: &str, content: &str, now_ms: i64) -> DueContent {
+        let (preamble, sections) = split_sections(content, file_name);
+        let mut due = DueContent {
+            content: preamble.to_string(),
+            timed: Vec::new(),
+            titles: None,
+        };
+        let mut titles = Vec::new();
+        let mut left_out = false;
+        for section in sections {
+            if let Some(every) = section.every {
+                let key = format!("{}#{}", file_name, section.title);
+                let every_ms = every.as_millis() as i64;
+                if let Some(&last) = self.last_run_ms.get(&key) {
+                    if now_ms < last + every_ms - every_ms / 20 {
+                        left_out = true;
+                        continue;
+                    }
+                }
+                due.timed.push(key);
+            }
+            due.content.push_str(section.text);
+            titles.push(section.title);
+        }
+        if left_out {
+            due.titles = Some(titles);
+        }
+        due
+    }
+
+    /// Note that the sections `keys` ran at `at_ms`, returning whether
+    /// there were any.
+    pub(super) fn mark_run<'a>(
+        &mut self,
+        keys: impl IntoIterator<Item = &'a String>,
+        at_ms: i64,
+    ) -> bool {
+        let mut changed = false;
+        for key in keys {
+            self.last_run_ms.insert(key.clone(), at_ms);
+            changed = true;
+        }
+        changed
+    }
+
+    /// The file the log persists to, if it does.
+    pub(super) fn file(&self) -> Option<JsonFile> {
+        self.file.clone()
+    }
+
+    /// The log as saved to its file.
+    pub(super) fn to_json(&self) -> Option<String> {
+        serde_json::to_string(&self.last_run_ms).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR_MS: i64 = 60 * 60 * 1000;
+
+    #[test]
+    fn test_parse_every() {
+        assert_eq!(parse_every("every: 24h"), Some(Duration::from_secs(86400)));
+        assert_eq!(parse_every("every:1h30m"), Some(Duration::from_secs(5400)));
+        assert_eq!(parse_every("every: 2d"), Some(Duration::from_secs(172800)));
+        assert_eq!(parse_every("every: 45S"), Some(Duration::from_secs(45)));
+        assert_eq!(parse_every("every: 24"), None);
+        assert_eq!(parse_every("every: 0h"), None);
+        assert_eq!(parse_every("every: daily"), None);
+        assert_eq!(parse_every("every: h"), None);
+    }
+
+    #[test]
+    fn test_h2_title() {
+        assert_eq!(h2_title("## Daily\n"), Some(("Daily", None)));
+        assert_eq!(
+            h2_title("## Daily <!-- every: 24h -->"),
+            Some(("Daily", Some("every: 24h")))
+        );
+        assert_eq!(
+            h2_title("## Notes <!-- keep short -->"),
+            Some(("Notes", None))
+        );
+        assert_eq!(h2_title("### Sub"), None);
+        assert_eq!(h2_title("# Title"), None);
+    }
+
+    #[test]
+    fn test_due() {
+        let content = "# Tasks\nAlways\n## Hourly\n- [ ] a\n\
+                       ## Daily <!-- every: 24h -->\n- [ ] b\n### Detail\nc\n\
+                       ## Odd <!-- every: soon -->\n- [ ] d\n";
+        let mut log = SectionLog::default();
+
+        // Never run, so everything is due
+        let due = log.due("HEARTBEAT.md", content, 0);
+        assert_eq!(due.content, content);
+        assert_eq!(due.timed, ["HEARTBEAT.md#Daily"]);
+        assert!(due.titles.is_none());
+
+        log.mark_run(&due.timed, 0);
+        let due = log.due("HEARTBEAT.md", content, HOUR_MS);
+        assert_eq!(
+            due.content,
+            "# Tasks\nAlways\n## Hourly\n- [ ] a\n## Odd <!-- every: soon -->\n- [ ] d\n"
+        );
+        assert!(due.timed.is_empty());
+        assert_eq!(due.titles.unwrap(), ["Hourly", "Odd"]);
+
+        // Due again just short of a day later
+        let due = log.due("HEARTBEAT.md", content, 24 * HOUR_MS - 1000);
+        assert_eq!(due.timed, ["HEARTBEAT.md#Daily"]);
+
+        // Files without sections are unchanged
+        let due = log.due("TODO.md", "Do it", HOUR_MS);
+        assert_eq!(due.content, "Do it");
+        assert!(due.titles.is_none());
+    }
+}
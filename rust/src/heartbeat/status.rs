@@ -0,0 +1,394 @@
+//! Heartbeat status: when the next tick fires, what recent ticks did and
+//! how many ticks had each outcome.
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::persist::JsonFile;
+
+/// Ticks kept in the history; at the default interval about a week.
+pub(super) const HISTORY_LIMIT: usize = 400;
+
+/// Characters of the agent's response kept per history entry.
+const RESPONSE_SNIPPET_CHARS: usize = 200;
+
+/// Longest interval that backing off after errors stretches ticks to.
+const MAX_BACKOFF_S: u64 = 6 * 60 * 60;
+
+/// File in the workspace the counters persist to, when enabled.
+pub(super) const METRICS_FILE: &str = ".heartbeat_metrics.json";
+
+/// What a heartbeat tick did.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum TickOutcome {
+    /// The agent replied that nothing needs attention.
+    Ok,
+    /// The agent acted on a task.
+    Action,
+    /// Every task file was empty or missing, so the agent was not woken.
+    SkippedEmpty,
+    /// The tick fell outside the active hours.
+    SkippedInactive,
+    /// Another tick was still running.
+    SkippedBusy,
+    /// The user was recently active, so the tick was put off.
+    SkippedActive,
+    /// The service was paused.
+    SkippedPaused,
+}
+
+impl TickOutcome {
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            TickOutcome::Ok => "ok",
+            TickOutcome::Action => "action",
+            TickOutcome::SkippedEmpty => "skipped_empty",
+            TickOutcome::SkippedInactive => "skipped_inactive",
+            TickOutcome::SkippedBusy => "skipped_busy",
+            TickOutcome::SkippedActive => "skipped_active",
+            TickOutcome::SkippedPaused => "skipped_paused",
+        }
+    }
+}
+
+/// Number of ticks per outcome.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct TickCounters {
+    total: u64,
+    ok: u64,
+    action: u64,
+    error: u64,
+    skipped_empty: u64,
+    skipped_inactive: u64,
+    skipped_busy: u64,
+    skipped_active: u64,
+    skipped_paused: u64,
+    /// Ticks the agent answered OK since it last acted or a tick failed.
+    current_ok_streak: u64,
+    longest_ok_streak: u64,
+}
+
+/// A tick as kept in the history.
+#[derive(Clone)]
+pub(super) struct TickRecord {
+    pub(super) at_ms: i64,
+    pub(super) result: &'static str,
+    pub(super) duration_ms: i64,
+    /// The start of the agent's response.
+    pub(super) response: Option<String>,
+    pub(super) error: Option<String>,
+}
+
+#[derive(Default)]
+pub(super) struct HeartbeatStatus {
+    pub(super) next_tick_at_ms: Option<i64>,
+    last_tick_at_ms: Option<i64>,
+    last_result: Option<&'static str>,
+    last_error: Option<String>,
+    counters: TickCounters,
+    history: VecDeque<TickRecord>,
+    /// Where the counters are saved after each tick, if anywhere.
+    metrics_file: Option<JsonFile>,
+    /// Ticks that failed since the agent last answered; skipped ticks
+    /// leave this unchanged.
+    pub(super) consecutive_failures: u32,
+    /// Ticks that called the callback, numbering them for its context.
+    ticks_started: u64,
+}
+
+pub(super) type SharedStatus = Arc<parking_lot::Mutex<HeartbeatStatus>>;
+
+impl HeartbeatStatus {
+    /// Status whose counters persist to `METRICS_FILE` in `workspace`,
+    /// starting from the counts saved there.
+    pub(super) fn persisted(workspace: &Path) -> Self {
+        let file = JsonFile::new(workspace.join(METRICS_FILE));
+        Self {
+            counters: file.load().unwrap_or_default(),
+            metrics_file: Some(file),
+            ..Self::default()
+        }
+    }
+
+    /// Record a tick, manual or scheduled, that started at `started_ms`,
+    /// returning its history entry.
+    pub(super) fn record(
+        &mut self,
+        started_ms: i64,
+        result: &Result<TickOutcome, String>,
+        response: Option<&str>,
+    ) -> TickRecord {
+        let now = now_ms();
+        self.last_tick_at_ms = Some(now);
+        let (name, error) = match result {
+            Ok(outcome) => (outcome.as_str(), None),
+            Err(e) => ("error", Some(e.clone())),
+        };
+        self.last_result = Some(name);
+        self.last_error = error.clone();
+        match result {
+            Err(_) => self.consecutive_failures += 1,
+            Ok(TickOutcome::Ok | TickOutcome::Action) => self.consecutive_failures = 0,
+            Ok(_) => {}
+        }
+
+        let counters = &mut self.counters;
+        match result {
+            Ok(TickOutcome::Ok) => {
+                counters.current_ok_streak += 1;
+                counters.longest_ok_streak =
+                    counters.longest_ok_streak.max(counters.current_ok_streak);
+            }
+            Ok(TickOutcome::Action) | Err(_) => counters.current_ok_streak = 0,
+            Ok(_) => {}
+        }
+        counters.total += 1;
+        *match result {
+            Ok(TickOutcome::Ok) => &mut counters.ok,
+            Ok(TickOutcome::Action) => &mut counters.action,
+            Ok(TickOutcome::SkippedEmpty) => &mut counters.skipped_empty,
+            Ok(TickOutcome::SkippedInactive) => &mut counters.skipped_inactive,
+            Ok(TickOutcome::SkippedBusy) => &mut counters.skipped_busy,
+            Ok(TickOutcome::SkippedActive) => &mut counters.skipped_active,
+            Ok(TickOutcome::SkippedPaused) => &mut counters.skipped_paused,
+            Err(_) => &mut counters.error,
+        } += 1;
+
+        if self.history.len() == HISTORY_LIMIT {
+            self.history.pop_front();
+        }
+        let record = TickRecord {
+            at_ms: started_ms,
+            result: name,
+            duration_ms: now - started_ms,
+            response: response.map(|r| r.chars().take(RESPONSE_SNIPPET_CHARS).collect()),
+            error,
+        };
+        self.history.push_back(record.clone());
+        record
+    }
+
+    /// Count a tick that is about to call the callback, returning its
+    /// number, starting at 1.
+    pub(super) fn start_tick(&mut self) -> u64 {
+        self.ticks_started += 1;
+        self.ticks_started
+    }
+
+    /// Zero the counters and forget the history.
+    pub(super) fn reset_metrics(&mut self) {
+        self.counters = TickCounters::default();
+        self.history.clear();
+    }
+
+    /// The file the counters persist to, if they do.
+    pub(super) fn metrics_file(&self) -> Option<JsonFile> {
+        self.metrics_file.clone()
+    }
+
+    /// The counters as saved to the metrics file.
+    pub(super) fn counters_json(&self) -> Option<String> {
+        serde_json::to_string(&self.counters).ok()
+    }
+
+    /// The most recent `limit` ticks, newest first.
+    pub(super) fn history<'py>(
+        &self,
+        py: Python<'py>,
+        limit: usize,
+    ) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        self.history
+            .iter()
+            .rev()
+            .take(limit)
+            .map(|tick| {
+                let dict = PyDict::new(py);
+                dict.set_item("at_ms", tick.at_ms)?;
+                dict.set_item("result", tick.result)?;
+                dict.set_item("duration_ms", tick.duration_ms)?;
+                dict.set_item("response", tick.response.as_deref())?;
+                dict.set_item("error", tick.error.as_deref())?;
+                Ok(dict)
+            })
+            .collect()
+    }
+
+    /// The tick counters.
+    pub(super) fn metrics<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let c = &self.counters;
+        let dict = PyDict::new(py);
+        dict.set_item("total", c.total)?;
+        dict.set_item("ok", c.ok)?;
+        dict.set_item("action", c.action)?;
+        dict.set_item("error", c.error)?;
+        dict.set_item("skipped_empty", c.skipped_empty)?;
+        dict.set_item("skipped_inactive", c.skipped_inactive)?;
+        dict.set_item("skipped_busy", c.skipped_busy)?;
+        dict.set_item("skipped_active", c.skipped_active)?;
+        dict.set_item("skipped_paused", c.skipped_paused)?;
+        dict.set_item("current_ok_streak", c.current_ok_streak)?;
+        dict.set_item("longest_ok_streak", c.longest_ok_streak)?;
+        Ok(dict)
+    }
+
+    /// The interval until the next tick: stretched while the agent keeps
+    /// replying OK, if `adaptive` is set, then backed off after failures.
+    pub(super) fn effective_interval_s(
+        &self,
+        interval_s: u64,
+        adaptive: Option<AdaptiveInterval>,
+    ) -> u64 {
+        let interval_s = adaptive.map_or(interval_s, |adaptive| {
+            adaptive.interval_s(interval_s, self.counters.current_ok_streak)
+        });
+        backoff_interval_s(interval_s, self.consecutive_failures)
+    }
+
+    pub(super) fn to_dict<'py>(
+        &self,
+        py: Python<'py>,
+        running: bool,
+        interval_s: Option<u64>,
+        adaptive: Option<AdaptiveInterval>,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("running", running)?;
+        dict.set_item("interval_s", interval_s)?;
+        dict.set_item("next_tick_at_ms", self.next_tick_at_ms.filter(|_| running))?;
+        dict.set_item("last_tick_at_ms", self.last_tick_at_ms)?;
+        dict.set_item("last_result", self.last_result)?;
+        dict.set_item("last_error", self.last_error.as_deref())?;
+        dict.set_item("ticks_total", self.counters.total)?;
+        dict.set_item("consecutive_failures", self.consecutive_failures)?;
+        dict.set_item(
+            "effective_interval_s",
+            interval_s.map(|interval_s| self.effective_interval_s(interval_s, adaptive)),
+        )?;
+        Ok(dict)
+    }
+}
+
+/// Lengthening the interval while the agent keeps replying OK.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) struct AdaptiveInterval {
+    /// What the interval is multiplied by.
+    pub(super) factor: f64,
+    /// OK replies in a row per multiplication.
+    pub(super) after_oks: u32,
+}
+
+impl AdaptiveInterval {
+    /// The interval after `ok_streak` OK replies in a row: multiplied by
+    /// `factor` for every `after_oks` of them, but not beyond
+    /// `MAX_BACKOFF_S` unless the configured interval is already longer.
+    fn interval_s(&self, interval_s: u64, ok_streak: u64) -> u64 {
+        let cap = MAX_BACKOFF_S.max(interval_s);
+        let steps = (ok_streak / u64::from(self.after_oks)).min(64) as i32;
+        (interval_s as f64 * self.factor.powi(steps)).min(cap as f64) as u64
+    }
+}
+
+/// The interval after `failures` consecutive failed ticks: doubled per
+/// failure, but not beyond `MAX_BACKOFF_S` unless the configured interval
+/// is already longer.
+fn backoff_interval_s(interval_s: u64, failures: u32) -> u64 {
+    let cap = MAX_BACKOFF_S.max(interval_s);
+    interval_s
+        .saturating_mul(1u64.checked_shl(failures).unwrap_or(u64::MAX))
+        .min(cap)
+}
+
+pub(super) fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_interval_s() {
+        assert_eq!(backoff_interval_s(60, 0), 60);
+        assert_eq!(backoff_interval_s(60, 1), 120);
+        assert_eq!(backoff_interval_s(60, 3), 480);
+        assert_eq!(backoff_interval_s(1800, 4), MAX_BACKOFF_S);
+        assert_eq!(backoff_interval_s(60, 100), MAX_BACKOFF_S);
+        assert_eq!(backoff_interval_s(86400, 2), 86400);
+    }
+
+    #[test]
+    fn test_adaptive_interval_s() {
+        let adaptive = AdaptiveInterval {
+            factor: 2.0,
+            after_oks: 3,
+        };
+        assert_eq!(adaptive.interval_s(60, 0), 60);
+        assert_eq!(adaptive.interval_s(60, 2), 60);
+        assert_eq!(adaptive.interval_s(60, 3), 120);
+        assert_eq!(adaptive.interval_s(60, 7), 240);
+        assert_eq!(adaptive.interval_s(1800, 1000), MAX_BACKOFF_S);
+        assert_eq!(adaptive.interval_s(86400, 9), 86400);
+    }
+
+    #[test]
+    fn test_ok_streaks() {
+        let mut status = HeartbeatStatus::default();
+        for _ in 0..3 {
+            status.record(0, &Ok(TickOutcome::Ok), None);
+        }
+        status.record(0, &Ok(TickOutcome::SkippedEmpty), None);
+        status.record(0, &Ok(TickOutcome::Ok), None);
+        assert_eq!(status.counters.current_ok_streak, 4);
+        status.record(0, &Ok(TickOutcome::Action), None);
+        status.record(0, &Ok(TickOutcome::Ok), None);
+        assert_eq!(status.counters.current_ok_streak, 1);
+        status.record(0, &Err("down".to_string()), None);
+        assert_eq!(status.counters.current_ok_streak, 0);
+        assert_eq!(status.counters.longest_ok_streak, 4);
+    }
+
+    #[test]
+    fn test_consecutive_failures() {
+        let mut status = HeartbeatStatus::default();
+        status.record(0, &Err("down".to_string()), None);
+        status.record(0, &Ok(TickOutcome::SkippedEmpty), None);
+        status.record(0, &Err("down".to_string()), None);
+        assert_eq!(status.consecutive_failures, 2);
+        status.record(0, &Ok(TickOutcome::Ok), None);
+        assert_eq!(status.consecutive_failures, 0);
+    }
+
+    #[test]
+    fn test_record_counts_and_bounds_history() {
+        let mut status = HeartbeatStatus::default();
+        for _ in 0..HISTORY_LIMIT {
+            status.record(0, &Ok(TickOutcome::SkippedEmpty), None);
+        }
+        status.record(0, &Ok(TickOutcome::Action), Some(&"x".repeat(500)));
+        status.record(0, &Err("boom".to_string()), None);
+
+        assert_eq!(status.counters.total, HISTORY_LIMIT as u64 + 2);
+        assert_eq!(status.counters.skipped_empty, HISTORY_LIMIT as u64);
+        assert_eq!(status.counters.action, 1);
+        assert_eq!(status.counters.error, 1);
+        assert_eq!(status.history.len(), HISTORY_LIMIT);
+
+        let last = status.history.back().unwrap();
+        assert_eq!(last.result, "error");
+        assert_eq!(last.error.as_deref(), Some("boom"));
+        let action = &status.history[HISTORY_LIMIT - 2];
+        assert_eq!(
+            action.response.as_ref().unwrap().len(),
+            RESPONSE_SNIPPET_CHARS
+        );
+
+        status.reset_metrics();
+        assert_eq!(status.counters.total, 0);
+        assert!(status.history.is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+//! A Markdown log in the workspace of what the agent did on each tick.
+
+use chrono::{TimeZone, Utc};
+use chrono_tz::Tz;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Characters of the agent's response kept per log entry.
+const LOG_RESPONSE_CHARS: usize = 2000;
+
+/// Default size in bytes past which the log is rotated.
+pub(super) const DEFAULT_LOG_MAX_BYTES: u64 = 1024 * 1024;
+
+/// The rotated copy of a log, e.g. `heartbeat.log.md.1`.
+fn rotated_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".1");
+    path.with_file_name(name)
+}
+
+/// Whether `path` is named like a tick log, e.g. `heartbeat.log.md`, or a
+/// rotated one, which wildcards never match.
+pub(super) fn is_log(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().contains(".log."))
+}
+
+/// A log entry: a heading with the tick's start time in `tz` and its
+/// outcome, then the start of the response or the error.
+pub(super) fn entry(
+    at_ms: i64,
+    outcome: &str,
+    response: Option<&str>,
+    error: Option<&str>,
+    tz: Tz,
+) -> String {
+    let at = Utc
+        .timestamp_millis_opt(at_ms)
+        .single()
+        .unwrap_or_else(Utc::now)
+        .with_timezone(&tz);
+    let mut entry = format!("## {} {}\n\n", at.format("%Y-%m-%d %H:%M:%S %Z"), outcome);
+    let body = match (error, response) {
+        (Some(error), _) => format!("Error: {}", error),
+        (None, Some(response)) => {
+            let mut body: String = response.trim().chars().take(LOG_RESPONSE_CHARS).collect();
+            if response.trim().chars().nth(LOG_RESPONSE_CHARS).is_some() {
+                body.push('…');
+            }
+            body
+        }
+        (None, None) => String::new(),
+    };
+    if !body.is_empty() {
+        entry.push_str(&body);
+        entry.push_str("\n\n");
+    }
+    entry
+}
+
+/// Append `entry` to the log at `path`, first moving a log that would grow
+/// past `max_bytes` to its rotated copy, replacing the previous one.
+pub(super) fn append(path: &Path, max_bytes: u64, entry: &str) -> std::io::Result<()> {
+    let size = std::fs::metadata(path).map_or(0, |meta| meta.len());
+    if size > 0 && size + entry.len() as u64 > max_bytes {
+        std::fs::rename(path, rotated_path(path))?;
+    }
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(entry.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry() {
+        let tz: Tz = "Europe/Berlin".parse().unwrap();
+        // 2026-01-02 03:04:05 UTC
+        let at_ms = 1_767_323_045_000;
+        assert_eq!(
+            entry(at_ms, "action", Some(" Watered the plants\n"), None, tz),
+            "## 2026-01-02 04:04:05 CET action\n\nWatered the plants\n\n"
+        );
+        assert_eq!(
+            entry(at_ms, "error", None, Some("agent offline"), Tz::UTC),
+            "## 2026-01-02 03:04:05 UTC error\n\nError: agent offline\n\n"
+        );
+        let long = "x".repeat(LOG_RESPONSE_CHARS + 1);
+        let entry = entry(at_ms, "action", Some(&long), None, Tz::UTC);
+        assert!(entry.ends_with("x…\n\n"));
+    }
+
+    #[test]
+    fn test_is_log() {
+        assert!(is_log(Path::new("/w/heartbeat.log.md")));
+        assert!(is_log(Path::new("/w/heartbeat.log.md.1")));
+        assert!(!is_log(Path::new("/w/HEARTBEAT.md")));
+        assert!(!is_log(Path::new("/w/changelog.md")));
+        assert_eq!(
+            rotated_path(Path::new("/w/heartbeat.log.md")),
+            PathBuf::from("/w/heartbeat.log.md.1")
+        );
+    }
+}
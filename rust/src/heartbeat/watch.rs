@@ -0,0 +1,86 @@
+//! Watching the task files so edits trigger a tick without waiting for the
+//! next scheduled one.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::time::Instant;
+
+use super::{files, SharedSettings, Ticker};
+
+/// How often the task files are checked for changes.
+const WATCH_POLL_MS: u64 = 500;
+
+/// Default quiet period after an edit before the tick fires.
+pub(super) const DEFAULT_WATCH_DEBOUNCE_MS: u64 = 2000;
+
+/// Default minimum time between ticks triggered by edits.
+pub(super) const DEFAULT_WATCH_MIN_SPACING_S: u64 = 60;
+
+#[derive(Clone, Copy)]
+pub(super) struct WatchConfig {
+    /// Edits closer together than this count as one change.
+    pub(super) debounce: Duration,
+    /// Ticks triggered by edits are at least this far apart.
+    pub(super) min_spacing: Duration,
+}
+
+/// Modification time and size of each task file; `None` for missing files.
+type Fingerprint = Vec<(PathBuf, Option<(SystemTime, u64)>)>;
+
+fn fingerprint(workspace: &Path, names: &[String]) -> Fingerprint {
+    files::resolve(workspace, names)
+        .into_iter()
+        .map(|path| {
+            let meta = std::fs::metadata(&path)
+                .ok()
+                .and_then(|m| Some((m.modified().ok()?, m.len())));
+            (path, meta)
+        })
+        .collect()
+}
+
+/// Poll the task files until `running` is cleared, ticking once a change
+/// has settled for the debounce period and the minimum spacing has passed.
+pub(super) async fn watch_loop(
+    ticker: &Ticker,
+    settings: &SharedSettings,
+    running: &Arc<AtomicBool>,
+    config: WatchConfig,
+) {
+    let names = settings.lock().files.clone();
+    let mut last = fingerprint(&ticker.workspace, &names);
+    // When the most recent unhandled change was seen
+    let mut changed_at: Option<Instant> = None;
+    let mut last_tick: Option<Instant> = None;
+
+    while running.load(Ordering::Relaxed) {
+        tokio::time::sleep(Duration::from_millis(WATCH_POLL_MS)).await;
+        if !running.load(Ordering::Relaxed) {
+            break;
+        }
+
+        let names = settings.lock().files.clone();
+        let current = fingerprint(&ticker.workspace, &names);
+        if current != last {
+            last = current;
+            changed_at = Some(Instant::now());
+            continue;
+        }
+
+        let Some(changed) = changed_at else {
+            continue;
+        };
+        if changed.elapsed() < config.debounce
+            || last_tick.is_some_and(|t| t.elapsed() < config.min_spacing)
+        {
+            continue;
+        }
+
+        changed_at = None;
+        last_tick = Some(Instant::now());
+        eprintln!("[heartbeat] Task files changed");
+        ticker.tick().await;
+    }
+}
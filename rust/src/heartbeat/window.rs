@@ -0,0 +1,96 @@
+//! Active-hours window: the local times of day at which heartbeats may run.
+
+use chrono::{DateTime, NaiveTime, TimeZone};
+use chrono_tz::Tz;
+
+/// A daily window of local time in some timezone, `start` inclusive and
+/// `end` exclusive.
+///
+/// A window whose end is before its start wraps past midnight, so 22:00 to
+/// 07:00 covers the night.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) struct ActiveHours {
+    pub(super) start: NaiveTime,
+    pub(super) end: NaiveTime,
+}
+
+impl ActiveHours {
+    /// Build a window from `HH:MM` strings.
+    pub(super) fn parse(start: &str, end: &str) -> Result<Self, String> {
+        let start = parse_hhmm(start)?;
+        let end = parse_hhmm(end)?;
+        if start == end {
+            return Err("active_start and active_end must differ".to_string());
+        }
+        Ok(Self { start, end })
+    }
+
+    /// Whether `time` falls inside the window.
+    pub(super) fn contains_time(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    /// Whether the instant `at` falls inside the window, read in `tz`.
+    pub(super) fn contains<Z: TimeZone>(&self, at: &DateTime<Z>, tz: Tz) -> bool {
+        self.contains_time(at.with_timezone(&tz).time())
+    }
+}
+
+/// Parse a `HH:MM` time of day.
+fn parse_hhmm(text: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(text.trim(), "%H:%M")
+        .map_err(|_| format!("Invalid time '{}', expected HH:MM", text))
+}
+
+/// Format a time of day as `HH:MM`.
+pub(super) fn format_hhmm(time: NaiveTime) -> String {
+    time.format("%H:%M").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hhmm: &str) -> NaiveTime {
+        parse_hhmm(hhmm).unwrap()
+    }
+
+    #[test]
+    fn test_contains_time() {
+        let day = ActiveHours::parse("08:00", "22:00").unwrap();
+        assert!(day.contains_time(at("08:00")));
+        assert!(day.contains_time(at("21:59")));
+        assert!(!day.contains_time(at("22:00")));
+        assert!(!day.contains_time(at("04:00")));
+
+        let night = ActiveHours::parse("22:00", "07:00").unwrap();
+        assert!(night.contains_time(at("23:30")));
+        assert!(night.contains_time(at("00:00")));
+        assert!(night.contains_time(at("06:59")));
+        assert!(!night.contains_time(at("07:00")));
+        assert!(!night.contains_time(at("12:00")));
+    }
+
+    #[test]
+    fn test_contains_in_timezone() {
+        let hours = ActiveHours::parse("08:00", "22:00").unwrap();
+        let berlin = chrono_tz::Europe::Berlin;
+        // 06:30 UTC is 08:30 in Berlin during summer time
+        let summer = chrono::Utc.with_ymd_and_hms(2024, 7, 1, 6, 30, 0).unwrap();
+        assert!(hours.contains(&summer, berlin));
+        // 06:30 UTC is 07:30 in Berlin during winter time
+        let winter = chrono::Utc.with_ymd_and_hms(2024, 1, 15, 6, 30, 0).unwrap();
+        assert!(!hours.contains(&winter, berlin));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(ActiveHours::parse("8am", "22:00").is_err());
+        assert!(ActiveHours::parse("24:00", "22:00").is_err());
+        assert!(ActiveHours::parse("09:00", "09:00").is_err());
+    }
+}
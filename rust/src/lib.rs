@@ -14,14 +14,14 @@ mod tools;
 use bus::MessageBus;
 use context::ContextBuilder;
 use cron::{CronJob, CronJobState, CronPayload, CronSchedule, CronService};
-use heartbeat::HeartbeatService;
+use heartbeat::{HeartbeatManager, HeartbeatService};
 use memory::MemoryStore;
 use messages::{InboundMessage, OutboundMessage};
 use session::{Session, SessionManager};
 use skills::SkillsLoader;
 use tools::{
-    EditFileTool, ExecTool, ListDirTool, ReadFileTool, ToolRegistry, WebFetchTool, WebSearchTool,
-    WriteFileTool,
+    EditFileTool, ExecTool, ListDirTool, ReadFileTool, ToolRegistry, WebFetchManyTool,
+    WebFetchTool, WebSearchTool, WriteFileTool,
 };
 
 /// Rust implementation of debot core modules.
@@ -41,6 +41,7 @@ fn debot_rust(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<ExecTool>()?;
     m.add_class::<WebSearchTool>()?;
     m.add_class::<WebFetchTool>()?;
+    m.add_class::<WebFetchManyTool>()?;
 
     // Session classes
     m.add_class::<Session>()?;
@@ -55,6 +56,7 @@ fn debot_rust(m: &Bound<'_, PyModule>) -> PyResult<()> {
 
     // Heartbeat service
     m.add_class::<HeartbeatService>()?;
+    m.add_class::<HeartbeatManager>()?;
 
     // Cron service
     m.add_class::<CronService>()?;
@@ -565,7 +565,7 @@ fn safe_filename(name: &str) -> String {
 }
 
 /// Convert Python object to JSON value.
-fn python_to_json(obj: Bound<'_, PyAny>) -> PyResult<serde_json::Value> {
+pub(crate) fn python_to_json(obj: Bound<'_, PyAny>) -> PyResult<serde_json::Value> {
     if obj.is_none() {
         Ok(serde_json::Value::Null)
     } else if let Ok(b) = obj.extract::<bool>() {
@@ -608,7 +608,7 @@ fn python_dict_to_json_map(
 }
 
 /// Convert JSON value to Python object.
-fn json_to_python(py: Python<'_>, value: &serde_json::Value) -> PyResult<PyObject> {
+pub(crate) fn json_to_python(py: Python<'_>, value: &serde_json::Value) -> PyResult<PyObject> {
     use pyo3::types::PyBool;
 
     match value {
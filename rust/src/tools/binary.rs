@@ -0,0 +1,165 @@
+//! Binary responses to web_fetch, such as images and archives, which are
+//! saved to a file or returned as base64 instead of as text.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::path::{Component, Path, PathBuf};
+
+/// Default limit on the bytes of a binary body returned as base64: 64 KiB.
+pub(super) const DEFAULT_MAX_BASE64_BYTES: usize = 64 * 1024;
+
+/// Bytes at the start of a body searched for a NUL, which text never has.
+const SNIFF_BYTES: usize = 8000;
+
+/// A response body that is not text.
+pub(super) struct BinaryBody {
+    pub(super) final_url: String,
+    pub(super) status: u16,
+    pub(super) content_type: String,
+    pub(super) bytes: Vec<u8>,
+    pub(super) download_truncated: bool,
+}
+
+/// Whether a Content-Type names a textual format.
+fn is_textual(content_type: &str) -> bool {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    essence.starts_with("text/")
+        || essence.ends_with("+json")
+        || essence.ends_with("+xml")
+        || matches!(
+            essence.as_str(),
+            "application/json"
+                | "application/xml"
+                | "application/javascript"
+                | "application/ecmascript"
+                | "application/x-www-form-urlencoded"
+        )
+}
+
+/// Whether a body with this Content-Type should be handled as binary: the
+/// type is not textual, or the body has a NUL near its start. A missing
+/// Content-Type is judged by the body alone.
+pub(super) fn is_binary(content_type: &str, body: &[u8]) -> bool {
+    if !content_type.trim().is_empty() && !is_textual(content_type) {
+        return true;
+    }
+    // UTF-16 text is full of NULs, but starts with a byte order mark
+    if encoding_rs::Encoding::for_bom(body).is_some() {
+        return false;
+    }
+    body[..body.len().min(SNIFF_BYTES)].contains(&0)
+}
+
+/// Where to save a body for `save_to`: relative paths are taken from
+/// `workspace`, and with a workspace the path may not lead outside it.
+pub(super) fn resolve_save_path(
+    workspace: Option<&Path>,
+    save_to: &str,
+) -> Result<PathBuf, String> {
+    let path = Path::new(save_to.trim());
+    if path.as_os_str().is_empty() {
+        return Err("save_to is empty".to_string());
+    }
+    if path.components().any(|c| c == Component::ParentDir) {
+        return Err(format!("save_to '{}' may not contain '..'", save_to));
+    }
+    match workspace {
+        Some(workspace) if path.is_absolute() && !path.starts_with(workspace) => Err(format!(
+            "save_to '{}' is outside the workspace {}",
+            save_to,
+            workspace.display()
+        )),
+        Some(workspace) => Ok(workspace.join(path)),
+        None => Ok(path.to_path_buf()),
+    }
+}
+
+/// The JSON result for `body`, which is saved to `save_path` if given and
+/// otherwise returned as base64, cut to `max_base64_bytes`.
+pub(super) async fn binary_result(
+    url: &str,
+    body: &BinaryBody,
+    save_path: Option<&Path>,
+    max_base64_bytes: usize,
+    attempts: u32,
+) -> serde_json::Value {
+    let sha256: String = Sha256::digest(&body.bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let mut result = json!({
+        "url": url,
+        "finalUrl": body.final_url,
+        "status": body.status,
+        "extractor": "binary",
+        "contentType": body.content_type,
+        "bytes": body.bytes.len(),
+        "sha256": sha256,
+        "downloadTruncated": body.download_truncated,
+        "cached": false,
+        "attempts": attempts
+    });
+    match save_path {
+        Some(path) => {
+            if let Some(parent) = path.parent() {
+                if let Err(e) = tokio::fs::create_dir_all(parent).await {
+                    result["error"] = json!(format!("Error creating directories: {}", e));
+                    return result;
+                }
+            }
+            match tokio::fs::write(path, &body.bytes).await {
+                Ok(()) => result["savedTo"] = json!(path.display().to_string()),
+                Err(e) => result["error"] = json!(format!("Error writing file: {}", e)),
+            }
+        }
+        None => {
+            let shown = &body.bytes[..body.bytes.len().min(max_base64_bytes)];
+            result["base64"] = json!(BASE64.encode(shown));
+            result["base64Truncated"] = json!(shown.len() < body.bytes.len());
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_binary() {
+        assert!(is_binary("image/png", b"\x89PNG\r\n"));
+        assert!(is_binary("application/octet-stream", b"plain words"));
+        assert!(!is_binary("text/html; charset=utf-8", b"<p>hi</p>"));
+        assert!(!is_binary("application/ld+json", b"{}"));
+        assert!(!is_binary("", b"just text"));
+        assert!(is_binary("", b"PK\x03\x04\x00\x00"));
+        assert!(is_binary("text/plain", b"looks\x00binary"));
+        assert!(!is_binary("text/plain", b"\xff\xfeh\x00i\x00"));
+    }
+
+    #[test]
+    fn test_resolve_save_path() {
+        let workspace = Path::new("/work");
+        assert_eq!(
+            resolve_save_path(Some(workspace), "files/a.png").unwrap(),
+            Path::new("/work/files/a.png")
+        );
+        assert_eq!(
+            resolve_save_path(Some(workspace), "/work/a.png").unwrap(),
+            Path::new("/work/a.png")
+        );
+        assert!(resolve_save_path(Some(workspace), "/etc/a.png").is_err());
+        assert!(resolve_save_path(Some(workspace), "../a.png").is_err());
+        assert!(resolve_save_path(None, " ").is_err());
+        assert_eq!(
+            resolve_save_path(None, "a.png").unwrap(),
+            Path::new("a.png")
+        );
+    }
+}
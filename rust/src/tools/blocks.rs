@@ -0,0 +1,210 @@
+//! Lists and blockquotes for html_to_markdown. They nest, so they are found
+//! by walking their tags rather than by a single regex.
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// The tags of the elements converted here.
+static BLOCK_TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)<(/?)(ul|ol|blockquote)\b([^>]*)>").unwrap());
+
+/// The tags that delimit the items of a list.
+static ITEM_TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)<(/?)(ul|ol|li)\b[^>]*>").unwrap());
+
+static START: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)\bstart\s*=\s*["']?(-?\d+)"#).unwrap());
+
+/// A list or blockquote at the top level of a fragment.
+pub(super) struct Element<'a> {
+    tag: String,
+    attrs: &'a str,
+    content: &'a str,
+}
+
+/// `html` with each outermost list and blockquote replaced by `replace`'s
+/// result. An element left open runs to the end of `html`.
+pub(super) fn replace_blocks(html: &str, mut replace: impl FnMut(&Element) -> String) -> String {
+    let mut out = String::with_capacity(html.len());
+    // Tags open within the current outermost element
+    let mut open: Vec<String> = Vec::new();
+    let mut element_start = 0;
+    let mut content_start = 0;
+    let mut attrs = "";
+    let mut last = 0;
+
+    for caps in BLOCK_TAG.captures_iter(html) {
+        let tag = caps.get(0).unwrap();
+        let name = caps[2].to_ascii_lowercase();
+        if caps[1].is_empty() {
+            if open.is_empty() {
+                element_start = tag.start();
+                content_start = tag.end();
+                attrs = caps.get(3).unwrap().as_str();
+            }
+            open.push(name);
+            continue;
+        }
+        // A close without its open is ignored; one closing an outer tag
+        // closes those left open inside it too
+        let Some(depth) = open.iter().rposition(|t| *t == name) else {
+            continue;
+        };
+        open.truncate(depth);
+        if open.is_empty() {
+            out.push_str(&html[last..element_start]);
+            out.push_str(&replace(&Element {
+                tag: name,
+                attrs,
+                content: &html[content_start..tag.start()],
+            }));
+            last = tag.end();
+        }
+    }
+    if let Some(tag) = open.first() {
+        out.push_str(&html[last..element_start]);
+        out.push_str(&replace(&Element {
+            tag: tag.clone(),
+            attrs,
+            content: &html[content_start..],
+        }));
+    } else {
+        out.push_str(&html[last..]);
+    }
+    out
+}
+
+/// The HTML of each item of a list, with the lists nested in it.
+fn list_items(content: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut depth = 0usize;
+    let mut item_start: Option<usize> = None;
+    for caps in ITEM_TAG.captures_iter(content) {
+        let tag = caps.get(0).unwrap();
+        let closing = !caps[1].is_empty();
+        match (caps[2].to_ascii_lowercase().as_str(), closing) {
+            ("li", false) if depth == 0 => {
+                // An item whose end tag was left out ends at the next one
+                if let Some(start) = item_start {
+                    items.push(&content[start..tag.start()]);
+                }
+                item_start = Some(tag.end());
+            }
+            ("li", true) if depth == 0 => {
+                if let Some(start) = item_start.take() {
+                    items.push(&content[start..tag.start()]);
+                }
+            }
+            ("li", _) => {}
+            (_, false) => depth += 1,
+            (_, true) => depth = depth.saturating_sub(1),
+        }
+    }
+    if let Some(start) = item_start {
+        items.push(&content[start..]);
+    }
+    items
+}
+
+/// Indent every line of `text` after the first by `width` spaces, dropping
+/// blank lines outside code fences so the list stays tight.
+fn indent_item(text: &str, width: usize) -> String {
+    let mut lines = Vec::new();
+    let mut in_fence = false;
+    for (i, line) in text.lines().enumerate() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        } else if line.trim().is_empty() && !in_fence {
+            continue;
+        }
+        if i == 0 || line.is_empty() {
+            lines.push(line.to_string());
+        } else {
+            lines.push(format!("{}{}", " ".repeat(width), line));
+        }
+    }
+    lines.join("\n")
+}
+
+impl Element<'_> {
+    /// The element as markdown, its content converted by `convert`.
+    /// Items of an `<ol>` are numbered from its `start`, and a nested
+    /// list is indented under its item.
+    pub(super) fn to_markdown(&self, convert: &mut dyn FnMut(&str) -> String) -> String {
+        if self.tag == "blockquote" {
+            return convert(self.content)
+                .lines()
+                .map(|line| {
+                    if line.is_empty() {
+                        ">".to_string()
+                    } else {
+                        format!("> {}", line)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+
+        let mut number: i64 = START
+            .captures(self.attrs)
+            .and_then(|c| c[1].parse().ok())
+            .unwrap_or(1);
+        let mut lines = Vec::new();
+        for item in list_items(self.content) {
+            let text = convert(item);
+            if text.is_empty() {
+                continue;
+            }
+            let marker = if self.tag == "ol" {
+                number += 1;
+                format!("{}. ", number - 1)
+            } else {
+                "- ".to_string()
+            };
+            lines.push(format!("{}{}", marker, indent_item(&text, marker.len())));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A stand-in for html_to_markdown that knows only paragraphs.
+    fn to_markdown(html: &str) -> String {
+        replace_blocks(html, |element| {
+            let mut convert = |fragment: &str| {
+                let text = to_markdown(fragment)
+                    .replace("<p>", "")
+                    .replace("</p>", "\n\n");
+                Regex::new(r"\n{3,}")
+                    .unwrap()
+                    .replace_all(text.trim(), "\n\n")
+                    .to_string()
+            };
+            format!("\n{}\n", element.to_markdown(&mut convert))
+        })
+    }
+
+    #[test]
+    fn test_nested_lists() {
+        let html = "<ol start=\"3\"><li>Fruit<ul><li>apple<li>pear</ul></li><li>Bread</li></ol>";
+        assert_eq!(
+            to_markdown(html),
+            "\n3. Fruit\n   - apple\n   - pear\n4. Bread\n"
+        );
+    }
+
+    #[test]
+    fn test_blockquote() {
+        let html = "<blockquote><p>Said</p><blockquote>Twice</blockquote></blockquote>after";
+        assert_eq!(to_markdown(html), "\n> Said\n>\n> > Twice\nafter");
+    }
+
+    #[test]
+    fn test_unclosed_list() {
+        assert_eq!(to_markdown("<ul><li>a<li>b"), "\n- a\n- b\n");
+        assert_eq!(to_markdown("a</ul>b"), "a</ul>b");
+    }
+}
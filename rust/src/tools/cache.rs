@@ -0,0 +1,239 @@
+//! In-memory caches of pages web_fetch has extracted: fresh for a time, or
+//! until their validators show they changed.
+
+use super::links::Link;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Default number of pages kept before the least recently used is evicted.
+pub(super) const DEFAULT_CACHE_MAX_ENTRIES: usize = 128;
+
+/// Default number of pages kept with their validators.
+pub(super) const DEFAULT_VALIDATOR_MAX_ENTRIES: usize = 64;
+
+/// A fetched page after extraction, before it is cut to `maxChars`.
+pub(super) struct FetchedPage {
+    pub(super) final_url: String,
+    pub(super) status: u16,
+    pub(super) extractor: &'static str,
+    pub(super) download_truncated: bool,
+    /// Name of the encoding the body was decoded from.
+    pub(super) charset: &'static str,
+    /// Metadata of an HTML page.
+    pub(super) meta: Option<serde_json::Value>,
+    /// Links of an HTML page, in `links` mode.
+    pub(super) links: Vec<Link>,
+    pub(super) text: String,
+}
+
+/// The `ETag` and `Last-Modified` a page was served with, to ask in a
+/// conditional request whether it has changed since.
+pub(super) struct Validated {
+    pub(super) etag: Option<String>,
+    pub(super) last_modified: Option<String>,
+    pub(super) page: Arc<FetchedPage>,
+}
+
+/// Cache key: the URL and the extract mode.
+type Key = (String, String);
+
+struct Entry<V> {
+    value: V,
+    stored_at: Instant,
+    /// Tick of the last lookup or store, for LRU eviction.
+    last_used: u64,
+}
+
+/// Pages keyed by URL and extract mode, each kept for `ttl`, evicting the
+/// least recently used past `max_entries`. Clones share the entries.
+#[derive(Clone)]
+pub(super) struct ResponseCache {
+    ttl: Duration,
+    max_entries: usize,
+    inner: Arc<parking_lot::Mutex<Entries<Arc<FetchedPage>>>>,
+}
+
+/// Pages served with validators, keyed by URL and extract mode, evicting
+/// the least recently used past `max_entries`. Clones share the entries.
+#[derive(Clone)]
+pub(super) struct ValidatorCache {
+    max_entries: usize,
+    inner: Arc<parking_lot::Mutex<Entries<Arc<Validated>>>>,
+}
+
+struct Entries<V> {
+    map: HashMap<Key, Entry<V>>,
+    tick: u64,
+}
+
+impl<V> Default for Entries<V> {
+    fn default() -> Self {
+        Self {
+            map: HashMap::new(),
+            tick: 0,
+        }
+    }
+}
+
+impl ResponseCache {
+    pub(super) fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries,
+            inner: Arc::default(),
+        }
+    }
+
+    /// The page cached for `url` in `extract_mode`, if still fresh.
+    pub(super) fn get(&self, url: &str, extract_mode: &str) -> Option<Arc<FetchedPage>> {
+        let mut entries = self.inner.lock();
+        let key = (url.to_string(), extract_mode.to_string());
+        let entry = entries.touch(&key)?;
+        if entry.stored_at.elapsed() >= self.ttl {
+            entries.map.remove(&key);
+            return None;
+        }
+        Some(entry.value.clone())
+    }
+
+    /// Cache `page` under the URL it was requested by and the one it was
+    /// finally served from.
+    pub(super) fn insert(&self, url: &str, extract_mode: &str, page: Arc<FetchedPage>) {
+        let mut entries = self.inner.lock();
+        for url in [url, page.final_url.as_str()] {
+            entries.insert((url.to_string(), extract_mode.to_string()), page.clone());
+        }
+        entries.evict(self.max_entries);
+    }
+}
+
+impl ValidatorCache {
+    pub(super) fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            inner: Arc::default(),
+        }
+    }
+
+    /// The validators and page last stored for `url` in `extract_mode`.
+    pub(super) fn get(&self, url: &str, extract_mode: &str) -> Option<Arc<Validated>> {
+        let key = (url.to_string(), extract_mode.to_string());
+        Some(self.inner.lock().touch(&key)?.value.clone())
+    }
+
+    /// Keep `validated` for `url` in `extract_mode`, replacing what was.
+    pub(super) fn insert(&self, url: &str, extract_mode: &str, validated: Validated) {
+        let mut entries = self.inner.lock();
+        let key = (url.to_string(), extract_mode.to_string());
+        entries.insert(key, Arc::new(validated));
+        entries.evict(self.max_entries);
+    }
+}
+
+impl<V> Entries<V> {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    /// The entry for `key`, marked as just used.
+    fn touch(&mut self, key: &Key) -> Option<&mut Entry<V>> {
+        let tick = self.next_tick();
+        let entry = self.map.get_mut(key)?;
+        entry.last_used = tick;
+        Some(entry)
+    }
+
+    fn insert(&mut self, key: Key, value: V) {
+        let last_used = self.next_tick();
+        self.map.insert(
+            key,
+            Entry {
+                value,
+                stored_at: Instant::now(),
+                last_used,
+            },
+        );
+    }
+
+    /// Drop the least recently used entries past `max_entries`.
+    fn evict(&mut self, max_entries: usize) {
+        while self.map.len() > max_entries {
+            let oldest = self
+                .map
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(key) => self.map.remove(&key),
+                None => break,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(final_url: &str) -> Arc<FetchedPage> {
+        Arc::new(FetchedPage {
+            final_url: final_url.to_string(),
+            status: 200,
+            extractor: "raw",
+            download_truncated: false,
+            charset: "UTF-8",
+            meta: None,
+            links: Vec::new(),
+            text: final_url.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let cache = ResponseCache::new(Duration::from_secs(60), 2);
+        cache.insert("a", "markdown", page("a"));
+        cache.insert("b", "markdown", page("b"));
+        assert!(cache.get("a", "markdown").is_some());
+        cache.insert("c", "markdown", page("c"));
+        // "b" was used least recently
+        assert!(cache.get("b", "markdown").is_none());
+        assert!(cache.get("a", "markdown").is_some());
+        assert!(cache.get("c", "markdown").is_some());
+        assert!(cache.get("a", "text").is_none());
+    }
+
+    #[test]
+    fn test_final_url_and_ttl() {
+        let cache = ResponseCache::new(Duration::from_secs(60), 8);
+        cache.insert("http://x/old", "text", page("http://x/new"));
+        assert_eq!(
+            cache.get("http://x/new", "text").unwrap().text,
+            "http://x/new"
+        );
+
+        let cache = ResponseCache::new(Duration::ZERO, 8);
+        cache.insert("a", "text", page("a"));
+        assert!(cache.get("a", "text").is_none());
+    }
+
+    #[test]
+    fn test_validators() {
+        let validated = |etag: &str| Validated {
+            etag: Some(etag.to_string()),
+            last_modified: None,
+            page: page("a"),
+        };
+        let cache = ValidatorCache::new(1);
+        cache.insert("a", "text", validated("\"v1\""));
+        cache.insert("a", "text", validated("\"v2\""));
+        assert_eq!(
+            cache.get("a", "text").unwrap().etag.as_deref(),
+            Some("\"v2\"")
+        );
+        assert!(cache.get("a", "markdown").is_none());
+        cache.insert("b", "text", validated("\"v1\""));
+        assert!(cache.get("a", "text").is_none());
+    }
+}
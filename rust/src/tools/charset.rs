@@ -0,0 +1,129 @@
+//! Working out the character encoding of a fetched page.
+
+use encoding_rs::{Encoding, UTF_8};
+use regex::bytes::Regex;
+use std::sync::LazyLock;
+
+/// Bytes at the start of a page searched for a `<meta>` charset.
+const META_SCAN_BYTES: usize = 4096;
+
+/// A `<meta charset="...">` or `<meta http-equiv="Content-Type"
+/// content="text/html; charset=...">` tag.
+static META_CHARSET: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)<meta[^>]*?charset\s*=\s*["']?\s*([a-z0-9_.:-]+)"#).unwrap()
+});
+
+/// The encoding named by the `charset` parameter of a Content-Type.
+fn from_content_type(content_type: &str) -> Option<&'static Encoding> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("charset") {
+            return None;
+        }
+        Encoding::for_label(value.trim().trim_matches(['"', '\'']).as_bytes())
+    })
+}
+
+/// The encoding named by a `<meta>` tag near the start of an HTML page.
+fn from_meta(body: &[u8]) -> Option<&'static Encoding> {
+    let head = &body[..body.len().min(META_SCAN_BYTES)];
+    let label = META_CHARSET.captures(head)?.get(1)?.as_bytes();
+    let encoding = Encoding::for_label(label)?;
+    // A page read as ASCII cannot be UTF-16, whatever it says
+    if encoding == encoding_rs::UTF_16LE || encoding == encoding_rs::UTF_16BE {
+        return Some(UTF_8);
+    }
+    Some(encoding)
+}
+
+/// Guess the encoding from the bytes themselves, with the top-level
+/// domain of the page as a hint.
+fn sniff(body: &[u8], tld: Option<&str>) -> &'static Encoding {
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(body, true);
+    detector.guess(tld.map(str::as_bytes), true)
+}
+
+/// Decode `body`, returning its text and the encoding used.
+///
+/// The encoding is taken from a byte order mark, then the Content-Type's
+/// `charset`, then a `<meta>` tag, and is otherwise UTF-8 if the body is
+/// valid UTF-8 or else sniffed. A `partial` body was cut short, so a
+/// character split at its end is dropped rather than replaced.
+pub(super) fn decode(
+    body: &[u8],
+    content_type: &str,
+    tld: Option<&str>,
+    partial: bool,
+) -> (String, &'static Encoding) {
+    let encoding = Encoding::for_bom(body)
+        .map(|(encoding, _)| encoding)
+        .or_else(|| from_content_type(content_type))
+        .or_else(|| from_meta(body))
+        .unwrap_or_else(|| {
+            let valid_utf8 = match std::str::from_utf8(body) {
+                Ok(_) => true,
+                // Only a character split at the end of a partial body
+                Err(e) => partial && e.error_len().is_none(),
+            };
+            if valid_utf8 {
+                UTF_8
+            } else {
+                sniff(body, tld)
+            }
+        });
+
+    let mut decoder = encoding.new_decoder();
+    let mut text = String::with_capacity(
+        decoder
+            .max_utf8_buffer_length(body.len())
+            .unwrap_or(body.len()),
+    );
+    // Not the last chunk when partial, so a split character is held back
+    let _ = decoder.decode_to_string(body, &mut text, !partial);
+    (text, encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_type_and_meta() {
+        let (body, _, _) = encoding_rs::SHIFT_JIS.encode("日本語のページ");
+        let (text, encoding) = decode(&body, "text/plain; charset=Shift_JIS", None, false);
+        assert_eq!(text, "日本語のページ");
+        assert_eq!(encoding.name(), "Shift_JIS");
+
+        let mut page = b"<html><head><meta http-equiv=\"Content-Type\" \
+                         content=\"text/html; charset=windows-1251\"></head><body>"
+            .to_vec();
+        page.extend_from_slice(&encoding_rs::WINDOWS_1251.encode("Привет, мир").0);
+        let (text, encoding) = decode(&page, "text/html", None, false);
+        assert!(text.ends_with("Привет, мир"));
+        assert_eq!(encoding.name(), "windows-1251");
+
+        let page = b"<meta charset='utf-8'>caf\xc3\xa9";
+        assert_eq!(
+            decode(page, "text/html", None, false).0,
+            "<meta charset='utf-8'>café"
+        );
+    }
+
+    #[test]
+    fn test_sniffing_and_partial() {
+        let (body, _, _) = encoding_rs::WINDOWS_1251.encode(
+            "Съешь же ещё этих мягких французских булок, да выпей чаю. \
+             Широкая электрификация южных губерний даст мощный толчок подъёму сельского хозяйства.",
+        );
+        let (text, encoding) = decode(&body, "text/plain", Some("ru"), false);
+        assert_eq!(encoding.name(), "windows-1251");
+        assert!(text.starts_with("Съешь"));
+
+        // A UTF-8 body cut inside a character loses only that character
+        let body = "ok é".as_bytes();
+        let (text, encoding) = decode(&body[..body.len() - 1], "text/plain", None, true);
+        assert_eq!(text, "ok ");
+        assert_eq!(encoding, UTF_8);
+    }
+}
@@ -0,0 +1,56 @@
+//! Cookies web_fetch keeps between calls, in memory only.
+
+use reqwest::cookie::{CookieStore, Jar};
+use reqwest::header::HeaderValue;
+use url::Url;
+
+/// The cookies sites set in a tool's responses, sent back on its later
+/// requests until cleared. They are never written to disk.
+#[derive(Default)]
+pub(super) struct SessionCookies {
+    jar: parking_lot::RwLock<Jar>,
+}
+
+impl SessionCookies {
+    /// Forget every cookie.
+    pub(super) fn clear(&self) {
+        *self.jar.write() = Jar::default();
+    }
+
+    /// Whether there are cookies to send to `url`.
+    pub(super) fn has_cookies_for(&self, url: &Url) -> bool {
+        self.cookies(url).is_some()
+    }
+}
+
+impl CookieStore for SessionCookies {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        self.jar.read().set_cookies(cookie_headers, url);
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        self.jar.read().cookies(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cookies_until_cleared() {
+        let cookies = SessionCookies::default();
+        let url = Url::parse("https://docs.example.com/guide").unwrap();
+        let set = HeaderValue::from_static("session=abc; Path=/");
+        cookies.set_cookies(&mut std::iter::once(&set), &url);
+        assert_eq!(
+            cookies.cookies(&url),
+            Some(HeaderValue::from_static("session=abc"))
+        );
+        let other = Url::parse("https://other.example.org/").unwrap();
+        assert!(!cookies.has_cookies_for(&other));
+
+        cookies.clear();
+        assert!(!cookies.has_cookies_for(&url));
+    }
+}
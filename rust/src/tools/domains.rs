@@ -0,0 +1,160 @@
+//! Which domains web_fetch may fetch from, checked for the URL asked for
+//! and every redirect on the way.
+
+use serde_json::json;
+use std::fmt;
+use url::Url;
+
+/// Hosts on the `allowed` domains, or any host if there are none, apart
+/// from those on the `blocked` ones. A domain covers itself and every
+/// subdomain.
+#[derive(Clone, Default)]
+pub(super) struct DomainPolicy {
+    allowed: Vec<String>,
+    blocked: Vec<String>,
+}
+
+/// A URL on a host the policy does not allow, as the error ending a
+/// redirect to it.
+#[derive(Debug)]
+pub(super) struct DomainNotAllowed {
+    pub(super) host: String,
+}
+
+impl DomainPolicy {
+    /// The policy for the domain lists a tool was given, such as
+    /// `example.com` or `*.example.com`. Fails on an empty entry.
+    pub(super) fn new(allowed: Vec<String>, blocked: Vec<String>) -> Result<Self, String> {
+        Ok(Self {
+            allowed: normalize(allowed, "allowed_domains")?,
+            blocked: normalize(blocked, "blocked_domains")?,
+        })
+    }
+
+    pub(super) fn allowed(&self) -> &[String] {
+        &self.allowed
+    }
+
+    pub(super) fn blocked(&self) -> &[String] {
+        &self.blocked
+    }
+
+    /// Whether `url` may be fetched.
+    pub(super) fn check(&self, url: &Url) -> Result<(), DomainNotAllowed> {
+        let host = url
+            .host_str()
+            .unwrap_or_default()
+            .trim_end_matches('.')
+            .to_ascii_lowercase();
+        let on_any = |domains: &[String]| domains.iter().any(|d| is_on_domain(&host, d));
+        if on_any(&self.blocked) || !(self.allowed.is_empty() || on_any(&self.allowed)) {
+            return Err(DomainNotAllowed { host });
+        }
+        Ok(())
+    }
+
+    /// Follow up to `max_redirects` redirects, ending with
+    /// `DomainNotAllowed` at one to a host the policy does not allow.
+    pub(super) fn redirect_policy(&self, max_redirects: usize) -> reqwest::redirect::Policy {
+        let policy = self.clone();
+        reqwest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() > max_redirects {
+                return attempt.error("too many redirects");
+            }
+            match policy.check(attempt.url()) {
+                Ok(()) => attempt.follow(),
+                Err(e) => attempt.error(e),
+            }
+        })
+    }
+}
+
+impl DomainNotAllowed {
+    /// The error a redirect to a host not allowed ended `e` with.
+    pub(super) fn find(e: &reqwest::Error) -> Option<&Self> {
+        let mut source: Option<&(dyn std::error::Error + 'static)> = Some(e);
+        while let Some(error) = source {
+            if let Some(e) = error.downcast_ref::<Self>() {
+                return Some(e);
+            }
+            source = error.source();
+        }
+        None
+    }
+
+    /// The result of fetching `url`, stopped at this host.
+    pub(super) fn to_json(&self, url: &str) -> serde_json::Value {
+        json!({
+            "error": "domain_not_allowed",
+            "message": format!("Fetching from {} is not allowed", self.host),
+            "url": url,
+            "host": self.host
+        })
+    }
+}
+
+impl fmt::Display for DomainNotAllowed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "domain_not_allowed: {}", self.host)
+    }
+}
+
+impl std::error::Error for DomainNotAllowed {}
+
+/// `domains` as policies compare them, lowercase and without `*.` or
+/// outer dots. Fails on an empty one, naming the `setting`.
+pub(super) fn normalize(domains: Vec<String>, setting: &str) -> Result<Vec<String>, String> {
+    domains
+        .into_iter()
+        .map(|domain| {
+            let domain = domain.trim().trim_start_matches("*.").trim_matches('.');
+            if domain.is_empty() {
+                return Err(format!("{} entries must be domain names", setting));
+            }
+            Ok(domain.to_ascii_lowercase())
+        })
+        .collect()
+}
+
+/// Whether `host` is `domain` or one of its subdomains.
+pub(super) fn is_on_domain(host: &str, domain: &str) -> bool {
+    host.strip_suffix(domain)
+        .is_some_and(|rest| rest.is_empty() || rest.ends_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(allowed: &[&str], blocked: &[&str]) -> DomainPolicy {
+        let list = |domains: &[&str]| domains.iter().map(|d| d.to_string()).collect();
+        DomainPolicy::new(list(allowed), list(blocked)).unwrap()
+    }
+
+    fn allows(policy: &DomainPolicy, url: &str) -> bool {
+        policy.check(&Url::parse(url).unwrap()).is_ok()
+    }
+
+    #[test]
+    fn test_check() {
+        let open = policy(&[], &["ads.example.com"]);
+        assert!(allows(&open, "https://example.com/"));
+        assert!(allows(&open, "https://other.org/"));
+        assert!(!allows(&open, "https://ads.example.com/x"));
+        assert!(!allows(&open, "https://cdn.ADS.example.com./x"));
+
+        let closed = policy(&["*.Example.com", "docs.rs"], &["ads.example.com"]);
+        assert!(allows(&closed, "https://example.com/"));
+        assert!(allows(&closed, "https://api.example.com/v1"));
+        assert!(allows(&closed, "https://docs.rs/serde"));
+        assert!(!allows(&closed, "https://notexample.com/"));
+        assert!(!allows(&closed, "https://ads.example.com/"));
+        assert_eq!(closed.allowed(), ["example.com", "docs.rs"]);
+
+        let e = closed
+            .check(&Url::parse("http://127.0.0.1:8080/").unwrap())
+            .unwrap_err();
+        assert_eq!(e.host, "127.0.0.1");
+        assert!(DomainPolicy::new(vec![" ".into()], Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+//! Results from DuckDuckGo's lite and HTML pages, which need no API key.
+
+use scraper::{ElementRef, Html, Selector};
+use std::sync::LazyLock;
+use url::Url;
+
+use super::web::SearchHit;
+
+/// The lite results page, which is the simplest to parse.
+pub(super) const LITE_URL: &str = "https://lite.duckduckgo.com/lite/";
+
+/// Result links and snippets, in the lite page's classes and the HTML
+/// page's.
+static RESULT_PARTS: LazyLock<Selector> = LazyLock::new(|| {
+    Selector::parse("a.result-link, a.result__a, .result-snippet, .result__snippet").unwrap()
+});
+
+static RESULT_LINKS: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("a.result-link, a.result__a").unwrap());
+
+/// The rows of sponsored results on the lite page.
+static SPONSORED: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("tr.result-sponsored a.result-link").unwrap());
+
+/// The text of an element with its whitespace collapsed.
+fn text_of(element: ElementRef) -> String {
+    let text: String = element.text().collect();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The target of a result link, which DuckDuckGo usually wraps in a
+/// redirect through `/l/?uddg=<url>`. `None` for ads, which go through
+/// `/y.js`, and for links that are not HTTP(S).
+fn result_url(href: &str) -> Option<String> {
+    let base = Url::parse(LITE_URL).unwrap();
+    let url = base.join(href.trim()).ok()?;
+    let url = if url
+        .host_str()
+        .is_some_and(|h| h.ends_with("duckduckgo.com"))
+    {
+        if url.path().starts_with("/y.js") {
+            return None;
+        }
+        let target = url.query_pairs().find(|(k, _)| k == "uddg")?.1;
+        Url::parse(&target).ok()?
+    } else {
+        url
+    };
+    matches!(url.scheme(), "http" | "https").then(|| String::from(url))
+}
+
+/// The results on a DuckDuckGo lite or HTML results page, in order. Each
+/// snippet goes with the link before it.
+pub(super) fn parse_results(html: &str) -> Vec<SearchHit> {
+    let document = Html::parse_document(html);
+    let sponsored: Vec<_> = document.select(&SPONSORED).map(|a| a.id()).collect();
+
+    let mut hits: Vec<SearchHit> = Vec::new();
+    // Whether the last link was kept, so its snippet is too
+    let mut kept = false;
+    for part in document.select(&RESULT_PARTS) {
+        if RESULT_LINKS.matches(&part) {
+            let url = part
+                .value()
+                .attr("href")
+                .filter(|_| !sponsored.contains(&part.id()))
+                .and_then(result_url);
+            kept = false;
+            if let Some(url) = url {
+                if !hits.iter().any(|hit| hit.url == url) {
+                    hits.push(SearchHit {
+                        title: text_of(part),
+                        url,
+                        ..Default::default()
+                    });
+                    kept = true;
+                }
+            }
+        } else if kept {
+            let snippet = text_of(part);
+            if let Some(hit) = hits.last_mut().filter(|hit| hit.snippet.is_none()) {
+                hit.snippet = (!snippet.is_empty()).then_some(snippet);
+            }
+        }
+    }
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LITE: &str = r#"<html><body><table>
+        <tr class="result-sponsored"><td>1.&nbsp;</td>
+          <td><a rel="nofollow" href="https://duckduckgo.com/y.js?ad_provider=x" class='result-link'>Buy now</a></td></tr>
+        <tr class="result-sponsored"><td></td><td class='result-snippet'>An ad.</td></tr>
+        <tr><td valign="top">1.&nbsp;</td>
+          <td><a rel="nofollow" href="//duckduckgo.com/l/?uddg=https%3A%2F%2Fwww.rust-lang.org%2F&amp;rut=abc" class='result-link'>Rust
+            Programming <b>Language</b></a></td></tr>
+        <tr><td>&nbsp;&nbsp;&nbsp;</td><td class='result-snippet'>A language empowering
+          everyone to build <b>reliable</b> software.</td></tr>
+        <tr><td>&nbsp;&nbsp;&nbsp;</td><td><span class='link-text'>www.rust-lang.org</span></td></tr>
+        <tr><td valign="top">2.&nbsp;</td>
+          <td><a rel="nofollow" href="https://doc.rust-lang.org/book/" class='result-link'>The Book</a></td></tr>
+        </table></body></html>"#;
+
+    #[test]
+    fn test_parse_lite_results() {
+        assert_eq!(
+            parse_results(LITE),
+            vec![
+                SearchHit {
+                    title: "Rust Programming Language".into(),
+                    url: "https://www.rust-lang.org/".into(),
+                    snippet: Some(
+                        "A language empowering everyone to build reliable software.".into()
+                    ),
+                    ..Default::default()
+                },
+                SearchHit {
+                    title: "The Book".into(),
+                    url: "https://doc.rust-lang.org/book/".into(),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_html_results() {
+        let html = r#"<div class="result"><h2><a class="result__a" href="https://example.com/a">A</a></h2>
+            <a class="result__snippet" href="https://example.com/a">About <b>a</b>.</a></div>"#;
+        assert_eq!(
+            parse_results(html),
+            vec![SearchHit {
+                title: "A".into(),
+                url: "https://example.com/a".into(),
+                snippet: Some("About a.".into()),
+                ..Default::default()
+            }]
+        );
+        assert!(parse_results("<html><body>No results.</body></html>").is_empty());
+    }
+}
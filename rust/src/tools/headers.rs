@@ -0,0 +1,139 @@
+//! Extra request headers for web_fetch, and keeping their secrets out of
+//! results.
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::collections::HashMap;
+
+/// Headers the client sets itself, which callers may not override.
+const DENIED_HEADERS: &[&str] = &["host", "content-length", "transfer-encoding", "connection"];
+
+/// Headers whose values are credentials.
+const SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "x-api-key",
+    "x-subscription-token",
+];
+
+/// Replaces sensitive header values in results and errors.
+pub(super) const REDACTED: &str = "[REDACTED]";
+
+/// Whether the value of header `name` is a credential: a known one, or one
+/// named like a token, secret, password or key.
+fn is_sensitive(name: &HeaderName) -> bool {
+    let name = name.as_str();
+    SENSITIVE_HEADERS.contains(&name)
+        || ["token", "secret", "password", "api-key", "apikey"]
+            .iter()
+            .any(|word| name.contains(word))
+}
+
+/// Parse `headers` into a header map, refusing invalid names and values and
+/// those in `DENIED_HEADERS`.
+pub(super) fn parse_headers(headers: &HashMap<String, String>) -> Result<HeaderMap, String> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        let name = HeaderName::from_bytes(name.trim().as_bytes())
+            .map_err(|_| format!("Invalid header name '{}'", name))?;
+        if DENIED_HEADERS.contains(&name.as_str()) {
+            return Err(format!("Header '{}' cannot be set", name));
+        }
+        let mut value = HeaderValue::from_str(value.trim())
+            .map_err(|_| format!("Invalid value for header '{}'", name))?;
+        value.set_sensitive(is_sensitive(&name));
+        map.insert(name, value);
+    }
+    Ok(map)
+}
+
+/// `defaults` with `extra` added, replacing defaults of the same name.
+pub(super) fn merge_headers(defaults: &HeaderMap, extra: HeaderMap) -> HeaderMap {
+    let mut merged = defaults.clone();
+    for (name, value) in extra {
+        if let Some(name) = name {
+            merged.insert(name, value);
+        }
+    }
+    merged
+}
+
+/// `headers` as JSON, with sensitive values redacted.
+pub(super) fn redacted_json(headers: &HeaderMap) -> serde_json::Value {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if value.is_sensitive() || is_sensitive(name) {
+                REDACTED.to_string()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            (name.to_string(), serde_json::Value::String(value))
+        })
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+/// `text` with the values of sensitive `headers` redacted, for error
+/// messages that may quote them.
+pub(super) fn redact_secrets(text: &str, headers: &HeaderMap) -> String {
+    let mut text = text.to_string();
+    for (name, value) in headers {
+        if !(value.is_sensitive() || is_sensitive(name)) {
+            continue;
+        }
+        let value = String::from_utf8_lossy(value.as_bytes());
+        // A bearer token may be quoted without its scheme
+        let secrets = [value.as_ref(), value.split_once(' ').map_or("", |(_, s)| s)];
+        for secret in secrets.into_iter().filter(|s| !s.is_empty()) {
+            text = text.replace(secret, REDACTED);
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_and_merge() {
+        let defaults =
+            parse_headers(&headers(&[("Accept", "text/html"), ("X-Team", "a")])).unwrap();
+        let extra = parse_headers(&headers(&[("accept", "application/json")])).unwrap();
+        let merged = merge_headers(&defaults, extra);
+        assert_eq!(merged["accept"], "application/json");
+        assert_eq!(merged["x-team"], "a");
+
+        assert!(parse_headers(&headers(&[("Host", "evil.example")]))
+            .unwrap_err()
+            .contains("cannot be set"));
+        assert!(parse_headers(&headers(&[("Bad Name", "x")])).is_err());
+        assert!(parse_headers(&headers(&[("X-A", "line\nbreak")])).is_err());
+    }
+
+    #[test]
+    fn test_redaction() {
+        let map = parse_headers(&headers(&[
+            ("Authorization", "Bearer s3cret"),
+            ("X-Github-Token", "ghp_abc"),
+            ("Accept", "application/json"),
+        ]))
+        .unwrap();
+        let json = redacted_json(&map);
+        assert_eq!(json["authorization"], REDACTED);
+        assert_eq!(json["x-github-token"], REDACTED);
+        assert_eq!(json["accept"], "application/json");
+        assert_eq!(
+            redact_secrets("token s3cret and ghp_abc rejected", &map),
+            "token [REDACTED] and [REDACTED] rejected"
+        );
+    }
+}
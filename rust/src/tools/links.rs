@@ -0,0 +1,155 @@
+//! The links on an HTML page, for crawling.
+
+use scraper::{Html, Selector};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use url::Url;
+
+use super::ratelimit::registered_domain;
+
+/// Default number of links returned for a page.
+pub(super) const DEFAULT_MAX_LINKS: usize = 200;
+
+static ANCHORS: LazyLock<Selector> = LazyLock::new(|| Selector::parse("a[href]").unwrap());
+
+static BASE: LazyLock<Selector> = LazyLock::new(|| Selector::parse("base[href]").unwrap());
+
+static IMAGES: LazyLock<Selector> = LazyLock::new(|| Selector::parse("img[alt]").unwrap());
+
+/// A link on a page, its URL absolute and without a fragment.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct Link {
+    pub(super) url: String,
+    pub(super) text: String,
+    pub(super) rel: Option<String>,
+}
+
+impl Link {
+    pub(super) fn to_json(&self) -> serde_json::Value {
+        json!({"url": self.url, "text": self.text, "rel": self.rel})
+    }
+
+    /// Whether the link's host is under the registered `domain`.
+    pub(super) fn is_on_domain(&self, domain: &str) -> bool {
+        Url::parse(&self.url)
+            .ok()
+            .and_then(|url| url.host_str().map(registered_domain))
+            .is_some_and(|host| host == domain)
+    }
+}
+
+/// The HTTP(S) links of `html` in page order, resolved against its
+/// `<base>` or else `base`, the page's final URL. A URL linked more than
+/// once is listed once, with the first text found for it.
+pub(super) fn page_links(html: &str, base: &Url) -> Vec<Link> {
+    let document = Html::parse_document(html);
+    let base = document
+        .select(&BASE)
+        .next()
+        .and_then(|b| base.join(b.value().attr("href")?.trim()).ok())
+        .unwrap_or_else(|| base.clone());
+
+    let mut links: Vec<Link> = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for anchor in document.select(&ANCHORS) {
+        let href = anchor.value().attr("href").unwrap_or_default().trim();
+        if href.is_empty() || href.starts_with('#') {
+            continue;
+        }
+        let Ok(mut url) = base.join(href) else {
+            continue;
+        };
+        if !matches!(url.scheme(), "http" | "https") {
+            continue;
+        }
+        url.set_fragment(None);
+        let url = String::from(url);
+
+        let mut text = anchor
+            .text()
+            .flat_map(str::split_whitespace)
+            .collect::<Vec<_>>()
+            .join(" ");
+        // An image link is known by the image's alt text
+        if text.is_empty() {
+            text = anchor
+                .select(&IMAGES)
+                .filter_map(|img| img.value().attr("alt"))
+                .map(str::trim)
+                .find(|alt| !alt.is_empty())
+                .or_else(|| anchor.value().attr("title").map(str::trim))
+                .unwrap_or_default()
+                .to_string();
+        }
+
+        if let Some(&i) = seen.get(&url) {
+            if links[i].text.is_empty() {
+                links[i].text = text;
+            }
+            continue;
+        }
+        let rel = anchor
+            .value()
+            .attr("rel")
+            .map(|rel| rel.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|rel| !rel.is_empty());
+        seen.insert(url.clone(), links.len());
+        links.push(Link { url, text, rel });
+    }
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(url: &str, text: &str, rel: Option<&str>) -> Link {
+        Link {
+            url: url.to_string(),
+            text: text.to_string(),
+            rel: rel.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_page_links() {
+        let html = r##"<html><body>
+            <a href="#top">Top</a>
+            <a href="/about#team"><img src="t.png" alt="Team"></a>
+            <a href="guide.html">The
+              guide</a>
+            <a href="/about">About us</a>
+            <a href="https://other.org/x" rel="nofollow  external">Elsewhere</a>
+            <a href="mailto:me@example.com">Mail</a>
+            <a href="javascript:void(0)">Nothing</a>
+            </body></html>"##;
+        let base = Url::parse("https://docs.example.com/v1/index.html").unwrap();
+        let links = page_links(html, &base);
+        assert_eq!(
+            links,
+            vec![
+                link("https://docs.example.com/about", "Team", None),
+                link("https://docs.example.com/v1/guide.html", "The guide", None),
+                link(
+                    "https://other.org/x",
+                    "Elsewhere",
+                    Some("nofollow external")
+                ),
+            ]
+        );
+        assert!(links[0].is_on_domain("example.com"));
+        assert!(!links[2].is_on_domain("example.com"));
+    }
+
+    #[test]
+    fn test_base_href() {
+        let html = r#"<head><base href="https://cdn.example.com/assets/"></head>
+            <a href="a.css"></a><a href="a.css">Style</a>"#;
+        let base = Url::parse("https://example.com/").unwrap();
+        assert_eq!(
+            page_links(html, &base),
+            vec![link("https://cdn.example.com/assets/a.css", "Style", None)]
+        );
+    }
+}
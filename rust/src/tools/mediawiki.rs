@@ -0,0 +1,145 @@
+//! Articles on Wikipedia and other MediaWiki sites, fetched as plain text
+//! from the site's API instead of extracted from pages full of navboxes,
+//! reference marks and edit links.
+
+use super::domains::is_on_domain;
+use percent_encoding::percent_decode_str;
+use regex::Regex;
+use std::sync::LazyLock;
+use url::Url;
+
+/// Domains whose sites web_fetch takes to run MediaWiki by default.
+pub(super) const DEFAULT_MEDIAWIKI_HOSTS: &[&str] = &["wikipedia.org"];
+
+/// A section heading of a plain text extract, as in `== History ==`.
+static HEADING: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^(={2,6}) *(.+?) *={2,6} *$").unwrap());
+
+/// The URL of the API request for the plain text of the article at `url`,
+/// if it is one on a site of `hosts`: `/wiki/Title`, or
+/// `/w/index.php?title=Title` showing its current revision.
+pub(super) fn extracts_url(url: &Url, hosts: &[String]) -> Option<Url> {
+    let host = url.host_str()?.trim_end_matches('.').to_ascii_lowercase();
+    if !hosts.iter().any(|domain| is_on_domain(&host, domain)) {
+        return None;
+    }
+    let title = match url.path().strip_prefix("/wiki/") {
+        Some(title) if url.query().is_none() => {
+            percent_decode_str(title).decode_utf8().ok()?.into_owned()
+        }
+        Some(_) => return None,
+        None if url.path() == "/w/index.php" => {
+            let mut title = None;
+            for (key, value) in url.query_pairs() {
+                match &*key {
+                    "title" => title = Some(value.into_owned()),
+                    "action" if value == "view" => {}
+                    _ => return None,
+                }
+            }
+            title?
+        }
+        None => return None,
+    };
+    if title.trim().is_empty() {
+        return None;
+    }
+    let mut api = url.join("/w/api.php").ok()?;
+    api.query_pairs_mut()
+        .append_pair("action", "query")
+        .append_pair("prop", "extracts")
+        .append_pair("explaintext", "1")
+        .append_pair("exsectionformat", "wiki")
+        .append_pair("redirects", "1")
+        .append_pair("format", "json")
+        .append_pair("formatversion", "2")
+        .append_pair("titles", &title);
+    Some(api)
+}
+
+/// The text of the article in an `extracts_url` response, headed by its
+/// title, with its sections as markdown headings or, without `markdown`,
+/// bare lines. `None` if the article was not found or has no text.
+pub(super) fn article_text(data: &serde_json::Value, markdown: bool) -> Option<String> {
+    let page = data["query"]["pages"].get(0)?;
+    if page.get("missing").is_some() || page.get("invalid").is_some() {
+        return None;
+    }
+    let title = page["title"].as_str()?;
+    let extract = page["extract"].as_str()?.trim();
+    if extract.is_empty() {
+        return None;
+    }
+    let extract = HEADING.replace_all(extract, |caps: &regex::Captures| {
+        if markdown {
+            format!("{} {}", "#".repeat(caps[1].len()), &caps[2])
+        } else {
+            caps[2].to_string()
+        }
+    });
+    Some(format!("# {}\n\n{}", title, extract))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_extracts_url() {
+        let hosts = vec!["wikipedia.org".to_string()];
+        let api = |url: &str| extracts_url(&Url::parse(url).unwrap(), &hosts).map(String::from);
+
+        let expected = "https://en.wikipedia.org/w/api.php?action=query&prop=extracts\
+            &explaintext=1&exsectionformat=wiki&redirects=1&format=json&formatversion=2\
+            &titles=C%2B%2B";
+        assert_eq!(
+            api("https://en.wikipedia.org/wiki/C%2B%2B#History").as_deref(),
+            Some(expected)
+        );
+        assert_eq!(
+            api("https://en.wikipedia.org/w/index.php?title=C%2B%2B").as_deref(),
+            Some(expected)
+        );
+        assert!(api("https://de.m.wikipedia.org/wiki/K%C3%B6ln")
+            .unwrap()
+            .ends_with("&titles=K%C3%B6ln"));
+        // Old revisions, edits and pages off the hosts are fetched as pages
+        assert_eq!(
+            api("https://en.wikipedia.org/w/index.php?title=Rust&oldid=1"),
+            None
+        );
+        assert_eq!(
+            api("https://en.wikipedia.org/w/index.php?title=Rust&action=edit"),
+            None
+        );
+        assert_eq!(api("https://en.wikipedia.org/wiki/"), None);
+        assert_eq!(api("https://en.wikipedia.org/"), None);
+        assert_eq!(api("https://wiki.example.org/wiki/Rust"), None);
+    }
+
+    #[test]
+    fn test_article_text() {
+        let data = json!({"query": {"pages": [{
+            "pageid": 1,
+            "title": "Rust",
+            "extract": "Rust is a language.\n\n\n== History ==\nIt began in 2006.\n\n=== Name ===\nA fungus."
+        }]}});
+        assert_eq!(
+            article_text(&data, true).as_deref(),
+            Some("# Rust\n\nRust is a language.\n\n\n## History\nIt began in 2006.\n\n### Name\nA fungus.")
+        );
+        assert_eq!(
+            article_text(&data, false).as_deref(),
+            Some(
+                "# Rust\n\nRust is a language.\n\n\nHistory\nIt began in 2006.\n\nName\nA fungus."
+            )
+        );
+
+        let missing = json!({"query": {"pages": [{"title": "Nope", "missing": true}]}});
+        assert_eq!(article_text(&missing, true), None);
+        let empty = json!({"query": {"pages": [{"title": "Stub", "extract": ""}]}});
+        assert_eq!(article_text(&empty, true), None);
+        assert_eq!(article_text(&json!({"error": {"code": "x"}}), true), None);
+    }
+}
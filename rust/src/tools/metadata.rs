@@ -0,0 +1,151 @@
+//! Metadata of an HTML page, for link previews and citations.
+
+use scraper::{Html, Selector};
+use serde_json::json;
+use url::Url;
+
+/// The `content` of the first `<meta>` tag matching one of `selectors`.
+fn meta_content(document: &Html, selectors: &[&str]) -> Option<String> {
+    selectors.iter().find_map(|selector| {
+        let selector = Selector::parse(&format!("meta{}", selector)).ok()?;
+        document
+            .select(&selector)
+            .filter_map(|meta| meta.value().attr("content"))
+            .map(str::trim)
+            .find(|content| !content.is_empty())
+            .map(str::to_string)
+    })
+}
+
+/// The `href` of the first `<link>` matching `selector`, resolved against
+/// `base`.
+fn link_href(document: &Html, selector: &str, base: &Url) -> Option<String> {
+    let selector = Selector::parse(&format!("link{}", selector)).ok()?;
+    document
+        .select(&selector)
+        .filter_map(|link| link.value().attr("href"))
+        .find_map(|href| base.join(href.trim()).ok())
+        .map(String::from)
+}
+
+/// The page's `title`, `description`, `siteName`, `author`,
+/// `publishedTime`, `canonicalUrl`, `lang` and `favicon`, each only if
+/// present. Open Graph tags are preferred to their plain equivalents, and
+/// URLs are resolved against `base`, the page's final URL.
+pub(super) fn page_metadata(html: &str, base: &Url) -> serde_json::Value {
+    let document = Html::parse_document(html);
+    let title = meta_content(&document, &[r#"[property="og:title"]"#]).or_else(|| {
+        let selector = Selector::parse("title").unwrap();
+        let title = document
+            .select(&selector)
+            .next()?
+            .text()
+            .collect::<String>();
+        let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+        (!title.is_empty()).then_some(title)
+    });
+    let lang = document
+        .root_element()
+        .value()
+        .attr("lang")
+        .map(str::trim)
+        .filter(|lang| !lang.is_empty())
+        .map(str::to_string);
+
+    let fields = [
+        ("title", title),
+        (
+            "description",
+            meta_content(
+                &document,
+                &[
+                    r#"[property="og:description"]"#,
+                    r#"[name="description"]"#,
+                    r#"[name="twitter:description"]"#,
+                ],
+            ),
+        ),
+        (
+            "siteName",
+            meta_content(
+                &document,
+                &[
+                    r#"[property="og:site_name"]"#,
+                    r#"[name="application-name"]"#,
+                ],
+            ),
+        ),
+        (
+            "author",
+            meta_content(
+                &document,
+                &[r#"[name="author"]"#, r#"[property="article:author"]"#],
+            ),
+        ),
+        (
+            "publishedTime",
+            meta_content(
+                &document,
+                &[
+                    r#"[property="article:published_time"]"#,
+                    r#"[itemprop="datePublished"]"#,
+                    r#"[name="date"]"#,
+                ],
+            ),
+        ),
+        (
+            "canonicalUrl",
+            link_href(&document, r#"[rel="canonical"]"#, base).or_else(|| {
+                meta_content(&document, &[r#"[property="og:url"]"#])
+                    .and_then(|url| base.join(&url).ok())
+                    .map(String::from)
+            }),
+        ),
+        ("lang", lang),
+        ("favicon", link_href(&document, "[rel~=icon]", base)),
+    ];
+    let mut meta = json!({});
+    for (name, value) in fields {
+        if let Some(value) = value {
+            meta[name] = json!(value);
+        }
+    }
+    meta
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_metadata() {
+        let html = r#"<html lang="en"><head>
+            <title> Plain
+              title </title>
+            <meta property="og:title" content="Open Graph title">
+            <meta name="description" content="What it is about">
+            <meta property="og:site_name" content="Example">
+            <meta name="author" content="A. Writer">
+            <meta property="article:published_time" content="2026-01-02T03:04:05Z">
+            <link rel="canonical" href="/story">
+            <link rel="shortcut icon" href="favicon.ico">
+            </head><body></body></html>"#;
+        let base = Url::parse("https://example.com/news/story?ref=x").unwrap();
+        assert_eq!(
+            page_metadata(html, &base),
+            json!({
+                "title": "Open Graph title",
+                "description": "What it is about",
+                "siteName": "Example",
+                "author": "A. Writer",
+                "publishedTime": "2026-01-02T03:04:05Z",
+                "canonicalUrl": "https://example.com/story",
+                "lang": "en",
+                "favicon": "https://example.com/news/favicon.ico"
+            })
+        );
+
+        let html = "<html><head><title> Plain\n title </title></head></html>";
+        assert_eq!(page_metadata(html, &base), json!({"title": "Plain title"}));
+    }
+}
@@ -1,13 +1,33 @@
 //! Tools module - agent capabilities for interacting with the environment.
 
 pub mod base;
+mod binary;
+mod blocks;
+mod cache;
+mod charset;
+mod cookies;
+mod domains;
+mod duckduckgo;
 pub mod filesystem;
+mod headers;
+mod links;
+mod mediawiki;
+mod metadata;
+mod proxy;
+mod ratelimit;
+mod readability;
+mod refresh;
 pub mod registry;
+mod retry;
+mod robots;
 pub mod shell;
+mod sitemap;
+mod tables;
+mod tokens;
 pub mod web;
 
 // Tool trait is used internally but not exported to Python
 pub use filesystem::{EditFileTool, ListDirTool, ReadFileTool, WriteFileTool};
 pub use registry::ToolRegistry;
 pub use shell::ExecTool;
-pub use web::{WebFetchTool, WebSearchTool};
+pub use web::{WebFetchManyTool, WebFetchTool, WebSearchTool};
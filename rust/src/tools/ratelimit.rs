@@ -0,0 +1,138 @@
+//! Per-domain rate limiting of the requests web tools make.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+
+/// Default requests per minute to one domain.
+const DEFAULT_REQUESTS_PER_MINUTE: f64 = 60.0;
+
+/// Default longest a request waits for its domain's bucket to refill.
+pub(super) const DEFAULT_MAX_WAIT_S: f64 = 30.0;
+
+/// Labels that, under a two-letter country code, form part of the suffix
+/// domains are registered under, as in `example.co.uk`.
+const SECOND_LEVEL_LABELS: &[&str] = &["ac", "co", "com", "edu", "gov", "net", "org"];
+
+/// The limiter every tool uses unless given its own rate.
+static SHARED: LazyLock<RateLimiter> =
+    LazyLock::new(|| RateLimiter::new(DEFAULT_REQUESTS_PER_MINUTE));
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// A token bucket per registered domain, refilled at `per_minute` tokens a
+/// minute and holding at most a tenth of that, so a burst of requests is
+/// spread out rather than sent at once. Clones share the buckets.
+#[derive(Clone)]
+pub(super) struct RateLimiter {
+    per_minute: f64,
+    buckets: Arc<parking_lot::Mutex<HashMap<String, Bucket>>>,
+}
+
+impl RateLimiter {
+    pub(super) fn new(per_minute: f64) -> Self {
+        Self {
+            per_minute,
+            buckets: Arc::default(),
+        }
+    }
+
+    /// The limiter shared by every tool in the process.
+    pub(super) fn shared() -> Self {
+        SHARED.clone()
+    }
+
+    fn capacity(&self) -> f64 {
+        (self.per_minute / 10.0).max(1.0)
+    }
+
+    /// Take a token for `domain`, or return how long until one is free.
+    fn try_acquire(&self, domain: &str) -> Result<(), Duration> {
+        let capacity = self.capacity();
+        let per_second = self.per_minute / 60.0;
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock();
+        let bucket = buckets.entry(domain.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_second).min(capacity);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+    }
+
+    /// Take a token for `domain`. With `wait`, sleep until one is free if
+    /// that takes at most `max_wait`. Otherwise, or if it would take
+    /// longer, return how long until one is free.
+    pub(super) async fn acquire(
+        &self,
+        domain: &str,
+        wait: bool,
+        max_wait: Duration,
+    ) -> Result<(), Duration> {
+        let deadline = Instant::now() + max_wait;
+        loop {
+            let retry_after = match self.try_acquire(domain) {
+                Ok(()) => return Ok(()),
+                Err(retry_after) => retry_after,
+            };
+            if !wait || Instant::now() + retry_after > deadline {
+                return Err(retry_after);
+            }
+            // Another call may take the token first, so try again after
+            tokio::time::sleep(retry_after).await;
+        }
+    }
+}
+
+/// The domain `host` is registered under, which requests are limited by:
+/// the last two labels, or three under suffixes like `co.uk`. IP
+/// addresses are their own domain.
+pub(super) fn registered_domain(host: &str) -> String {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    if host.trim_matches(['[', ']']).parse::<IpAddr>().is_ok() {
+        return host;
+    }
+    let labels: Vec<&str> = host.split('.').collect();
+    let keep = match labels.as_slice() {
+        [.., second, tld] if tld.len() == 2 && SECOND_LEVEL_LABELS.contains(second) => 3,
+        _ => 2,
+    };
+    labels[labels.len().saturating_sub(keep)..].join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registered_domain() {
+        assert_eq!(registered_domain("docs.example.com"), "example.com");
+        assert_eq!(registered_domain("Example.COM."), "example.com");
+        assert_eq!(registered_domain("a.b.example.co.uk"), "example.co.uk");
+        assert_eq!(registered_domain("localhost"), "localhost");
+        assert_eq!(registered_domain("127.0.0.1"), "127.0.0.1");
+        assert_eq!(registered_domain("[::1]"), "[::1]");
+    }
+
+    #[test]
+    fn test_bucket() {
+        // Six a minute allows one request at a time, then one per 10s
+        let limiter = RateLimiter::new(6.0);
+        assert!(limiter.try_acquire("example.com").is_ok());
+        let retry_after = limiter.try_acquire("example.com").unwrap_err();
+        assert!(retry_after > Duration::from_secs(9) && retry_after <= Duration::from_secs(10));
+        // Domains have their own buckets, shared by clones
+        assert!(limiter.clone().try_acquire("example.org").is_ok());
+        assert!(limiter.clone().try_acquire("example.org").is_err());
+    }
+}
@@ -0,0 +1,257 @@
+//! Finding the main content of a page, without its navigation, sidebars,
+//! banners and footer, after Mozilla's Readability: blocks are scored by
+//! their text, weighted by their class names and reduced by the share of
+//! their text in links.
+
+use ego_tree::NodeId;
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// Elements that are never part of the main content.
+static BOILERPLATE: LazyLock<Selector> = LazyLock::new(|| {
+    Selector::parse(
+        "nav, aside, footer, form, script, style, noscript, iframe, svg, button, \
+         [role=navigation], [role=banner], [role=contentinfo], [role=complementary], \
+         [role=dialog], [aria-hidden=true]",
+    )
+    .unwrap()
+});
+
+/// Class names and ids of elements unlikely to be content...
+static UNLIKELY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        "(?i)-ad-|banner|breadcrumb|combx|comment|community|consent|cookie|disqus|extra|footer|gdpr|\
+         header|menu|modal|nav|newsletter|pager|pagination|popup|promo|related|remark|replies|\
+         rss|share|shoutbox|sidebar|skyscraper|social|sponsor|subscribe|supplemental",
+    )
+    .unwrap()
+});
+
+/// ...unless they also look like this.
+static MAYBE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)and|article|body|column|content|main|shadow").unwrap());
+
+static POSITIVE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)article|body|content|entry|hentry|h-entry|main|page|post|text|blog|story")
+        .unwrap()
+});
+
+static NEGATIVE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        "(?i)-ad-|hidden|banner|combx|comment|com-|contact|foot|footnote|gdpr|masthead|media|\
+         meta|outbrain|promo|related|scroll|share|shoutbox|sidebar|skyscraper|sponsor|\
+         shopping|tags|tool|widget",
+    )
+    .unwrap()
+});
+
+/// Blocks whose text is scored.
+static PARAGRAPHS: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse("p, pre, td, blockquote, div").unwrap());
+
+/// Children that keep a `<div>` from being scored as a paragraph itself.
+const BLOCK_CHILDREN: &[&str] = &[
+    "p",
+    "div",
+    "table",
+    "ul",
+    "ol",
+    "pre",
+    "blockquote",
+    "section",
+    "article",
+];
+
+static LINKS: LazyLock<Selector> = LazyLock::new(|| Selector::parse("a").unwrap());
+
+/// Shortest text, in characters, of a block that is scored.
+const MIN_PARAGRAPH_CHARS: usize = 25;
+
+/// The text of an element with its whitespace collapsed.
+fn text_of(element: ElementRef) -> String {
+    element
+        .text()
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The share of an element's text inside links.
+fn link_density(element: ElementRef) -> f64 {
+    let length = text_of(element).chars().count();
+    if length == 0 {
+        return 0.0;
+    }
+    let in_links: usize = element
+        .select(&LINKS)
+        .map(|a| text_of(a).chars().count())
+        .sum();
+    in_links as f64 / length as f64
+}
+
+/// The class names and id of an element.
+fn class_and_id(element: ElementRef) -> String {
+    let value = element.value();
+    format!(
+        "{} {}",
+        value.attr("class").unwrap_or_default(),
+        value.id().unwrap_or_default()
+    )
+}
+
+/// The score an element starts with, by its tag and class names.
+fn initial_score(element: ElementRef) -> f64 {
+    let by_tag = match element.value().name() {
+        "div" | "article" | "main" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" | "address" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    let names = class_and_id(element);
+    let mut weight = 0.0;
+    if POSITIVE.is_match(&names) {
+        weight += 25.0;
+    }
+    if NEGATIVE.is_match(&names) {
+        weight -= 25.0;
+    }
+    by_tag + weight
+}
+
+/// Whether an element is boilerplate to drop before scoring.
+fn is_boilerplate(element: ElementRef) -> bool {
+    if matches!(element.value().name(), "html" | "body" | "article" | "main") {
+        return false;
+    }
+    if BOILERPLATE.matches(&element) {
+        return true;
+    }
+    // A page header outside the article; one inside has its title
+    if element.value().name() == "header" {
+        return !element
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .any(|a| matches!(a.value().name(), "article" | "main"));
+    }
+    let names = class_and_id(element);
+    UNLIKELY.is_match(&names) && !MAYBE.is_match(&names)
+}
+
+/// The HTML of the main content of `html`, or `None` if no block has
+/// enough text to tell.
+pub(super) fn main_content(html: &str) -> Option<String> {
+    let mut document = Html::parse_document(html);
+
+    let boilerplate: Vec<NodeId> = document
+        .root_element()
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .filter(|element| is_boilerplate(*element))
+        .map(|element| element.id())
+        .collect();
+    for id in boilerplate {
+        if let Some(mut node) = document.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+
+    // Each paragraph adds to the score of its parent, and half that to its
+    // grandparent
+    let mut scores: HashMap<NodeId, f64> = HashMap::new();
+    for paragraph in document.select(&PARAGRAPHS) {
+        if paragraph.value().name() == "div"
+            && paragraph
+                .children()
+                .filter_map(ElementRef::wrap)
+                .any(|child| BLOCK_CHILDREN.contains(&child.value().name()))
+        {
+            continue;
+        }
+        let text = text_of(paragraph);
+        let length = text.chars().count();
+        if length < MIN_PARAGRAPH_CHARS {
+            continue;
+        }
+        let score = 1.0 + text.matches(',').count() as f64 + (length as f64 / 100.0).min(3.0);
+        let ancestors = paragraph.ancestors().filter_map(ElementRef::wrap).take(2);
+        for (level, ancestor) in ancestors.enumerate() {
+            if ancestor.value().name() == "html" {
+                break;
+            }
+            let entry = scores
+                .entry(ancestor.id())
+                .or_insert_with(|| initial_score(ancestor));
+            *entry += score / (level as f64 + 1.0);
+        }
+    }
+
+    let element = |id: NodeId| document.tree.get(id).and_then(ElementRef::wrap);
+    let final_score =
+        |id: NodeId, score: f64| score * (1.0 - element(id).map_or(0.0, link_density));
+    let (top_id, top_score) = scores
+        .iter()
+        .map(|(&id, &score)| (id, final_score(id, score)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    let top = element(top_id)?;
+
+    // Siblings of the top block that score well, or read like paragraphs,
+    // are part of the content too
+    let Some(parent) = top.parent().and_then(ElementRef::wrap) else {
+        return Some(top.html());
+    };
+    let threshold = (top_score * 0.2).max(10.0);
+    let mut content = String::new();
+    for sibling in parent.children().filter_map(ElementRef::wrap) {
+        let keep = sibling.id() == top_id
+            || scores
+                .get(&sibling.id())
+                .is_some_and(|&score| final_score(sibling.id(), score) >= threshold)
+            || (sibling.value().name() == "p" && {
+                let text = text_of(sibling);
+                let length = text.chars().count();
+                let density = link_density(sibling);
+                (length > 80 && density < 0.25)
+                    || (length > 0 && density == 0.0 && text.ends_with('.'))
+            });
+        if keep {
+            content.push_str(&sibling.html());
+        }
+    }
+    Some(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARTICLE: &str = r#"<html><body>
+        <nav><a href="/">Home</a> <a href="/news">News</a></nav>
+        <div class="cookie-banner">We use cookies, to improve your experience, okay?</div>
+        <div id="page">
+          <div class="article-body">
+            <p>The first paragraph of the story, which goes on for a while, with commas.</p>
+            <p>A second paragraph, also long enough to count as content for scoring.</p>
+          </div>
+          <aside><p>Related: another story you might like, with a long enough title.</p></aside>
+        </div>
+        <footer><p>Copyright 2026, Example News, all rights reserved, everywhere.</p></footer>
+        </body></html>"#;
+
+    #[test]
+    fn test_main_content() {
+        let content = main_content(ARTICLE).unwrap();
+        assert!(content.contains("The first paragraph"));
+        assert!(content.contains("A second paragraph"));
+        for boilerplate in ["Home", "cookies", "Related", "Copyright"] {
+            assert!(!content.contains(boilerplate), "{boilerplate} kept");
+        }
+    }
+
+    #[test]
+    fn test_no_content() {
+        assert!(main_content("<html><body><p>Short.</p></body></html>").is_none());
+    }
+}
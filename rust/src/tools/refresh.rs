@@ -0,0 +1,122 @@
+//! Interstitial pages that send readers on without an HTTP redirect.
+
+use scraper::{Html, Node, Selector};
+use std::sync::LazyLock;
+use url::Url;
+
+/// Most refreshes web_fetch follows from the page it was asked for.
+pub(super) const MAX_REFRESH_HOPS: usize = 3;
+
+/// Characters of visible text below which a page whose canonical URL is
+/// on another host counts as an interstitial.
+const NEAR_EMPTY_CHARS: usize = 32;
+
+static META: LazyLock<Selector> = LazyLock::new(|| Selector::parse("meta[content]").unwrap());
+
+static CANONICAL: LazyLock<Selector> =
+    LazyLock::new(|| Selector::parse(r#"link[rel~="canonical"][href]"#).unwrap());
+
+/// Where `html` sends readers on to: the URL of a `<meta http-equiv=
+/// "refresh">`, or else its canonical link if that is on another host and
+/// the page has next to no text of its own. URLs are resolved against
+/// `base`, the page's final URL, and one back to it is ignored.
+pub(super) fn refresh_target(html: &str, base: &Url) -> Option<Url> {
+    let document = Html::parse_document(html);
+    let refresh = document
+        .select(&META)
+        .filter(|meta| {
+            meta.value()
+                .attr("http-equiv")
+                .is_some_and(|equiv| equiv.trim().eq_ignore_ascii_case("refresh"))
+        })
+        .find_map(|meta| refresh_url(meta.value().attr("content")?));
+    let target = match refresh {
+        Some(url) => base.join(url).ok()?,
+        None if visible_chars(&document) < NEAR_EMPTY_CHARS => {
+            let href = document.select(&CANONICAL).next()?.value().attr("href")?;
+            let canonical = base.join(href.trim()).ok()?;
+            if canonical.host_str() == base.host_str() {
+                return None;
+            }
+            canonical
+        }
+        None => return None,
+    };
+    let page = |url: &Url| url[..url::Position::AfterQuery].to_string();
+    (page(&target) != page(base)).then_some(target)
+}
+
+/// The URL of a refresh's `content`, as in `0; url='/next'`.
+fn refresh_url(content: &str) -> Option<&str> {
+    let (_, rest) = content.split_once([';', ','])?;
+    let rest = rest.trim_start();
+    let url = match rest.get(..3) {
+        Some(key) if key.eq_ignore_ascii_case("url") => rest[3..].trim_start().strip_prefix('=')?,
+        _ => rest,
+    };
+    let url = url.trim().trim_matches(['"', '\'']).trim();
+    (!url.is_empty()).then_some(url)
+}
+
+/// Characters of text outside the page's head, not counting whitespace,
+/// scripts and styles.
+fn visible_chars(document: &Html) -> usize {
+    document
+        .root_element()
+        .descendants()
+        .filter_map(|node| match node.value() {
+            Node::Text(text) => Some((node, text)),
+            _ => None,
+        })
+        .filter(|(node, _)| {
+            node.ancestors().all(|a| {
+                a.value()
+                    .as_element()
+                    .is_none_or(|e| !matches!(e.name(), "script" | "style" | "head" | "noscript"))
+            })
+        })
+        .map(|(_, text)| text.chars().filter(|c| !c.is_whitespace()).count())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refresh_target() {
+        let base = Url::parse("https://example.com/old/page").unwrap();
+        let target = |html: &str| refresh_target(html, &base).map(String::from);
+
+        let html = r#"<meta http-equiv="Refresh" content="0; URL='/new/page'">"#;
+        assert_eq!(
+            target(html).as_deref(),
+            Some("https://example.com/new/page")
+        );
+        let html = r#"<meta http-equiv="refresh" content="5;next.html"><p>Moved</p>"#;
+        assert_eq!(
+            target(html).as_deref(),
+            Some("https://example.com/old/next.html")
+        );
+        // Reloading itself, or without a URL, goes nowhere
+        assert_eq!(target(r#"<meta http-equiv="refresh" content="300">"#), None);
+        let html = r#"<meta http-equiv="refresh" content="0; url=/old/page#top">"#;
+        assert_eq!(target(html), None);
+
+        let canonical = r#"<link rel="canonical" href="https://example.org/story">"#;
+        let html = format!(
+            "<head>{}<script>var x = 1;</script></head><body>Loading…</body>",
+            canonical
+        );
+        assert_eq!(target(&html).as_deref(), Some("https://example.org/story"));
+        let html = format!(
+            "<head>{}</head><body><p>{}</p></body>",
+            canonical,
+            "text ".repeat(10)
+        );
+        assert_eq!(target(&html), None);
+        // Short pages on the site of their canonical URL are kept
+        let html = r#"<link rel="canonical" href="/posts/launch"><p>It is out.</p>"#;
+        assert_eq!(target(html), None);
+    }
+}
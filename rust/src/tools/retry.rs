@@ -0,0 +1,107 @@
+//! Retrying web requests that fail in ways worth trying again.
+
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::time::Duration;
+
+use super::proxy::is_proxy_auth_error;
+
+/// Default retries after the first attempt.
+pub(super) const DEFAULT_MAX_RETRIES: u32 = 2;
+
+/// Delay before the first retry, doubled for each one after.
+const BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Longest delay before a retry. A `Retry-After` asking for longer ends the
+/// retries instead.
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Send `request`, retrying up to `max_retries` times after timeouts,
+/// connection errors, 429s and 5xx responses with exponential backoff, or
+/// after the delay a `Retry-After` header asks for. Returns the last result
+/// and the number of attempts made.
+///
+/// Other statuses are returned at once, as is the last retryable one and
+/// a proxy refusing our credentials.
+pub(super) async fn send_with_retry(
+    request: RequestBuilder,
+    max_retries: u32,
+) -> (reqwest::Result<Response>, u32) {
+    let mut attempt = 0;
+    loop {
+        // Requests with string bodies always clone
+        let Some(this_try) = request.try_clone() else {
+            return (request.send().await, attempt + 1);
+        };
+        let result = this_try.send().await;
+        attempt += 1;
+        let backoff = BASE_DELAY * 2u32.saturating_pow(attempt - 1);
+        let delay = match &result {
+            Ok(r) if is_retryable_status(r.status()) => retry_after(r).unwrap_or(backoff),
+            Err(e) if is_proxy_auth_error(e) => return (result, attempt),
+            Err(e) if e.is_timeout() || e.is_connect() || e.is_request() => backoff,
+            _ => return (result, attempt),
+        };
+        if attempt > max_retries || delay > MAX_DELAY {
+            return (result, attempt);
+        }
+        tokio::time::sleep(delay).await;
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// The delay a `Retry-After` header asks for, in seconds or as an HTTP
+/// date.
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    parse_retry_after(value, chrono::Utc::now())
+}
+
+fn parse_retry_after(value: &str, now: chrono::DateTime<chrono::Utc>) -> Option<Duration> {
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (at.with_timezone(&chrono::Utc) - now)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = chrono::Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap();
+        assert_eq!(parse_retry_after("7", now), Some(Duration::from_secs(7)));
+        assert_eq!(
+            parse_retry_after("Fri, 02 Jan 2026 03:04:35 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        // A date already past means retry at once
+        assert_eq!(
+            parse_retry_after("Fri, 02 Jan 2026 03:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_is_retryable_status() {
+        assert!(is_retryable_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable_status(StatusCode::BAD_GATEWAY));
+        assert!(!is_retryable_status(StatusCode::NOT_FOUND));
+        assert!(!is_retryable_status(StatusCode::OK));
+    }
+}
@@ -0,0 +1,228 @@
+//! robots.txt rules for web_fetch, cached per origin.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use url::Url;
+
+use super::web::read_body;
+
+/// How long an origin's robots.txt is used before it is fetched again.
+const ROBOTS_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// How long fetching a robots.txt may take before the origin is taken to
+/// allow everything.
+const ROBOTS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Bytes of a robots.txt read at most, as crawlers commonly do.
+const ROBOTS_MAX_BYTES: usize = 500 * 1024;
+
+/// Rules from each origin's robots.txt and when they were fetched, keyed by
+/// origin. Shared by every call of a tool.
+pub(super) type RobotsCache = Arc<parking_lot::Mutex<HashMap<String, CachedRules>>>;
+
+pub(super) struct CachedRules {
+    rules: Arc<RobotsRules>,
+    fetched_at: Instant,
+}
+
+/// An `Allow` or `Disallow` line.
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+/// The rules of a robots.txt that apply to one user agent. No rules means
+/// everything is allowed.
+#[derive(Default)]
+pub(super) struct RobotsRules {
+    rules: Vec<Rule>,
+}
+
+impl RobotsRules {
+    /// Parse `text`, keeping the rules of the groups for `user_agent`, or
+    /// of the `*` groups if none names it. A group's `User-agent` names it
+    /// when it equals the product token, the part before the first `/`.
+    fn parse(text: &str, user_agent: &str) -> Self {
+        let product = user_agent.split('/').next().unwrap_or_default().trim();
+        let mut ours = Vec::new();
+        let mut any = Vec::new();
+        // Agents of the current group, and whether its rules have begun, so
+        // the next `User-agent` line starts a new group
+        let mut agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        agents.clear();
+                        in_rules = false;
+                    }
+                    agents.push(value.to_string());
+                }
+                key @ ("allow" | "disallow") => {
+                    in_rules = true;
+                    // An empty `Disallow` allows everything
+                    if value.is_empty() {
+                        continue;
+                    }
+                    for agent in &agents {
+                        let rules = if agent == "*" {
+                            &mut any
+                        } else if agent.eq_ignore_ascii_case(product) {
+                            &mut ours
+                        } else {
+                            continue;
+                        };
+                        rules.push(Rule {
+                            allow: key == "allow",
+                            pattern: value.to_string(),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        let rules = if ours.is_empty() { any } else { ours };
+        Self { rules }
+    }
+
+    /// Whether `path`, with its query, may be fetched. The longest matching
+    /// pattern decides, and `Allow` wins a tie.
+    pub(super) fn is_allowed(&self, path: &str) -> bool {
+        if path == "/robots.txt" {
+            return true;
+        }
+        self.rules
+            .iter()
+            .filter(|rule| pattern_matches(&rule.pattern, path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+}
+
+/// Whether a robots.txt path pattern matches the start of `path`. `*`
+/// matches any characters and a trailing `$` anchors it to the end.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return !anchored || rest.is_empty();
+    };
+    for part in middle {
+        let Some(at) = rest.find(part) else {
+            return false;
+        };
+        rest = &rest[at + part.len()..];
+    }
+    if anchored {
+        rest.ends_with(last)
+    } else {
+        rest.contains(last)
+    }
+}
+
+/// The rules of `url`'s origin for `user_agent`, from `cache` while fresh.
+///
+/// A robots.txt that is missing, fails or times out allows everything, and
+/// that is cached too.
+pub(super) async fn rules_for(
+    cache: &RobotsCache,
+    client: &reqwest::Client,
+    url: &Url,
+    user_agent: &str,
+) -> Arc<RobotsRules> {
+    let origin = url.origin().ascii_serialization();
+    if let Some(cached) = cache.lock().get(&origin) {
+        if cached.fetched_at.elapsed() < ROBOTS_TTL {
+            return cached.rules.clone();
+        }
+    }
+
+    let rules = match url.join("/robots.txt") {
+        Ok(robots_url) => fetch(client, robots_url, user_agent).await,
+        Err(_) => RobotsRules::default(),
+    };
+    let rules = Arc::new(rules);
+    cache.lock().insert(
+        origin,
+        CachedRules {
+            rules: rules.clone(),
+            fetched_at: Instant::now(),
+        },
+    );
+    rules
+}
+
+async fn fetch(client: &reqwest::Client, robots_url: Url, user_agent: &str) -> RobotsRules {
+    let response = client
+        .get(robots_url.as_str())
+        .timeout(ROBOTS_TIMEOUT)
+        .send()
+        .await;
+    match response {
+        Ok(r) if r.status().is_success() => match read_body(r, ROBOTS_MAX_BYTES).await {
+            Ok((text, _)) => RobotsRules::parse(&text, user_agent),
+            Err(_) => RobotsRules::default(),
+        },
+        _ => RobotsRules::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64)";
+
+    #[test]
+    fn test_pattern_matches() {
+        assert!(pattern_matches("/private", "/private/a"));
+        assert!(!pattern_matches("/private", "/public"));
+        assert!(pattern_matches("/*.pdf$", "/docs/a.pdf"));
+        assert!(!pattern_matches("/*.pdf$", "/docs/a.pdf?x=1"));
+        assert!(pattern_matches("/a*b*c", "/axxbyyc/d"));
+        assert!(!pattern_matches("/a*b*c", "/axxcyyb"));
+        assert!(pattern_matches("/exact$", "/exact"));
+        assert!(!pattern_matches("/exact$", "/exact/more"));
+    }
+
+    #[test]
+    fn test_groups_and_precedence() {
+        let text = "# comment\n\
+                    User-agent: *\n\
+                    Disallow: /private\n\
+                    Allow: /private/open\n\
+                    Disallow:\n\
+                    \n\
+                    User-agent: OtherBot\n\
+                    Disallow: /\n";
+        let rules = RobotsRules::parse(text, AGENT);
+        assert!(rules.is_allowed("/"));
+        assert!(!rules.is_allowed("/private/secret"));
+        assert!(rules.is_allowed("/private/open/page"));
+
+        // A group naming our product token replaces the `*` groups
+        let text = "User-agent: *\nDisallow: /\n\n\
+                    User-agent: otherbot\nUser-agent: mozilla\nDisallow: /tmp\n";
+        let rules = RobotsRules::parse(text, AGENT);
+        assert!(rules.is_allowed("/page"));
+        assert!(!rules.is_allowed("/tmp/x"));
+        assert!(rules.is_allowed("/robots.txt"));
+
+        assert!(RobotsRules::parse("", AGENT).is_allowed("/anything"));
+    }
+}
@@ -0,0 +1,211 @@
+//! A site's URL inventory from its sitemap, for web_fetch's `sitemap`
+//! mode.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use flate2::read::GzDecoder;
+use regex::Regex;
+use serde_json::json;
+use std::io::Read;
+use std::sync::LazyLock;
+use url::Url;
+
+/// Most sitemaps of a sitemap index read, the latest first.
+pub(super) const MAX_SITEMAPS: usize = 10;
+
+/// A `<url>` or `<sitemap>` entry, with or without an XML namespace prefix.
+static ENTRY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?is)<(?:[\w.-]+:)?(url|sitemap)\b[^>]*>(.*?)</(?:[\w.-]+:)?(?:url|sitemap)\s*>")
+        .unwrap()
+});
+
+static LOC: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<(?:[\w.-]+:)?loc\b[^>]*>(.*?)</").unwrap());
+
+static LASTMOD: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<(?:[\w.-]+:)?lastmod\b[^>]*>(.*?)</").unwrap());
+
+/// A URL listed in a sitemap, and when it last changed.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct SitemapEntry {
+    pub(super) loc: String,
+    pub(super) lastmod: Option<String>,
+}
+
+/// The pages and, for a sitemap index, the sitemaps a sitemap lists.
+#[derive(Debug, Default)]
+pub(super) struct Sitemap {
+    pub(super) urls: Vec<SitemapEntry>,
+    pub(super) sitemaps: Vec<SitemapEntry>,
+}
+
+impl SitemapEntry {
+    pub(super) fn to_json(&self) -> serde_json::Value {
+        json!({"url": self.loc, "lastmod": self.lastmod})
+    }
+
+    /// When the entry last changed, if it says in a form we can read.
+    fn modified(&self) -> Option<DateTime<Utc>> {
+        let lastmod = self.lastmod.as_deref()?;
+        DateTime::parse_from_rfc3339(lastmod)
+            .map(|t| t.with_timezone(&Utc))
+            .ok()
+            .or_else(|| {
+                let date = NaiveDate::parse_from_str(lastmod.get(..10)?, "%Y-%m-%d").ok()?;
+                Some(date.and_hms_opt(0, 0, 0)?.and_utc())
+            })
+    }
+}
+
+/// The sitemap to read for `url`: `/sitemap.xml` for a site's front page,
+/// or else `url` itself.
+pub(super) fn sitemap_url(url: &Url) -> Url {
+    if url.path() == "/" && url.query().is_none() {
+        url.join("/sitemap.xml").unwrap_or_else(|_| url.clone())
+    } else {
+        url.clone()
+    }
+}
+
+/// The entries of a sitemap or sitemap index. Tolerates a document cut
+/// short, keeping the entries before the cut.
+pub(super) fn parse_sitemap(xml: &str) -> Sitemap {
+    let mut sitemap = Sitemap::default();
+    for caps in ENTRY.captures_iter(xml) {
+        let field = |re: &Regex| re.captures(&caps[2]).map(|c| xml_text(&c[1]));
+        let Some(loc) = field(&LOC).filter(|loc| !loc.is_empty()) else {
+            continue;
+        };
+        let lastmod = field(&LASTMOD).filter(|lastmod| !lastmod.is_empty());
+        let entry = SitemapEntry { loc, lastmod };
+        if caps[1].eq_ignore_ascii_case("sitemap") {
+            sitemap.sitemaps.push(entry);
+        } else {
+            sitemap.urls.push(entry);
+        }
+    }
+    sitemap
+}
+
+/// The text of an element, without CDATA markers or entities.
+fn xml_text(text: &str) -> String {
+    let text = text.trim();
+    let text = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|t| t.strip_suffix("]]>"))
+        .unwrap_or(text);
+    html_escape::decode_html_entities(text.trim()).into_owned()
+}
+
+/// `body` unzipped if it is gzipped, as `.xml.gz` sitemaps are, up to
+/// `max_bytes`, and whether it was cut short, there or by ending early.
+pub(super) fn gunzip(body: Vec<u8>, max_bytes: usize) -> std::io::Result<(Vec<u8>, bool)> {
+    if !body.starts_with(&[0x1f, 0x8b]) {
+        return Ok((body, false));
+    }
+    let mut unzipped = Vec::new();
+    let read = GzDecoder::new(body.as_slice())
+        .take(max_bytes as u64 + 1)
+        .read_to_end(&mut unzipped);
+    // What could be read of a body cut short
+    let ended_early = match read {
+        Err(_) if !unzipped.is_empty() => true,
+        Err(e) => return Err(e),
+        Ok(_) => false,
+    };
+    let truncated = ended_early || unzipped.len() > max_bytes;
+    unzipped.truncate(max_bytes);
+    Ok((unzipped, truncated))
+}
+
+/// Sort `entries` by `lastmod`, the newest first, those without one last.
+pub(super) fn sort_newest_first(entries: &mut [SitemapEntry]) {
+    entries.sort_by_cached_key(|entry| std::cmp::Reverse(entry.modified()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    const URLSET: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url><loc>https://example.com/</loc><lastmod>2024-01-05</lastmod></url>
+  <url>
+    <loc> https://example.com/changelog?a=1&amp;b=2 </loc>
+    <lastmod>2024-03-01T10:00:00+02:00</lastmod>
+  </url>
+  <url><loc><![CDATA[https://example.com/about]]></loc></url>
+  <url><loc>https://example.com/blog</loc><lastmod>2024-03-01T09:00:00Z</lastmod></url>
+  <url><loc>https://example.com/cut"#;
+
+    #[test]
+    fn test_parse_sitemap() {
+        let sitemap = parse_sitemap(URLSET);
+        assert!(sitemap.sitemaps.is_empty());
+        let locs: Vec<_> = sitemap.urls.iter().map(|e| e.loc.as_str()).collect();
+        assert_eq!(
+            locs,
+            [
+                "https://example.com/",
+                "https://example.com/changelog?a=1&b=2",
+                "https://example.com/about",
+                "https://example.com/blog"
+            ]
+        );
+        assert_eq!(sitemap.urls[2].lastmod, None);
+
+        let mut urls = sitemap.urls;
+        sort_newest_first(&mut urls);
+        let locs: Vec<_> = urls.iter().map(|e| e.loc.as_str()).collect();
+        assert_eq!(
+            locs,
+            [
+                "https://example.com/blog",
+                "https://example.com/changelog?a=1&b=2",
+                "https://example.com/",
+                "https://example.com/about"
+            ]
+        );
+
+        let index = r#"<sm:sitemapindex xmlns:sm="http://www.sitemaps.org/schemas/sitemap/0.9">
+            <sm:sitemap><sm:loc>https://example.com/posts.xml.gz</sm:loc></sm:sitemap>
+            </sm:sitemapindex>"#;
+        let sitemap = parse_sitemap(index);
+        assert!(sitemap.urls.is_empty());
+        assert_eq!(sitemap.sitemaps[0].loc, "https://example.com/posts.xml.gz");
+    }
+
+    #[test]
+    fn test_gunzip() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(URLSET.as_bytes()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+        let gzipped_prefix = gzipped[..gzipped.len() * 3 / 4].to_vec();
+        assert_eq!(
+            gunzip(gzipped.clone(), 1 << 20).unwrap(),
+            (URLSET.as_bytes().to_vec(), false)
+        );
+        assert_eq!(
+            gunzip(gzipped, 10).unwrap(),
+            (URLSET.as_bytes()[..10].to_vec(), true)
+        );
+        let (partial, truncated) = gunzip(gzipped_prefix, 1 << 20).unwrap();
+        assert!(truncated && URLSET.as_bytes().starts_with(&partial) && !partial.is_empty());
+        assert_eq!(gunzip(b"<urlset/>".to_vec(), 3).unwrap().0, b"<urlset/>");
+    }
+
+    #[test]
+    fn test_sitemap_url() {
+        let url = |u: &str| sitemap_url(&Url::parse(u).unwrap()).to_string();
+        assert_eq!(
+            url("https://example.com"),
+            "https://example.com/sitemap.xml"
+        );
+        assert_eq!(
+            url("https://example.com/news-sitemap.xml"),
+            "https://example.com/news-sitemap.xml"
+        );
+    }
+}
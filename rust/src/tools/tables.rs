@@ -0,0 +1,198 @@
+//! Converting HTML tables to GitHub-flavored markdown tables.
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// The start of a row, whose end tag is often left out.
+static ROW: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)<tr\b[^>]*>").unwrap());
+
+/// The start of a cell, whose end tag is often left out.
+static CELL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)<t[dh]\b([^>]*)>").unwrap());
+
+static CAPTION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<caption[^>]*>(.*?)</caption>").unwrap());
+
+static TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]+>").unwrap());
+
+static WHITESPACE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s+").unwrap());
+
+/// A cell's text: tags removed, whitespace collapsed and pipes escaped.
+/// Entities are left for the final pass over the page to decode.
+fn cell_text(html: &str) -> String {
+    let text = TAG.replace_all(html, " ");
+    WHITESPACE
+        .replace_all(&text, " ")
+        .trim()
+        .replace('|', "\\|")
+}
+
+/// A `colspan` or `rowspan` attribute, 1 if missing or invalid.
+fn span(attrs: &str, name: &str) -> usize {
+    let pattern = format!(r#"(?i)\b{}\s*=\s*["']?(\d+)"#, name);
+    Regex::new(&pattern)
+        .unwrap()
+        .captures(attrs)
+        .and_then(|c| c[1].parse().ok())
+        .unwrap_or(1)
+        .clamp(1, 100)
+}
+
+/// The text of each cell in each row of a table, with the cells a colspan
+/// or rowspan covers left blank.
+fn table_rows(html: &str) -> Vec<Vec<String>> {
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    // Rows still covered by a rowspan from above, by column
+    let mut pending: Vec<usize> = Vec::new();
+
+    let row_starts: Vec<_> = ROW.find_iter(html).collect();
+    for (i, start) in row_starts.iter().enumerate() {
+        let end = row_starts
+            .get(i + 1)
+            .map_or(html.len(), |next| next.start());
+        let row_html = &html[start.end()..end];
+        let mut row = Vec::new();
+
+        let cells: Vec<_> = CELL.captures_iter(row_html).collect();
+        for (j, cell) in cells.iter().enumerate() {
+            let tag = cell.get(0).unwrap();
+            let end = cells
+                .get(j + 1)
+                .map_or(row_html.len(), |next| next.get(0).unwrap().start());
+            let text = cell_text(&row_html[tag.end()..end]);
+
+            while pending.get(row.len()).is_some_and(|&rows| rows > 0) {
+                pending[row.len()] -= 1;
+                row.push(String::new());
+            }
+            let (colspan, rowspan) = (span(&cell[1], "colspan"), span(&cell[1], "rowspan"));
+            for k in 0..colspan {
+                let column = row.len();
+                if pending.len() <= column {
+                    pending.resize(column + 1, 0);
+                }
+                pending[column] = rowspan - 1;
+                row.push(if k == 0 { text.clone() } else { String::new() });
+            }
+        }
+        // Columns at the end still covered from above
+        while row.len() < pending.len() {
+            if pending[row.len()] > 0 {
+                pending[row.len()] -= 1;
+            }
+            row.push(String::new());
+        }
+
+        if !cells.is_empty() {
+            rows.push(row);
+        }
+    }
+    rows
+}
+
+/// A table as markdown. The first row is the header, usually of `<th>`
+/// cells, and is used even if it is not, since a markdown table needs one.
+fn table_to_markdown(html: &str) -> String {
+    let mut rows = table_rows(html);
+    if rows.is_empty() {
+        return flatten_table(html);
+    }
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    for row in &mut rows {
+        row.resize(width, String::new());
+    }
+
+    let mut lines = Vec::new();
+    if let Some(caption) = CAPTION.captures(html) {
+        let caption = cell_text(&caption[1]);
+        if !caption.is_empty() {
+            lines.push(caption);
+            lines.push(String::new());
+        }
+    }
+    let line = |cells: &[String]| format!("| {} |", cells.join(" | "));
+    lines.push(line(&rows[0]));
+    lines.push(line(&vec!["---".to_string(); width]));
+    lines.extend(rows[1..].iter().map(|row| line(row)));
+    format!("\n\n{}\n\n", lines.join("\n"))
+}
+
+/// A table as plain text, its cells separated by spaces, for a table
+/// nested in another's cell, where a markdown table cannot go.
+fn flatten_table(html: &str) -> String {
+    let text = table_rows(html)
+        .iter()
+        .flatten()
+        .filter(|cell| !cell.is_empty())
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(" {} ", text)
+}
+
+/// Convert each `<table>` in `html` to a markdown table. Tables nested in
+/// another are flattened to text inside the outer table's cell.
+pub(super) fn convert_tables(html: &str) -> String {
+    let mut html = html.to_string();
+    loop {
+        // ASCII lowercasing keeps byte offsets
+        let lower = html.to_ascii_lowercase();
+        let Some(close) = lower.find("</table") else {
+            break;
+        };
+        let close_end = lower[close..]
+            .find('>')
+            .map_or(lower.len(), |i| close + i + 1);
+        // The innermost table is the last one opened before the first close
+        let Some(open) = lower[..close].rfind("<table") else {
+            // A stray end tag
+            html.replace_range(close..close_end, "");
+            continue;
+        };
+        let content_start = lower[open..close].find('>').map_or(close, |i| open + i + 1);
+        let nested =
+            lower[..open].matches("<table").count() > lower[..open].matches("</table").count();
+        let content = &html[content_start..close];
+        let replacement = if nested {
+            flatten_table(content)
+        } else {
+            table_to_markdown(content)
+        };
+        html.replace_range(open..close_end, &replacement);
+    }
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simple_table() {
+        let html = "<table><caption>Plans</caption>\
+                    <tr><th>Plan</th><th>Price</th></tr>\
+                    <tr><td>Free <b>tier</b></td><td>$0</td></tr>\
+                    <tr><td>Pro | Team<td>$10</table>";
+        assert_eq!(
+            convert_tables(html),
+            "\n\nPlans\n\n| Plan | Price |\n| --- | --- |\n| Free tier | $0 |\n| Pro \\| Team | $10 |\n\n"
+        );
+    }
+
+    #[test]
+    fn test_spans() {
+        let html = "<table><tr><th colspan=2>Name</th><th>Age</th></tr>\
+                    <tr><td rowspan=\"2\">A</td><td>B</td><td>1</td></tr>\
+                    <tr><td>C</td><td>2</td></tr></table>";
+        assert_eq!(
+            convert_tables(html),
+            "\n\n| Name |  | Age |\n| --- | --- | --- |\n| A | B | 1 |\n|  | C | 2 |\n\n"
+        );
+    }
+
+    #[test]
+    fn test_nested_table() {
+        let html = "<TABLE><tr><th>Outer</th></tr>\
+                    <tr><td><table><tr><td>x</td><td>y</td></tr></table></td></tr></TABLE>";
+        assert_eq!(convert_tables(html), "\n\n| Outer |\n| --- |\n| x y |\n\n");
+    }
+}
@@ -0,0 +1,105 @@
+//! Estimating how many tokens of a model's context a text takes up, to cut
+//! fetched pages to a token budget rather than a number of characters.
+
+use super::web::TRUNCATION_MARKER;
+
+/// The name of the tokenizer counts are estimated with.
+#[cfg(feature = "tiktoken")]
+pub(super) const TOKENIZER: &str = "cl100k_base";
+#[cfg(not(feature = "tiktoken"))]
+pub(super) const TOKENIZER: &str = "bytes/4";
+
+/// The number of tokens `text` is estimated to take, with `cl100k_base`.
+#[cfg(feature = "tiktoken")]
+pub(super) fn estimate_tokens(text: &str) -> usize {
+    tiktoken_rs::cl100k_base_singleton()
+        .encode_ordinary(text)
+        .len()
+}
+
+/// The number of tokens `text` is estimated to take, at four bytes of
+/// UTF-8 a token. CJK text, at three bytes a character, comes out at most
+/// tokens per character.
+#[cfg(not(feature = "tiktoken"))]
+pub(super) fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+/// Cut `text` to at most `max_tokens` estimated tokens, ending in the
+/// truncation marker, at the last paragraph break if that keeps at least
+/// half of what fits. Returns `None` if it fits. `max_tokens` must be
+/// positive.
+pub(super) fn truncate_tokens(text: &str, max_tokens: usize) -> Option<String> {
+    if estimate_tokens(text) <= max_tokens {
+        return None;
+    }
+    let fits = |end: usize| {
+        let mut cut = text[..end].to_string();
+        cut.push(TRUNCATION_MARKER);
+        estimate_tokens(&cut) <= max_tokens
+    };
+    // The longest prefix that fits with the marker, ending between
+    // characters. The whole text doesn't fit, so the last boundary is the
+    // start of its last character.
+    let boundaries: Vec<usize> = text.char_indices().map(|(i, _)| i).collect();
+    let (mut low, mut high) = (0, boundaries.len() - 1);
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        if fits(boundaries[mid]) {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    let end = boundaries[low];
+    let end = match text[..end].rfind("\n\n") {
+        Some(paragraph) if paragraph >= end / 2 => paragraph,
+        _ => end,
+    };
+    let mut truncated = text[..end].trim_end().to_string();
+    truncated.push(TRUNCATION_MARKER);
+    Some(truncated)
+}
+
+#[cfg(all(test, not(feature = "tiktoken")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("hello world"), 3);
+        // Three bytes a character
+        assert_eq!(estimate_tokens("こんにちは、世界"), 6);
+    }
+
+    #[test]
+    fn test_truncate_tokens() {
+        assert_eq!(truncate_tokens("short", 10), None);
+
+        let text = format!("{}\n\n{}", "a".repeat(30), "b".repeat(30));
+        let truncated = truncate_tokens(&text, 10).unwrap();
+        assert_eq!(truncated, format!("{}…", "a".repeat(30)));
+
+        // Without a paragraph break late enough, cut mid-text
+        let text = "世".repeat(40);
+        let truncated = truncate_tokens(&text, 10).unwrap();
+        assert_eq!(truncated, format!("{}…", "世".repeat(12)));
+        assert!(estimate_tokens(&truncated) <= 10);
+    }
+
+    #[test]
+    fn test_truncate_tokens_multibyte_last_char() {
+        let text = format!("{}😀", "a".repeat(97));
+        let truncated = truncate_tokens(&text, 25).unwrap();
+        assert_eq!(truncated, format!("{}…", "a".repeat(97)));
+        assert!(estimate_tokens(&truncated) <= 25);
+    }
+
+    #[test]
+    fn test_truncate_tokens_single_char() {
+        assert_eq!(truncate_tokens("a", 1), None);
+        assert_eq!(truncate_tokens("😀", 1), None);
+        assert_eq!(truncate_tokens("😀😀😀😀😀", 1).as_deref(), Some("…"));
+    }
+}
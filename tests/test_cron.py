@@ -0,0 +1,2087 @@
+"""Tests for the cron module, run against the Rust extension and the Python fallback."""
+
+import pytest
+
+from debot.cron import CronJob, CronJobState, CronPayload, CronSchedule, CronService, _service_py, _types_py
+
+# The Python fallback's counterpart of each name imported from debot.cron
+FALLBACK = {
+    "CronJob": _types_py.CronJob,
+    "CronJobState": _types_py.CronJobState,
+    "CronPayload": _types_py.CronPayload,
+    "CronSchedule": _types_py.CronSchedule,
+    "CronService": _service_py.CronService,
+}
+
+# 2025-01-01T00:00:00Z
+JAN_1_MS = 1735689600000
+HOUR_MS = 60 * 60 * 1000
+DAY_MS = 24 * HOUR_MS
+
+
+@pytest.fixture(autouse=True, params=["rust", "python"])
+def implementation(request, monkeypatch):
+    """Run each test against both implementations."""
+    if request.param == "python":
+        for name, fallback in FALLBACK.items():
+            monkeypatch.setitem(globals(), name, fallback)
+    elif CronService is _service_py.CronService:
+        pytest.skip("debot_rust is not installed")
+    return request.param
+
+
+class TestPreviewSchedule:
+    """Tests for CronService.preview_schedule."""
+
+    def test_at_future(self):
+        """An `at` schedule yields its single fire time."""
+        schedule = CronSchedule(kind="at", at_ms=JAN_1_MS + HOUR_MS)
+        runs = CronService.preview_schedule(schedule, count=5, from_ms=JAN_1_MS)
+        assert runs == [JAN_1_MS + HOUR_MS]
+
+    def test_at_past(self):
+        """An `at` schedule in the past yields nothing."""
+        schedule = CronSchedule(kind="at", at_ms=JAN_1_MS - HOUR_MS)
+        assert CronService.preview_schedule(schedule, from_ms=JAN_1_MS) == []
+
+    def test_every(self):
+        """An `every` schedule yields evenly spaced fire times."""
+        schedule = CronSchedule(kind="every", every_ms=HOUR_MS)
+        runs = CronService.preview_schedule(schedule, count=3, from_ms=JAN_1_MS)
+        assert runs == [JAN_1_MS + HOUR_MS, JAN_1_MS + 2 * HOUR_MS, JAN_1_MS + 3 * HOUR_MS]
+
+    def test_cron_utc(self):
+        """A 5-field cron expression is evaluated in UTC by default."""
+        schedule = CronSchedule(kind="cron", expr="0 9 * * *")
+        runs = CronService.preview_schedule(schedule, count=2, from_ms=JAN_1_MS)
+        assert runs == [JAN_1_MS + 9 * HOUR_MS, JAN_1_MS + 9 * HOUR_MS + DAY_MS]
+
+    def test_cron_with_tz(self):
+        """The schedule's tz is honored for cron expressions."""
+        schedule = CronSchedule(kind="cron", expr="0 9 * * *", tz="Europe/Berlin")
+        runs = CronService.preview_schedule(schedule, count=1, from_ms=JAN_1_MS)
+        # 09:00 CET is 08:00 UTC
+        assert runs == [JAN_1_MS + 8 * HOUR_MS]
+
+    def test_daily_keeps_local_time_across_dst(self):
+        """A daily 08:00 job stays at 08:00 local when DST starts."""
+        schedule = CronSchedule(kind="daily", hour=8, tz="America/New_York")
+        # 2025-03-08T12:00Z, the day before spring-forward
+        from_ms = 1741435200000
+        runs = CronService.preview_schedule(schedule, count=2, from_ms=from_ms)
+        # 08:00 EST (13:00Z) on Mar 8, then 08:00 EDT (12:00Z) on Mar 9
+        assert runs == [from_ms + HOUR_MS, from_ms + DAY_MS]
+
+    def test_daily_spring_forward_gap(self):
+        """A local time skipped by DST is shifted forward by the gap."""
+        schedule = CronSchedule(kind="daily", hour=2, minute=30, tz="America/New_York")
+        # 2025-03-08T12:00Z; 02:30 does not exist on 2025-03-09
+        from_ms = 1741435200000
+        runs = CronService.preview_schedule(schedule, count=2, from_ms=from_ms)
+        # 03:30 EDT (07:30Z) on Mar 9, then 02:30 EDT (06:30Z) on Mar 10
+        half = HOUR_MS // 2
+        assert runs == [from_ms + 19 * HOUR_MS + half, from_ms + 42 * HOUR_MS + half]
+
+    def test_daily_fall_back_uses_first_occurrence(self):
+        """A repeated local time fires once, at its first occurrence."""
+        schedule = CronSchedule(kind="daily", hour=1, minute=30, tz="America/New_York")
+        # 2025-11-02T00:00Z; 01:30 happens twice that night
+        from_ms = 1762041600000
+        runs = CronService.preview_schedule(schedule, count=1, from_ms=from_ms)
+        # 01:30 EDT is 05:30Z
+        assert runs == [from_ms + 5 * HOUR_MS + HOUR_MS // 2]
+
+    def test_weekly_mask(self):
+        """Weekly schedules fire only on the masked weekdays."""
+        # Monday (bit 0) and Friday (bit 4); Jan 1 2025 is a Wednesday
+        schedule = CronSchedule(kind="weekly", hour=9, weekdays=0b10001)
+        runs = CronService.preview_schedule(schedule, count=3, from_ms=JAN_1_MS)
+        assert runs == [
+            JAN_1_MS + 2 * DAY_MS + 9 * HOUR_MS,
+            JAN_1_MS + 5 * DAY_MS + 9 * HOUR_MS,
+            JAN_1_MS + 9 * DAY_MS + 9 * HOUR_MS,
+        ]
+
+    def test_daily_weekly_invalid(self):
+        """Missing hours and empty weekday masks are rejected."""
+        with pytest.raises(ValueError, match="hour"):
+            CronService.preview_schedule(CronSchedule(kind="daily"))
+        with pytest.raises(ValueError, match="weekdays"):
+            CronService.preview_schedule(CronSchedule(kind="weekly", hour=9, weekdays=0))
+
+    def test_cron_invalid(self):
+        """Invalid expressions and timezones raise ValueError."""
+        with pytest.raises(ValueError):
+            CronService.preview_schedule(CronSchedule(kind="cron", expr="not a cron"))
+        with pytest.raises(ValueError):
+            CronService.preview_schedule(CronSchedule(kind="cron", expr="0 9 * * *", tz="Mars/Base"))
+
+
+@pytest.fixture
+def service(tmp_path):
+    return CronService(tmp_path / "cron" / "jobs.json")
+
+
+def every_hour():
+    return CronSchedule(kind="every", every_ms=HOUR_MS)
+
+
+class TestScheduleValidation:
+    """Tests for schedule checks when jobs are added or updated."""
+
+    async def test_rejects_invalid_schedules(self, service):
+        """add_job, upsert_job and update_job refuse what preview_schedule refuses."""
+        with pytest.raises(ValueError, match="hour"):
+            await service.add_job("a", CronSchedule(kind="daily"), "a")
+        with pytest.raises(ValueError, match="hour"):
+            await service.upsert_job("a", CronSchedule(kind="weekly", weekdays=1), "a")
+        job = await service.add_job("a", every_hour(), "a")
+        with pytest.raises(ValueError, match="weekdays"):
+            await service.update_job(job.id, schedule=CronSchedule(kind="weekly", hour=9, weekdays=0))
+        assert [j.name for j in await service.list_jobs()] == ["a"]
+
+
+class TestAtTime:
+    """Tests for ISO-8601 `at` times on one-shot schedules."""
+
+    def test_offset_string(self):
+        """Strings with an offset are converted to epoch milliseconds."""
+        schedule = CronSchedule(kind="at", at="2099-07-01T09:00:00+02:00")
+        assert schedule.at_ms == 4086572400000
+
+    def test_utc_string(self):
+        """A trailing Z means UTC."""
+        schedule = CronSchedule(kind="at", at="2099-07-01T07:00:00Z")
+        assert schedule.at_ms == 4086572400000
+
+    def test_naive_string_uses_tz(self):
+        """Naive strings are read as wall-clock time in the schedule's tz."""
+        schedule = CronSchedule(kind="at", at="2099-07-01 09:00", tz="Europe/Berlin")
+        assert schedule.at_ms == 4086572400000
+        assert CronSchedule(kind="at", at="2099-07-01T07:00").at_ms == 4086572400000
+
+    def test_epoch_ms(self):
+        """Integers are taken as epoch milliseconds."""
+        assert CronSchedule(kind="at", at=4086572400000).at_ms == 4086572400000
+
+    def test_rejects_invalid(self):
+        """Unparsable strings, past times, and at with at_ms raise ValueError."""
+        with pytest.raises(ValueError, match="ISO-8601"):
+            CronSchedule(kind="at", at="next tuesday")
+        with pytest.raises(ValueError, match="past"):
+            CronSchedule(kind="at", at="2020-01-01T00:00:00Z")
+        with pytest.raises(ValueError, match="not both"):
+            CronSchedule(kind="at", at=4086572400000, at_ms=4086572400000)
+
+
+class TestJobTags:
+    """Tests for job tags."""
+
+    async def test_add_job_with_tags(self, service):
+        """Tags set on add_job are visible on the job."""
+        job = await service.add_job("digest", every_hour(), "send digest", tags=["agent:a", "daily"])
+        assert job.tags == ["agent:a", "daily"]
+
+    async def test_list_jobs_filters_by_all_tags(self, service):
+        """list_jobs(tags=...) returns only jobs carrying every requested tag."""
+        await service.add_job("a", every_hour(), "a", tags=["agent:a", "daily"])
+        await service.add_job("b", every_hour(), "b", tags=["agent:a"])
+        await service.add_job("c", every_hour(), "c", tags=["agent:b", "daily"])
+
+        names = sorted(j.name for j in await service.list_jobs(tags=["agent:a"]))
+        assert names == ["a", "b"]
+        names = sorted(j.name for j in await service.list_jobs(tags=["agent:a", "daily"]))
+        assert names == ["a"]
+        assert len(await service.list_jobs()) == 3
+
+    async def test_update_job_tags(self, service):
+        """update_job replaces the tag list."""
+        job = await service.add_job("a", every_hour(), "a", tags=["old"])
+        updated = await service.update_job(job.id, tags=["new"])
+        assert updated.tags == ["new"]
+        assert await service.update_job("missing", tags=["x"]) is None
+
+    async def test_remove_jobs_by_tag(self, service):
+        """remove_jobs_by_tag removes every matching job and reports their ids."""
+        a = await service.add_job("a", every_hour(), "a", tags=["agent:a"])
+        b = await service.add_job("b", every_hour(), "b", tags=["agent:a", "x"])
+        await service.add_job("c", every_hour(), "c", tags=["agent:b"])
+
+        removed = await service.remove_jobs_by_tag("agent:a")
+        assert sorted(removed) == sorted([a.id, b.id])
+        assert [j.name for j in await service.list_jobs()] == ["c"]
+
+    async def test_tags_persisted(self, service, tmp_path):
+        """Tags are written to the store JSON."""
+        import json
+
+        await service.add_job("a", every_hour(), "a", tags=["persisted"])
+        await service.flush()
+        data = json.loads((tmp_path / "cron" / "jobs.json").read_text())
+        assert data["jobs"][0]["tags"] == ["persisted"]
+
+
+class TestListJobsPaging:
+    """Tests for searching, sorting and paging in list_jobs."""
+
+    async def add_jobs(self, service):
+        for name in ["delta", "Alpha", "charlie", "bravo", "alphabet"]:
+            await service.add_job(name, every_hour(), name)
+
+    async def test_name_contains(self, service):
+        """name_contains matches substrings regardless of case."""
+        await self.add_jobs(service)
+        names = sorted(j.name for j in await service.list_jobs(name_contains="ALPHA"))
+        assert names == ["Alpha", "alphabet"]
+
+    async def test_sort_by(self, service):
+        """sort_by orders by name, creation or last run."""
+        await self.add_jobs(service)
+        by_name = [j.name for j in await service.list_jobs(sort_by="name")]
+        assert by_name == ["Alpha", "alphabet", "bravo", "charlie", "delta"]
+        by_created = [j.name for j in await service.list_jobs(sort_by="created")]
+        assert by_created == ["delta", "Alpha", "charlie", "bravo", "alphabet"]
+
+        bravo = (await service.list_jobs(name_contains="bravo"))[0]
+        await service.run_job(bravo.id)
+        assert (await service.list_jobs(sort_by="last_run"))[0].name == "bravo"
+        with pytest.raises(ValueError):
+            await service.list_jobs(sort_by="size")
+
+    async def test_pages_with_total(self, service):
+        """offset/limit select a page; with_total also counts all matches."""
+        await self.add_jobs(service)
+        page, total = await service.list_jobs(sort_by="name", offset=1, limit=2, with_total=True)
+        assert [j.name for j in page] == ["alphabet", "bravo"]
+        assert total == 5
+        page, total = await service.list_jobs(sort_by="name", offset=4, limit=2, with_total=True)
+        assert [j.name for j in page] == ["delta"]
+        page, total = await service.list_jobs(name_contains="a", offset=10, with_total=True)
+        assert page == []
+        assert total == 5
+
+
+class TestExportImport:
+    """Tests for export_jobs / import_jobs."""
+
+    async def test_round_trip(self, service, tmp_path):
+        """Exported jobs import into a fresh service with state intact."""
+        import json
+
+        await service.add_job("a", every_hour(), "hello", tags=["t"])
+        exported = await service.export_jobs()
+        data = json.loads(exported)
+        assert data["version"] == 10
+        assert data["jobs"][0]["name"] == "a"
+        assert "state" in data["jobs"][0]
+
+        other = CronService(tmp_path / "other.json")
+        imported = await other.import_jobs(exported)
+        assert len(imported) == 1
+        assert imported[0].payload.message == "hello"
+        assert imported[0].tags == ["t"]
+        assert imported[0].state.next_run_at_ms is not None
+
+    async def test_merge_regenerates_colliding_ids(self, service):
+        """Merging jobs whose ids already exist assigns new ids."""
+        job = await service.add_job("a", every_hour(), "a")
+        imported = await service.import_jobs(await service.export_jobs(), merge=True)
+        assert imported[0].id != job.id
+        assert len(await service.list_jobs()) == 2
+
+    async def test_replace(self, service):
+        """merge=False replaces the existing job set."""
+        await service.add_job("a", every_hour(), "a")
+        exported = await service.export_jobs()
+        await service.add_job("b", every_hour(), "b")
+        await service.import_jobs(exported, merge=False)
+        assert [j.name for j in await service.list_jobs()] == ["a"]
+
+    async def test_rejects_unknown_version(self, service):
+        """Payloads with an unknown version raise ValueError."""
+        with pytest.raises(ValueError, match="version"):
+            await service.import_jobs('{"version": 99, "jobs": []}')
+
+    async def test_rejects_invalid_schedule(self, service):
+        """Jobs with malformed schedules are rejected."""
+        import json
+
+        await service.add_job("a", every_hour(), "a")
+        data = json.loads(await service.export_jobs())
+        data["jobs"][0]["schedule"] = {"kind": "cron", "expr": "bogus"}
+        with pytest.raises(ValueError):
+            await service.import_jobs(json.dumps(data))
+
+
+V1_STORE = {
+    "version": 1,
+    "jobs": [
+        {
+            "id": "abcd1234",
+            "name": "legacy",
+            "enabled": True,
+            "schedule": {"kind": "every", "everyMs": HOUR_MS, "atMs": None, "expr": None, "tz": None},
+            "payload": {"kind": "agent_turn", "message": "hi", "deliver": False, "channel": None, "to": None},
+            "state": {},
+            "createdAtMs": 0,
+            "updatedAtMs": 0,
+            "deleteAfterRun": False,
+        }
+    ],
+}
+
+
+class TestExportIcs:
+    """Tests for iCalendar export."""
+
+    def events(self, ics):
+        return ics.split("BEGIN:VEVENT")[1:]
+
+    async def test_renders_calendar(self, service):
+        """at, every and cron jobs render as single, recurring and expanded events."""
+        await service.add_job("once", CronSchedule(kind="at", at_ms=4086572400000), "hi, there")
+        every = await service.add_job("poll", CronSchedule(kind="every", every_ms=2 * HOUR_MS), "p")
+        cron = await service.add_job("tick", CronSchedule(kind="cron", expr="0 9 * * *"), "t")
+
+        ics = await service.export_ics(occurrences=3)
+        assert ics.startswith("BEGIN:VCALENDAR\r\n")
+        assert ics.endswith("END:VCALENDAR\r\n")
+        events = self.events(ics)
+        assert len(events) == 5
+
+        once = next(e for e in events if "SUMMARY:once" in e)
+        assert "DTSTART:20990701T070000Z" in once
+        assert "DESCRIPTION:hi\\, there" in once
+        assert "RRULE" not in once
+
+        poll = next(e for e in events if "SUMMARY:poll" in e)
+        assert f"UID:{every.id}@debot" in poll
+        assert "RRULE:FREQ=HOURLY;INTERVAL=2" in poll
+
+        ticks = [e for e in events if "SUMMARY:tick" in e]
+        assert len(ticks) == 3
+        assert all(f"UID:{cron.id}-" in e for e in ticks)
+
+    async def test_stable_uids_and_disabled_jobs(self, service):
+        """UIDs are stable across exports and disabled jobs are left out."""
+        await service.add_job("tick", CronSchedule(kind="cron", expr="0 9 * * *"), "t")
+        off = await service.add_job("off", every_hour(), "o")
+        await service.enable_job(off.id, enabled=False)
+
+        def uids(ics):
+            return [line for line in ics.split("\r\n") if line.startswith("UID:")]
+
+        first = await service.export_ics()
+        assert uids(first) == uids(await service.export_ics())
+        assert "SUMMARY:off" not in first
+
+    async def test_folds_long_lines(self, service):
+        """Content lines are folded at 75 octets."""
+        await service.add_job("a", every_hour(), "x" * 200)
+        ics = await service.export_ics()
+        assert all(len(line.encode()) <= 75 for line in ics.split("\r\n"))
+        assert "x" * 200 in ics.replace("\r\n ", "")
+
+
+class TestStoreVersioning:
+    """Tests for store schema versioning and migration."""
+
+    async def test_migrates_v1_store(self, tmp_path):
+        """A v1 store is loaded and rewritten at the current version."""
+        import json
+
+        path = tmp_path / "jobs.json"
+        path.write_text(json.dumps(V1_STORE))
+
+        service = CronService(path)
+        jobs = await service.list_jobs()
+        assert [j.id for j in jobs] == ["abcd1234"]
+        assert jobs[0].tags == []
+
+        data = json.loads(path.read_text())
+        assert data["version"] == 10
+        assert data["jobs"][0]["tags"] == []
+
+    async def test_migrates_v9_store(self, tmp_path):
+        """A v9 store, without run statistics or snoozes, is rewritten at v10."""
+        import json
+
+        path = tmp_path / "jobs.json"
+        path.write_text(json.dumps(dict(V1_STORE, version=9)))
+
+        service = CronService(path)
+        job = (await service.list_jobs())[0]
+        assert job.state.run_count == 0
+        assert job.state.snoozed_until_ms is None
+
+        data = json.loads(path.read_text())
+        assert data["version"] == 10
+        assert data["jobs"][0]["state"]["runCount"] == 0
+
+    async def test_newer_version_left_untouched(self, tmp_path):
+        """A store from a newer version is reported and never overwritten."""
+        import json
+
+        path = tmp_path / "jobs.json"
+        original = json.dumps({"version": 99, "jobs": [{"future": True}]})
+        path.write_text(original)
+
+        errors = []
+        service = CronService(path, on_load_error=errors.append)
+        assert len(errors) == 1
+        assert "version 99" in errors[0]
+
+        await service.add_job("a", every_hour(), "a")
+        assert path.read_text() == original
+
+    async def test_corrupt_store_left_untouched(self, tmp_path):
+        """A corrupt store is reported and not replaced by an empty one."""
+        path = tmp_path / "jobs.json"
+        path.write_text("{not json")
+
+        errors = []
+        service = CronService(path, on_load_error=errors.append)
+        await service.add_job("a", every_hour(), "a")
+        assert errors
+        assert path.read_text() == "{not json"
+
+    async def test_store_problems_are_events(self, tmp_path, capfd):
+        """With on_event set, migrations and load failures are events, not stderr lines."""
+        import asyncio
+        import json
+
+        migrated = tmp_path / "old.json"
+        migrated.write_text(json.dumps(V1_STORE))
+        migrated_events = []
+        CronService(migrated, on_event=migrated_events.append)
+        corrupt = tmp_path / "corrupt.json"
+        corrupt.write_text("{not json")
+        corrupt_events = []
+        CronService(corrupt, on_event=corrupt_events.append)
+        await asyncio.sleep(0.1)
+
+        assert [(e["event"], e["from_version"], e["to_version"]) for e in migrated_events] == [
+            ("store_migrated", 1, 10)
+        ]
+        assert [e["event"] for e in corrupt_events] == ["store_load_failed"]
+        assert "corrupt.json" in corrupt_events[0]["error"]
+        err = capfd.readouterr().err.lower()
+        assert "migrated" not in err
+        assert "failed to load" not in err
+
+    async def test_jobs_loaded_at_construction(self, service, tmp_path):
+        """Jobs persisted by one instance are visible to the next without start()."""
+        await service.add_job("a", every_hour(), "a")
+        await service.flush()
+        other = CronService(tmp_path / "cron" / "jobs.json")
+        assert [j.name for j in await other.list_jobs()] == ["a"]
+
+
+class TestStoreWatch:
+    """Tests for reloading the store on external changes."""
+
+    async def test_reloads_external_edit(self, tmp_path):
+        """Jobs appended to the store by another writer are picked up."""
+        import asyncio
+        import json
+
+        path = tmp_path / "jobs.json"
+        service = CronService(path, watch_store=True)
+        job = await service.add_job("mine", every_hour(), "a")
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(0.2)
+            # Settle the service's own debounced write before editing
+            await service.flush()
+            before = (await service.list_jobs())[0].state.next_run_at_ms
+
+            data = json.loads(path.read_text())
+            added = dict(data["jobs"][0], id="external1", name="theirs")
+            data["jobs"].append(added)
+            path.write_text(json.dumps(data))
+
+            await asyncio.sleep(3)
+            jobs = {j.id: j for j in await service.list_jobs()}
+            assert set(jobs) == {job.id, "external1"}
+            # The unchanged job keeps its in-memory runtime state
+            assert jobs[job.id].state.next_run_at_ms == before
+        finally:
+            service.stop()
+            task.cancel()
+
+    async def test_keeps_jobs_created_since_last_save(self, tmp_path):
+        """A reload does not drop jobs that never made it to disk."""
+        import asyncio
+        import json
+
+        path = tmp_path / "jobs.json"
+        path.write_text("not json")
+        service = CronService(path, watch_store=True, on_load_error=lambda message: None)
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(0.2)
+            # The store is read-only until it is repaired, so this job is not saved
+            job = await service.add_job("mine", every_hour(), "a")
+            await asyncio.sleep(0.5)
+            other = dict(job.to_dict(), id="external1", name="theirs")
+            path.write_text(json.dumps({"version": 1, "jobs": [other]}))
+
+            await asyncio.sleep(3)
+            assert {j.name for j in await service.list_jobs()} == {"mine", "theirs"}
+            await service.flush()
+            assert {j["name"] for j in json.loads(path.read_text())["jobs"]} == {"mine", "theirs"}
+        finally:
+            service.stop()
+            task.cancel()
+
+
+class TestLifecycleEvents:
+    """Tests for the on_event lifecycle callback."""
+
+    async def test_job_events(self, tmp_path):
+        """Running a job emits started/finished events with the job id."""
+        import asyncio
+
+        events = []
+
+        async def on_job(job):
+            return "done"
+
+        service = CronService(tmp_path / "jobs.json", on_job=on_job, on_event=events.append)
+        job = await service.add_job("a", every_hour(), "a")
+        await service.run_job(job.id)
+        await service.flush()
+        await asyncio.sleep(0.2)
+
+        kinds = [e["event"] for e in events]
+        assert kinds.index("job_started") < kinds.index("job_finished")
+        assert "store_saved" in kinds
+        started = next(e for e in events if e["event"] == "job_started")
+        assert started["job_id"] == job.id
+        assert started["ts_ms"] > 0
+
+    async def test_job_failed_event(self, tmp_path):
+        """A raising callback produces a job_failed event carrying the error."""
+        import asyncio
+
+        events = []
+
+        async def on_job(job):
+            raise RuntimeError("boom")
+
+        service = CronService(tmp_path / "jobs.json", on_job=on_job, on_event=events.append)
+        job = await service.add_job("a", every_hour(), "a")
+        await service.run_job(job.id)
+        await asyncio.sleep(0.2)
+
+        failed = [e for e in events if e["event"] == "job_failed"]
+        assert len(failed) == 1
+        assert "boom" in failed[0]["error"]
+        assert (await service.list_jobs())[0].state.last_status == "error"
+
+    async def test_event_callback_errors_counted(self, tmp_path):
+        """Exceptions from on_event are swallowed and counted."""
+        import asyncio
+
+        def on_event(event):
+            raise ValueError("bad hook")
+
+        service = CronService(tmp_path / "jobs.json", on_event=on_event)
+        await service.add_job("a", every_hour(), "a")
+        await asyncio.sleep(0.2)
+        assert service.status()["event_errors"] >= 1
+
+
+class TestBulkOperations:
+    """Tests for enable_jobs / remove_jobs / clear_jobs."""
+
+    async def test_enable_jobs_reports_affected(self, service):
+        """Only ids that exist are reported; next runs follow the enabled flag."""
+        a = await service.add_job("a", every_hour(), "a")
+        b = await service.add_job("b", every_hour(), "b")
+
+        affected = await service.enable_jobs([a.id, b.id, "missing"], enabled=False)
+        assert sorted(affected) == sorted([a.id, b.id])
+        jobs = await service.list_jobs(include_disabled=True)
+        assert all(not j.enabled and j.state.next_run_at_ms is None for j in jobs)
+
+        assert await service.enable_jobs([a.id]) == [a.id]
+        enabled = await service.list_jobs()
+        assert [j.id for j in enabled] == [a.id]
+        assert enabled[0].state.next_run_at_ms is not None
+
+    async def test_remove_jobs(self, service):
+        """remove_jobs removes the listed jobs and skips unknown ids."""
+        a = await service.add_job("a", every_hour(), "a")
+        await service.add_job("b", every_hour(), "b")
+
+        assert await service.remove_jobs([a.id, "missing"]) == [a.id]
+        assert [j.name for j in await service.list_jobs()] == ["b"]
+        assert await service.remove_jobs(["missing"]) == []
+
+    async def test_clear_jobs(self, service):
+        """clear_jobs removes tagged jobs, or every job without a tag."""
+        await service.add_job("a", every_hour(), "a", tags=["x"])
+        b = await service.add_job("b", every_hour(), "b")
+
+        assert len(await service.clear_jobs(tag="x")) == 1
+        assert await service.clear_jobs() == [b.id]
+        assert await service.list_jobs(include_disabled=True) == []
+
+    async def test_saves_once(self, tmp_path):
+        """A bulk operation writes the store a single time."""
+        import asyncio
+
+        events = []
+        service = CronService(tmp_path / "jobs.json", on_event=events.append)
+        ids = [(await service.add_job(n, every_hour(), n)).id for n in "abc"]
+        await service.flush()
+        await asyncio.sleep(0.1)
+        events.clear()
+
+        await service.enable_jobs(ids, enabled=False)
+        await service.flush()
+        await asyncio.sleep(0.1)
+        assert [e["event"] for e in events].count("store_saved") == 1
+
+
+@pytest.fixture
+def http_server():
+    """Local HTTP server recording requests; responds with the status in the path."""
+    import http.server
+    import threading
+
+    requests = []
+
+    class Handler(http.server.BaseHTTPRequestHandler):
+        def _handle(self):
+            length = int(self.headers.get("Content-Length") or 0)
+            requests.append(
+                {
+                    "method": self.command,
+                    "path": self.path,
+                    "headers": self.headers,
+                    "body": self.rfile.read(length).decode(),
+                }
+            )
+            status = int(self.path.strip("/") or 200)
+            self.send_response(status)
+            self.end_headers()
+            self.wfile.write(b"response body")
+
+        do_GET = do_POST = do_PUT = _handle
+
+        def log_message(self, *args):
+            pass
+
+    server = http.server.ThreadingHTTPServer(("127.0.0.1", 0), Handler)
+    thread = threading.Thread(target=server.serve_forever, daemon=True)
+    thread.start()
+    yield f"http://127.0.0.1:{server.server_address[1]}", requests
+    server.shutdown()
+
+
+class TestWebhookPayload:
+    """Tests for webhook payloads."""
+
+    def webhook(self, url, **kwargs):
+        return CronPayload(kind="webhook", url=url, **kwargs)
+
+    async def test_rejects_invalid_url(self, service):
+        """Webhook URLs must be http(s)."""
+        with pytest.raises(ValueError, match="http"):
+            await service.add_job("w", every_hour(), "", payload=self.webhook("ftp://example.com"))
+        with pytest.raises(ValueError, match="url"):
+            await service.add_job("w", every_hour(), "", payload=CronPayload(kind="webhook"))
+
+    async def test_round_trips_through_store(self, service, tmp_path):
+        """Webhook fields are persisted and reloaded."""
+        payload = self.webhook(
+            "https://example.com/hook", method="PUT", headers={"X-Token": "t"}, body="{}"
+        )
+        await service.add_job("w", every_hour(), "", payload=payload)
+        await service.flush()
+
+        other = CronService(tmp_path / "cron" / "jobs.json")
+        loaded = (await other.list_jobs())[0].payload
+        assert loaded.kind == "webhook"
+        assert loaded.url == "https://example.com/hook"
+        assert loaded.method == "PUT"
+        assert loaded.headers == {"X-Token": "t"}
+        assert loaded.body == "{}"
+
+    async def test_executes_request(self, service, http_server):
+        """Running a webhook job sends the configured request without the agent callback."""
+        base, requests = http_server
+        called = []
+        service.set_callback(called.append)
+        payload = self.webhook(f"{base}/200", headers={"X-Token": "secret"}, body="ping")
+        job = await service.add_job("w", every_hour(), "", payload=payload)
+
+        await service.run_job(job.id)
+        assert not called
+        assert requests[0]["method"] == "POST"
+        assert requests[0]["headers"]["X-Token"] == "secret"
+        assert requests[0]["body"] == "ping"
+        assert (await service.list_jobs())[0].state.last_status == "ok"
+
+    async def test_non_2xx_is_error(self, service, http_server):
+        """Non-2xx responses mark the run as failed with the status and body."""
+        base, _ = http_server
+        job = await service.add_job("w", every_hour(), "", payload=self.webhook(f"{base}/503"))
+
+        await service.run_job(job.id)
+        state = (await service.list_jobs())[0].state
+        assert state.last_status == "error"
+        assert "503" in state.last_error
+        assert "response body" in state.last_error
+
+
+class TestShellPayload:
+    """Tests for shell payloads."""
+
+    def shell(self, command, **kwargs):
+        return CronPayload(kind="shell", command=command, **kwargs)
+
+    async def test_disabled_by_default(self, service):
+        """Shell jobs are refused unless allow_shell=True."""
+        with pytest.raises(ValueError, match="allow_shell"):
+            await service.add_job("s", every_hour(), "", payload=self.shell("echo hi"))
+
+    async def test_runs_command(self, tmp_path):
+        """The command runs in cwd and its output and exit code are recorded."""
+        service = CronService(tmp_path / "jobs.json", allow_shell=True)
+        payload = self.shell("pwd; echo oops >&2", cwd=str(tmp_path))
+        job = await service.add_job("s", every_hour(), "", payload=payload)
+
+        await service.run_job(job.id)
+        state = (await service.list_jobs())[0].state
+        assert state.last_status == "ok"
+        assert state.last_exit_code == 0
+        assert str(tmp_path) in state.last_output
+        assert "STDERR:\noops" in state.last_output
+
+    async def test_nonzero_exit_is_error(self, tmp_path):
+        """A non-zero exit code marks the run as failed."""
+        service = CronService(tmp_path / "jobs.json", allow_shell=True)
+        job = await service.add_job("s", every_hour(), "", payload=self.shell("exit 3"))
+
+        await service.run_job(job.id)
+        state = (await service.list_jobs())[0].state
+        assert state.last_status == "error"
+        assert state.last_exit_code == 3
+
+    async def test_long_output_truncated(self, tmp_path):
+        """Output past the limit is counted but not kept."""
+        service = CronService(tmp_path / "jobs.json", allow_shell=True)
+        payload = self.shell("yes | head -c 20000000")
+        job = await service.add_job("s", every_hour(), "", payload=payload)
+
+        await service.run_job(job.id)
+        state = (await service.list_jobs())[0].state
+        assert state.last_status == "ok"
+        assert state.last_output.startswith("y\ny\n")
+        assert state.last_output.endswith("... (truncated, 19996000 more chars)")
+
+    async def test_timeout(self, tmp_path):
+        """Commands exceeding timeout_ms are killed and reported."""
+        service = CronService(tmp_path / "jobs.json", allow_shell=True)
+        payload = self.shell("sleep 5", timeout_ms=100)
+        job = await service.add_job("s", every_hour(), "", payload=payload)
+
+        await service.run_job(job.id)
+        state = (await service.list_jobs())[0].state
+        assert state.last_status == "error"
+        assert "timed out" in state.last_error
+
+    async def test_stored_shell_job_not_run_without_flag(self, tmp_path):
+        """A shell job already in the store does not run when shell is disabled."""
+        path = tmp_path / "jobs.json"
+        allowed = CronService(path, allow_shell=True)
+        job = await allowed.add_job("s", every_hour(), "", payload=self.shell("echo hi"))
+        await allowed.flush()
+
+        service = CronService(path)
+        await service.run_job(job.id)
+        state = (await service.list_jobs())[0].state
+        assert state.last_status == "error"
+        assert "allow_shell" in state.last_error
+
+
+class TestRunDurations:
+    """Tests for per-job run duration tracking."""
+
+    async def test_records_durations(self, tmp_path):
+        """Each run updates last/avg duration and the run count, which persist."""
+        import asyncio
+
+        delays = [0.05, 0.15]
+
+        async def on_job(job):
+            await asyncio.sleep(delays.pop(0))
+
+        path = tmp_path / "jobs.json"
+        service = CronService(path, on_job=on_job)
+        job = await service.add_job("a", every_hour(), "a")
+        await service.run_job(job.id)
+        await service.run_job(job.id)
+        await service.flush()
+
+        state = (await service.list_jobs())[0].state
+        assert state.run_count == 2
+        assert state.last_duration_ms >= 150
+        assert 100 <= state.avg_duration_ms < state.last_duration_ms
+
+        reloaded = (await CronService(path).list_jobs())[0].state
+        assert reloaded.run_count == 2
+        assert reloaded.last_duration_ms == state.last_duration_ms
+
+    async def test_status_reports_slowest_job(self, tmp_path):
+        """status() names the slowest job among recent runs."""
+        import asyncio
+
+        async def on_job(job):
+            await asyncio.sleep(0.1 if job["job_name"] == "slow" else 0)
+
+        service = CronService(tmp_path / "jobs.json", on_job=on_job)
+        assert service.status()["slowest_job"] is None
+
+        fast = await service.add_job("fast", every_hour(), "a")
+        slow = await service.add_job("slow", every_hour(), "a")
+        await service.run_job(fast.id)
+        await service.run_job(slow.id)
+
+        slowest = service.status()["slowest_job"]
+        assert slowest["job_id"] == slow.id
+        assert slowest["duration_ms"] >= 100
+
+
+class TestAutoDisable:
+    """Tests for disabling jobs after consecutive failures."""
+
+    async def test_disables_after_threshold(self, tmp_path):
+        """A job is disabled once it fails disable_after_failures times in a row."""
+        async def on_job(job):
+            raise RuntimeError("boom")
+
+        service = CronService(tmp_path / "jobs.json", on_job=on_job)
+        job = await service.add_job("a", every_hour(), "a", disable_after_failures=2)
+
+        await service.run_job(job.id)
+        state = (await service.list_jobs())[0].state
+        assert state.consecutive_failures == 1
+
+        await service.run_job(job.id)
+        job = (await service.list_jobs(include_disabled=True))[0]
+        assert not job.enabled
+        assert job.state.next_run_at_ms is None
+        assert "Auto-disabled after 2 consecutive failures" in job.state.last_error
+        assert "boom" in job.state.last_error
+
+    async def test_service_default_and_override(self, tmp_path):
+        """The service default applies unless the job sets 0 to opt out."""
+        async def on_job(job):
+            raise RuntimeError("boom")
+
+        service = CronService(tmp_path / "jobs.json", on_job=on_job, disable_after_failures=1)
+        a = await service.add_job("a", every_hour(), "a")
+        b = await service.add_job("b", every_hour(), "b", disable_after_failures=0)
+        await service.run_job(a.id)
+        await service.run_job(b.id)
+
+        enabled = [j.name for j in await service.list_jobs()]
+        assert enabled == ["b"]
+
+    async def test_success_and_reenable_reset_counter(self, tmp_path):
+        """A successful run or re-enabling resets consecutive_failures."""
+        outcomes = [RuntimeError("x"), None, RuntimeError("y")]
+
+        async def on_job(job):
+            error = outcomes.pop(0)
+            if error:
+                raise error
+
+        service = CronService(tmp_path / "jobs.json", on_job=on_job)
+        job = await service.add_job("a", every_hour(), "a", disable_after_failures=1)
+
+        await service.run_job(job.id)
+        assert (await service.list_jobs(include_disabled=True))[0].state.consecutive_failures == 1
+
+        enabled = await service.enable_job(job.id)
+        assert enabled.state.consecutive_failures == 0
+
+        await service.run_job(job.id)
+        await service.run_job(job.id)
+        assert not (await service.list_jobs(include_disabled=True))[0].enabled
+
+
+class TestUpsertJob:
+    """Tests for upsert_job."""
+
+    async def test_creates_then_updates(self, service):
+        """The first call creates the job; later calls update it in place."""
+        job, created = await service.upsert_job("digest", every_hour(), "v1")
+        assert created
+
+        daily = CronSchedule(kind="every", every_ms=DAY_MS)
+        updated, created = await service.upsert_job("digest", daily, "v2")
+        assert not created
+        assert updated.id == job.id
+        assert updated.created_at_ms == job.created_at_ms
+        assert updated.payload.message == "v2"
+        assert updated.schedule.every_ms == DAY_MS
+        assert len(await service.list_jobs()) == 1
+
+    async def test_preserves_history(self, service):
+        """Run state survives an upsert."""
+        job, _ = await service.upsert_job("a", every_hour(), "a")
+        await service.run_job(job.id)
+        updated, _ = await service.upsert_job("a", every_hour(), "b")
+        assert updated.state.run_count == 1
+        assert updated.state.last_status == "ok"
+
+    async def test_name_match_is_exact(self, service):
+        """Names are matched case-sensitively and exactly."""
+        await service.upsert_job("Digest", every_hour(), "a")
+        _, created = await service.upsert_job("digest", every_hour(), "a")
+        assert created
+        _, created = await service.upsert_job("Digest ", every_hour(), "a")
+        assert created
+
+    async def test_disabled_job_not_matched(self, service):
+        """Disabled jobs with the same name are left alone."""
+        job, _ = await service.upsert_job("a", every_hour(), "a")
+        await service.enable_job(job.id, enabled=False)
+        new, created = await service.upsert_job("a", every_hour(), "a")
+        assert created
+        assert new.id != job.id
+
+
+class TestJobCallbacks:
+    """Tests for callbacks bound to individual jobs."""
+
+    async def test_job_callback_overrides_default(self, tmp_path):
+        """A job's own callback runs instead of the service callback."""
+        default, own = [], []
+        service = CronService(tmp_path / "jobs.json", on_job=lambda job: default.append(job["job_id"]))
+        a = await service.add_job("a", every_hour(), "a", callback=lambda job: own.append(job["job_id"]))
+        b = await service.add_job("b", every_hour(), "b")
+        await service.run_job(a.id)
+        await service.run_job(b.id)
+        assert own == [a.id]
+        assert default == [b.id]
+
+    async def test_bind_after_restart(self, tmp_path):
+        """Bindings are not persisted; unbound jobs fall back to the default."""
+        path = tmp_path / "jobs.json"
+        service = CronService(path)
+        job = await service.add_job("a", every_hour(), "a", callback=lambda job: None)
+        await service.flush()
+
+        default, own = [], []
+        restarted = CronService(path, on_job=lambda job: default.append(job["job_id"]))
+        await restarted.run_job(job.id)
+        assert default == [job.id]
+
+        assert await restarted.bind_callback(job.id, lambda job: own.append(job["job_id"]))
+        await restarted.run_job(job.id)
+        assert own == [job.id]
+
+        assert await restarted.bind_callback(job.id, None)
+        await restarted.run_job(job.id)
+        assert default == [job.id, job.id]
+        assert not await restarted.bind_callback("missing", lambda job: None)
+
+
+class TestPayloadKinds:
+    """Tests for payload kind validation and the callback argument."""
+
+    async def test_rejects_unknown_kind(self, service):
+        """Only known payload kinds are accepted."""
+        with pytest.raises(ValueError, match="agent_turn"):
+            await service.add_job("a", every_hour(), "", payload=CronPayload(kind="agent-turn"))
+        job = await service.add_job("a", every_hour(), "", payload=CronPayload(kind="system_event"))
+        with pytest.raises(ValueError):
+            await service.update_job(job.id, payload=CronPayload(kind="email"))
+
+    async def test_callback_receives_payload_dict(self, tmp_path):
+        """Callbacks get the payload fields and job identity as a dict."""
+        received = []
+        service = CronService(tmp_path / "jobs.json", on_job=received.append)
+        payload = CronPayload(kind="system_event", message="ping", deliver=True, channel="tg", to="42")
+        job = await service.add_job("a", every_hour(), "", payload=payload)
+        await service.run_job(job.id)
+        assert received == [
+            {
+                "kind": "system_event",
+                "message": "ping",
+                "deliver": True,
+                "channel": "tg",
+                "to": "42",
+                "job_id": job.id,
+                "job_name": "a",
+            }
+        ]
+
+    async def test_pass_job_compat(self, tmp_path):
+        """pass_job=True hands the CronJob itself to the callback."""
+        received = []
+        service = CronService(tmp_path / "jobs.json", on_job=received.append, pass_job=True)
+        job = await service.add_job("a", every_hour(), "a")
+        await service.run_job(job.id)
+        assert received[0].id == job.id
+        assert received[0].payload.message == "a"
+
+
+class TestRunJobOutcome:
+    """Tests for the run_job result."""
+
+    async def test_reports_success(self, service):
+        """A successful run reports status, timing and the next run."""
+        job = await service.add_job("a", every_hour(), "a")
+        result = await service.run_job(job.id)
+        assert result["status"] == "ok"
+        assert result["error"] is None
+        assert result["duration_ms"] >= 0
+        assert result["started_at_ms"] > 0
+        assert result["next_run_at_ms"] > result["started_at_ms"]
+
+    async def test_reports_failure(self, tmp_path):
+        """A failed run reports the error."""
+        async def on_job(job):
+            raise RuntimeError("boom")
+
+        service = CronService(tmp_path / "jobs.json", on_job=on_job)
+        job = await service.add_job("a", every_hour(), "a")
+        result = await service.run_job(job.id)
+        assert result["status"] == "error"
+        assert "boom" in result["error"]
+
+    async def test_missing_or_disabled(self, service):
+        """Unknown jobs, and disabled jobs without force, return None."""
+        assert await service.run_job("missing") is None
+        job = await service.add_job("a", every_hour(), "a")
+        await service.enable_job(job.id, enabled=False)
+        assert await service.run_job(job.id) is None
+        assert (await service.run_job(job.id, force=True))["status"] == "ok"
+
+    async def test_no_wait(self, tmp_path):
+        """wait=False returns immediately while the job runs in the background."""
+        import asyncio
+
+        done = asyncio.Event()
+
+        async def on_job(job):
+            await asyncio.sleep(0.2)
+            done.set()
+
+        service = CronService(tmp_path / "jobs.json", on_job=on_job)
+        job = await service.add_job("a", every_hour(), "a")
+        result = await service.run_job(job.id, wait=False)
+        assert result["status"] == "started"
+        assert not done.is_set()
+        await asyncio.wait_for(done.wait(), timeout=2)
+
+
+class TestReschedule:
+    """Tests for whether a run advances the job's schedule."""
+
+    def schedules(self):
+        import time
+
+        now = int(time.time() * 1000)
+        return {
+            "at": CronSchedule(kind="at", at_ms=now + HOUR_MS),
+            "every": every_hour(),
+            "cron": CronSchedule(kind="cron", expr="0 9 * * *"),
+        }
+
+    async def test_manual_run_keeps_schedule(self, service):
+        """A forced run records the result but keeps the next run and enabled state."""
+        for kind, schedule in self.schedules().items():
+            job = await service.add_job(kind, schedule, kind)
+            result = await service.run_job(job.id, force=True)
+            assert result["next_run_at_ms"] == job.state.next_run_at_ms, kind
+
+            after = next(j for j in await service.list_jobs(include_disabled=True) if j.id == job.id)
+            assert after.enabled, kind
+            assert after.state.next_run_at_ms == job.state.next_run_at_ms, kind
+            assert after.state.last_run_at_ms is not None, kind
+            assert after.state.run_count == 1, kind
+
+    async def test_skipped_manual_run_keeps_schedule(self, tmp_path):
+        """A manual run skipped for overlapping leaves a due job's next run alone."""
+        import asyncio
+        import time
+
+        async def slow(job):
+            await asyncio.sleep(0.3)
+
+        offset = [0]
+        service = CronService(
+            tmp_path / "jobs.json", on_job=slow, clock=lambda: int(time.time() * 1000) + offset[0]
+        )
+        job = await service.add_job("a", every_hour(), "a")
+        await service.run_job(job.id, wait=False)
+        await asyncio.sleep(0.05)
+        # The job is now due, but still running
+        offset[0] = HOUR_MS + 1000
+
+        result = await service.run_job(job.id)
+        assert result["status"] == "skipped"
+        assert result["next_run_at_ms"] == job.state.next_run_at_ms
+        result = await service.run_job(job.id, reschedule=True)
+        assert result["status"] == "skipped"
+        assert result["next_run_at_ms"] > job.state.next_run_at_ms
+        await service.shutdown()
+
+    async def test_manual_run_can_reschedule(self, service):
+        """reschedule=True advances the schedule like a scheduled run."""
+        schedules = self.schedules()
+        at = await service.add_job("at", schedules["at"], "at")
+        await service.run_job(at.id, reschedule=True)
+        at = (await service.list_jobs(include_disabled=True))[0]
+        assert not at.enabled
+        assert at.state.next_run_at_ms is None
+
+        every = await service.add_job("every", schedules["every"], "every")
+        result = await service.run_job(every.id, reschedule=True)
+        assert result["next_run_at_ms"] >= result["started_at_ms"] + HOUR_MS
+
+        cron = await service.add_job("cron", schedules["cron"], "cron")
+        result = await service.run_job(cron.id, reschedule=True)
+        assert result["next_run_at_ms"] > result["started_at_ms"]
+
+    async def test_scheduled_runs_advance(self, tmp_path):
+        """Runs driven by the scheduler advance the schedule for every kind."""
+        import asyncio
+        import time
+
+        runs = []
+        service = CronService(tmp_path / "jobs.json", on_job=lambda job: runs.append(job["job_name"]))
+        now = int(time.time() * 1000)
+        at = await service.add_job("at", CronSchedule(kind="at", at_ms=now + 200), "at")
+        every = await service.add_job("every", CronSchedule(kind="every", every_ms=300), "every")
+        cron = await service.add_job("cron", CronSchedule(kind="cron", expr="* * * * * *"), "cron")
+
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(1.3)
+        finally:
+            service.stop()
+            task.cancel()
+
+        assert {"at", "every", "cron"} <= set(runs)
+        jobs = {j.id: j for j in await service.list_jobs(include_disabled=True)}
+        assert not jobs[at.id].enabled
+        assert jobs[at.id].state.next_run_at_ms is None
+        for job in (every, cron):
+            after = jobs[job.id]
+            assert after.enabled
+            assert after.state.next_run_at_ms > after.state.last_run_at_ms
+
+
+class TestDryRun:
+    """Tests for dry-run mode."""
+
+    async def test_scheduler_dry_run(self, tmp_path):
+        """Due jobs advance and emit events without invoking the callback."""
+        import asyncio
+
+        runs, events = [], []
+        service = CronService(
+            tmp_path / "jobs.json",
+            on_job=lambda job: runs.append(job["job_id"]),
+            on_event=events.append,
+            dry_run=True,
+        )
+        job = await service.add_job("a", CronSchedule(kind="every", every_ms=200), "a")
+        first = job.state.next_run_at_ms
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(0.5)
+        finally:
+            service.stop()
+            task.cancel()
+
+        assert runs == []
+        job = (await service.list_jobs())[0]
+        assert job.state.last_status == "dry_run"
+        assert job.state.next_run_at_ms > first
+        dry = [e for e in events if e["event"] == "job_dry_run"]
+        assert dry and dry[0]["job_id"] == job.id and dry[0]["kind"] == "agent_turn"
+
+    async def test_run_job_override(self, tmp_path):
+        """run_job(dry_run=...) overrides the service setting either way."""
+        runs = []
+        service = CronService(tmp_path / "jobs.json", on_job=lambda job: runs.append(job["job_id"]))
+        job = await service.add_job("a", every_hour(), "a")
+        result = await service.run_job(job.id, dry_run=True)
+        assert result["status"] == "dry_run"
+        assert runs == []
+
+        dry = CronService(tmp_path / "dry.json", on_job=lambda job: runs.append(job["job_id"]), dry_run=True)
+        job = await dry.add_job("a", every_hour(), "a")
+        assert (await dry.run_job(job.id, dry_run=False))["status"] == "ok"
+        assert runs == [job.id]
+
+
+class TestNonBlockingStatus:
+    """Tests for status/set_callback not blocking on the scheduler."""
+
+    async def test_status_tracks_changes(self, service):
+        """status() reflects added and removed jobs."""
+        assert service.status()["jobs"] == 0
+        job = await service.add_job("a", every_hour(), "a")
+        status = service.status()
+        assert status["jobs"] == 1
+        assert status["next_wake_at_ms"] == job.state.next_run_at_ms
+        await service.remove_job(job.id)
+        assert service.status()["jobs"] == 0
+
+    async def test_callable_from_job_callback(self, tmp_path):
+        """status() and set_callback() work from inside a running job."""
+        seen = []
+
+        def on_job(job):
+            seen.append(service.status()["jobs"])
+            service.set_callback(lambda job: seen.append("replaced"))
+
+        service = CronService(tmp_path / "jobs.json", on_job=on_job)
+        job = await service.add_job("a", every_hour(), "a")
+        assert (await service.run_job(job.id))["status"] == "ok"
+        await service.run_job(job.id)
+        assert seen == [1, "replaced"]
+
+
+class TestRestartState:
+    """Tests for runtime state carried across a service restart."""
+
+    async def run_briefly(self, service, seconds=0.5):
+        import asyncio
+
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(seconds)
+        finally:
+            service.stop()
+            task.cancel()
+
+    def set_next_run(self, path, next_run_at_ms):
+        import json
+
+        data = json.loads(path.read_text())
+        data["jobs"][0]["state"]["nextRunAtMs"] = next_run_at_ms
+        path.write_text(json.dumps(data))
+
+    async def test_snooze_survives_restart(self, tmp_path):
+        """A snoozed job does not fire early after a restart."""
+        import time
+
+        path = tmp_path / "jobs.json"
+        service = CronService(path)
+        job = await service.add_job("a", CronSchedule(kind="every", every_ms=100), "a")
+        until = int(time.time() * 1000) + HOUR_MS
+        snoozed = await service.snooze_job(job.id, until)
+        assert snoozed.state.next_run_at_ms == until
+        await service.flush()
+
+        runs = []
+        restarted = CronService(path, on_job=lambda job: runs.append(job["job_id"]))
+        await self.run_briefly(restarted)
+        assert runs == []
+        job = (await restarted.list_jobs())[0]
+        assert job.state.snoozed_until_ms == until
+        assert job.state.next_run_at_ms == until
+
+    async def test_cancel_snooze(self, service):
+        """until_ms=None resumes the regular schedule."""
+        import time
+
+        job = await service.add_job("a", every_hour(), "a")
+        await service.snooze_job(job.id, int(time.time() * 1000) + DAY_MS)
+        resumed = await service.snooze_job(job.id, None)
+        assert resumed.state.snoozed_until_ms is None
+        assert resumed.state.next_run_at_ms <= job.state.next_run_at_ms + 1000
+        with pytest.raises(ValueError):
+            await service.snooze_job(job.id, 1)
+
+    async def test_stored_at_run_kept(self, tmp_path):
+        """A one-shot job keeps its stored next run across a restart."""
+        import time
+
+        path = tmp_path / "jobs.json"
+        service = CronService(path)
+        at = int(time.time() * 1000) + HOUR_MS
+        await service.add_job("a", CronSchedule(kind="at", at_ms=at), "a")
+        await service.flush()
+        self.set_next_run(path, at + 5000)
+
+        restarted = CronService(path)
+        await self.run_briefly(restarted, 0.2)
+        assert (await restarted.list_jobs())[0].state.next_run_at_ms == at + 5000
+
+    async def test_recently_missed_run_fires(self, tmp_path):
+        """A run missed within the catch-up window fires on start."""
+        import time
+
+        path = tmp_path / "jobs.json"
+        service = CronService(path)
+        job = await service.add_job("a", every_hour(), "a")
+        await service.flush()
+        self.set_next_run(path, int(time.time() * 1000) - 60_000)
+
+        runs = []
+        restarted = CronService(path, on_job=lambda job: runs.append(job["job_id"]))
+        await self.run_briefly(restarted)
+        assert runs == [job.id]
+
+    async def test_stale_run_recomputed(self, tmp_path):
+        """A run missed long ago is dropped and the next run recomputed."""
+        import time
+
+        path = tmp_path / "jobs.json"
+        service = CronService(path)
+        await service.add_job("a", every_hour(), "a")
+        await service.flush()
+        now = int(time.time() * 1000)
+        self.set_next_run(path, now - DAY_MS)
+
+        runs = []
+        restarted = CronService(path, on_job=lambda job: runs.append(job["job_id"]))
+        await self.run_briefly(restarted, 0.2)
+        assert runs == []
+        assert (await restarted.list_jobs())[0].state.next_run_at_ms > now
+
+
+class TestSchedulerWake:
+    """Tests for waking the scheduler loop on job changes."""
+
+    async def test_new_job_runs_without_waiting_out_idle_sleep(self, tmp_path):
+        """A job added during the idle wait runs on its own schedule."""
+        import asyncio
+
+        runs = []
+        service = CronService(tmp_path / "jobs.json", on_job=lambda job: runs.append(job["job_id"]))
+        task = asyncio.ensure_future(service.start())
+        try:
+            # Let the loop enter its idle (60s) wait
+            await asyncio.sleep(0.1)
+            job = await service.add_job("fast", CronSchedule(kind="every", every_ms=200), "a")
+            await asyncio.sleep(0.35)
+            assert runs[:1] == [job.id]
+        finally:
+            service.stop()
+            task.cancel()
+
+
+class TestIdleInterval:
+    """Tests for the capped scheduler sleep and clock-jump handling."""
+
+    def test_rejects_zero_interval(self, tmp_path):
+        """A zero idle interval would spin the loop."""
+        with pytest.raises(ValueError):
+            CronService(tmp_path / "jobs.json", idle_interval_ms=0)
+
+    async def test_job_fires_after_clock_jump(self, tmp_path):
+        """A job due after a simulated suspend fires promptly on resume."""
+        import asyncio
+        import time
+
+        offset = [0]
+        runs, events = [], []
+        service = CronService(
+            tmp_path / "jobs.json",
+            on_job=lambda job: runs.append(job["job_id"]),
+            on_event=events.append,
+            idle_interval_ms=100,
+            clock=lambda: int(time.time() * 1000) + offset[0],
+        )
+        at = int(time.time() * 1000) + HOUR_MS
+        job = await service.add_job("a", CronSchedule(kind="at", at_ms=at), "a")
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(0.2)
+            assert runs == []
+            # Resume an hour later: the wall clock jumps, the monotonic clock does not
+            offset[0] = HOUR_MS + 1000
+            await asyncio.sleep(0.4)
+        finally:
+            service.stop()
+            task.cancel()
+
+        assert runs == [job.id]
+        assert any(e["event"] == "clock_jump" and e["skew_ms"] > HOUR_MS for e in events)
+
+    async def test_every_job_fires_once_after_clock_jump(self, tmp_path):
+        """A recurring job is rescheduled from the jumped clock, not the system one."""
+        import asyncio
+        import time
+
+        offset = [0]
+        runs = []
+        service = CronService(
+            tmp_path / "jobs.json",
+            on_job=lambda job: runs.append(job["job_id"]),
+            idle_interval_ms=100,
+            clock=lambda: int(time.time() * 1000) + offset[0],
+        )
+        job = await service.add_job("a", CronSchedule(kind="every", every_ms=60_000), "a")
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(0.2)
+            assert runs == []
+            offset[0] = HOUR_MS
+            await asyncio.sleep(0.5)
+        finally:
+            service.stop()
+            task.cancel()
+
+        assert runs == [job.id]
+
+    async def test_failing_clock_emits_clock_error(self, tmp_path):
+        """A clock that raises is reported and the system clock is used."""
+        import time
+
+        def clock():
+            raise RuntimeError("no time")
+
+        events = []
+        service = CronService(tmp_path / "jobs.json", on_event=events.append, clock=clock)
+        job = await service.add_job("a", CronSchedule(kind="every", every_ms=60_000), "a")
+
+        assert abs(job.state.next_run_at_ms - (int(time.time() * 1000) + 60_000)) < 5_000
+        assert any(e["event"] == "clock_error" and "no time" in e["error"] for e in events)
+
+    async def test_events_stamped_by_clock(self, tmp_path):
+        """Events carry the service clock's time, not the system one."""
+        import asyncio
+        import time
+
+        events = []
+        service = CronService(
+            tmp_path / "jobs.json",
+            on_event=events.append,
+            clock=lambda: int(time.time() * 1000) + 10 * HOUR_MS,
+        )
+        job = await service.add_job("a", every_hour(), "a")
+        await asyncio.sleep(0.1)
+
+        added = next(e for e in events if e["event"] == "job_added")
+        assert abs(added["ts_ms"] - job.created_at_ms) < 5_000
+
+    def test_at_checked_against_clock(self):
+        """`at` must lie in the future of the given clock, not the system one."""
+        import time
+
+        at = int(time.time() * 1000) + HOUR_MS
+        assert CronSchedule(kind="at", at=at).at_ms == at
+        with pytest.raises(ValueError, match="in the past"):
+            CronSchedule(kind="at", at=at, clock=lambda: at + 1)
+
+
+class TestShutdown:
+    """Tests for CronService.shutdown."""
+
+    async def test_start_returns_promptly(self, service):
+        """shutdown() ends start() without waiting out the idle sleep."""
+        import asyncio
+
+        task = asyncio.ensure_future(service.start())
+        await asyncio.sleep(0.1)
+        assert await service.shutdown() is True
+        await asyncio.wait_for(task, timeout=1)
+
+    async def test_waits_for_in_flight_jobs(self, tmp_path):
+        """shutdown() waits for running jobs and saves their outcome."""
+        import asyncio
+
+        async def on_job(job):
+            await asyncio.sleep(0.3)
+
+        path = tmp_path / "jobs.json"
+        service = CronService(path, on_job=on_job)
+        job = await service.add_job("a", every_hour(), "a")
+        await service.run_job(job.id, wait=False)
+        await asyncio.sleep(0.05)
+
+        assert await service.shutdown() is True
+        state = (await CronService(path).list_jobs())[0].state
+        assert state.last_status == "ok"
+
+    async def test_timeout(self, tmp_path):
+        """shutdown() gives up after timeout_s and reports it."""
+        import asyncio
+
+        async def on_job(job):
+            await asyncio.sleep(1)
+
+        service = CronService(tmp_path / "jobs.json", on_job=on_job)
+        job = await service.add_job("a", every_hour(), "a")
+        await service.run_job(job.id, wait=False)
+        await asyncio.sleep(0.05)
+        assert await service.shutdown(timeout_s=0.1) is False
+
+    async def test_timeout_values(self, service):
+        """NaN and negative timeouts are refused; an infinite one waits like None."""
+        with pytest.raises(ValueError):
+            await service.shutdown(timeout_s=float("nan"))
+        with pytest.raises(ValueError):
+            await service.shutdown(timeout_s=-1)
+        assert await service.shutdown(timeout_s=float("inf")) is True
+
+
+class TestClose:
+    """Tests for CronService.close and interrupted runs."""
+
+    async def test_close_marks_running_jobs_interrupted(self, tmp_path):
+        """A job cut off by close() is retried as a failure on the next start."""
+        import asyncio
+        import json
+
+        path = tmp_path / "jobs.json"
+
+        async def slow(job):
+            await asyncio.sleep(2)
+
+        service = CronService(path, on_job=slow)
+        job = await service.add_job("a", every_hour(), "a")
+        await service.run_job(job.id, wait=False)
+        await asyncio.sleep(0.1)
+        service.close()
+
+        state = json.loads(path.read_text())["jobs"][0]["state"]
+        assert state["lastStatus"] == "interrupted"
+
+        runs, events = [], []
+        restarted = CronService(
+            path, on_job=lambda job: runs.append(job["job_id"]), on_event=events.append
+        )
+        interrupted = (await restarted.list_jobs())[0]
+        assert interrupted.state.last_status == "interrupted"
+
+        task = asyncio.ensure_future(restarted.start())
+        try:
+            await asyncio.sleep(0.3)
+        finally:
+            restarted.stop()
+            task.cancel()
+
+        assert runs == [job.id]
+        assert any(e["event"] == "job_interrupted" for e in events)
+        assert (await restarted.list_jobs())[0].state.last_status == "ok"
+
+    async def test_repeated_interruptions_disable_job(self, tmp_path):
+        """A job interrupted on every run is auto-disabled instead of retried forever."""
+        import asyncio
+
+        path = tmp_path / "jobs.json"
+
+        async def slow(job):
+            await asyncio.sleep(2)
+
+        service = CronService(path, on_job=slow)
+        job = await service.add_job("a", every_hour(), "a", disable_after_failures=2)
+        await service.run_job(job.id, wait=False)
+        services, tasks, events = [service], [], []
+        # Close while the job runs, then restart: the first restart retries
+        # it, and closing during that retry makes the second failure
+        for _ in range(2):
+            await asyncio.sleep(0.1)
+            services[-1].close()
+            events = []
+            services.append(CronService(path, on_job=slow, on_event=events.append))
+            tasks.append(asyncio.ensure_future(services[-1].start()))
+        await asyncio.sleep(0.1)
+        for service, task in zip(services[1:], tasks):
+            service.stop()
+            task.cancel()
+
+        disabled = (await services[-1].list_jobs(include_disabled=True))[0]
+        assert not disabled.enabled
+        assert disabled.state.consecutive_failures == 2
+        assert "Auto-disabled after 2 consecutive failures" in disabled.state.last_error
+        assert [e["event"] for e in events if e["event"] in ("job_interrupted", "job_auto_disabled")] == [
+            "job_auto_disabled"
+        ]
+
+    async def test_close_writes_pending_changes(self, tmp_path):
+        """close() writes debounced changes before returning."""
+        import json
+
+        path = tmp_path / "jobs.json"
+        service = CronService(path)
+        await service.add_job("a", every_hour(), "a")
+        service.close()
+        assert [j["name"] for j in json.loads(path.read_text())["jobs"]] == ["a"]
+
+    async def test_close_from_job_callback(self, tmp_path):
+        """close() called from inside a running job writes the store instead of panicking."""
+        import json
+
+        path = tmp_path / "jobs.json"
+        service = None
+
+        def close_service(job):
+            service.close()
+
+        service = CronService(path, on_job=close_service)
+        job = await service.add_job("a", every_hour(), "a")
+        await service.run_job(job.id)
+        assert [j["name"] for j in json.loads(path.read_text())["jobs"]] == ["a"]
+
+
+class TestDebouncedPersistence:
+    """Tests for debounced, change-aware store writes."""
+
+    async def test_coalesces_rapid_changes(self, tmp_path):
+        """Successive changes are written together after a short delay."""
+        import asyncio
+
+        events = []
+        path = tmp_path / "jobs.json"
+        service = CronService(path, on_event=events.append)
+        for name in "abc":
+            await service.add_job(name, every_hour(), name)
+        assert not path.exists()
+
+        await asyncio.sleep(0.5)
+        assert [e["event"] for e in events].count("store_saved") == 1
+        assert len(await CronService(path).list_jobs()) == 3
+
+    async def test_skips_unchanged_content(self, tmp_path):
+        """Flushing without changes does not rewrite the file."""
+        import asyncio
+
+        events = []
+        service = CronService(tmp_path / "jobs.json", on_event=events.append)
+        await service.add_job("a", every_hour(), "a")
+        await service.flush()
+        await service.flush()
+        await asyncio.sleep(0.4)
+        assert [e["event"] for e in events].count("store_saved") == 1
+
+    async def test_flush_writes_immediately(self, tmp_path):
+        """flush() persists pending changes without waiting for the debounce."""
+        path = tmp_path / "jobs.json"
+        service = CronService(path)
+        await service.add_job("a", every_hour(), "a")
+        await service.flush()
+        assert [j.name for j in await CronService(path).list_jobs()] == ["a"]
+
+    async def test_flush_races_debounced_save(self, tmp_path):
+        """A debounced save firing during flush() never leaves an older job set on disk."""
+        import asyncio
+        import json
+
+        path = tmp_path / "jobs.json"
+        service = CronService(path)
+        for i in range(200):
+            await service.add_job(f"pad{i}", every_hour(), "x" * 200)
+        await service.flush()
+
+        for i in range(5):
+            # Change the jobs just as the debounced save of the previous change fires
+            await service.add_job(f"a{i}", every_hour(), "a")
+            await asyncio.sleep(0.24 + i * 0.005)
+            await asyncio.gather(service.add_job(f"b{i}", every_hour(), "b"), service.flush())
+            await service.flush()
+            await asyncio.sleep(0.05)
+            names = {j["name"] for j in json.loads(path.read_text())["jobs"]}
+            assert names == {j.name for j in await service.list_jobs()}
+
+    async def test_shutdown_flushes(self, tmp_path):
+        """shutdown() writes pending changes."""
+        path = tmp_path / "jobs.json"
+        service = CronService(path)
+        await service.add_job("a", every_hour(), "a")
+        await service.shutdown()
+        assert [j.name for j in await CronService(path).list_jobs()] == ["a"]
+
+    async def test_write_errors_are_reported(self, tmp_path):
+        """A failed write raises from flush() and emits store_error."""
+        import asyncio
+
+        events = []
+        blocker = tmp_path / "not_a_dir"
+        blocker.write_text("")
+        service = CronService(blocker / "jobs.json", on_event=events.append)
+        await service.add_job("a", every_hour(), "a")
+
+        with pytest.raises(OSError):
+            await service.flush()
+        await asyncio.sleep(0.1)
+        errors = [e for e in events if e["event"] == "store_error"]
+        assert len(errors) == 1
+        assert "jobs.json" in errors[0]["error"]
+
+
+class TestJobIds:
+    """Tests for job ID generation."""
+
+    async def test_ids_are_full_uuids(self, service):
+        """New jobs get full UUIDs, with short_id for display."""
+        import uuid
+
+        job = await service.add_job("a", every_hour(), "a")
+        assert str(uuid.UUID(job.id)) == job.id
+        assert job.short_id == job.id[:8]
+
+    async def test_legacy_short_ids_still_work(self, tmp_path):
+        """Jobs stored with 8-character IDs load and can be managed by ID."""
+        import json
+
+        path = tmp_path / "jobs.json"
+        path.write_text(json.dumps(V1_STORE))
+
+        service = CronService(path)
+        assert await service.enable_job("abcd1234", False) is not None
+        assert (await service.list_jobs(include_disabled=True))[0].short_id == "abcd1234"
+        assert await service.remove_job("abcd1234")
+
+
+class TestCronMetrics:
+    """Tests for CronService.metrics."""
+
+    async def test_counts_executions_and_failures(self, tmp_path):
+        """Runs, failures and average duration are recorded per job."""
+        async def on_job(job):
+            if job["job_name"] == "bad":
+                raise RuntimeError("boom")
+
+        service = CronService(tmp_path / "jobs.json", on_job=on_job)
+        good = await service.add_job("good", every_hour(), "g")
+        bad = await service.add_job("bad", every_hour(), "b")
+        await service.run_job(good.id)
+        await service.run_job(good.id)
+        await service.run_job(bad.id)
+
+        m = service.metrics()
+        assert m["executions"] == 3
+        assert m["failures"] == 1
+        assert m["avg_duration_ms"] >= 0
+        assert m["jobs"][good.id]["executions"] == 2
+        assert m["jobs"][bad.id]["failures"] == 1
+
+    async def test_counts_timeouts(self, tmp_path):
+        """Shell commands that time out are counted as timeouts."""
+        service = CronService(tmp_path / "jobs.json", allow_shell=True)
+        payload = CronPayload(kind="shell", command="sleep 5", timeout_ms=50)
+        job = await service.add_job("s", every_hour(), "", payload=payload)
+        await service.run_job(job.id)
+
+        m = service.metrics()
+        assert m["timeouts"] == 1
+        assert m["failures"] == 1
+
+    async def test_skips_overlapping_runs(self, tmp_path):
+        """A job is not run again while it is still executing."""
+        import asyncio
+
+        async def on_job(job):
+            await asyncio.sleep(0.2)
+
+        service = CronService(tmp_path / "jobs.json", on_job=on_job)
+        job = await service.add_job("a", every_hour(), "a")
+        await service.run_job(job.id, wait=False)
+        await asyncio.sleep(0.05)
+
+        result = await service.run_job(job.id)
+        assert result["status"] == "skipped"
+        await service.shutdown()
+
+        m = service.metrics()
+        assert m["skipped_overlap"] == 1
+        assert m["executions"] == 1
+
+    async def test_per_service(self, tmp_path):
+        """Each service counts only its own runs."""
+        first = CronService(tmp_path / "a.json")
+        second = CronService(tmp_path / "b.json")
+        job = await first.add_job("a", every_hour(), "a")
+        await first.run_job(job.id)
+
+        assert first.metrics()["executions"] == 1
+        assert second.metrics()["executions"] == 0
+
+    async def test_reset(self, service):
+        """reset_metrics clears all counters."""
+        job = await service.add_job("a", every_hour(), "a")
+        await service.run_job(job.id)
+        service.reset_metrics()
+        m = service.metrics()
+        assert m["executions"] == 0
+        assert m["avg_duration_ms"] is None
+        assert m["jobs"] == {}
+
+
+class TestJobPriority:
+    """Tests for job priority."""
+
+    async def test_due_jobs_run_by_priority(self, tmp_path):
+        """Jobs due in the same tick run highest priority first."""
+        import asyncio
+        import time
+
+        runs = []
+        service = CronService(tmp_path / "jobs.json", on_job=lambda job: runs.append(job["job_name"]))
+        at = CronSchedule(kind="at", at_ms=int(time.time() * 1000) + 300)
+        await service.add_job("cleanup", at, "c")
+        await service.add_job("digest", at, "d", priority=10)
+        await service.add_job("other", at, "o", priority=-1)
+
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(0.6)
+            assert runs == ["digest", "cleanup", "other"]
+        finally:
+            service.stop()
+            task.cancel()
+
+    async def test_priority_is_settable_and_persisted(self, tmp_path):
+        """Priority is set by add_job/update_job and survives a reload."""
+        path = tmp_path / "jobs.json"
+        service = CronService(path)
+        job = await service.add_job("a", every_hour(), "a")
+        assert job.priority == 0
+        job = await service.update_job(job.id, priority=5)
+        assert job.priority == 5
+        await service.flush()
+
+        assert (await CronService(path).list_jobs())[0].priority == 5
+
+    async def test_list_jobs_tiebreak(self, service):
+        """Jobs with the same next run are listed by descending priority."""
+        at = CronSchedule(kind="at", at_ms=4086572400000)
+        await service.add_job("low", at, "l")
+        await service.add_job("high", at, "h", priority=1)
+        assert [j.name for j in await service.list_jobs()] == ["high", "low"]
+
+
+class TestUpcomingJobs:
+    """Tests for upcoming_jobs."""
+
+    async def test_expands_and_sorts_occurrences(self, service):
+        """Repeating jobs are listed once per run in the window, by fire time."""
+        import time
+
+        now = int(time.time() * 1000)
+        fast = await service.add_job("fast", CronSchedule(kind="every", every_ms=20 * 60 * 1000), "f")
+        slow = await service.add_job("slow", CronSchedule(kind="every", every_ms=50 * 60 * 1000), "s")
+        await service.add_job("later", CronSchedule(kind="every", every_ms=3 * HOUR_MS), "l")
+
+        upcoming = await service.upcoming_jobs(HOUR_MS + 60_000, now_ms=now)
+        assert [job.name for job, _ in upcoming] == ["fast", "fast", "slow", "fast"]
+        times = [at for _, at in upcoming]
+        assert times == sorted(times)
+        assert times[0] == fast.state.next_run_at_ms
+        assert times[2] == slow.state.next_run_at_ms
+
+    async def test_cron_expression(self, service):
+        """Cron jobs are expanded to every match within the window."""
+        await service.add_job("tick", CronSchedule(kind="cron", expr="*/15 * * * *"), "t")
+        upcoming = await service.upcoming_jobs(HOUR_MS)
+        assert len(upcoming) == 4
+
+    async def test_skips_disabled_jobs(self, service):
+        """Disabled jobs are not listed."""
+        job = await service.add_job("a", every_hour(), "a")
+        await service.enable_job(job.id, enabled=False)
+        assert await service.upcoming_jobs(2 * HOUR_MS) == []
+
+
+class TestDictConversion:
+    """Tests for to_dict/from_dict and equality."""
+
+    async def test_job_round_trip(self, service):
+        """A job survives to_dict/from_dict with every field, including Nones."""
+        job = await service.add_job("a", every_hour(), "hello", tags=["x"], priority=2)
+        d = job.to_dict()
+        assert d["name"] == "a"
+        assert d["schedule"]["everyMs"] == HOUR_MS
+        assert d["schedule"]["atMs"] is None
+        assert d["state"]["lastStatus"] is None
+
+        copy = CronJob.from_dict(d)
+        assert copy.to_dict() == d
+        assert copy == job
+
+    def test_parts_round_trip(self):
+        """Schedules, payloads and states round-trip and compare by value."""
+        schedule = CronSchedule(kind="weekly", hour=9, minute=30, weekdays=0b11, tz="Europe/Berlin")
+        payload = CronPayload(kind="webhook", url="https://example.com", headers={"X-A": "1"}, timeout_ms=500)
+        state = CronJobState(next_run_at_ms=5, last_error="boom", avg_duration_ms=1.5, run_count=3)
+        for value in (schedule, payload, state):
+            copy = type(value).from_dict(value.to_dict())
+            assert copy == value
+            assert copy.to_dict() == value.to_dict()
+
+        assert CronSchedule(kind="every", every_ms=1) != CronSchedule(kind="every", every_ms=2)
+
+    async def test_jobs_hash_by_id(self, service):
+        """Jobs are equal and hash alike when their IDs match."""
+        job = await service.add_job("a", every_hour(), "a")
+        renamed = await service.update_job(job.id, name="b")
+        other = await service.add_job("a", every_hour(), "a")
+        assert job == renamed
+        assert len({job, renamed, other}) == 2
+
+    def test_from_dict_rejects_invalid(self):
+        """Malformed dicts raise ValueError."""
+        with pytest.raises(ValueError):
+            CronSchedule.from_dict({"everyMs": 5})
+
+
+class TestQuietHours:
+    """Tests for quiet-hours windows."""
+
+    def window_around_now(self):
+        """A UTC quiet window from an hour ago to an hour from now."""
+        import datetime
+        import time
+
+        now = int(time.time() * 1000)
+        utc = datetime.timezone.utc
+        start = datetime.datetime.fromtimestamp(now / 1000 - 3600, utc).strftime("%H:%M")
+        end = datetime.datetime.fromtimestamp(now / 1000 + 3600, utc).strftime("%H:%M")
+        end_ms = (now + HOUR_MS) // 60_000 * 60_000
+        return now, start, end, end_ms
+
+    async def test_defers_to_window_end(self, service):
+        """A next run inside the quiet window moves to the window's end."""
+        _, start, end, end_ms = self.window_around_now()
+        schedule = CronSchedule(kind="every", every_ms=60_000, quiet_start=start, quiet_end=end)
+        job = await service.add_job("a", schedule, "a")
+        assert job.state.next_run_at_ms == end_ms
+        assert (await service.upcoming_jobs(HOUR_MS - 120_000)) == []
+
+    async def test_outside_window_unchanged(self, service):
+        """Runs outside the quiet window are not moved."""
+        now, start, end, _ = self.window_around_now()
+        # The window ends an hour from now; the first run is two hours out
+        schedule = CronSchedule(kind="every", every_ms=2 * HOUR_MS, quiet_start=start, quiet_end=end)
+        job = await service.add_job("a", schedule, "a")
+        assert job.state.next_run_at_ms - now < 2 * HOUR_MS + 1000
+
+    async def test_skip_policy_drops_run(self, tmp_path):
+        """With quiet_policy="skip", a due run is dropped and marked skipped."""
+        import asyncio
+
+        now, start, end, _ = self.window_around_now()
+        runs, events = [], []
+        service = CronService(
+            tmp_path / "jobs.json", on_job=lambda job: runs.append(job["job_id"]), on_event=events.append
+        )
+        schedule = CronSchedule(
+            kind="at", at_ms=now + 300, quiet_start=start, quiet_end=end, quiet_policy="skip"
+        )
+        job = await service.add_job("a", schedule, "a")
+
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(0.6)
+        finally:
+            service.stop()
+            task.cancel()
+
+        assert runs == []
+        job = (await service.list_jobs(include_disabled=True))[0]
+        assert job.state.last_status == "skipped"
+        assert not job.enabled
+        assert any(e["event"] == "job_skipped" and e["reason"] == "quiet_hours" for e in events)
+
+    def test_rejects_invalid_windows(self):
+        """Malformed times, half-open windows and unknown policies raise ValueError."""
+        with pytest.raises(ValueError, match="HH:MM"):
+            CronSchedule(kind="every", every_ms=1, quiet_start="25:00", quiet_end="07:00")
+        with pytest.raises(ValueError, match="both"):
+            CronSchedule(kind="every", every_ms=1, quiet_start="22:00")
+        with pytest.raises(ValueError, match="quiet_policy"):
+            CronSchedule(kind="every", every_ms=1, quiet_start="22:00", quiet_end="07:00", quiet_policy="later")
+
+
+class TestJobChaining:
+    """Tests for on_success/on_failure follow-up jobs."""
+
+    def disabled_at(self):
+        return CronSchedule(kind="at", at_ms=4086572400000)
+
+    async def test_runs_follow_up_by_outcome(self, tmp_path):
+        """The success or failure follow-up runs after its parent."""
+        runs = []
+
+        async def on_job(job):
+            runs.append(job["job_name"])
+            if job["job_name"] == "backup-bad":
+                raise RuntimeError("disk full")
+
+        service = CronService(tmp_path / "jobs.json", on_job=on_job)
+        verify = await service.add_job("verify", self.disabled_at(), "v")
+        await service.enable_job(verify.id, enabled=False)
+        alert = await service.add_job("alert", self.disabled_at(), "a")
+        ok = await service.add_job("backup", every_hour(), "b", on_success_job_id=verify.id, on_failure_job_id=alert.id)
+        bad = await service.add_job(
+            "backup-bad", every_hour(), "b", on_success_job_id=verify.id, on_failure_job_id=alert.id
+        )
+
+        assert (await service.run_job(ok.id))["status"] == "ok"
+        await service.run_job(bad.id)
+        assert runs == ["backup", "verify", "backup-bad", "alert"]
+
+        jobs = {j.name: j for j in await service.list_jobs(include_disabled=True)}
+        assert jobs["verify"].state.last_triggered_by == ok.id
+        assert jobs["alert"].state.last_triggered_by == bad.id
+        assert jobs["backup"].state.last_triggered_by is None
+
+    async def test_follow_ups_keep_their_schedule(self, tmp_path):
+        """A rescheduled run does not advance, disable or delete its follow-ups."""
+        runs = []
+        service = CronService(tmp_path / "jobs.json", on_job=lambda job: runs.append(job["job_name"]))
+        verify = await service.add_job("verify", every_hour(), "v")
+        await service.enable_job(verify.id, enabled=False)
+        once = await service.add_job("once", self.disabled_at(), "o", delete_after_run=True)
+        ok = await service.add_job("backup", every_hour(), "b", on_success_job_id=verify.id)
+        other = await service.add_job("backup-2", every_hour(), "b", on_success_job_id=once.id)
+
+        await service.run_job(ok.id, reschedule=True)
+        await service.run_job(other.id, reschedule=True)
+        assert runs == ["backup", "verify", "backup-2", "once"]
+
+        jobs = {j.name: j for j in await service.list_jobs(include_disabled=True)}
+        assert not jobs["verify"].enabled
+        assert jobs["verify"].state.next_run_at_ms is None
+        assert jobs["once"].enabled
+        assert jobs["once"].state.next_run_at_ms == once.state.next_run_at_ms
+
+    async def test_cycles_are_bounded(self, tmp_path):
+        """A cycle stops after the chain depth limit."""
+        import asyncio
+
+        runs, events = [], []
+        service = CronService(
+            tmp_path / "jobs.json", on_job=lambda job: runs.append(job["job_name"]), on_event=events.append
+        )
+        a = await service.add_job("a", every_hour(), "a")
+        b = await service.add_job("b", every_hour(), "b", on_success_job_id=a.id)
+        await service.update_job(a.id, on_success_job_id=b.id)
+
+        await service.run_job(a.id)
+        await asyncio.sleep(0.1)
+        assert runs == ["a", "b", "a", "b", "a", "b"]
+        assert [e["event"] for e in events].count("chain_limit_reached") == 1
+
+    async def test_links_persist_and_clear(self, tmp_path):
+        """Links survive a reload and an empty string clears them."""
+        path = tmp_path / "jobs.json"
+        service = CronService(path)
+        target = await service.add_job("t", every_hour(), "t")
+        job = await service.add_job("a", every_hour(), "a", on_failure_job_id=target.id)
+        await service.flush()
+
+        reloaded = CronService(path)
+        loaded = next(j for j in await reloaded.list_jobs() if j.name == "a")
+        assert loaded.on_failure_job_id == target.id
+        assert loaded.on_success_job_id is None
+
+        cleared = await reloaded.update_job(job.id, on_failure_job_id="")
+        assert cleared.on_failure_job_id is None
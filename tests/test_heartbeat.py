@@ -0,0 +1,1287 @@
+"""Tests for the heartbeat module (Rust implementation)."""
+
+import asyncio
+import time
+from datetime import datetime, timedelta, timezone
+
+import pytest
+
+from debot.cron import CronSchedule
+from debot.heartbeat import HeartbeatManager, HeartbeatService
+
+
+def recorder(reply="HEARTBEAT_OK"):
+    """An async heartbeat callback that records the prompts it receives."""
+    prompts = []
+
+    async def on_heartbeat(prompt):
+        prompts.append(prompt)
+        return reply
+
+    return on_heartbeat, prompts
+
+
+class TestPromptAndToken:
+    """Tests for the configurable prompt and OK token."""
+
+    async def test_defaults(self, tmp_path):
+        """Without overrides the built-in prompt and token are used."""
+        on_heartbeat, prompts = recorder()
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat)
+        assert service.ok_token == "HEARTBEAT_OK"
+        assert await service.trigger_now(skip_if_empty=False) == "HEARTBEAT_OK"
+        assert prompts == [service.prompt]
+        assert "HEARTBEAT.md" in prompts[0]
+
+    async def test_custom_prompt(self, tmp_path):
+        """A configured prompt is sent, and can be changed at runtime."""
+        on_heartbeat, prompts = recorder()
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat, prompt="Lies AUFGABEN.md")
+        await service.trigger_now(skip_if_empty=False)
+        service.prompt = "Lies TODO.md"
+        await service.trigger_now(skip_if_empty=False)
+        assert prompts == ["Lies AUFGABEN.md", "Lies TODO.md"]
+
+    def test_ok_token(self, tmp_path):
+        """The OK token is configurable but must not be blank."""
+        service = HeartbeatService(tmp_path, ok_token="ALLES_OK")
+        assert service.ok_token == "ALLES_OK"
+        service.ok_token = "NICHTS_ZU_TUN"
+        assert service.ok_token == "NICHTS_ZU_TUN"
+        with pytest.raises(ValueError):
+            service.ok_token = "__"
+        with pytest.raises(ValueError):
+            HeartbeatService(tmp_path, ok_token="")
+
+
+class TestTaskFiles:
+    """Tests for configurable and multiple task files."""
+
+    def test_default_file(self, tmp_path):
+        """By default only HEARTBEAT.md in the workspace is read."""
+        service = HeartbeatService(tmp_path)
+        assert service.heartbeat_file == [str(tmp_path / "HEARTBEAT.md")]
+
+    def test_custom_file(self, tmp_path):
+        """A single file name can be configured."""
+        service = HeartbeatService(tmp_path, heartbeat_file="TASKS.md")
+        assert service.heartbeat_file == [str(tmp_path / "TASKS.md")]
+        with pytest.raises(ValueError):
+            HeartbeatService(tmp_path, heartbeat_file=[])
+
+    def test_glob(self, tmp_path):
+        """Wildcards resolve to the matching files, sorted."""
+        (tmp_path / "HEARTBEAT.work.md").write_text("Check mail")
+        (tmp_path / "HEARTBEAT.home.md").write_text("")
+        (tmp_path / "NOTES.md").write_text("Not a task file")
+        service = HeartbeatService(tmp_path, heartbeat_file=["HEARTBEAT*.md", "TODO.md"])
+        assert service.heartbeat_file == [
+            str(tmp_path / "HEARTBEAT.home.md"),
+            str(tmp_path / "HEARTBEAT.work.md"),
+            str(tmp_path / "TODO.md"),
+        ]
+
+    async def test_prompt_lists_files_with_tasks(self, tmp_path):
+        """Only files with actionable content are named in the prompt."""
+        (tmp_path / "A.md").write_text("# Header\n- [ ]\n")
+        (tmp_path / "B.md").write_text("Water the plants")
+        on_heartbeat, prompts = recorder()
+        service = HeartbeatService(
+            tmp_path, on_heartbeat=on_heartbeat, heartbeat_file=["A.md", "B.md", "C.md"]
+        )
+        await service.trigger_now()
+        assert prompts[0].startswith(service.prompt)
+        assert prompts[0].endswith("Task files: B.md")
+
+    async def test_finished_and_commented_tasks_are_not_work(self, tmp_path):
+        """Checked items, frontmatter and comment blocks do not make a file actionable."""
+        (tmp_path / "A.md").write_text(
+            "---\ntitle: Chores\n---\n<!--\n- [ ] example\n-->\n- [x] Water the plants\n"
+        )
+        (tmp_path / "B.md").write_text("---\n- [x] Feed the cat\n- [ ] Walk the dog\n")
+        on_heartbeat, prompts = recorder()
+        service = HeartbeatService(
+            tmp_path, on_heartbeat=on_heartbeat, heartbeat_file=["A.md", "B.md"]
+        )
+        await service.trigger_now()
+        assert prompts[0].endswith("Task files: B.md")
+
+    async def test_default_file_keeps_prompt(self, tmp_path):
+        """The default file is already named by the prompt, so it is not repeated."""
+        (tmp_path / "HEARTBEAT.md").write_text("Do something")
+        on_heartbeat, prompts = recorder()
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat)
+        await service.trigger_now()
+        assert prompts == [service.prompt]
+
+
+def window_around_now(offset_hours):
+    """An hour-long HH:MM window starting `offset_hours` from now (UTC)."""
+    now = datetime.now(timezone.utc)
+    start = now + timedelta(hours=offset_hours)
+    end = start + timedelta(hours=1)
+    return start.strftime("%H:%M"), end.strftime("%H:%M")
+
+
+class TestSections:
+    """Tests for task file sections with their own cadence."""
+
+    CONTENT = (
+        "# Tasks\n"
+        "## Hourly\n- [ ] Check the inbox\n"
+        "## Daily <!-- every: 24h -->\n- [ ] Summarize the news\n"
+        "## Odd <!-- every: whenever -->\n- [ ] Stretch\n"
+    )
+
+    async def test_only_due_sections(self, tmp_path):
+        """A section with its own cadence is left out until it is due again."""
+        (tmp_path / "HEARTBEAT.md").write_text(self.CONTENT)
+        calls = []
+
+        async def on_heartbeat(prompt, context):
+            calls.append((prompt, context["files"][0]["content"]))
+            return "HEARTBEAT_OK"
+
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat, interval_s=1, rich_callback=True)
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(2.3)
+        finally:
+            service.stop()
+        await asyncio.wait_for(task, 1)
+
+        assert len(calls) == 2
+        assert calls[0] == (service.prompt, self.CONTENT)
+        prompt, content = calls[1]
+        assert prompt == service.prompt + "\n\nSections of HEARTBEAT.md due now: Hourly, Odd"
+        assert "Summarize the news" not in content
+        assert "Check the inbox" in content and "Stretch" in content
+        assert (tmp_path / ".heartbeat_sections.json").exists()
+
+        # The last run is persisted, and manual triggers show every section
+        on_heartbeat, prompts = recorder()
+        (tmp_path / "HEARTBEAT.md").write_text("## Daily <!-- every: 1d -->\n- [ ] Summarize the news\n")
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat, interval_s=1)
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(1.3)
+            assert service.status()["last_result"] == "skipped_empty"
+            await service.trigger_now()
+            assert len(prompts) == 1
+        finally:
+            service.stop()
+        await asyncio.wait_for(task, 1)
+
+
+class TestActiveHours:
+    """Tests for the active-hours window."""
+
+    def test_no_window(self, tmp_path):
+        """Without a window ticks always run."""
+        service = HeartbeatService(tmp_path)
+        assert service.active_start is None
+        assert service.active_end is None
+        assert service.tz == "UTC"
+        assert service.is_active_now
+
+    def test_window(self, tmp_path):
+        """The window is read in the configured timezone."""
+        start, end = window_around_now(-0.5)
+        service = HeartbeatService(tmp_path, active_start=start, active_end=end)
+        assert (service.active_start, service.active_end) == (start, end)
+        assert service.is_active_now
+
+        start, end = window_around_now(2)
+        service.set_active_hours(start, end)
+        assert not service.is_active_now
+
+    def test_wrap_around(self, tmp_path):
+        """A window ending before it starts wraps past midnight."""
+        start, _ = window_around_now(-0.5)
+        _, end = window_around_now(-2)
+        # From half an hour ago round to an hour ago: all but half an hour
+        service = HeartbeatService(tmp_path, active_start=start, active_end=end)
+        assert service.is_active_now
+
+    def test_invalid(self, tmp_path):
+        """Bad times, one-sided windows and unknown timezones are rejected."""
+        with pytest.raises(ValueError):
+            HeartbeatService(tmp_path, active_start="8am", active_end="22:00")
+        with pytest.raises(ValueError):
+            HeartbeatService(tmp_path, active_start="08:00")
+        with pytest.raises(ValueError):
+            HeartbeatService(tmp_path, active_start="08:00", active_end="08:00")
+        with pytest.raises(ValueError):
+            HeartbeatService(tmp_path, tz="Mars/Olympus")
+
+    def test_set_tz(self, tmp_path):
+        """The timezone can be changed at runtime, and clearing keeps it."""
+        service = HeartbeatService(tmp_path, active_start="08:00", active_end="22:00")
+        service.set_active_hours("09:00", "17:00", tz="Europe/Berlin")
+        assert (service.active_start, service.tz) == ("09:00", "Europe/Berlin")
+        service.set_active_hours()
+        assert service.active_start is None
+        assert service.tz == "Europe/Berlin"
+        assert service.is_active_now
+
+    async def test_ticks_outside_window_are_skipped(self, tmp_path):
+        """Ticks outside the window are skipped; a new window applies next tick."""
+        (tmp_path / "HEARTBEAT.md").write_text("Water the plants")
+        on_heartbeat, prompts = recorder()
+        start, end = window_around_now(2)
+        service = HeartbeatService(
+            tmp_path, on_heartbeat=on_heartbeat, interval_s=1, active_start=start, active_end=end
+        )
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(1.5)
+            assert prompts == []
+            service.set_active_hours(*window_around_now(-0.5))
+            await asyncio.sleep(1.2)
+            assert prompts == [service.prompt]
+        finally:
+            service.stop()
+            await asyncio.sleep(0)
+            task.cancel()
+
+
+class TestStatus:
+    """Tests for status()."""
+
+    def test_initial(self, tmp_path):
+        """Before any tick nothing is recorded."""
+        service = HeartbeatService(tmp_path, interval_s=60)
+        assert service.status() == {
+            "running": False,
+            "paused": False,
+            "interval_s": 60,
+            "next_tick_at_ms": None,
+            "last_tick_at_ms": None,
+            "last_result": None,
+            "last_error": None,
+            "ticks_total": 0,
+            "consecutive_failures": 0,
+            "effective_interval_s": 60,
+        }
+
+    async def test_trigger_now(self, tmp_path):
+        """Manual triggers record whether the agent acted."""
+        on_heartbeat, _ = recorder("Watered the plants")
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat)
+        await service.trigger_now(skip_if_empty=False)
+        status = service.status()
+        assert status["last_result"] == "action"
+        assert status["ticks_total"] == 1
+        assert status["last_tick_at_ms"] is not None
+
+    async def test_error(self, tmp_path):
+        """A failing callback is recorded with its error."""
+
+        async def on_heartbeat(prompt):
+            raise RuntimeError("agent offline")
+
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat)
+        with pytest.raises(RuntimeError):
+            await service.trigger_now(skip_if_empty=False)
+        status = service.status()
+        assert status["last_result"] == "error"
+        assert "agent offline" in status["last_error"]
+
+    async def test_running(self, tmp_path):
+        """The loop reports its next tick and records skipped ticks."""
+        on_heartbeat, prompts = recorder()
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat, interval_s=1)
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(0.2)
+            status = service.status()
+            assert status["running"]
+            now_ms = int(time.time() * 1000)
+            assert now_ms < status["next_tick_at_ms"] <= now_ms + 1000
+
+            await asyncio.sleep(1.1)
+            status = service.status()
+            assert status["last_result"] == "skipped_empty"
+            assert status["ticks_total"] == 1
+            assert prompts == []
+
+            (tmp_path / "HEARTBEAT.md").write_text("Water the plants")
+            await asyncio.sleep(1.0)
+            assert service.status()["last_result"] == "ok"
+            assert service.status()["ticks_total"] == 2
+        finally:
+            service.stop()
+            await asyncio.sleep(0)
+            task.cancel()
+        assert service.status()["next_tick_at_ms"] is None
+
+
+class TestSetInterval:
+    """Tests for changing the interval at runtime."""
+
+    def test_setter(self, tmp_path):
+        """The interval can be set by method or property, but not to zero."""
+        service = HeartbeatService(tmp_path, interval_s=60)
+        service.set_interval(120)
+        assert service.interval_s == 120
+        service.interval_s = 30
+        assert service.status()["interval_s"] == 30
+        with pytest.raises(ValueError):
+            service.set_interval(0)
+        with pytest.raises(ValueError):
+            HeartbeatService(tmp_path, interval_s=0)
+
+    async def test_applies_to_sleeping_loop(self, tmp_path):
+        """Shortening the interval wakes the loop instead of waiting it out."""
+        (tmp_path / "HEARTBEAT.md").write_text("Water the plants")
+        on_heartbeat, prompts = recorder()
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat, interval_s=3600)
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(0.2)
+            before = service.status()["next_tick_at_ms"]
+            service.set_interval(1)
+            await asyncio.sleep(0.1)
+            assert service.status()["next_tick_at_ms"] < before
+            await asyncio.sleep(1.0)
+            assert prompts == [service.prompt]
+        finally:
+            service.stop()
+            await asyncio.sleep(0)
+            task.cancel()
+
+
+class TestJitter:
+    """Tests for the random delay added before each tick."""
+
+    def test_setter(self, tmp_path):
+        """Jitter defaults to none and cannot be negative."""
+        service = HeartbeatService(tmp_path)
+        assert service.jitter_s == 0
+        service.jitter_s = 1.5
+        assert service.jitter_s == 1.5
+        with pytest.raises(ValueError):
+            service.jitter_s = -1
+        with pytest.raises(ValueError):
+            HeartbeatService(tmp_path, jitter_s=-1)
+
+    async def test_next_tick_includes_jitter(self, tmp_path):
+        """Services started together get different, jittered tick times."""
+        services = [HeartbeatService(tmp_path, interval_s=60, jitter_s=30) for _ in range(3)]
+        tasks = [asyncio.ensure_future(service.start()) for service in services]
+        try:
+            started = time.time() * 1000
+            await asyncio.sleep(0.2)
+            next_ticks = [service.status()["next_tick_at_ms"] for service in services]
+        finally:
+            for service in services:
+                service.stop()
+            await asyncio.wait_for(asyncio.gather(*tasks), 1)
+        for next_tick in next_ticks:
+            assert started + 59_000 <= next_tick <= started + 90_500
+        assert len(set(next_ticks)) > 1
+
+
+class TestSchedule:
+    """Tests for ticking on a cron schedule instead of an interval."""
+
+    def test_validation(self, tmp_path):
+        """A schedule replaces the interval and must repeat."""
+        every = CronSchedule(kind="every", every_ms=60_000)
+        with pytest.raises(ValueError):
+            HeartbeatService(tmp_path, interval_s=60, schedule=every)
+        with pytest.raises(ValueError):
+            HeartbeatService(tmp_path, schedule=CronSchedule(kind="at", at_ms=int(time.time() * 1000) + 60_000))
+        with pytest.raises(ValueError):
+            HeartbeatService(tmp_path, schedule=CronSchedule(kind="cron", expr="not a cron"))
+        service = HeartbeatService(tmp_path, schedule=every)
+        assert service.schedule == every
+        assert service.status()["interval_s"] is None
+        assert service.status()["effective_interval_s"] is None
+
+    async def test_next_tick_follows_cron_in_tz(self, tmp_path):
+        """The next tick is the next matching time in the schedule's timezone."""
+        schedule = CronSchedule(kind="cron", expr="0 9,13,17 * * MON-FRI", tz="Asia/Tokyo")
+        service = HeartbeatService(tmp_path, schedule=schedule)
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(0.2)
+            next_ms = service.status()["next_tick_at_ms"]
+            at = datetime.fromtimestamp(next_ms / 1000, timezone(timedelta(hours=9)))
+            assert (at.hour, at.minute, at.second) in {(9, 0, 0), (13, 0, 0), (17, 0, 0)}
+            assert at.weekday() < 5
+            assert 0 < next_ms - time.time() * 1000 <= 3 * 24 * 3600 * 1000
+        finally:
+            service.stop()
+            await asyncio.sleep(0)
+            task.cancel()
+
+    async def test_ticks_on_schedule(self, tmp_path):
+        """Ticks fire at the schedule's times; set_interval switches back."""
+        (tmp_path / "HEARTBEAT.md").write_text("Water the plants")
+        on_heartbeat, prompts = recorder()
+        service = HeartbeatService(
+            tmp_path, on_heartbeat=on_heartbeat, schedule=CronSchedule(kind="every", every_ms=300)
+        )
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(1.0)
+            assert len(prompts) >= 2
+            service.set_interval(3600)
+            assert service.schedule is None
+            await asyncio.sleep(0.1)
+            seen = len(prompts)
+            assert service.status()["next_tick_at_ms"] > time.time() * 1000 + 3000 * 1000
+            await asyncio.sleep(0.5)
+            assert len(prompts) == seen
+        finally:
+            service.stop()
+            await asyncio.sleep(0)
+            task.cancel()
+
+    async def test_set_schedule_wakes_loop(self, tmp_path):
+        """Setting a schedule re-arms a loop sleeping on the interval."""
+        (tmp_path / "HEARTBEAT.md").write_text("Water the plants")
+        on_heartbeat, prompts = recorder()
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat, interval_s=3600)
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(0.2)
+            service.set_schedule(CronSchedule(kind="every", every_ms=200))
+            await asyncio.sleep(0.5)
+            assert prompts
+        finally:
+            service.stop()
+            await asyncio.sleep(0)
+            task.cancel()
+
+
+class TestActivitySuppression:
+    """Tests for putting off ticks while the user is active."""
+
+    def test_setting(self, tmp_path):
+        """The window is off by default and must be positive when set."""
+        service = HeartbeatService(tmp_path)
+        assert service.suppress_within_s is None
+        service.suppress_within_s = 90
+        assert service.suppress_within_s == 90
+        with pytest.raises(ValueError):
+            service.suppress_within_s = 0
+        with pytest.raises(ValueError):
+            HeartbeatService(tmp_path, suppress_within_s=-1)
+
+    async def test_skipped_and_retried_after_window(self, tmp_path):
+        """A tick during the window is skipped and retried when it ends."""
+        (tmp_path / "HEARTBEAT.md").write_text("Water the plants")
+        on_heartbeat, prompts = recorder()
+        service = HeartbeatService(
+            tmp_path, on_heartbeat=on_heartbeat, interval_s=1, suppress_within_s=1.5
+        )
+        service.notify_activity()
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(1.2)
+            assert prompts == []
+            assert service.status()["last_result"] == "skipped_active"
+            assert service.status()["next_tick_at_ms"] - time.time() * 1000 < 500
+            await asyncio.sleep(0.6)
+            assert prompts == [service.prompt]
+            assert [h["result"] for h in service.history()] == ["ok", "skipped_active"]
+            assert service.metrics()["skipped_active"] == 1
+        finally:
+            service.stop()
+            await asyncio.sleep(0)
+            task.cancel()
+
+    async def test_trigger_now_not_suppressed(self, tmp_path):
+        """Manual triggers run even while the user is active."""
+        on_heartbeat, prompts = recorder()
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat, suppress_within_s=3600)
+        service.notify_activity()
+        assert await service.trigger_now(skip_if_empty=False) == "HEARTBEAT_OK"
+        assert len(prompts) == 1
+
+
+class TestWatch:
+    """Tests for ticking when the task files change."""
+
+    async def run_watching(self, tmp_path, edits, **kwargs):
+        """Start a watching service, apply `edits` and return the prompts seen."""
+        on_heartbeat, prompts = recorder()
+        service = HeartbeatService(
+            tmp_path, on_heartbeat=on_heartbeat, interval_s=3600, watch=True, **kwargs
+        )
+        assert service.watch
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(0.2)
+            await edits(tmp_path / "HEARTBEAT.md")
+            await asyncio.sleep(1.5)
+        finally:
+            service.stop()
+            await asyncio.sleep(0)
+            task.cancel()
+        return prompts, service
+
+    async def test_edit_triggers_tick(self, tmp_path):
+        """An edit triggers a tick long before the periodic one."""
+
+        async def edits(path):
+            path.write_text("Water the plants")
+
+        prompts, service = await self.run_watching(tmp_path, edits, watch_debounce_ms=100)
+        assert prompts == [service.prompt]
+        assert service.status()["last_result"] == "ok"
+
+    async def test_burst_is_debounced(self, tmp_path):
+        """A burst of edits causes a single tick."""
+
+        async def edits(path):
+            for i in range(4):
+                path.write_text("Task " * (i + 1))
+                await asyncio.sleep(0.3)
+
+        prompts, _ = await self.run_watching(
+            tmp_path, edits, watch_debounce_ms=400, watch_min_spacing_s=0
+        )
+        assert len(prompts) == 1
+
+    async def test_min_spacing(self, tmp_path):
+        """Edits after a watch-triggered tick wait out the minimum spacing."""
+
+        async def edits(path):
+            path.write_text("First task")
+            await asyncio.sleep(1.0)
+            path.write_text("Second task")
+
+        prompts, _ = await self.run_watching(
+            tmp_path, edits, watch_debounce_ms=100, watch_min_spacing_s=60
+        )
+        assert len(prompts) == 1
+
+    def test_off_by_default(self, tmp_path):
+        """Watching is opt-in."""
+        assert not HeartbeatService(tmp_path).watch
+
+
+class TestHistoryAndMetrics:
+    """Tests for the tick history and counters."""
+
+    async def test_history(self, tmp_path):
+        """Ticks are listed newest first with a snippet of the response."""
+        replies = iter(["HEARTBEAT_OK", "Watered the plants. " * 20])
+
+        async def on_heartbeat(prompt):
+            return next(replies)
+
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat)
+        await service.trigger_now(skip_if_empty=False)
+        await service.trigger_now(skip_if_empty=False)
+
+        history = service.history()
+        assert [tick["result"] for tick in history] == ["action", "ok"]
+        assert history[0]["response"].startswith("Watered the plants.")
+        assert len(history[0]["response"]) == 200
+        assert history[0]["error"] is None
+        assert history[0]["duration_ms"] >= 0
+        assert history[0]["at_ms"] >= history[1]["at_ms"]
+        assert service.history(limit=1) == history[:1]
+
+    async def test_metrics_and_reset(self, tmp_path):
+        """Counters are kept per result and can be reset."""
+
+        async def failing(prompt):
+            raise RuntimeError("agent offline")
+
+        on_heartbeat, _ = recorder()
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat)
+        await service.trigger_now(skip_if_empty=False)
+        service.set_callback(failing)
+        with pytest.raises(RuntimeError):
+            await service.trigger_now(skip_if_empty=False)
+
+        metrics = service.metrics()
+        assert metrics["total"] == 2
+        assert metrics["ok"] == 1
+        assert metrics["error"] == 1
+        assert metrics["action"] == 0
+        assert service.history()[0]["error"].endswith("agent offline")
+
+        service.reset_metrics()
+        assert service.metrics()["total"] == 0
+        assert service.history() == []
+
+    async def test_persisted_metrics(self, tmp_path):
+        """With persist_metrics the counters survive a restart."""
+        on_heartbeat, _ = recorder()
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat, persist_metrics=True)
+        await service.trigger_now(skip_if_empty=False)
+        assert (tmp_path / ".heartbeat_metrics.json").exists()
+
+        restarted = HeartbeatService(tmp_path, persist_metrics=True)
+        assert restarted.metrics()["ok"] == 1
+        assert restarted.status()["ticks_total"] == 1
+        assert restarted.history() == []
+        assert HeartbeatService(tmp_path).metrics()["total"] == 0
+
+
+class TestOkStreaks:
+    """Tests for counting OK replies in a row and the adaptive interval."""
+
+    @staticmethod
+    def scripted(replies):
+        """A heartbeat callback giving `replies` in turn."""
+        replies = iter(replies)
+
+        async def on_heartbeat(prompt):
+            return next(replies)
+
+        return on_heartbeat
+
+    async def test_streaks(self, tmp_path):
+        """Actions end the current streak; the longest one is kept."""
+        (tmp_path / "HEARTBEAT.md").write_text("- [ ] Water the plants")
+        on_heartbeat = self.scripted(["HEARTBEAT_OK"] * 3 + ["Watered them", "HEARTBEAT_OK"])
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat)
+        for _ in range(5):
+            await service.trigger_now()
+        metrics = service.metrics()
+        assert (metrics["ok"], metrics["action"]) == (4, 1)
+        assert metrics["current_ok_streak"] == 1
+        assert metrics["longest_ok_streak"] == 3
+
+        service.reset_metrics()
+        assert service.metrics()["longest_ok_streak"] == 0
+
+    async def test_adaptive_interval(self, tmp_path):
+        """The interval stretches after OK runs and snaps back after an action."""
+        (tmp_path / "HEARTBEAT.md").write_text("- [ ] Water the plants")
+        on_heartbeat = self.scripted(["HEARTBEAT_OK"] * 4 + ["Watered them"])
+        service = HeartbeatService(
+            tmp_path, on_heartbeat=on_heartbeat, interval_s=60, adaptive_interval=True, adaptive_after_oks=2
+        )
+        intervals = []
+        for _ in range(5):
+            await service.trigger_now()
+            intervals.append(service.status()["effective_interval_s"])
+        assert intervals == [60, 120, 120, 240, 60]
+
+        service.adaptive_interval = False
+        assert service.adaptive_interval is False
+        with pytest.raises(ValueError):
+            HeartbeatService(tmp_path, adaptive_factor=1)
+        with pytest.raises(ValueError):
+            HeartbeatService(tmp_path, adaptive_after_oks=0)
+
+
+class TestBackoff:
+    """Tests for backing off after failed ticks."""
+
+    async def test_backoff_and_recovery(self, tmp_path):
+        """Failures double the interval; the first success restores it."""
+        (tmp_path / "HEARTBEAT.md").write_text("Water the plants")
+        calls = []
+
+        async def failing(prompt):
+            calls.append(prompt)
+            raise RuntimeError("provider down")
+
+        service = HeartbeatService(tmp_path, on_heartbeat=failing, interval_s=1)
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(1.2)
+            status = service.status()
+            assert status["consecutive_failures"] == 1
+            assert status["effective_interval_s"] == 2
+            assert status["interval_s"] == 1
+
+            # The next tick waits the doubled interval
+            await asyncio.sleep(1.2)
+            assert len(calls) == 1
+
+            on_heartbeat, prompts = recorder()
+            service.set_callback(on_heartbeat)
+            await asyncio.sleep(0.8)
+            assert prompts == [service.prompt]
+            status = service.status()
+            assert status["consecutive_failures"] == 0
+            assert status["effective_interval_s"] == 1
+        finally:
+            service.stop()
+            await asyncio.sleep(0)
+            task.cancel()
+
+
+class TestFailureAlert:
+    """Tests for alerting once ticks keep failing."""
+
+    async def test_alert_once_per_run_of_failures(self, tmp_path):
+        """The alert fires when the threshold is reached, and again only after a success."""
+        alerts = []
+
+        async def on_alert(info):
+            alerts.append(info)
+
+        fail = True
+
+        async def on_heartbeat(prompt):
+            if fail:
+                raise RuntimeError("provider down")
+            return "HEARTBEAT_OK"
+
+        service = HeartbeatService(
+            tmp_path, on_heartbeat=on_heartbeat, alert_after_failures=2, on_alert=on_alert
+        )
+
+        async def run(times):
+            for _ in range(times):
+                if fail:
+                    with pytest.raises(RuntimeError):
+                        await service.trigger_now(skip_if_empty=False)
+                else:
+                    await service.trigger_now(skip_if_empty=False)
+
+        await run(1)
+        assert alerts == []
+        await run(2)
+        assert len(alerts) == 1
+        assert alerts[0]["failures"] == 2
+        assert "provider down" in alerts[0]["error"]
+        assert service.status()["consecutive_failures"] == 3
+
+        fail = False
+        await run(1)
+        fail = True
+        await run(2)
+        assert [a["failures"] for a in alerts] == [2, 2]
+
+    def test_setting(self, tmp_path):
+        """The threshold is off by default and must be positive."""
+        service = HeartbeatService(tmp_path)
+        assert service.alert_after_failures is None
+        service.alert_after_failures = 10
+        assert service.alert_after_failures == 10
+        with pytest.raises(ValueError):
+            service.alert_after_failures = 0
+        with pytest.raises(ValueError):
+            HeartbeatService(tmp_path, alert_after_failures=0)
+
+
+class TestTickTimeout:
+    """Tests for the limit on how long the callback may take."""
+
+    async def test_trigger_now_timeout(self, tmp_path):
+        """trigger_now raises TimeoutError and records an error."""
+
+        async def wedged(prompt):
+            await asyncio.sleep(3600)
+
+        service = HeartbeatService(tmp_path, on_heartbeat=wedged)
+        assert service.tick_timeout_s == 600
+        with pytest.raises(TimeoutError):
+            await service.trigger_now(timeout_s=0.1, skip_if_empty=False)
+        status = service.status()
+        assert status["last_result"] == "error"
+        assert "timed out" in status["last_error"]
+
+        service.tick_timeout_s = 0.1
+        with pytest.raises(TimeoutError):
+            await service.trigger_now(skip_if_empty=False)
+        with pytest.raises(ValueError):
+            service.tick_timeout_s = 0
+
+    async def test_loop_continues_after_timeout(self, tmp_path):
+        """A wedged callback fails its tick instead of stopping the loop."""
+        (tmp_path / "HEARTBEAT.md").write_text("Water the plants")
+        calls = []
+
+        async def wedged(prompt):
+            calls.append(prompt)
+            await asyncio.sleep(3600)
+
+        service = HeartbeatService(
+            tmp_path, on_heartbeat=wedged, interval_s=1, tick_timeout_s=0.2
+        )
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(1.4)
+            assert service.status()["last_result"] == "error"
+            assert service.metrics()["error"] == 1
+            # Backed off to two seconds after the failure
+            await asyncio.sleep(2.2)
+            assert len(calls) == 2
+        finally:
+            service.stop()
+            await asyncio.sleep(0)
+            task.cancel()
+
+
+class TestArchiveCompleted:
+    """Tests for moving completed tasks to the archive."""
+
+    async def test_archives_after_action(self, tmp_path):
+        """Checked items move to the archive; the rest is kept byte for byte."""
+        path = tmp_path / "HEARTBEAT.md"
+        path.write_bytes(b"# Tasks\r\n- [x] Water plants\r\n- [ ] Call mom\n  * [X] Feed cat\n+ [x] Take out bins\nNotes")
+        on_heartbeat, _ = recorder("Did the chores")
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat, archive_completed=True)
+        assert service.archive_completed
+        await service.trigger_now()
+
+        assert path.read_bytes() == b"# Tasks\r\n- [ ] Call mom\nNotes"
+        archive = (tmp_path / "HEARTBEAT.archive.md").read_text()
+        assert archive.startswith("## Archived ")
+        assert archive.endswith("\n\n- [x] Water plants\n  * [X] Feed cat\n+ [x] Take out bins\n")
+
+        # A second archive run appends under its own header
+        path.write_text("- [ ] Call mom\n- [x] Pay rent\n")
+        await service.trigger_now()
+        archive = (tmp_path / "HEARTBEAT.archive.md").read_text()
+        assert archive.count("## Archived ") == 2
+        assert archive.endswith("- [x] Pay rent\n")
+
+    async def test_not_after_ok_or_when_disabled(self, tmp_path):
+        """OK replies and the default setting leave the file alone."""
+        path = tmp_path / "HEARTBEAT.md"
+        path.write_text("- [x] Water plants\n- [ ] Call mom\n")
+        on_heartbeat, _ = recorder()
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat, archive_completed=True)
+        await service.trigger_now()
+        service.archive_completed = False
+        service.set_callback(recorder("Did the chores")[0])
+        await service.trigger_now()
+        assert path.read_text() == "- [x] Water plants\n- [ ] Call mom\n"
+        assert not (tmp_path / "HEARTBEAT.archive.md").exists()
+
+    def test_wildcards_skip_archives(self, tmp_path):
+        """Archive files are never treated as task files."""
+        (tmp_path / "HEARTBEAT.md").write_text("")
+        (tmp_path / "HEARTBEAT.archive.md").write_text("- [x] Old")
+        service = HeartbeatService(tmp_path, heartbeat_file="HEARTBEAT*.md")
+        assert service.heartbeat_file == [str(tmp_path / "HEARTBEAT.md")]
+
+
+class TestTriggerResult:
+    """Tests for what trigger_now returns and the empty-file check."""
+
+    async def test_skips_empty_files(self, tmp_path):
+        """By default an empty task file is a skip, not an agent turn."""
+        on_heartbeat, prompts = recorder()
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat)
+        result = await service.trigger_now()
+        assert result["skipped"] is True
+        assert result["outcome"] == "skipped_empty"
+        assert result["response"] is None
+        assert result["duration_ms"] >= 0
+        assert prompts == []
+        assert service.status()["last_result"] == "skipped_empty"
+
+        assert await service.trigger_now(skip_if_empty=False) == "HEARTBEAT_OK"
+        assert prompts == [service.prompt]
+
+    async def test_ok_and_action(self, tmp_path):
+        """The outcome tells an OK reply from an action."""
+        (tmp_path / "HEARTBEAT.md").write_text("- [ ] Water the plants")
+        on_heartbeat, _ = recorder()
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat)
+        result = await service.trigger_now()
+        assert (result["skipped"], result["outcome"], result["response"]) == (False, "ok", "HEARTBEAT_OK")
+
+        on_heartbeat, _ = recorder("Watered them")
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat)
+        result = await service.trigger_now()
+        assert (result["skipped"], result["outcome"], result["response"]) == (False, "action", "Watered them")
+
+
+class TestTickLog:
+    """Tests for the log of ticks kept in the workspace."""
+
+    async def test_appends_and_rotates(self, tmp_path):
+        """Ticks that woke the agent are logged; a full log is rotated."""
+        (tmp_path / "HEARTBEAT.md").write_text("- [ ] Water the plants")
+        on_heartbeat, _ = recorder("Watered the plants")
+        service = HeartbeatService(
+            tmp_path, on_heartbeat=on_heartbeat, heartbeat_file="*.md", log_file="heartbeat.log.md", log_max_bytes=100
+        )
+        log = tmp_path / "heartbeat.log.md"
+        await service.trigger_now()
+        content = log.read_text()
+        assert content.startswith("## ") and " action\n\nWatered the plants\n" in content
+        assert service.heartbeat_file == [str(tmp_path / "HEARTBEAT.md")]
+
+        async def failing(prompt):
+            raise RuntimeError("agent offline")
+
+        service = HeartbeatService(tmp_path, on_heartbeat=failing, log_file="heartbeat.log.md", log_max_bytes=100)
+        with pytest.raises(RuntimeError):
+            await service.trigger_now()
+        assert "Error: Callback error" in log.read_text()
+        assert content in (tmp_path / "heartbeat.log.md.1").read_text()
+
+    async def test_skips_and_write_failures(self, tmp_path):
+        """Skipped ticks are not logged, and a log that cannot be written is ignored."""
+        on_heartbeat, _ = recorder()
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat, log_file="heartbeat.log.md")
+        await service.trigger_now()
+        assert not (tmp_path / "heartbeat.log.md").exists()
+
+        (tmp_path / "HEARTBEAT.md").write_text("- [ ] Water the plants")
+        service.log_file = "missing/heartbeat.log.md"
+        assert (await service.trigger_now())["outcome"] == "ok"
+        assert service.log_file == "missing/heartbeat.log.md"
+
+
+class TestBusyGuard:
+    """Tests for skipping ticks while one is still running."""
+
+    async def test_trigger_now_skips_or_waits(self, tmp_path):
+        """A second trigger is skipped while one runs, or queues with wait."""
+        release = asyncio.Event()
+        prompts = []
+
+        async def slow(prompt):
+            prompts.append(prompt)
+            await release.wait()
+            return "HEARTBEAT_OK"
+
+        service = HeartbeatService(tmp_path, on_heartbeat=slow)
+        first = asyncio.ensure_future(service.trigger_now(skip_if_empty=False))
+        await asyncio.sleep(0.1)
+
+        assert await service.trigger_now(skip_if_empty=False) is None
+        assert service.status()["last_result"] == "skipped_busy"
+
+        queued = asyncio.ensure_future(service.trigger_now(wait=True, skip_if_empty=False))
+        await asyncio.sleep(0.1)
+        assert len(prompts) == 1
+
+        release.set()
+        assert await first == "HEARTBEAT_OK"
+        assert await queued == "HEARTBEAT_OK"
+        assert len(prompts) == 2
+        metrics = service.metrics()
+        assert metrics["skipped_busy"] == 1
+        assert metrics["ok"] == 2
+
+    async def test_scheduled_tick_skipped_while_busy(self, tmp_path):
+        """A scheduled tick finding a manual one in flight is skipped."""
+        (tmp_path / "HEARTBEAT.md").write_text("Water the plants")
+        release = asyncio.Event()
+
+        async def slow(prompt):
+            await release.wait()
+            return "HEARTBEAT_OK"
+
+        service = HeartbeatService(tmp_path, on_heartbeat=slow, interval_s=1)
+        manual = asyncio.ensure_future(service.trigger_now())
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(1.3)
+            assert service.metrics()["skipped_busy"] == 1
+        finally:
+            release.set()
+            await manual
+            service.stop()
+            await asyncio.sleep(0)
+            task.cancel()
+
+
+class TestRichCallback:
+    """Tests for the structured context passed with rich_callback."""
+
+    async def test_context(self, tmp_path):
+        """The callback gets the task files, tick number and trigger kind."""
+        (tmp_path / "HEARTBEAT.md").write_text("Water the plants")
+        (tmp_path / "EMPTY.md").write_text("# Nothing\n")
+        calls = []
+
+        async def on_heartbeat(prompt, context):
+            calls.append((prompt, context))
+            return "HEARTBEAT_OK"
+
+        service = HeartbeatService(
+            tmp_path,
+            on_heartbeat=on_heartbeat,
+            heartbeat_file=["HEARTBEAT.md", "EMPTY.md"],
+            rich_callback=True,
+        )
+        await service.trigger_now()
+        await service.trigger_now()
+
+        prompt, context = calls[0]
+        assert prompt == service.prompt
+        assert context["tick"] == 1
+        assert context["manual"] is True
+        assert context["files"] == [
+            {
+                "name": "HEARTBEAT.md",
+                "path": str(tmp_path / "HEARTBEAT.md"),
+                "content": "Water the plants",
+            }
+        ]
+        assert calls[1][1]["tick"] == 2
+
+    async def test_scheduled_tick_context(self, tmp_path):
+        """Scheduled ticks are not marked manual."""
+        (tmp_path / "HEARTBEAT.md").write_text("Water the plants")
+        contexts = []
+
+        async def on_heartbeat(prompt, context):
+            contexts.append(context)
+            return "HEARTBEAT_OK"
+
+        service = HeartbeatService(
+            tmp_path, on_heartbeat=on_heartbeat, interval_s=1, rich_callback=True
+        )
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(1.3)
+        finally:
+            service.stop()
+            await asyncio.sleep(0)
+            task.cancel()
+        assert [c["manual"] for c in contexts] == [False]
+
+    async def test_prompt_only_by_default(self, tmp_path):
+        """Without the flag the callback still gets just the prompt."""
+        on_heartbeat, prompts = recorder()
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat)
+        await service.trigger_now(skip_if_empty=False)
+        assert prompts == [service.prompt]
+
+
+class TestDelivery:
+    """Tests for delivering responses that are not OK."""
+
+    def deliveries(self):
+        delivered = []
+
+        async def on_deliver(payload):
+            delivered.append(payload)
+
+        return on_deliver, delivered
+
+    async def test_action_is_delivered(self, tmp_path):
+        """A response that is not OK reaches on_deliver with the settings."""
+        on_heartbeat, _ = recorder("Reminder: water the plants")
+        on_deliver, delivered = self.deliveries()
+        service = HeartbeatService(
+            tmp_path,
+            on_heartbeat=on_heartbeat,
+            deliver=True,
+            channel="telegram",
+            to="12345",
+            on_deliver=on_deliver,
+        )
+        assert (service.deliver, service.channel, service.to) == (True, "telegram", "12345")
+        await service.trigger_now(skip_if_empty=False)
+        assert delivered == [
+            {
+                "message": "Reminder: water the plants",
+                "channel": "telegram",
+                "to": "12345",
+                "manual": True,
+            }
+        ]
+
+    async def test_ok_is_never_delivered(self, tmp_path):
+        """OK responses are not delivered."""
+        on_heartbeat, _ = recorder("heartbeat_ok")
+        on_deliver, delivered = self.deliveries()
+        service = HeartbeatService(
+            tmp_path, on_heartbeat=on_heartbeat, deliver=True, on_deliver=on_deliver
+        )
+        await service.trigger_now()
+        assert delivered == []
+
+    async def test_disabled_and_runtime_settings(self, tmp_path):
+        """Delivery is opt-in and its settings can change at runtime."""
+        on_heartbeat, _ = recorder("Did the chores")
+        on_deliver, delivered = self.deliveries()
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat)
+        service.set_deliver_callback(on_deliver)
+        await service.trigger_now(skip_if_empty=False)
+        assert delivered == []
+
+        service.deliver = True
+        service.channel = "slack"
+        service.to = "#home"
+        await service.trigger_now(skip_if_empty=False)
+        assert [(d["channel"], d["to"]) for d in delivered] == [("slack", "#home")]
+
+    async def test_delivery_failure_does_not_fail_tick(self, tmp_path):
+        """A failing on_deliver is logged; the tick still counts as an action."""
+        on_heartbeat, _ = recorder("Did the chores")
+
+        async def on_deliver(payload):
+            raise RuntimeError("chat offline")
+
+        service = HeartbeatService(
+            tmp_path, on_heartbeat=on_heartbeat, deliver=True, on_deliver=on_deliver
+        )
+        assert await service.trigger_now(skip_if_empty=False) == "Did the chores"
+        assert service.status()["last_result"] == "action"
+
+
+class TestResultHook:
+    """Tests for the on_result hook."""
+
+    async def test_reports_every_tick(self, tmp_path):
+        """Manual and skipped ticks are reported, sync or async hooks alike."""
+        results = []
+
+        async def on_result(info):
+            results.append(info)
+
+        on_heartbeat, _ = recorder("Did the chores")
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat, on_result=on_result)
+        await service.trigger_now(skip_if_empty=False)
+        await asyncio.sleep(0.1)
+        assert len(results) == 1
+        assert results[0]["outcome"] == "action"
+        assert results[0]["response"] == "Did the chores"
+        assert results[0]["error"] is None
+        assert results[0]["duration_ms"] >= 0
+
+        service.set_result_callback(results.append)
+        (tmp_path / "HEARTBEAT.md").write_text("")
+        service.set_interval(1)
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(1.3)
+        finally:
+            service.stop()
+            await asyncio.sleep(0)
+            task.cancel()
+        assert [r["outcome"] for r in results] == ["action", "skipped_empty"]
+
+    async def test_errors_are_counted_not_raised(self, tmp_path):
+        """A failing or slow hook never affects the tick."""
+
+        async def on_result(info):
+            await asyncio.sleep(10)
+
+        def broken(info):
+            raise RuntimeError("telemetry down")
+
+        on_heartbeat, _ = recorder()
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat, on_result=on_result)
+        started = time.monotonic()
+        assert await service.trigger_now(skip_if_empty=False) == "HEARTBEAT_OK"
+        assert time.monotonic() - started < 1
+
+        service.set_result_callback(broken)
+        assert await service.trigger_now(skip_if_empty=False) == "HEARTBEAT_OK"
+        await asyncio.sleep(0.1)
+        assert service.metrics()["on_result_errors"] == 1
+        service.reset_metrics()
+        assert service.metrics()["on_result_errors"] == 0
+
+
+class TestPauseResume:
+    """Tests for pausing the loop without stopping it."""
+
+    async def test_pause_skips_and_resume_reschedules(self, tmp_path):
+        """Paused ticks are skipped; resuming counts a full interval from then."""
+        (tmp_path / "HEARTBEAT.md").write_text("Water the plants")
+        on_heartbeat, prompts = recorder()
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat, interval_s=1)
+        task = asyncio.ensure_future(service.start())
+        try:
+            await asyncio.sleep(0.2)
+            service.pause()
+            assert service.paused and service.status()["paused"]
+            await asyncio.sleep(1.2)
+            assert prompts == []
+            assert service.status()["last_result"] == "skipped_paused"
+            assert service.metrics()["skipped_paused"] == 1
+
+            await asyncio.sleep(0.3)
+            service.resume()
+            await asyncio.sleep(0.1)
+            assert not service.status()["paused"]
+            assert service.status()["next_tick_at_ms"] - time.time() * 1000 > 700
+            assert prompts == []
+            await asyncio.sleep(1.0)
+            assert prompts == [service.prompt]
+        finally:
+            service.stop()
+            await asyncio.sleep(0)
+            task.cancel()
+
+    async def test_trigger_now_while_paused(self, tmp_path):
+        """Manual triggers still run while paused."""
+        on_heartbeat, prompts = recorder()
+        service = HeartbeatService(tmp_path, on_heartbeat=on_heartbeat)
+        service.pause()
+        assert await service.trigger_now(skip_if_empty=False) == "HEARTBEAT_OK"
+        assert len(prompts) == 1
+
+
+class TestWaitStopped:
+    """Tests for stopping promptly and waiting for the loop to exit."""
+
+    async def test_stop_wakes_long_sleep(self, tmp_path):
+        """A loop sleeping a long interval exits right after stop()."""
+        service = HeartbeatService(tmp_path, interval_s=3600, watch=True)
+        task = asyncio.ensure_future(service.start())
+        await asyncio.sleep(0.2)
+        assert not await service.wait_stopped(timeout_s=0.1)
+        service.stop()
+        started = time.monotonic()
+        assert await service.wait_stopped(timeout_s=2)
+        assert time.monotonic() - started < 1
+        await asyncio.wait_for(task, 1)
+
+    async def test_never_started(self, tmp_path):
+        """Without a running loop there is nothing to wait for."""
+        service = HeartbeatService(tmp_path)
+        assert await service.wait_stopped()
+        assert await service.wait_stopped(timeout_s=0.1)
+        with pytest.raises(ValueError):
+            await service.wait_stopped(timeout_s=0)
+
+
+class TestHeartbeatManager:
+    """Tests for running several named heartbeats together."""
+
+    async def test_runs_named_heartbeats(self, tmp_path):
+        """Each heartbeat ticks on its own, and results are tagged with its name."""
+        results = []
+        manager = HeartbeatManager(on_result=results.append)
+        for name in ("alice", "bob"):
+            workspace = tmp_path / name
+            workspace.mkdir()
+            (workspace / "HEARTBEAT.md").write_text("- [ ] Water the plants")
+            on_heartbeat, _ = recorder()
+            manager.add(name, workspace, interval_s=1, on_heartbeat=on_heartbeat)
+        assert manager.names() == ["alice", "bob"]
+        assert len(manager) == 2 and "alice" in manager
+        assert set(manager.status_all()) == {"alice", "bob"}
+
+        task = asyncio.ensure_future(manager.start())
+        try:
+            await asyncio.sleep(1.3)
+            assert manager.is_running
+            assert {r["name"] for r in results} == {"alice", "bob"}
+            assert all(r["outcome"] == "ok" for r in results)
+
+            bob = manager.get("bob")
+            assert manager.remove("bob")
+            assert not manager.remove("bob")
+            assert manager.get("bob") is None
+            assert await bob.wait_stopped(timeout_s=1)
+        finally:
+            manager.stop()
+        await asyncio.wait_for(task, 2)
+        assert not manager.is_running
+
+    async def test_add_validation(self, tmp_path):
+        """Names are unique, and on_result belongs to the manager."""
+        manager = HeartbeatManager()
+        manager.add("alice", tmp_path)
+        with pytest.raises(ValueError):
+            manager.add("alice", tmp_path)
+        with pytest.raises(ValueError):
+            manager.add("bob", tmp_path, on_result=print)
+        assert manager.names() == ["alice"]